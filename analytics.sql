@@ -5,7 +5,7 @@ WITH depth_volatility AS (
     AVG(spread) as avg_spread_usd,
     AVG(imbalance_ratio) as avg_imbalance,
     PERCENTILE_CONT(0.9) WITHIN GROUP (ORDER BY spread) as p90_spread
-  FROM read_parquet('s3://orderbook-data/orderbook/*/*/*/*/*.avro')
+  FROM read_parquet('s3://orderbook-data/orderbook/*/*/*/*/*/*/*.avro')
   WHERE timestamp_ms > EXTRACT(epoch FROM NOW() - INTERVAL '1 hour') * 1000
   GROUP BY 1
 )
@@ -0,0 +1,78 @@
+//! The per-message hot path over the recorded fixture messages, stage by stage:
+//! `cargo bench --bench hotpath`. Each bench runs the whole fixture, one-sided and
+//! truncated messages included, so results read as messages (or books) per second.
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use orderbook::binance::DepthMessage;
+use orderbook::book::Level;
+use orderbook::pipeline::{self, Outcome};
+use orderbook::{schema, sink, OrderBook};
+
+const FIXTURE: &str = include_str!("../tests/fixtures/depth20.jsonl");
+
+fn messages() -> Vec<&'static str> {
+    FIXTURE.lines().filter(|l| !l.trim().is_empty()).collect()
+}
+
+fn levels() -> Vec<(Vec<Level>, Vec<Level>)> {
+    messages().into_iter()
+        .filter_map(|m| Some(DepthMessage::parse(&mut m.as_bytes().to_vec()).ok()??.levels()))
+        .collect()
+}
+
+fn books() -> Vec<OrderBook> {
+    messages().into_iter().enumerate()
+        .filter_map(|(i, m)| match pipeline::process(m, i as i64 * 100, schema::writer_version()) {
+            Outcome::Book(book) => Some(*book),
+            _ => None,
+        })
+        .collect()
+}
+
+fn parse(c: &mut Criterion) {
+    let messages = messages();
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Elements(messages.len() as u64));
+    group.bench_function("depth_message", |b| b.iter_batched_ref(
+        || messages.iter().map(|m| m.as_bytes().to_vec()).collect::<Vec<_>>(),
+        |copies| copies.iter_mut().filter_map(|bytes| Some(DepthMessage::parse(bytes).ok()??.levels().0.len())).sum::<usize>(),
+        BatchSize::SmallInput,
+    ));
+    group.bench_function("pipeline", |b| b.iter(|| {
+        messages.iter().filter(|m| matches!(pipeline::process(black_box(m), 0, schema::writer_version()), Outcome::Book(_))).count()
+    }));
+    group.finish();
+}
+
+fn normalize(c: &mut Criterion) {
+    let levels = levels();
+    let mut group = c.benchmark_group("normalize");
+    group.throughput(Throughput::Elements(levels.len() as u64));
+    group.bench_function("from_levels", |b| b.iter(|| {
+        levels.iter().filter_map(|(bids, asks)| OrderBook::from_levels(0, black_box(bids), black_box(asks))).count()
+    }));
+    group.finish();
+}
+
+fn serialize(c: &mut Criterion) {
+    let books = books();
+    let schema = schema::parsed(schema::ORDERBOOK).unwrap();
+    let mut group = c.benchmark_group("avro");
+    group.throughput(Throughput::Elements(books.len() as u64));
+    // one object per book, as the collector writes them
+    group.bench_function("object_per_book", |b| b.iter(|| {
+        books.iter().map(|book| sink::encode(schema::ORDERBOOK, std::slice::from_ref(book)).unwrap().len()).sum::<usize>()
+    }));
+    group.bench_function("object_per_book/reused_buffer", |b| {
+        let mut buf = Vec::new();
+        b.iter(|| books.iter().map(|book| {
+            sink::encode_into(schema, std::slice::from_ref(book), &mut buf).unwrap();
+            buf.len()
+        }).sum::<usize>())
+    });
+    group.bench_function("one_object", |b| b.iter(|| sink::encode(schema::ORDERBOOK, black_box(&books)).unwrap().len()));
+    group.finish();
+}
+
+criterion_group!(benches, parse, normalize, serialize);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+//! Depth message parsing: `cargo bench --bench parse`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use orderbook::binance::{self, DepthMessage};
+use orderbook::pipeline;
+
+const DEPTH20: &str = r#"{"lastUpdateId":1027024,"bids":[["65000.10","0.500"],["65000.00","1.250"],["64995.50","2.000"],["64990.00","0.750"],["64900.00","3.000"],["64899.99","0.010"],["64899.50","0.200"],["64898.00","1.100"],["64897.25","0.050"],["64896.00","4.000"],["64895.10","0.300"],["64894.00","0.600"],["64893.75","2.250"],["64892.00","0.125"],["64891.50","0.900"],["64890.00","1.000"],["64889.99","0.002"],["64888.00","3.500"],["64887.40","0.450"],["64886.00","0.800"]],"asks":[["65000.20","0.400"],["65001.00","1.000"],["65010.00","2.500"],["65050.00","1.500"],["65500.00","4.000"],["65500.01","0.010"],["65501.00","0.200"],["65502.50","1.100"],["65503.00","0.050"],["65504.25","4.000"],["65505.00","0.300"],["65506.10","0.600"],["65507.00","2.250"],["65508.00","0.125"],["65509.90","0.900"],["65510.00","1.000"],["65511.00","0.002"],["65512.00","3.500"],["65513.30","0.450"],["65514.00","0.800"]]}"#;
+
+fn decimals(c: &mut Criterion) {
+    let mut bytes = DEPTH20.as_bytes().to_vec();
+    let Some(DepthMessage::Partial(depth)) = DepthMessage::parse(&mut bytes).unwrap() else { unreachable!() };
+    let strings: Vec<&str> = depth.bids.iter().chain(&depth.asks).flatten().copied().collect();
+
+    c.bench_function("decimal/std", |b| b.iter(|| {
+        strings.iter().map(|s| s.parse::<f64>().unwrap()).sum::<f64>()
+    }));
+    c.bench_function("decimal/fast_float", |b| b.iter(|| {
+        strings.iter().map(|s| binance::parse_decimal(s).unwrap()).sum::<f64>()
+    }));
+}
+
+fn messages(c: &mut Criterion) {
+    c.bench_function("depth20/serde_json_value", |b| b.iter(|| {
+        let v: serde_json::Value = serde_json::from_str(black_box(DEPTH20)).unwrap();
+        v["bids"].as_array().unwrap().iter()
+            .map(|l| l[0].as_str().unwrap().parse::<f64>().unwrap())
+            .sum::<f64>()
+    }));
+    c.bench_function("depth20/borrowed", |b| b.iter(|| {
+        let mut bytes = black_box(DEPTH20).as_bytes().to_vec();
+        DepthMessage::parse(&mut bytes).unwrap().unwrap().levels()
+    }));
+    c.bench_function("depth20/pipeline", |b| b.iter(|| pipeline::process(black_box(DEPTH20), 0, 3)));
+}
+
+criterion_group!(benches, decimals, messages);
+criterion_main!(benches);
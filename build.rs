@@ -0,0 +1,10 @@
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    // the gRPC service is generated from proto/orderbook.proto, with a vendored protoc
+    #[cfg(feature = "grpc")]
+    {
+        println!("cargo:rerun-if-changed=proto/orderbook.proto");
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path().expect("vendored protoc"));
+        tonic_build::compile_protos("proto/orderbook.proto").expect("compile proto/orderbook.proto");
+    }
+}
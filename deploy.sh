@@ -1,4 +1,10 @@
 #!/bin/bash
 cargo lambda build --release --bin orderbook-lambda
-cargo lambda build --release --bin recovery
+# the others don't collect, so they leave out alerts, futures and user data capture
+cargo lambda build --release --no-default-features --bin recovery
+cargo lambda build --release --no-default-features --bin dlq-replayer
+cargo lambda build --release --no-default-features --bin compactor
+cargo lambda build --release --no-default-features --bin heartbeat
+cargo lambda build --release --no-default-features --bin snapshot-query
+cargo lambda build --release --no-default-features --bin impact
 sam deploy
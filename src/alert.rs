@@ -0,0 +1,107 @@
+//! SNS alerts for failures that keep repeating: after `ALERT_AFTER_FAILURES`
+//! consecutive reconnects or S3 write failures for a symbol, one structured alert
+//! goes to `ALERT_TOPIC_ARN` with the symbol, what kept failing, for how long and
+//! the last error. A streak alerts once; it ends at the next success. Builds without
+//! the `alerts` feature only log them.
+
+#[cfg(feature = "alerts")]
+use aws_config::BehaviorVersion;
+#[cfg(feature = "alerts")]
+use aws_sdk_sns::Client;
+use serde::Serialize;
+#[cfg(feature = "alerts")]
+use tokio::sync::OnceCell;
+
+use crate::config;
+#[cfg(feature = "alerts")]
+use crate::Error;
+
+/// Consecutive failures before a streak alerts.
+pub const DEFAULT_AFTER: u32 = 3;
+
+/// The websocket dropped or couldn't connect.
+pub const RECONNECT: &str = "reconnect";
+/// A book couldn't be stored in the main bucket.
+pub const S3_WRITE: &str = "s3_write";
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Alert {
+    /// Upper-cased.
+    pub symbol: String,
+    /// `reconnect` or `s3_write`.
+    pub error_class: String,
+    pub failures: u32,
+    /// From the first failure of the streak to the one that raised the alert.
+    pub duration_ms: i64,
+    pub first_failure_ms: i64,
+    pub last_error: String,
+}
+
+impl Alert {
+    /// The SNS subject: short, and within its 100 character limit.
+    pub fn subject(&self) -> String {
+        format!("orderbook {}: {} consecutive {} failures", self.symbol, self.failures, self.error_class)
+    }
+}
+
+/// Consecutive failures of one class for one symbol.
+#[derive(Debug, Clone)]
+pub struct Streak {
+    error_class: &'static str,
+    after: u32,
+    failures: u32,
+    first_failure_ms: i64,
+}
+
+impl Streak {
+    pub fn new(error_class: &'static str, after: u32) -> Self {
+        Streak { error_class, after: after.max(1), failures: 0, first_failure_ms: 0 }
+    }
+
+    /// Counts a failure, returning the alert when it's the one that reaches the
+    /// threshold.
+    pub fn fail(&mut self, symbol: &str, error: &str, now_ms: i64) -> Option<Alert> {
+        if self.failures == 0 {
+            self.first_failure_ms = now_ms;
+        }
+        self.failures += 1;
+        (self.failures == self.after).then(|| Alert {
+            symbol: symbol.to_uppercase(),
+            error_class: self.error_class.to_string(),
+            failures: self.failures,
+            duration_ms: now_ms - self.first_failure_ms,
+            first_failure_ms: self.first_failure_ms,
+            last_error: error.to_string(),
+        })
+    }
+
+    pub fn succeed(&mut self) {
+        self.failures = 0;
+    }
+}
+
+/// `ALERT_AFTER_FAILURES`, or 3.
+pub fn after() -> u32 {
+    config::var("ALERT_AFTER_FAILURES").and_then(|n| n.parse().ok()).unwrap_or(DEFAULT_AFTER)
+}
+
+/// `ALERT_TOPIC_ARN`, when set.
+pub fn topic_arn() -> Option<String> {
+    config::var("ALERT_TOPIC_ARN").filter(|t| !t.is_empty())
+}
+
+/// Publishes `alert` as JSON to `ALERT_TOPIC_ARN`; nothing without one.
+#[cfg(feature = "alerts")]
+pub async fn publish(alert: &Alert) -> Result<(), Error> {
+    static CLIENT: OnceCell<Client> = OnceCell::const_new();
+
+    let Some(topic) = topic_arn() else { return Ok(()) };
+    let client = CLIENT.get_or_init(|| async { Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await) }).await;
+    client.publish()
+        .topic_arn(topic)
+        .subject(alert.subject())
+        .message(serde_json::to_string(alert)?)
+        .send().await
+        .map_err(|e| Error::Sns(Box::new(e.into())))?;
+    Ok(())
+}
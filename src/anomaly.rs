@@ -0,0 +1,113 @@
+//! Spread and depth anomalies, with `ANOMALIES=1`. Each book's spread and top-of-book
+//! depth (the quantity within the narrowest depth bucket, both sides) are scored
+//! against rolling baselines of the symbol's last `ANOMALY_WINDOW` books: how many
+//! median absolute deviations (MADs) they are from the baseline's median. A score
+//! past `ANOMALY_MADS` either way flags the book, so flash events can be found in
+//! the archive by filtering on a boolean.
+
+use std::collections::VecDeque;
+
+use crate::{config, OrderBook};
+
+pub const DEFAULT_WINDOW: usize = 3000;
+pub const DEFAULT_MADS: f64 = 5.0;
+
+/// Books a baseline needs before it scores anything.
+const MIN_BASELINE: usize = 30;
+
+/// Whether `ANOMALIES` asks for books to be scored.
+pub fn enabled() -> bool {
+    config::var("ANOMALIES").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub window: usize,
+    pub mads: f64,
+}
+
+impl Settings {
+    /// `ANOMALY_WINDOW` books (default 3000, five minutes at 10 a second) and
+    /// `ANOMALY_MADS` (default 5).
+    pub fn from_env() -> Self {
+        Settings {
+            window: config::var("ANOMALY_WINDOW").and_then(|s| s.parse().ok()).filter(|&w| w > 0).unwrap_or(DEFAULT_WINDOW),
+            mads: config::var("ANOMALY_MADS").and_then(|s| s.parse().ok()).filter(|&m: &f64| m > 0.0).unwrap_or(DEFAULT_MADS),
+        }
+    }
+}
+
+/// The last `window` values of one measure.
+#[derive(Debug, Clone)]
+struct Baseline {
+    window: usize,
+    values: VecDeque<f64>,
+}
+
+impl Baseline {
+    fn new(window: usize) -> Self {
+        Baseline { window, values: VecDeque::with_capacity(window) }
+    }
+
+    /// MADs from the median, signed; 0 until the baseline has `MIN_BASELINE` values.
+    /// Where most values are equal, as a spread pinned at one tick, the MAD is 0 and
+    /// the mean absolute deviation stands in for it; with every value equal there is
+    /// no spread to measure against, and the score is 0.
+    fn score(&self, x: f64) -> f64 {
+        if self.values.len() < MIN_BASELINE {
+            return 0.0;
+        }
+        let mut values: Vec<f64> = self.values.iter().copied().collect();
+        let center = median(&mut values);
+        let mut deviations: Vec<f64> = values.iter().map(|v| (v - center).abs()).collect();
+        let mut scale = median(&mut deviations);
+        if scale == 0.0 {
+            scale = deviations.iter().sum::<f64>() / deviations.len() as f64;
+        }
+        if scale == 0.0 {
+            return 0.0;
+        }
+        (x - center) / scale
+    }
+
+    fn push(&mut self, x: f64) {
+        if self.values.len() == self.window {
+            self.values.pop_front();
+        }
+        self.values.push_back(x);
+    }
+}
+
+fn median(values: &mut [f64]) -> f64 {
+    let mid = values.len() / 2;
+    let (_, upper, _) = values.select_nth_unstable_by(mid, f64::total_cmp);
+    *upper
+}
+
+/// A symbol's spread and depth baselines.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    mads: f64,
+    spread: Baseline,
+    depth: Baseline,
+}
+
+impl Detector {
+    pub fn new(settings: Settings) -> Self {
+        Detector { mads: settings.mads, spread: Baseline::new(settings.window), depth: Baseline::new(settings.window) }
+    }
+
+    /// Scores `book` against the books before it, then adds it to the baselines.
+    pub fn flag(&mut self, book: OrderBook) -> OrderBook {
+        let depth = top_depth(&book);
+        let (spread_mads, depth_mads) = (self.spread.score(book.spread), self.depth.score(depth));
+        self.spread.push(book.spread);
+        self.depth.push(depth);
+        book.with_anomalies(spread_mads, depth_mads, self.mads)
+    }
+}
+
+/// Quantity within the narrowest depth bucket of mid, bids and asks together.
+pub fn top_depth(book: &OrderBook) -> f64 {
+    book.bids.first().map_or(0.0, |l| l.qty) + book.asks.first().map_or(0.0, |l| l.qty)
+}
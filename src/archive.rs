@@ -0,0 +1,129 @@
+//! Read/write access to an archive laid out like the bucket, either in S3 or
+//! mirrored in a local directory, for the offline tools.
+
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::layout::{self, KeyParts};
+use crate::manifest::{self, Manifest};
+use crate::{binance, config, sink, Error};
+
+pub enum Archive {
+    S3(Client),
+    Local(PathBuf),
+}
+
+impl Archive {
+    /// A local mirror when `dir` is given, otherwise the main bucket.
+    pub async fn open(dir: Option<String>) -> Result<Self, Error> {
+        Ok(match dir {
+            Some(dir) => Archive::Local(PathBuf::from(dir)),
+            None => Archive::S3(config::s3_client().await?),
+        })
+    }
+
+    /// Keys under `prefix`, sorted.
+    pub async fn list(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let mut keys = match self {
+            Archive::S3(s3) => sink::list_keys(s3, prefix).await?,
+            Archive::Local(root) => {
+                let mut keys = Vec::new();
+                walk(root, &root.join(prefix), &mut keys)?;
+                keys
+            }
+        };
+        keys.sort();
+        Ok(keys)
+    }
+
+    pub async fn get(&self, key: &str) -> Result<Vec<u8>, Error> {
+        match self {
+            Archive::S3(s3) => sink::get(s3, key).await,
+            Archive::Local(root) => Ok(fs::read(root.join(key))?),
+        }
+    }
+
+    pub async fn put(&self, key: &str, body: Vec<u8>) -> Result<(), Error> {
+        match self {
+            Archive::S3(s3) => sink::put(s3, key, body).await.map(|_| ()),
+            Archive::Local(root) => {
+                let path = root.join(key);
+                if let Some(parent) = path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                Ok(fs::write(path, body)?)
+            }
+        }
+    }
+
+    /// Names of the directories directly under `prefix`, sorted.
+    pub async fn dirs(&self, prefix: &str) -> Result<Vec<String>, Error> {
+        let mut dirs = match self {
+            Archive::S3(s3) => sink::list_dirs(s3, prefix).await?,
+            Archive::Local(root) => match fs::read_dir(root.join(prefix)) {
+                Ok(entries) => entries.flatten()
+                    .filter(|e| e.path().is_dir())
+                    .map(|e| e.file_name().to_string_lossy().into_owned())
+                    .collect(),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+                Err(e) => return Err(e.into()),
+            },
+        };
+        dirs.sort();
+        Ok(dirs)
+    }
+
+    /// The newest object of `symbol` under `prefix` as (record id, key), or `None`
+    /// when there is none. Walks the partition directories newest first, falling
+    /// back to older ones only when a newer one holds nothing for the symbol, so it
+    /// lists a handful of directories rather than the whole history. A compacted
+    /// hour gives the file its manifest names with the latest book, and that book's
+    /// receive time as the id.
+    pub async fn latest(&self, prefix: &str, symbol: &str) -> Result<Option<(i64, String)>, Error> {
+        let template = layout::template()?;
+        let parts = KeyParts { prefix, exchange: binance::EXCHANGE, symbol, at: Utc::now() };
+        let levels = template.dir_levels(&parts);
+
+        // depth-first, with the newest candidate of each level on top
+        let mut stack = vec![(String::new(), 0)];
+        while let Some((dir, level)) = stack.pop() {
+            let Some(segment) = levels.get(level) else {
+                let keys = self.list(&dir).await?;
+                if let Some((id, key)) = template.latest(&keys) {
+                    return Ok(Some((id, key.clone())));
+                }
+                // a compacted hour holds only its manifest and the files it names
+                let Some(manifest) = keys.iter().find(|k| k.ends_with(&format!("/{}", manifest::MANIFEST_FILE))) else { continue };
+                let manifest: Manifest = serde_json::from_slice(&self.get(manifest).await?)?;
+                match manifest.files.into_iter().filter(|f| f.records > 0).max_by_key(|f| f.last_ms) {
+                    Some(file) => return Ok(Some((file.last_ms, file.key))),
+                    None => continue,
+                }
+            };
+            match segment {
+                Some(fixed) => stack.push((format!("{}{}/", dir, fixed), level + 1)),
+                None => stack.extend(self.dirs(&dir).await?.into_iter().map(|d| (format!("{}{}/", dir, d), level + 1))),
+            }
+        }
+        Ok(None)
+    }
+}
+
+fn walk(root: &Path, dir: &Path, keys: &mut Vec<String>) -> Result<(), Error> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            walk(root, &path, keys)?;
+        } else if let Ok(rel) = path.strip_prefix(root) {
+            keys.push(rel.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,86 @@
+//! API credentials from Secrets Manager and request signing, for feeds that need an
+//! authenticated session: Binance user data streams, Coinbase's signed subscriptions.
+//!
+//! `API_SECRET_ID` names a secret holding JSON such as
+//! `{"api_key": "...", "api_secret": "...", "passphrase": "..."}` (`passphrase` only
+//! for Coinbase). It is fetched once per process.
+
+use aws_config::BehaviorVersion;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::Sha256;
+use std::fmt;
+use tokio::sync::OnceCell;
+
+use crate::{config, Error};
+
+#[derive(Deserialize, Clone, PartialEq)]
+pub struct Credentials {
+    pub api_key: String,
+    pub api_secret: String,
+    #[serde(default)]
+    pub passphrase: Option<String>,
+}
+
+/// Keeps the secret out of logs.
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Credentials").field("api_key", &self.api_key).finish_non_exhaustive()
+    }
+}
+
+impl Credentials {
+    pub fn from_json(secret: &str) -> Result<Self, Error> {
+        let creds: Credentials = serde_json::from_str(secret)
+            .map_err(|e| Error::Config(format!("api credentials secret: {}", e)))?;
+        if creds.api_key.is_empty() || creds.api_secret.is_empty() {
+            return Err(Error::Config("api credentials secret has an empty key or secret".into()));
+        }
+        Ok(creds)
+    }
+
+    /// Binance `SIGNED` endpoints: hex HMAC-SHA256 of the query string.
+    pub fn sign_binance(&self, query: &str) -> String {
+        hex::encode(hmac(self.api_secret.as_bytes(), query.as_bytes()))
+    }
+
+    /// The authenticated `subscribe` message for Coinbase Exchange channels, signed
+    /// over `timestamp` (epoch seconds) with the base64 secret.
+    pub fn coinbase_subscribe(&self, product_ids: &[&str], channels: &[&str], timestamp: i64) -> Result<String, Error> {
+        let secret = BASE64.decode(&self.api_secret)
+            .map_err(|e| Error::Config(format!("coinbase api secret is not base64: {}", e)))?;
+        let signature = BASE64.encode(hmac(&secret, format!("{}GET/users/self/verify", timestamp).as_bytes()));
+        Ok(serde_json::json!({
+            "type": "subscribe",
+            "product_ids": product_ids,
+            "channels": channels,
+            "key": self.api_key,
+            "passphrase": self.passphrase.as_deref().unwrap_or_default(),
+            "timestamp": timestamp.to_string(),
+            "signature": signature,
+        }).to_string())
+    }
+}
+
+fn hmac(key: &[u8], payload: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("hmac accepts any key length");
+    mac.update(payload);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// The credentials in `API_SECRET_ID`, or `None` when no secret is configured.
+pub async fn credentials() -> Result<Option<&'static Credentials>, Error> {
+    static CREDENTIALS: OnceCell<Option<Credentials>> = OnceCell::const_new();
+
+    CREDENTIALS.get_or_try_init(|| async {
+        let Some(secret_id) = config::var("API_SECRET_ID").filter(|s| !s.is_empty()) else { return Ok(None) };
+        let client = aws_sdk_secretsmanager::Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await);
+        let secret = client.get_secret_value().secret_id(&secret_id).send().await
+            .map_err(|e| Error::Secrets(Box::new(e.into())))?;
+        let json = secret.secret_string()
+            .ok_or_else(|| Error::Config(format!("secret {} has no string value", secret_id)))?;
+        Credentials::from_json(json).map(Some)
+    }).await.map(Option::as_ref)
+}
@@ -0,0 +1,177 @@
+//! Per-minute bars built from the snapshot stream, written under `aggregates/`
+//! alongside the snapshots or in place of them. A minute two runs share gets a
+//! partial bar from each, which `merge` combines when they are read.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, OrderBook};
+
+pub const BARS_PREFIX: &str = "aggregates";
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Whether `AGGREGATE` asks for bars: `bars` writes them next to the snapshots,
+/// `bars-only` writes them instead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    #[default]
+    Off,
+    Alongside,
+    Only,
+}
+
+impl Mode {
+    pub fn from_env() -> Self {
+        match config::var("AGGREGATE").as_deref() {
+            Some("bars") => Mode::Alongside,
+            Some("bars-only") => Mode::Only,
+            _ => Mode::Off,
+        }
+    }
+}
+
+/// One minute of snapshots. Depth is the cumulative quantity at the widest depth
+/// bucket (1% from mid by default) on each side.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Bar {
+    pub start_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub snapshots: i64,
+    pub open_mid: f64,
+    pub high_mid: f64,
+    pub low_mid: f64,
+    pub close_mid: f64,
+    pub mean_spread: f64,
+    pub max_spread: f64,
+    pub mean_imbalance: f64,
+    pub mean_bid_depth: f64,
+    pub min_bid_depth: f64,
+    pub mean_ask_depth: f64,
+    pub min_ask_depth: f64,
+    /// Partition time of the bar's first and last book. A run covering only part of
+    /// the minute writes a partial bar keyed by `first_ms`; `merge` puts them together.
+    #[serde(default)]
+    pub first_ms: i64,
+    #[serde(default)]
+    pub last_ms: i64,
+}
+
+impl Bar {
+    fn open(start_ms: i64, book: &OrderBook) -> Self {
+        let (bid_depth, ask_depth) = depth(book);
+        Bar {
+            start_ms,
+            exchange: book.exchange.clone(),
+            symbol: book.symbol.clone(),
+            snapshots: 1,
+            open_mid: book.mid_price,
+            high_mid: book.mid_price,
+            low_mid: book.mid_price,
+            close_mid: book.mid_price,
+            mean_spread: book.spread,
+            max_spread: book.spread,
+            mean_imbalance: book.imbalance_ratio,
+            mean_bid_depth: bid_depth,
+            min_bid_depth: bid_depth,
+            mean_ask_depth: ask_depth,
+            min_ask_depth: ask_depth,
+            first_ms: book.partition_time_ms(),
+            last_ms: book.partition_time_ms(),
+        }
+    }
+
+    fn add(&mut self, book: &OrderBook) {
+        let (bid_depth, ask_depth) = depth(book);
+        self.snapshots += 1;
+        let n = self.snapshots as f64;
+        let mean = |mean: f64, x: f64| mean + (x - mean) / n;
+
+        self.high_mid = self.high_mid.max(book.mid_price);
+        self.low_mid = self.low_mid.min(book.mid_price);
+        self.close_mid = book.mid_price;
+        self.mean_spread = mean(self.mean_spread, book.spread);
+        self.max_spread = self.max_spread.max(book.spread);
+        self.mean_imbalance = mean(self.mean_imbalance, book.imbalance_ratio);
+        self.mean_bid_depth = mean(self.mean_bid_depth, bid_depth);
+        self.min_bid_depth = self.min_bid_depth.min(bid_depth);
+        self.mean_ask_depth = mean(self.mean_ask_depth, ask_depth);
+        self.min_ask_depth = self.min_ask_depth.min(ask_depth);
+        self.last_ms = book.partition_time_ms();
+    }
+
+    /// Folds in `other`, a partial bar of the same minute from another run, as if
+    /// its books had been added here.
+    fn combine(&mut self, other: &Bar) {
+        let (n, m) = (self.snapshots as f64, other.snapshots as f64);
+        let mean = |a: f64, b: f64| (a * n + b * m) / (n + m);
+
+        if other.first_ms < self.first_ms {
+            (self.first_ms, self.open_mid) = (other.first_ms, other.open_mid);
+        }
+        if other.last_ms > self.last_ms {
+            (self.last_ms, self.close_mid) = (other.last_ms, other.close_mid);
+        }
+        self.high_mid = self.high_mid.max(other.high_mid);
+        self.low_mid = self.low_mid.min(other.low_mid);
+        self.mean_spread = mean(self.mean_spread, other.mean_spread);
+        self.max_spread = self.max_spread.max(other.max_spread);
+        self.mean_imbalance = mean(self.mean_imbalance, other.mean_imbalance);
+        self.mean_bid_depth = mean(self.mean_bid_depth, other.mean_bid_depth);
+        self.min_bid_depth = self.min_bid_depth.min(other.min_bid_depth);
+        self.mean_ask_depth = mean(self.mean_ask_depth, other.mean_ask_depth);
+        self.min_ask_depth = self.min_ask_depth.min(other.min_ask_depth);
+        self.snapshots += other.snapshots;
+    }
+}
+
+/// One bar per symbol and minute from `parts`, the partial bars overlapping or
+/// successive runs wrote for the same minutes, oldest first.
+pub fn merge(mut parts: Vec<Bar>) -> Vec<Bar> {
+    parts.sort_by(|a, b| (&a.symbol, a.start_ms, a.first_ms).cmp(&(&b.symbol, b.start_ms, b.first_ms)));
+    let mut bars: Vec<Bar> = Vec::with_capacity(parts.len());
+    for part in parts {
+        match bars.last_mut() {
+            Some(bar) if bar.symbol == part.symbol && bar.start_ms == part.start_ms => bar.combine(&part),
+            _ => bars.push(part),
+        }
+    }
+    bars.sort_by_key(|b| b.start_ms);
+    bars
+}
+
+fn depth(book: &OrderBook) -> (f64, f64) {
+    let widest = |levels: &[crate::book::Level]| levels.last().map_or(0.0, |l| l.qty);
+    (widest(&book.bids), widest(&book.asks))
+}
+
+/// Folds snapshots into the bar for their minute (by `partition_time_ms`).
+#[derive(Debug, Default)]
+pub struct BarBuilder {
+    current: Option<Bar>,
+}
+
+impl BarBuilder {
+    /// Adds a snapshot, returning the previous minute's bar once the minute rolls over.
+    pub fn push(&mut self, book: &OrderBook) -> Option<Bar> {
+        let at = book.partition_time_ms();
+        let start_ms = at - at.rem_euclid(MINUTE_MS);
+        match self.current.as_mut() {
+            Some(bar) if bar.start_ms == start_ms => {
+                bar.add(book);
+                None
+            }
+            _ => self.current.replace(Bar::open(start_ms, book)),
+        }
+    }
+
+    /// Takes the bar in progress once its minute is over.
+    pub fn flush_ended(&mut self, now_ms: i64) -> Option<Bar> {
+        self.current.take_if(|bar| now_ms - bar.start_ms >= MINUTE_MS)
+    }
+
+    /// Takes the bar in progress, if any.
+    pub fn flush(&mut self) -> Option<Bar> {
+        self.current.take()
+    }
+}
@@ -0,0 +1,62 @@
+//! Books batched per time window into one Avro object, for `BATCH_SECS`: a single
+//! writer takes every book of the window and is only finished when the window is
+//! over, rather than an encoder, a container and a put per book.
+
+use apache_avro::{Schema, Writer};
+use std::time::Duration;
+
+use crate::{config, sink, Error, OrderBook};
+
+/// `BATCH_SECS` as a window, or None to store one object per book.
+pub fn window() -> Option<Duration> {
+    config::var("BATCH_SECS").and_then(|s| s.parse().ok()).filter(|&s| s > 0).map(Duration::from_secs)
+}
+
+/// A finished window: its books and the object holding them.
+pub struct Batch {
+    pub start_ms: i64,
+    pub books: Vec<OrderBook>,
+    pub body: Vec<u8>,
+}
+
+/// Appends books to the current window's writer.
+pub struct BookBatcher {
+    window_ms: i64,
+    start_ms: i64,
+    writer: Option<Writer<'static, Vec<u8>>>,
+    books: Vec<OrderBook>,
+}
+
+impl BookBatcher {
+    pub fn new(window: Duration) -> Self {
+        BookBatcher { window_ms: (window.as_millis() as i64).max(1), start_ms: 0, writer: None, books: Vec::new() }
+    }
+
+    /// Appends `book` in `schema`, returning the previous window once the book's
+    /// partition time rolls over into a new one.
+    pub fn push(&mut self, schema: &'static Schema, book: OrderBook) -> Result<Option<Batch>, Error> {
+        let at_ms = book.partition_time_ms();
+        let start_ms = at_ms - at_ms.rem_euclid(self.window_ms);
+        let done = if start_ms != self.start_ms { self.flush()? } else { None };
+        self.start_ms = start_ms;
+        sink::append(self.writer.get_or_insert_with(|| Writer::new(schema, Vec::new())), schema, &book)?;
+        self.books.push(book);
+        Ok(done)
+    }
+
+    /// `flush` once the current window is over, so a quiet stream doesn't hold its
+    /// last books until the next one arrives.
+    pub fn flush_ended(&mut self, now_ms: i64) -> Result<Option<Batch>, Error> {
+        if now_ms - self.start_ms < self.window_ms {
+            return Ok(None);
+        }
+        self.flush()
+    }
+
+    /// Finishes the writer and takes whatever is batched.
+    pub fn flush(&mut self) -> Result<Option<Batch>, Error> {
+        let Some(writer) = self.writer.take() else { return Ok(None) };
+        let body = writer.into_inner()?;
+        Ok(Some(Batch { start_ms: self.start_ms, books: std::mem::take(&mut self.books), body }))
+    }
+}
@@ -0,0 +1,30 @@
+use orderbook::archive::Archive;
+use orderbook::{cli, columnar, lookup, sink};
+
+const USAGE: &str = "usage: avro2parquet --from <time> --to <time> [--symbol BTCUSDT] [--local <dir>] [--out <dir>]
+
+Rewrites orderbook/ Avro objects as one Parquet file per hour under parquet/orderbook/,
+keeping the year/month/day/hour partitions. Reads and writes S3 unless --local/--out are given.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (from, to) = cli::time_range(USAGE);
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into());
+    let source = Archive::open(cli::arg("local")).await?;
+    let target = match cli::arg("out") {
+        Some(dir) => Archive::open(Some(dir)).await?,
+        None => Archive::open(cli::arg("local")).await?,
+    };
+
+    for hour in cli::hours(from, to) {
+        let books = lookup::hour_books(&source, &symbol, hour).await?;
+        if books.is_empty() {
+            continue;
+        }
+
+        let key = format!("{}/part-0.parquet", sink::partition_dir("parquet/orderbook", hour));
+        target.put(&key, columnar::write_parquet(&books)?).await?;
+        println!("{} records -> {}", books.len(), key);
+    }
+    Ok(())
+}
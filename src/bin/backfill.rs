@@ -0,0 +1,37 @@
+use chrono::Utc;
+use orderbook::archive::Archive;
+use orderbook::{binance, cli, rest, schema, sink, trades, OrderBook};
+
+const USAGE: &str = "usage: backfill --from <time> --to <time> [--symbol BTCUSDT] [--depth] [--local <dir>]
+
+Downloads historical aggTrades for the window into trades/ (one object per hour).
+Binance REST has no historical depth, so --depth only adds a snapshot of the current book.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (from, to) = cli::time_range(USAGE);
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into()).to_uppercase();
+    let archive = Archive::open(cli::arg("local")).await?;
+
+    for (key, trades) in trades::backfill(&archive, &symbol, from, to).await? {
+        println!("{} trades -> {}", trades, key);
+    }
+
+    if cli::flag("depth") {
+        let venue = binance::venue()?;
+        let body = rest::get(&format!("{}?symbol={}&limit=1000", venue.rest_url("depth"), symbol), venue.weight("depth", 1000))
+            .await?
+            .bytes()
+            .await?;
+        let depth: binance::PartialDepth = serde_json::from_slice(&body)?;
+        let (bids, asks) = depth.levels();
+        let now = Utc::now();
+        if let Some(book) = OrderBook::from_levels(now.timestamp_millis(), &bids, &asks) {
+            let book = book.with_source(binance::EXCHANGE, &symbol);
+            let key = sink::partition_key("orderbook", &symbol, sink::at_ms(book.partition_time_ms()), book.timestamp_ms)?;
+            archive.put(&key, sink::encode(schema::ORDERBOOK, &[book])?).await?;
+            println!("depth snapshot -> {}", key);
+        }
+    }
+    Ok(())
+}
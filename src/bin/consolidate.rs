@@ -0,0 +1,83 @@
+use chrono::Duration;
+use orderbook::archive::Archive;
+use orderbook::consolidate::{self, CONSOLIDATED_PREFIX};
+use orderbook::spread::{self, Fees, SPREADS_PREFIX};
+use orderbook::{cli, lookup, schema, sink, OrderBook};
+use std::collections::BTreeMap;
+
+const USAGE: &str = "usage: consolidate --from <time> --to <time> --venue <name>=<dir|s3> [--venue ...] [--symbol BTCUSDT] [--window-ms 1000] [--max-age-ms 5000] [--sizes 1] [--fee-bps <name>=<bps> ...] [--out <dir>]
+
+Joins the books each venue's archive holds for the symbol on a shared clock, every
+--window-ms, and writes the merged best bid/offer and depth with each venue's
+contribution to consolidated/ (one object per hour). A venue's archive is a local
+mirror of its bucket, or s3 for the main bucket. Books older than --max-age-ms at an
+instant are left out. With two venues or more, every pair's mid difference and the
+edge of buying on one and selling on the other at each of --sizes, after taker fees
+(default 10 bps), also go to spreads/. Output goes to the main bucket, or --out.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (from, to) = cli::time_range(USAGE);
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into()).to_uppercase();
+    let number = |name, default| cli::arg(name).and_then(|v| v.parse().ok()).unwrap_or(default);
+    let (window_ms, max_age_ms) = (number("window-ms", 1000), number("max-age-ms", 5000));
+
+    let mut venues = Vec::new();
+    for venue in cli::args("venue") {
+        let Some((name, source)) = venue.split_once('=') else {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        };
+        venues.push((name.to_string(), Archive::open((source != "s3").then(|| source.to_string())).await?));
+    }
+    if venues.is_empty() {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    }
+    let out = Archive::open(cli::arg("out")).await?;
+    let sizes: Vec<f64> = match cli::arg("sizes") {
+        Some(list) => list.split(',').map(|s| s.trim().parse()).collect::<Result<_, _>>()?,
+        None => vec![1.0],
+    };
+    let mut fees = Fees::default();
+    for fee in cli::args("fee-bps") {
+        let (name, bps) = fee.split_once('=').ok_or("--fee-bps takes <name>=<bps>")?;
+        fees.venues.insert(name.to_string(), bps.parse()?);
+    }
+
+    // each venue's last book of the previous hour, so an hour's first instants have one
+    let mut carried: BTreeMap<String, OrderBook> = BTreeMap::new();
+    for hour in cli::hours(from, to) {
+        let (start, end) = (hour.max(from).timestamp_millis(), (hour + Duration::hours(1)).min(to).timestamp_millis());
+        let mut streams = BTreeMap::new();
+        for (name, archive) in &venues {
+            let mut books: Vec<OrderBook> = carried.remove(name).into_iter().collect();
+            books.extend(lookup::hour_books(archive, &symbol, hour).await?.into_iter()
+                .filter(|b| (start..end).contains(&b.partition_time_ms())));
+            books.sort_by_key(OrderBook::partition_time_ms);
+            streams.insert(name.clone(), books);
+        }
+
+        let aligned: Vec<_> = consolidate::align(&streams, window_ms, max_age_ms).into_iter()
+            .filter(|(at, _)| (start..end).contains(at))
+            .collect();
+        let records: Vec<_> = aligned.iter().filter_map(|(at, books)| consolidate::consolidate(&symbol, *at, books)).collect();
+        let spreads: Vec<_> = aligned.iter().flat_map(|(at, books)| spread::spreads(&symbol, *at, books, &sizes, &fees)).collect();
+        if let Some(first) = spreads.first() {
+            let key = sink::partition_key(SPREADS_PREFIX, &symbol, hour, first.timestamp_ms)?;
+            out.put(&key, sink::encode(schema::VENUE_SPREAD, &spreads)?).await?;
+            println!("{} venue spreads -> {}", spreads.len(), key);
+        }
+        for (name, books) in streams {
+            if let Some(last) = books.into_iter().last() {
+                carried.insert(name, last);
+            }
+        }
+        let Some(first) = records.first() else { continue };
+
+        let key = sink::partition_key(CONSOLIDATED_PREFIX, &symbol, hour, first.timestamp_ms)?;
+        out.put(&key, sink::encode(schema::CONSOLIDATED, &records)?).await?;
+        println!("{} consolidated records -> {}", records.len(), key);
+    }
+    Ok(())
+}
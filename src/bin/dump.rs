@@ -0,0 +1,107 @@
+use chrono::Utc;
+use orderbook::archive::Archive;
+use orderbook::{cli, fulldepth, gaps, lookup, migrate, OrderBook};
+
+const USAGE: &str = "usage: dump (--prefix <prefix> | --from <time> --to <time> [--gaps | --full-depth] | --latest) [--symbol BTCUSDT] [--format json|csv] [--local <dir>]
+
+Prints every OrderBook record under a key prefix or in a time range, one per line.
+--latest prints the newest object's key and how old it is instead, --gaps with a
+time range prints the recorded gaps overlapping it, and --full-depth prints the full
+books persisted in it, rebuilt from keyframes and changes, as JSON.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let archive = Archive::open(cli::arg("local")).await?;
+    let csv = cli::arg("format").as_deref() == Some("csv");
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into());
+
+    if cli::flag("latest") {
+        match archive.latest("orderbook", &symbol).await? {
+            // the key's id may be a lastUpdateId, so the age comes from the books
+            Some((_, key)) => {
+                let ms = migrate::read_orderbooks(&archive.get(&key).await?)?.iter().map(|b| b.timestamp_ms).max().unwrap_or_default();
+                println!("{} ({}s old)", key, (Utc::now().timestamp_millis() - ms) / 1000);
+            }
+            None => println!("nothing stored for {}", symbol),
+        }
+        return Ok(());
+    }
+    if cli::flag("gaps") {
+        let (from, to) = cli::time_range(USAGE);
+        for gap in gaps::manifest(&archive, &symbol, from, to).await? {
+            println!("{}", serde_json::to_string(&gap)?);
+        }
+        return Ok(());
+    }
+    if cli::flag("full-depth") {
+        let (from, to) = cli::time_range(USAGE);
+        for book in fulldepth::read_books(&archive, &symbol, from, to).await? {
+            println!("{}", serde_json::to_string(&book)?);
+        }
+        return Ok(());
+    }
+
+    let books: Vec<OrderBook> = match cli::arg("prefix") {
+        Some(prefix) => {
+            let mut books = Vec::new();
+            for key in archive.list(&prefix).await?.iter().filter(|k| k.ends_with(".avro")) {
+                books.extend(migrate::read_orderbooks(&archive.get(key).await?)?);
+            }
+            books
+        }
+        None => {
+            let (from, to) = cli::time_range(USAGE);
+            let mut books = Vec::new();
+            for hour in cli::hours(from, to) {
+                books.extend(lookup::hour_books(&archive, &symbol, hour).await?.into_iter()
+                    .filter(|b| (from.timestamp_millis()..to.timestamp_millis()).contains(&b.timestamp_ms)));
+            }
+            books
+        }
+    };
+
+    if csv {
+        println!("{}", csv_header(books.first()));
+    }
+    for book in &books {
+        if csv {
+            println!("{}", csv_row(book));
+        } else {
+            println!("{}", serde_json::to_string(book)?);
+        }
+    }
+    Ok(())
+}
+
+fn csv_header(first: Option<&OrderBook>) -> String {
+    let mut cols: Vec<String> = ["timestamp_ms", "event_time_ms", "last_update_id", "exchange", "symbol", "mid_price", "spread", "imbalance_ratio", "schema_version"]
+        .iter().map(|c| c.to_string()).collect();
+    if let Some(book) = first {
+        for (side, levels) in [("bid", &book.bids), ("ask", &book.asks)] {
+            for i in 0..levels.len() {
+                cols.push(format!("{}_price_{}", side, i));
+                cols.push(format!("{}_qty_{}", side, i));
+            }
+        }
+    }
+    cols.join(",")
+}
+
+fn csv_row(book: &OrderBook) -> String {
+    let mut cols = vec![
+        book.timestamp_ms.to_string(),
+        book.event_time_ms.to_string(),
+        book.last_update_id.to_string(),
+        book.exchange.clone(),
+        book.symbol.clone(),
+        book.mid_price.to_string(),
+        book.spread.to_string(),
+        book.imbalance_ratio.to_string(),
+        book.schema_version.to_string(),
+    ];
+    for level in book.bids.iter().chain(&book.asks) {
+        cols.push(level.price.to_string());
+        cols.push(level.qty.to_string());
+    }
+    cols.join(",")
+}
@@ -0,0 +1,44 @@
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use orderbook::archive::Archive;
+use orderbook::export::{self, Field};
+use orderbook::{cli, lookup};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+const USAGE: &str = "usage: export --from <time> --to <time> [--symbol BTCUSDT] [--fields timestamp,mid,spread,imbalance,depth] [--out books.csv[.gz]] [--local <dir>]
+
+Writes the stored books in a time range as CSV, one row per book with the chosen
+fields: timestamp, event_time, mid, spread, spread_bps, imbalance, depth (a bid and an
+ask column per depth bucket), vpin and mid_return. --out ending in .gz is gzipped;
+without --out the CSV goes to stdout.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (from, to) = cli::time_range(USAGE);
+    let archive = Archive::open(cli::arg("local")).await?;
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into());
+    let fields = Field::parse_list(&cli::arg("fields").unwrap_or_else(|| export::DEFAULT_FIELDS.into()))?;
+
+    let mut books = Vec::new();
+    for hour in cli::hours(from, to) {
+        books.extend(lookup::hour_books(&archive, &symbol, hour).await?.into_iter()
+            .filter(|b| (from.timestamp_millis()..to.timestamp_millis()).contains(&b.timestamp_ms)));
+    }
+
+    match cli::arg("out") {
+        Some(path) if path.ends_with(".gz") => {
+            let mut gz = GzEncoder::new(BufWriter::new(File::create(path)?), Compression::default());
+            export::write(&mut gz, &fields, &books)?;
+            gz.finish()?.flush()?;
+        }
+        Some(path) => {
+            let mut file = BufWriter::new(File::create(path)?);
+            export::write(&mut file, &fields, &books)?;
+            file.flush()?;
+        }
+        None => export::write(&mut std::io::stdout().lock(), &fields, &books)?,
+    }
+    eprintln!("{} books", books.len());
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use chrono::Utc;
+use futures_util::StreamExt;
+use orderbook::pipeline::{self, Outcome};
+use orderbook::{binance, cli, proxy, schema, OrderBook};
+use ratatui::crossterm::event::{self, Event, KeyCode};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Gauge, Paragraph, Row, Sparkline, Table};
+use ratatui::{DefaultTerminal, Frame};
+use std::collections::VecDeque;
+use tokio::time::{interval, Duration};
+
+/// Per-second book counts kept for the throughput sparkline.
+const HISTORY_SECS: usize = 120;
+const REFRESH: Duration = Duration::from_millis(200);
+
+/// What has come in so far and the latest book.
+#[derive(Default)]
+struct Monitor {
+    source: String,
+    book: Option<OrderBook>,
+    received: u64,
+    books: u64,
+    skipped: u64,
+    malformed: u64,
+    /// Books per whole second, newest last.
+    per_second: VecDeque<u64>,
+    second: i64,
+}
+
+impl Monitor {
+    fn on_text(&mut self, text: &str, relay: bool) {
+        self.received += 1;
+        let now_ms = Utc::now().timestamp_millis();
+        // the relay sends books already through the pipeline and stored
+        let outcome = if relay {
+            match serde_json::from_str::<OrderBook>(text) {
+                Ok(book) => Outcome::Book(Box::new(book)),
+                Err(_) => Outcome::Skipped,
+            }
+        } else {
+            pipeline::process(text, now_ms, schema::writer_version())
+        };
+        match outcome {
+            Outcome::Book(book) => {
+                self.books += 1;
+                self.tick(now_ms);
+                if let Some(count) = self.per_second.back_mut() {
+                    *count += 1;
+                }
+                self.book = Some(*book);
+            }
+            Outcome::Skipped => self.skipped += 1,
+            Outcome::Malformed(_) | Outcome::Invalid(_) => self.malformed += 1,
+        }
+    }
+
+    /// Moves the throughput history up to `now_ms`'s second.
+    fn tick(&mut self, now_ms: i64) {
+        let second = now_ms.div_euclid(1000);
+        let elapsed = if self.second == 0 { 1 } else { (second - self.second).clamp(0, HISTORY_SECS as i64) };
+        for _ in 0..elapsed {
+            self.per_second.push_back(0);
+        }
+        while self.per_second.len() > HISTORY_SECS {
+            self.per_second.pop_front();
+        }
+        self.second = second;
+    }
+
+    /// Books per second over the last ten whole seconds.
+    fn throughput(&self) -> f64 {
+        let complete: Vec<u64> = self.per_second.iter().rev().skip(1).take(10).copied().collect();
+        if complete.is_empty() {
+            return 0.0;
+        }
+        complete.iter().sum::<u64>() as f64 / complete.len() as f64
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, imbalance, body] = Layout::vertical([Constraint::Length(4), Constraint::Length(3), Constraint::Min(8)]).areas(frame.area());
+        let [ladder, flow] = Layout::horizontal([Constraint::Percentage(55), Constraint::Percentage(45)]).areas(body);
+
+        let stats = match &self.book {
+            Some(b) => vec![
+                Line::from(format!(
+                    "{}  mid {:.2}  spread {:.2} ({:.2} bps)  lastUpdateId {}",
+                    b.symbol, b.mid_price, b.spread, b.spread / b.mid_price * 10_000.0, b.last_update_id
+                )),
+                Line::from(format!(
+                    "books {}  received {}  skipped {}  malformed {}  {:.1} books/s",
+                    self.books, self.received, self.skipped, self.malformed, self.throughput()
+                )),
+            ],
+            None => vec![Line::from(format!("waiting for books ({} messages received)", self.received))],
+        };
+        frame.render_widget(Paragraph::new(stats).block(Block::bordered().title(format!(" {} (q to quit) ", self.source))), header);
+
+        let ratio = self.book.as_ref().map_or(0.0, |b| b.imbalance_ratio);
+        frame.render_widget(
+            Gauge::default()
+                .block(Block::bordered().title(" imbalance (bid − ask) / (bid + ask), top 5 levels "))
+                .gauge_style(Style::new().fg(if ratio >= 0.0 { Color::Green } else { Color::Red }))
+                .ratio((ratio + 1.0) / 2.0)
+                .label(format!("{:+.3}", ratio)),
+            imbalance,
+        );
+
+        let rows = self.book.iter().flat_map(|b| {
+            let asks = b.asks.iter().rev().map(|l| Row::new([format!("{:.2}", l.price), format!("{:.4}", l.qty)]).red());
+            let bids = b.bids.iter().map(|l| Row::new([format!("{:.2}", l.price), format!("{:.4}", l.qty)]).green());
+            asks.chain(bids).collect::<Vec<_>>()
+        });
+        let table = Table::new(rows, [Constraint::Length(14), Constraint::Length(14)])
+            .header(Row::new(["price", "qty"]).bold())
+            .block(Block::bordered().title(" ladder, asks over bids "));
+        frame.render_widget(table, ladder);
+
+        let counts: Vec<u64> = self.per_second.iter().copied().collect();
+        let visible = counts.len().saturating_sub(flow.width.saturating_sub(2) as usize);
+        frame.render_widget(
+            Sparkline::default()
+                .block(Block::bordered().title(format!(" books/s, last {}s ", HISTORY_SECS)))
+                .data(&counts[visible..])
+                .style(Style::new().fg(Color::Cyan)),
+            flow,
+        );
+    }
+}
+
+/// Watches `--symbol` (default btcusdt) straight from the venue through the
+/// pipeline, or with `--relay ws://host:port` the books a running service stores.
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "btcusdt".into()).to_lowercase();
+    let relay = cli::arg("relay");
+    let url = match &relay {
+        Some(relay) => format!("{}/?symbols={}", relay.trim_end_matches('/'), symbol),
+        None => format!("{}/{}@depth20@100ms", binance::venue()?.ws, symbol),
+    };
+    let ws = proxy::connect(&url).await?;
+    let (_, read) = ws.split();
+
+    let mut terminal = ratatui::init();
+    let result = run(&mut terminal, read, Monitor { source: url, ..Default::default() }, relay.is_some()).await;
+    ratatui::restore();
+    result
+}
+
+async fn run(
+    terminal: &mut DefaultTerminal,
+    mut read: impl StreamExt<Item = Result<tokio_tungstenite::tungstenite::Message, tokio_tungstenite::tungstenite::Error>> + Unpin,
+    mut monitor: Monitor,
+    relay: bool,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut refresh = interval(REFRESH);
+    loop {
+        tokio::select! {
+            msg = read.next() => match msg {
+                Some(Ok(msg)) if msg.is_text() => monitor.on_text(msg.to_text()?, relay),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+                None => return Err("connection closed".into()),
+            },
+            _ = refresh.tick() => {
+                monitor.tick(Utc::now().timestamp_millis());
+                terminal.draw(|frame| monitor.draw(frame))?;
+                while event::poll(Duration::ZERO)? {
+                    if let Event::Key(key) = event::read()? {
+                        if matches!(key.code, KeyCode::Char('q') | KeyCode::Esc) {
+                            return Ok(());
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
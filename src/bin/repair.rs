@@ -0,0 +1,29 @@
+use orderbook::archive::Archive;
+use orderbook::verify::Report;
+use orderbook::{cli, repair, sink};
+
+const USAGE: &str = "usage: repair --report <report.json> [--local <dir>] [--trades] [--dry-run]
+
+Heals what a verify --json report found. Every hole gets gap markers in the hours it
+covers (Binance has no depth history to refill it with), and with --trades the
+aggTrades Binance still has for it. Then each hour of the report's range that holds
+books gets its manifest rewritten. --dry-run prints what would be written.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let Some(path) = cli::arg("report") else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+    let report: Report = serde_json::from_slice(&std::fs::read(path)?)?;
+    let archive = Archive::open(cli::arg("local")).await?;
+
+    let repaired = repair::repair(&archive, &report, cli::flag("trades"), cli::flag("dry-run")).await?;
+    for gap in &repaired.gaps {
+        println!("gap {} to {} ({}s)", sink::at_ms(gap.from_ms), sink::at_ms(gap.to_ms), gap.duration_ms() / 1000);
+    }
+    for key in repaired.trades.iter().chain(&repaired.manifests) {
+        println!("-> {}", key);
+    }
+    Ok(())
+}
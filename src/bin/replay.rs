@@ -0,0 +1,40 @@
+use orderbook::archive::Archive;
+use orderbook::{cli, raw, replay, schema, sink};
+
+const USAGE: &str = "usage: replay (--input <file> | --key <s3 key>) [--out <file.avro>] [--schema-version <n>]
+
+Feeds recorded websocket payloads (JSON lines of {received_ms, payload}, optionally
+gzipped like the raw/ archive) through the
+production parse/normalize path. Prints records as JSON lines, or writes a byte-for-byte
+reproducible Avro file with --out.";
+
+/// Fixed sync marker so replayed Avro output is identical across runs.
+const REPLAY_MARKER: [u8; 16] = *b"orderbook-replay";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let bytes = match (cli::arg("input"), cli::arg("key")) {
+        (Some(path), _) => std::fs::read(path)?,
+        (None, Some(key)) => Archive::open(None).await?.get(&key).await?,
+        _ => {
+            eprintln!("{}", USAGE);
+            std::process::exit(2);
+        }
+    };
+    let version = cli::arg("schema-version").and_then(|v| v.parse().ok()).unwrap_or(schema::ORDERBOOK_VERSION);
+    let schema = schema::orderbook(version).ok_or("unknown schema version")?;
+
+    let text = raw::decode_recording(&bytes)?;
+    let result = replay::replay(&replay::parse_recording(&text), version);
+    eprintln!("{} records, {} skipped, {} malformed", result.books.len(), result.skipped, result.malformed);
+
+    match cli::arg("out") {
+        Some(out) => std::fs::write(out, sink::encode_with_marker(schema, &result.books, REPLAY_MARKER)?)?,
+        None => {
+            for book in &result.books {
+                println!("{}", serde_json::to_string(book)?);
+            }
+        }
+    }
+    Ok(())
+}
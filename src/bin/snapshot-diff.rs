@@ -0,0 +1,48 @@
+use orderbook::archive::Archive;
+use orderbook::{cli, diff, lookup, migrate, OrderBook};
+
+const USAGE: &str = "usage: snapshot-diff --before <file|time> --after <file|time> [--symbol BTCUSDT] [--local <dir>]
+
+Prints every level and feature that differs between two books, with the change for
+numbers. Each side is an Avro file, whose first book is used (--index picks another),
+or a time, for the symbol's stored book nearest it.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (Some(before), Some(after)) = (cli::arg("before"), cli::arg("after")) else {
+        eprintln!("{}", USAGE);
+        std::process::exit(2);
+    };
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into());
+    let index: usize = cli::arg("index").and_then(|i| i.parse().ok()).unwrap_or(0);
+
+    let mut archive = None;
+    let mut books = Vec::new();
+    for side in [&before, &after] {
+        let book = if std::path::Path::new(side).is_file() {
+            migrate::read_orderbooks(&std::fs::read(side)?)?.into_iter().nth(index)
+                .ok_or_else(|| format!("{} has no book {}", side, index))?
+        } else {
+            let at = cli::parse_time(side).ok_or_else(|| format!("{} is neither a file nor a time\n\n{}", side, USAGE))?;
+            if archive.is_none() {
+                archive = Some(Archive::open(cli::arg("local")).await?);
+            }
+            let archive = archive.as_ref().expect("opened above");
+            lookup::nearest_book(archive, &symbol, at.timestamp_millis()).await?
+                .ok_or_else(|| format!("no stored {} book near {}", symbol, side))?
+        };
+        books.push(book);
+    }
+
+    let (before, after): (&OrderBook, &OrderBook) = (&books[0], &books[1]);
+    println!("before: {} {} at {} ms", before.exchange, before.symbol, before.timestamp_ms);
+    println!("after:  {} {} at {} ms", after.exchange, after.symbol, after.timestamp_ms);
+    let changes = diff::books(before, after)?;
+    if changes.is_empty() {
+        println!("identical");
+    }
+    for change in &changes {
+        println!("{}", change);
+    }
+    Ok(())
+}
@@ -31,7 +31,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                 
                 if let Ok(text) = msg.to_text() {
                     // Check if it's a ping (just a number)
-                    if text.chars().all(|c| c.is_digit(10)) {
+                    if orderbook::binance::is_ping(text) {
                         println!("Message #{}: Ping (timestamp: {})", message_count, text);
                         println!();
                         continue;
@@ -0,0 +1,38 @@
+use orderbook::archive::Archive;
+use orderbook::{cli, sink, verify};
+
+const USAGE: &str = "usage: verify --from <time> --to <time> [--symbol BTCUSDT] [--local <dir>] [--json]
+
+Decodes every stored book in a range of hourly partitions, checks schema versions
+and that timestamps only move forward, and prints a completeness report with every
+run of minutes without a book. --json prints the report for repair instead. Exits
+with 1 when anything is missing or wrong.";
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let (from, to) = cli::time_range(USAGE);
+    let archive = Archive::open(cli::arg("local")).await?;
+    let symbol = cli::arg("symbol").unwrap_or_else(|| "BTCUSDT".into());
+
+    let report = verify::verify(&archive, &symbol, from, to).await?;
+    if cli::flag("json") {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    } else {
+        println!("{} {} to {}", report.symbol, sink::at_ms(report.from_ms), sink::at_ms(report.to_ms));
+        println!("{} files, {} books", report.files, report.books);
+        println!("{}/{} minutes with books ({:.2}%)", report.covered_minutes, report.minutes, report.completeness() * 100.0);
+        for hour in &report.missing_hours {
+            println!("missing hour {}", sink::at_ms(*hour));
+        }
+        for hole in &report.holes {
+            println!("missing {} to {} ({} min)", sink::at_ms(hole.from_ms), sink::at_ms(hole.to_ms), hole.minutes());
+        }
+        for problem in &report.problems {
+            println!("{}: {}", problem.key, problem.problem);
+        }
+    }
+    if !report.is_complete() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
@@ -0,0 +1,358 @@
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use crate::book::Level;
+use crate::{config, rest, Error};
+
+/// Exchange name used in object keys.
+pub const EXCHANGE: &str = "binance";
+
+pub const SPOT_WS: &str = "wss://stream.binance.us:9443/ws";
+pub const SPOT_REST: &str = "https://api.binance.us";
+pub const FUTURES_WS: &str = "wss://fstream.binance.com/ws";
+pub const FUTURES_REST: &str = "https://fapi.binance.com";
+
+/// `VENUE` presets: name, websocket base, REST base, whether it is USD-M futures.
+const VENUES: [(&str, &str, &str, bool); 5] = [
+    ("us", SPOT_WS, SPOT_REST, false),
+    ("com", "wss://stream.binance.com:9443/ws", "https://api.binance.com", false),
+    ("testnet", "wss://stream.testnet.binance.vision/ws", "https://testnet.binance.vision", false),
+    ("futures", FUTURES_WS, FUTURES_REST, true),
+    ("futures-testnet", "wss://stream.binancefuture.com/ws", "https://testnet.binancefuture.com", true),
+];
+
+/// A Binance deployment: where its websocket and REST APIs live. Websocket and
+/// REST calls always go to the same venue.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Venue {
+    pub name: String,
+    /// Raw stream base, ending in `/ws`.
+    pub ws: String,
+    pub rest: String,
+    /// USD-M futures: `/fapi/v1` REST paths, liquidations and funding.
+    pub futures: bool,
+}
+
+impl Venue {
+    pub fn preset(name: &str) -> Option<Self> {
+        VENUES.iter().find(|(n, ..)| *n == name).map(|&(name, ws, rest, futures)| Venue {
+            name: name.to_string(),
+            ws: ws.to_string(),
+            rest: rest.to_string(),
+            futures,
+        })
+    }
+
+    /// `VENUE` (default `us`, or `futures` when `MARKET=futures`), with `WS_BASE_URL`
+    /// and `REST_BASE_URL` overriding the preset's hosts, e.g. for a proxy.
+    pub fn from_env() -> Result<Self, Error> {
+        let name = config::var("VENUE").filter(|v| !v.is_empty()).unwrap_or_else(|| {
+            if config::var("MARKET").is_some_and(|m| m == "futures") { "futures".into() } else { "us".into() }
+        });
+        let mut venue = Venue::preset(&name).ok_or_else(|| {
+            let names: Vec<&str> = VENUES.iter().map(|(n, ..)| *n).collect();
+            Error::Config(format!("unknown venue {:?}, expected one of {}", name, names.join(", ")))
+        })?;
+        if let Some(ws) = config::var("WS_BASE_URL").filter(|v| !v.is_empty()) {
+            venue.ws = ws.trim_end_matches('/').to_string();
+        }
+        if let Some(rest) = config::var("REST_BASE_URL").filter(|v| !v.is_empty()) {
+            venue.rest = rest.trim_end_matches('/').to_string();
+        }
+        if !venue.ws.starts_with("wss://") && !venue.ws.starts_with("ws://") {
+            return Err(Error::Config(format!("websocket base {:?} is not a ws:// or wss:// URL", venue.ws)));
+        }
+        if !venue.rest.starts_with("https://") && !venue.rest.starts_with("http://") {
+            return Err(Error::Config(format!("REST base {:?} is not an http(s) URL", venue.rest)));
+        }
+        Ok(venue)
+    }
+
+    /// A market data REST endpoint such as `depth` or `aggTrades`, under `/api/v3`
+    /// on spot venues and `/fapi/v1` on futures.
+    pub fn rest_url(&self, endpoint: &str) -> String {
+        let version = if self.futures { "fapi/v1" } else { "api/v3" };
+        format!("{}/{}/{}", self.rest, version, endpoint)
+    }
+
+    /// The request weight Binance charges for `endpoint`; `limit` only matters for `depth`.
+    pub fn weight(&self, endpoint: &str, limit: u32) -> u32 {
+        match (endpoint, self.futures) {
+            ("depth", false) => match limit { 0..=100 => 5, 101..=500 => 25, 501..=1000 => 50, _ => 250 },
+            ("depth", true) => match limit { 0..=50 => 2, 51..=100 => 5, 101..=500 => 10, _ => 20 },
+            ("aggTrades", false) => 2,
+            ("aggTrades", true) => 20,
+            ("exchangeInfo", false) => 20,
+            ("userDataStream", _) => 2,
+            _ => 1,
+        }
+    }
+
+    /// Spot symbols are letters and digits (`BTCUSDT`, `BTCUSD` on binance.us);
+    /// futures also list delivery contracts such as `BTCUSDT_250627`.
+    pub fn validate_symbol(&self, symbol: &str) -> Result<(), Error> {
+        let alnum = |s: &str| (2..=20).contains(&s.len()) && s.chars().all(|c| c.is_ascii_alphanumeric());
+        let valid = match symbol.split_once('_') {
+            None => alnum(symbol),
+            Some((pair, expiry)) => self.futures && alnum(pair) && expiry.len() == 6 && expiry.chars().all(|c| c.is_ascii_digit()),
+        };
+        if !valid {
+            return Err(Error::Config(format!("{:?} is not a valid symbol on the {} venue", symbol, self.name)));
+        }
+        Ok(())
+    }
+}
+
+/// The process-wide venue, read and validated on first use.
+pub fn venue() -> Result<&'static Venue, Error> {
+    static VENUE: OnceLock<Venue> = OnceLock::new();
+
+    if let Some(venue) = VENUE.get() {
+        return Ok(venue);
+    }
+    let venue = Venue::from_env()?;
+    Ok(VENUE.get_or_init(|| venue))
+}
+
+/// The parts of an `exchangeInfo` reply that tick sizes are read from.
+#[derive(Deserialize, Debug)]
+struct ExchangeInfo {
+    symbols: Vec<SymbolInfo>,
+}
+
+#[derive(Deserialize, Debug)]
+struct SymbolInfo {
+    symbol: String,
+    #[serde(default)]
+    filters: Vec<SymbolFilter>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+struct SymbolFilter {
+    filter_type: String,
+    tick_size: Option<String>,
+}
+
+/// Each listed symbol's price increment, the `tickSize` of its `PRICE_FILTER`, from
+/// an `exchangeInfo` reply. Symbols without a positive one are left out.
+pub fn parse_tick_sizes(body: &str) -> Result<HashMap<String, f64>, Error> {
+    let info: ExchangeInfo = serde_json::from_str(body)?;
+    Ok(info.symbols.into_iter().filter_map(|s| {
+        let tick = s.filters.iter()
+            .find(|f| f.filter_type == "PRICE_FILTER")?
+            .tick_size.as_deref()?
+            .parse::<f64>().ok()
+            .filter(|t| *t > 0.0)?;
+        Some((s.symbol, tick))
+    }).collect())
+}
+
+static TICK_SIZES: OnceLock<HashMap<String, f64>> = OnceLock::new();
+
+/// Fetches every symbol's tick size from the venue's `exchangeInfo` on first use and
+/// returns the cached sizes afterwards; they change rarely enough to keep for the
+/// life of the process.
+pub async fn load_tick_sizes(venue: &Venue) -> Result<&'static HashMap<String, f64>, Error> {
+    if let Some(sizes) = TICK_SIZES.get() {
+        return Ok(sizes);
+    }
+    let body = rest::get(&venue.rest_url("exchangeInfo"), venue.weight("exchangeInfo", 0)).await?.error_for_status()?.text().await?;
+    let sizes = parse_tick_sizes(&body)?;
+    Ok(TICK_SIZES.get_or_init(|| sizes))
+}
+
+/// A symbol's cached tick size; `None` before `load_tick_sizes` or when the venue
+/// didn't list it.
+pub fn tick_size(symbol: &str) -> Option<f64> {
+    TICK_SIZES.get()?.get(&symbol.to_uppercase()).copied()
+}
+
+/// Depth stream the collector subscribes to per symbol.
+pub fn depth_stream(symbol: &str) -> String {
+    format!("{}@depth20@100ms", symbol.to_lowercase())
+}
+
+/// The combined-stream endpoint next to a raw `/ws` base, where payloads arrive as
+/// `{"stream": ..., "data": ...}` so many symbols can share one connection.
+pub fn combined_url(base: &str) -> String {
+    format!("{}/stream", base.strip_suffix("/ws").unwrap_or(base))
+}
+
+/// A `SUBSCRIBE` or `UNSUBSCRIBE` request for `streams`; the reply echoes `id`.
+pub fn stream_request(method: &str, streams: &[String], id: u64) -> String {
+    serde_json::json!({"method": method, "params": streams, "id": id}).to_string()
+}
+
+/// A combined-stream payload's stream name, or a request reply.
+#[derive(Deserialize, Debug, Default)]
+pub struct Control<'a> {
+    #[serde(borrow)]
+    pub stream: Option<&'a str>,
+    pub id: Option<u64>,
+    /// Set when a `SUBSCRIBE`/`UNSUBSCRIBE` request was rejected.
+    pub error: Option<serde_json::Value>,
+}
+
+impl<'a> Control<'a> {
+    /// Reads the routing fields only; `data` is skipped over, not built.
+    pub fn parse(text: &'a str) -> Option<Self> {
+        serde_json::from_str(text).ok()
+    }
+}
+
+/// Levels kept per side of a depth message.
+const MAX_LEVELS: usize = 20;
+
+/// A `[price, qty]` pair as Binance sends it, borrowed from the message buffer.
+pub type RawLevel<'a> = [&'a str; 2];
+
+/// Spot `depthN` stream payloads and REST depth snapshots.
+#[derive(Deserialize, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct PartialDepth<'a> {
+    pub last_update_id: u64,
+    #[serde(default, borrow)]
+    pub bids: Vec<RawLevel<'a>>,
+    #[serde(default, borrow)]
+    pub asks: Vec<RawLevel<'a>>,
+}
+
+impl PartialDepth<'_> {
+    pub fn levels(&self) -> (Vec<Level>, Vec<Level>) {
+        (levels(&self.bids), levels(&self.asks))
+    }
+}
+
+/// A `depthUpdate` event. Diff streams carry only changed levels; the futures
+/// `depthN` streams use this shape too but send the full top N each time.
+#[derive(Debug)]
+pub struct DepthUpdate<'a> {
+    pub event_time_ms: i64,
+    pub symbol: &'a str,
+    pub first_update_id: u64,
+    pub final_update_id: u64,
+    /// The previous event's `u` (futures `pu`), 0 on spot, which doesn't send it.
+    pub prev_final_update_id: u64,
+    pub bids: Vec<RawLevel<'a>>,
+    pub asks: Vec<RawLevel<'a>>,
+}
+
+#[derive(Debug)]
+pub enum DepthMessage<'a> {
+    Partial(PartialDepth<'a>),
+    Update(DepthUpdate<'a>),
+}
+
+/// Every field either shape can carry, so a payload is deserialized once and then
+/// sorted into a `DepthMessage`.
+#[derive(Deserialize)]
+struct Envelope<'a> {
+    #[serde(rename = "e")]
+    event: Option<&'a str>,
+    #[serde(rename = "E", default)]
+    event_time_ms: i64,
+    #[serde(rename = "s", default)]
+    symbol: &'a str,
+    #[serde(rename = "lastUpdateId")]
+    last_update_id: Option<u64>,
+    #[serde(rename = "U", default)]
+    first_update_id: u64,
+    #[serde(rename = "u", default)]
+    final_update_id: u64,
+    #[serde(rename = "pu", default)]
+    prev_final_update_id: u64,
+    #[serde(default, borrow, alias = "b")]
+    bids: Vec<RawLevel<'a>>,
+    #[serde(default, borrow, alias = "a")]
+    asks: Vec<RawLevel<'a>>,
+    /// The payload itself, on the combined-stream endpoint.
+    #[serde(default, borrow)]
+    data: Option<Box<Envelope<'a>>>,
+}
+
+impl<'a> DepthMessage<'a> {
+    /// Parses a websocket payload in place with simd-json, unwrapping combined-stream
+    /// payloads. Strings borrow from `bytes`, so the message can't outlive the buffer.
+    /// Valid JSON that is neither depth shape (subscription acks, other events) is `None`.
+    pub fn parse(bytes: &'a mut [u8]) -> Result<Option<Self>, simd_json::Error> {
+        let mut env: Envelope<'a> = simd_json::serde::from_slice(bytes)?;
+        if let Some(data) = env.data.take() {
+            env = *data;
+        }
+        Ok(match (env.event, env.last_update_id) {
+            (Some("depthUpdate"), _) => Some(DepthMessage::Update(DepthUpdate {
+                event_time_ms: env.event_time_ms,
+                symbol: env.symbol,
+                first_update_id: env.first_update_id,
+                final_update_id: env.final_update_id,
+                prev_final_update_id: env.prev_final_update_id,
+                bids: env.bids,
+                asks: env.asks,
+            })),
+            (None, Some(last_update_id)) => Some(DepthMessage::Partial(PartialDepth {
+                last_update_id,
+                bids: env.bids,
+                asks: env.asks,
+            })),
+            _ => None,
+        })
+    }
+
+    /// Exchange event time, for the shapes that carry one (partial depth doesn't).
+    pub fn event_time_ms(&self) -> Option<i64> {
+        match self {
+            DepthMessage::Partial(_) => None,
+            DepthMessage::Update(update) => Some(update.event_time_ms).filter(|&ms| ms > 0),
+        }
+    }
+
+    /// The exchange's sequence number for the book as of this message.
+    pub fn last_update_id(&self) -> u64 {
+        match self {
+            DepthMessage::Partial(depth) => depth.last_update_id,
+            DepthMessage::Update(update) => update.final_update_id,
+        }
+    }
+
+    /// The symbol, for the shapes that carry one (partial depth doesn't).
+    pub fn symbol(&self) -> Option<&'a str> {
+        match self {
+            DepthMessage::Partial(_) => None,
+            DepthMessage::Update(update) => Some(update.symbol).filter(|s| !s.is_empty()),
+        }
+    }
+
+    /// The `[price, qty]` strings as sent, best first.
+    pub fn raw_levels(&self) -> (&[RawLevel<'a>], &[RawLevel<'a>]) {
+        match self {
+            DepthMessage::Partial(depth) => (&depth.bids, &depth.asks),
+            DepthMessage::Update(update) => (&update.bids, &update.asks),
+        }
+    }
+
+    pub fn levels(&self) -> (Vec<Level>, Vec<Level>) {
+        match self {
+            DepthMessage::Partial(depth) => depth.levels(),
+            DepthMessage::Update(update) => (levels(&update.bids), levels(&update.asks)),
+        }
+    }
+}
+
+/// Up to 20 levels, best first; levels that don't parse as numbers are dropped.
+fn levels(raw: &[RawLevel]) -> Vec<Level> {
+    raw.iter().take(MAX_LEVELS)
+        .filter_map(|[p, q]| Some(Level::new(parse_decimal(p)?, parse_decimal(q)?)))
+        .collect()
+}
+
+/// Parses a decimal string such as `"65000.10"` without allocating.
+pub fn parse_decimal(s: &str) -> Option<f64> {
+    fast_float2::parse(s).ok()
+}
+
+/// Pings arrive as bare numeric payloads on some endpoints.
+pub fn is_ping(text: &str) -> bool {
+    text.chars().all(|c| c.is_ascii_digit())
+}
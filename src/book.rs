@@ -0,0 +1,463 @@
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::schema::ORDERBOOK_VERSION;
+use crate::{config, Error};
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct Level {
+    pub price: f64,
+    pub qty: f64,
+}
+
+impl Level {
+    pub fn new(price: f64, qty: f64) -> Self {
+        Level { price, qty }
+    }
+}
+
+/// A level in fixed point, exactly as the exchange sent it: the price is
+/// `price / 10^price_scale` and the quantity `qty / 10^qty_scale`, with the scales
+/// stored once per record.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExactLevel {
+    pub price: i64,
+    pub qty: i64,
+}
+
+/// Fixed-point copies of raw `[price, qty]` levels, best first, with the price and
+/// quantity scales: the most decimal places either column has. `None` when a level
+/// isn't a plain decimal or doesn't fit in an i64 at that scale.
+pub fn exact_levels(bids: &[[&str; 2]], asks: &[[&str; 2]]) -> Option<(Vec<ExactLevel>, Vec<ExactLevel>, i32, i32)> {
+    let (bids, asks) = (&bids[..bids.len().min(MAX_EXACT_LEVELS)], &asks[..asks.len().min(MAX_EXACT_LEVELS)]);
+    let parsed: Vec<[(i64, i32); 2]> = bids.iter().chain(asks)
+        .map(|[p, q]| Some([parse_fixed(p)?, parse_fixed(q)?]))
+        .collect::<Option<_>>()?;
+    let price_scale = parsed.iter().map(|[p, _]| p.1).max().unwrap_or(0);
+    let qty_scale = parsed.iter().map(|[_, q]| q.1).max().unwrap_or(0);
+    let rescale = |(units, scale): (i64, i32), to: i32| units.checked_mul(10i64.checked_pow((to - scale) as u32)?);
+    let mut levels = parsed.into_iter()
+        .map(|[p, q]| Some(ExactLevel { price: rescale(p, price_scale)?, qty: rescale(q, qty_scale)? }))
+        .collect::<Option<Vec<_>>>()?;
+    let asks = levels.split_off(bids.len());
+    Some((levels, asks, price_scale, qty_scale))
+}
+
+/// Levels kept per side in fixed point, as for the float levels they're parsed from.
+const MAX_EXACT_LEVELS: usize = 20;
+
+/// `"65000.10"` as (6500010, 2).
+pub(crate) fn parse_fixed(s: &str) -> Option<(i64, i32)> {
+    let (int, frac) = s.split_once('.').unwrap_or((s, ""));
+    if int.is_empty() || !int.bytes().chain(frac.bytes()).all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let units = int.bytes().chain(frac.bytes())
+        .try_fold(0i64, |n, b| n.checked_mul(10)?.checked_add((b - b'0') as i64))?;
+    Some((units, frac.len() as i32))
+}
+
+/// Whether `DECIMAL_PRICES` is on, adding fixed-point copies of the exchange's
+/// levels to every book; read once per process.
+pub fn decimal_prices() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+
+    *ON.get_or_init(|| config::var("DECIMAL_PRICES").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")))
+}
+
+/// Distances from mid (as a fraction of price) at which cumulative depth is sampled.
+pub const DEPTHS: [f64; 5] = [0.0001, 0.0005, 0.001, 0.005, 0.01];
+
+/// Parses a comma-separated bucket list such as `0.001,0.005,0.01`: fractions of mid
+/// between 0 and 1, strictly increasing.
+pub fn parse_depths(list: &str) -> Result<Vec<f64>, Error> {
+    let depths: Vec<f64> = list.split(',').map(|d| d.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::Config(format!("invalid depth buckets {:?}", list)))?;
+    let increasing = depths.windows(2).all(|w| w[0] < w[1]);
+    if depths.is_empty() || !increasing || depths.iter().any(|d| !(*d > 0.0 && *d < 1.0)) {
+        return Err(Error::Config(format!("depth buckets {:?} must be increasing fractions of mid", list)));
+    }
+    Ok(depths)
+}
+
+/// `DEPTH_BUCKETS`, or `DEPTHS` when unset; read once per process.
+pub fn depth_buckets() -> Result<&'static [f64], Error> {
+    static BUCKETS: OnceLock<Vec<f64>> = OnceLock::new();
+
+    if let Some(buckets) = BUCKETS.get() {
+        return Ok(buckets);
+    }
+    let buckets = match config::var("DEPTH_BUCKETS").filter(|v| !v.is_empty()) {
+        Some(list) => parse_depths(&list)?,
+        None => DEPTHS.to_vec(),
+    };
+    Ok(BUCKETS.get_or_init(|| buckets))
+}
+
+/// Notional amounts, in the quote currency, at which each side is swept.
+pub const NOTIONALS: [f64; 3] = [10_000.0, 100_000.0, 1_000_000.0];
+
+/// Parses a comma-separated notional list such as `10000,100000`: positive amounts,
+/// strictly increasing.
+pub fn parse_notionals(list: &str) -> Result<Vec<f64>, Error> {
+    let notionals: Vec<f64> = list.split(',').map(|n| n.trim().parse::<f64>())
+        .collect::<Result<_, _>>()
+        .map_err(|_| Error::Config(format!("invalid notional thresholds {:?}", list)))?;
+    let increasing = notionals.windows(2).all(|w| w[0] < w[1]);
+    if notionals.is_empty() || !increasing || notionals.iter().any(|n| !(*n > 0.0 && n.is_finite())) {
+        return Err(Error::Config(format!("notional thresholds {:?} must be increasing positive amounts", list)));
+    }
+    Ok(notionals)
+}
+
+/// `NOTIONAL_THRESHOLDS`, or `NOTIONALS` when unset; read once per process.
+pub fn notional_thresholds() -> Result<&'static [f64], Error> {
+    static THRESHOLDS: OnceLock<Vec<f64>> = OnceLock::new();
+
+    if let Some(notionals) = THRESHOLDS.get() {
+        return Ok(notionals);
+    }
+    let notionals = match config::var("NOTIONAL_THRESHOLDS").filter(|v| !v.is_empty()) {
+        Some(list) => parse_notionals(&list)?,
+        None => NOTIONALS.to_vec(),
+    };
+    Ok(THRESHOLDS.get_or_init(|| notionals))
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct OrderBook {
+    /// When the collector received the book (ingest time).
+    pub timestamp_ms: i64,
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+    pub spread: f64,
+    pub mid_price: f64,
+    pub imbalance_ratio: f64,
+    #[serde(default = "first_version")]
+    pub schema_version: i32,
+    /// Empty in records written before schema v4.
+    #[serde(default)]
+    pub exchange: String,
+    /// Upper-case exchange symbol, e.g. `BTCUSDT`; empty before schema v4.
+    #[serde(default)]
+    pub symbol: String,
+    /// The exchange's event time (Binance `E`), 0 when the payload has none, as
+    /// with spot partial depth. Comparing it to `timestamp_ms` separates exchange
+    /// latency from collector latency.
+    #[serde(default)]
+    pub event_time_ms: i64,
+    /// The exchange's book sequence number (`lastUpdateId`, or `u` for updates).
+    #[serde(default)]
+    pub last_update_id: i64,
+    /// The exchange's own levels in fixed point, with `DECIMAL_PRICES` on; empty
+    /// otherwise and before schema v7.
+    #[serde(default)]
+    pub exact_bids: Vec<ExactLevel>,
+    #[serde(default)]
+    pub exact_asks: Vec<ExactLevel>,
+    /// Decimal places of the `exact_*` prices and quantities.
+    #[serde(default)]
+    pub price_scale: i32,
+    #[serde(default)]
+    pub qty_scale: i32,
+    /// Cumulative notional (price × qty, in the quote currency) between mid and each
+    /// depth bucket, alongside `bids`/`asks`; empty before schema v8.
+    #[serde(default)]
+    pub bid_notional: Vec<f64>,
+    #[serde(default)]
+    pub ask_notional: Vec<f64>,
+    /// For each notional threshold, the price a sweep of that much from the best
+    /// level reaches and the base quantity it takes; `{0, 0}` when the levels don't
+    /// hold that much. Empty before schema v8.
+    #[serde(default)]
+    pub bid_sweeps: Vec<Level>,
+    #[serde(default)]
+    pub ask_sweeps: Vec<Level>,
+    /// `spread` as basis points of `mid_price`; 0 before schema v9.
+    #[serde(default)]
+    pub spread_bps: f64,
+    /// The symbol's price increment from `exchangeInfo`, 0 when it couldn't be
+    /// fetched and before schema v9.
+    #[serde(default)]
+    pub tick_size: f64,
+    /// `spread` in whole ticks, 0 without a `tick_size`.
+    #[serde(default)]
+    pub spread_in_ticks: f64,
+    /// The symbol's trade-flow toxicity (see `vpin`) when the book was stored.
+    #[serde(default)]
+    pub vpin: f64,
+    /// Full volume buckets behind `vpin`; 0 when there's no estimate, as with `VPIN`
+    /// off and before schema v10.
+    #[serde(default)]
+    pub vpin_buckets: i32,
+    /// For each notional threshold, the average price a market order of that size
+    /// would fill at on each side, and how much worse than mid that is in basis
+    /// points; 0 where the sweep can't fill. Empty before schema v11.
+    #[serde(default)]
+    pub bid_fill_prices: Vec<f64>,
+    #[serde(default)]
+    pub ask_fill_prices: Vec<f64>,
+    #[serde(default)]
+    pub bid_slippage_bps: Vec<f64>,
+    #[serde(default)]
+    pub ask_slippage_bps: Vec<f64>,
+    /// MADs the spread and top-of-book depth are from the symbol's rolling baselines
+    /// (see `anomaly`), signed, and whether each is past `ANOMALY_MADS`. 0 and false
+    /// with `ANOMALIES` off, early in a run and before schema v12.
+    #[serde(default)]
+    pub spread_mads: f64,
+    #[serde(default)]
+    pub depth_mads: f64,
+    #[serde(default)]
+    pub spread_anomaly: bool,
+    #[serde(default)]
+    pub depth_anomaly: bool,
+    /// Log-returns of mid since the symbol's previous stored book and over the last
+    /// `return_window_secs` (see `returns`). 0 for a run's first book, for the first
+    /// window of a run, and before schema v13.
+    #[serde(default)]
+    pub mid_return: f64,
+    #[serde(default)]
+    pub mid_return_window: f64,
+    #[serde(default)]
+    pub return_window_secs: i32,
+    /// Set when an aggressive order since the previous book swept several levels
+    /// (see `sweep`): the book side it hit (`bid` or `ask`), the prices it reached
+    /// and its notional. False, 0 and empty otherwise, with `SWEEPS` off and before
+    /// schema v14.
+    #[serde(default)]
+    pub sweep: bool,
+    #[serde(default)]
+    pub sweep_side: String,
+    #[serde(default)]
+    pub sweep_levels: i32,
+    #[serde(default)]
+    pub sweep_notional: f64,
+}
+
+fn first_version() -> i32 {
+    1
+}
+
+impl OrderBook {
+    /// Builds a normalized snapshot from raw (price, qty) levels, best first.
+    /// Returns None when either side of the book is empty.
+    pub fn from_levels(timestamp_ms: i64, bids: &[Level], asks: &[Level]) -> Option<Self> {
+        let (best_bid, best_ask) = (bids.first()?.price, asks.first()?.price);
+        let mid_price = (best_bid + best_ask) / 2.0;
+
+        let bid_vol: f64 = bids.iter().take(5).map(|b| b.qty).sum();
+        let ask_vol: f64 = asks.iter().take(5).map(|a| a.qty).sum();
+        let imbalance_ratio = if bid_vol + ask_vol > 0.0 {
+            (bid_vol - ask_vol) / (bid_vol + ask_vol)
+        } else {
+            0.0
+        };
+
+        let notionals = notional_thresholds().unwrap_or(&NOTIONALS);
+        let spread = best_ask - best_bid;
+        let (bid_sweeps, ask_sweeps) = (sweeps(bids, notionals), sweeps(asks, notionals));
+        let (bid_fill_prices, bid_slippage_bps) = slippage(&bid_sweeps, notionals, mid_price, false);
+        let (ask_fill_prices, ask_slippage_bps) = slippage(&ask_sweeps, notionals, mid_price, true);
+        Some(OrderBook {
+            timestamp_ms,
+            bids: normalize_to_depths(bids, mid_price, false),
+            asks: normalize_to_depths(asks, mid_price, true),
+            spread,
+            mid_price,
+            imbalance_ratio,
+            schema_version: ORDERBOOK_VERSION,
+            exchange: String::new(),
+            symbol: String::new(),
+            event_time_ms: 0,
+            last_update_id: 0,
+            exact_bids: Vec::new(),
+            exact_asks: Vec::new(),
+            price_scale: 0,
+            qty_scale: 0,
+            bid_notional: notional_to_depths(bids, mid_price, false),
+            ask_notional: notional_to_depths(asks, mid_price, true),
+            bid_sweeps,
+            ask_sweeps,
+            spread_bps: if mid_price > 0.0 { spread / mid_price * 10_000.0 } else { 0.0 },
+            tick_size: 0.0,
+            spread_in_ticks: 0.0,
+            vpin: 0.0,
+            vpin_buckets: 0,
+            bid_fill_prices,
+            ask_fill_prices,
+            bid_slippage_bps,
+            ask_slippage_bps,
+            spread_mads: 0.0,
+            depth_mads: 0.0,
+            spread_anomaly: false,
+            depth_anomaly: false,
+            mid_return: 0.0,
+            mid_return_window: 0.0,
+            return_window_secs: 0,
+            sweep: false,
+            sweep_side: String::new(),
+            sweep_levels: 0,
+            sweep_notional: 0.0,
+        })
+    }
+
+    /// Stamps the schema version the record will be written with.
+    pub fn with_version(mut self, version: i32) -> Self {
+        self.schema_version = version;
+        self
+    }
+
+    /// Stamps the exchange's event time and sequence number.
+    pub fn with_exchange_clock(mut self, event_time_ms: i64, last_update_id: i64) -> Self {
+        self.event_time_ms = event_time_ms;
+        self.last_update_id = last_update_id;
+        self
+    }
+
+    /// Adds fixed-point copies of the raw levels the book was built from; leaves the
+    /// book as it is when they don't parse.
+    pub fn with_exact_levels(mut self, bids: &[[&str; 2]], asks: &[[&str; 2]]) -> Self {
+        if let Some((bids, asks, price_scale, qty_scale)) = exact_levels(bids, asks) {
+            (self.exact_bids, self.exact_asks, self.price_scale, self.qty_scale) = (bids, asks, price_scale, qty_scale);
+        }
+        self
+    }
+
+    /// Stamps the symbol's tick size and the spread measured in it; a size that
+    /// isn't positive leaves the book as it is.
+    pub fn with_tick_size(mut self, tick_size: f64) -> Self {
+        if tick_size > 0.0 {
+            self.tick_size = tick_size;
+            // the spread is a whole number of ticks; rounding drops float noise
+            self.spread_in_ticks = (self.spread / tick_size).round();
+        }
+        self
+    }
+
+    /// Stamps a VPIN estimate and the number of buckets behind it.
+    pub fn with_vpin(mut self, (vpin, buckets): (f64, usize)) -> Self {
+        self.vpin = vpin;
+        self.vpin_buckets = buckets as i32;
+        self
+    }
+
+    /// Stamps anomaly scores, flagging each past `threshold` MADs either way.
+    pub fn with_anomalies(mut self, spread_mads: f64, depth_mads: f64, threshold: f64) -> Self {
+        self.spread_mads = spread_mads;
+        self.depth_mads = depth_mads;
+        self.spread_anomaly = spread_mads.abs() > threshold;
+        self.depth_anomaly = depth_mads.abs() > threshold;
+        self
+    }
+
+    /// Stamps mid log-returns since the previous stored book and over `window_secs`.
+    pub fn with_returns(mut self, mid_return: f64, mid_return_window: f64, window_secs: i32) -> Self {
+        self.mid_return = mid_return;
+        self.mid_return_window = mid_return_window;
+        self.return_window_secs = window_secs;
+        self
+    }
+
+    /// Flags a sweep of `levels` prices on `side`, worth `notional`.
+    pub fn with_sweep(mut self, side: &str, levels: i32, notional: f64) -> Self {
+        self.sweep = true;
+        self.sweep_side = side.to_string();
+        self.sweep_levels = levels;
+        self.sweep_notional = notional;
+        self
+    }
+
+    /// The best bid, recovered from mid and spread.
+    pub fn best_bid(&self) -> f64 {
+        self.mid_price - self.spread / 2.0
+    }
+
+    pub fn best_ask(&self) -> f64 {
+        self.mid_price + self.spread / 2.0
+    }
+
+    /// What partitions are keyed by: the exchange's event time, or ingest time
+    /// when the exchange didn't send one.
+    pub fn partition_time_ms(&self) -> i64 {
+        if self.event_time_ms > 0 { self.event_time_ms } else { self.timestamp_ms }
+    }
+
+    /// Stamps where the book came from; `symbol` is upper-cased.
+    pub fn with_source(mut self, exchange: &str, symbol: &str) -> Self {
+        self.exchange = exchange.to_string();
+        self.symbol = symbol.to_uppercase();
+        self
+    }
+}
+
+/// Cumulative volume between mid and each depth bucket, as (target price, volume)
+/// levels. Binaries validate `depth_buckets` at startup; an invalid list here falls
+/// back to `DEPTHS`.
+pub fn normalize_to_depths(levels: &[Level], mid: f64, is_ask: bool) -> Vec<Level> {
+    depth_buckets().unwrap_or(&DEPTHS).iter().map(|&d| {
+        let target_price = if is_ask {
+            mid * (1.0 + d)
+        } else {
+            mid * (1.0 - d)
+        };
+
+        let cumulative_volume = levels.iter()
+            .filter(|l| (is_ask && l.price <= target_price) || (!is_ask && l.price >= target_price))
+            .map(|l| l.qty)
+            .sum();
+
+        Level::new(target_price, cumulative_volume)
+    }).collect()
+}
+
+/// Cumulative notional between mid and each depth bucket, one amount per bucket of
+/// `normalize_to_depths`.
+pub fn notional_to_depths(levels: &[Level], mid: f64, is_ask: bool) -> Vec<f64> {
+    depth_buckets().unwrap_or(&DEPTHS).iter().map(|&d| {
+        let target_price = if is_ask {
+            mid * (1.0 + d)
+        } else {
+            mid * (1.0 - d)
+        };
+
+        levels.iter()
+            .filter(|l| (is_ask && l.price <= target_price) || (!is_ask && l.price >= target_price))
+            .map(|l| l.price * l.qty)
+            .sum()
+    }).collect()
+}
+
+/// Walks `levels`, best first, until each of `notionals` is filled: the price of the
+/// level that fills it and the base quantity taken up to that point, or `{0, 0}` once
+/// the levels run out.
+pub fn sweeps(levels: &[Level], notionals: &[f64]) -> Vec<Level> {
+    let mut filled = levels.iter().scan((0.0, 0.0), |(notional, qty), l| {
+        let before = (*notional, *qty);
+        *notional += l.price * l.qty;
+        *qty += l.qty;
+        Some((l.price, before, *notional))
+    }).peekable();
+    notionals.iter().map(|&target| {
+        while filled.next_if(|&(_, _, total)| total < target).is_some() {}
+        match filled.peek() {
+            Some(&(price, (notional, qty), _)) if price > 0.0 => Level::new(price, qty + (target - notional) / price),
+            _ => Level::new(0.0, 0.0),
+        }
+    }).collect()
+}
+
+/// The average fill price of each sweep (its notional over the quantity taken) and its
+/// slippage from `mid` in basis points, positive when the fill is worse than mid;
+/// both 0 for sweeps that couldn't fill.
+pub fn slippage(sweeps: &[Level], notionals: &[f64], mid: f64, is_ask: bool) -> (Vec<f64>, Vec<f64>) {
+    sweeps.iter().zip(notionals).map(|(sweep, notional)| {
+        if sweep.qty <= 0.0 || mid <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let price = notional / sweep.qty;
+        let worse = if is_ask { price - mid } else { mid - price };
+        (price, worse / mid * 10_000.0)
+    }).unzip()
+}
@@ -0,0 +1,89 @@
+//! Per-symbol collector state in DynamoDB (`CHECKPOINT_TABLE`): the `lastUpdateId`
+//! of the last stored book, when it was stored and under which key. The next
+//! invocation and the recovery Lambda read it to see exactly where collection
+//! stopped, rather than inferring that from S3 listings.
+
+use aws_config::BehaviorVersion;
+use aws_sdk_dynamodb::types::AttributeValue;
+use aws_sdk_dynamodb::Client;
+use std::collections::HashMap;
+
+use crate::{config, Error};
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Checkpoint {
+    /// Upper-cased, the table's partition key.
+    pub symbol: String,
+    pub last_update_id: i64,
+    /// When the last book was stored, in epoch milliseconds.
+    pub last_flush_ms: i64,
+    pub last_key: String,
+}
+
+impl Checkpoint {
+    pub fn to_item(&self) -> HashMap<String, AttributeValue> {
+        HashMap::from([
+            ("symbol".to_string(), AttributeValue::S(self.symbol.clone())),
+            ("last_update_id".to_string(), AttributeValue::N(self.last_update_id.to_string())),
+            ("last_flush_ms".to_string(), AttributeValue::N(self.last_flush_ms.to_string())),
+            ("last_key".to_string(), AttributeValue::S(self.last_key.clone())),
+        ])
+    }
+
+    /// `None` for items missing an attribute or holding the wrong type.
+    pub fn from_item(item: &HashMap<String, AttributeValue>) -> Option<Self> {
+        let number = |k: &str| item.get(k)?.as_n().ok()?.parse().ok();
+        let text = |k: &str| item.get(k)?.as_s().ok().cloned();
+        Some(Checkpoint {
+            symbol: text("symbol")?,
+            last_update_id: number("last_update_id")?,
+            last_flush_ms: number("last_flush_ms")?,
+            last_key: text("last_key")?,
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Checkpoints {
+    client: Client,
+    table: String,
+}
+
+impl Checkpoints {
+    pub fn new(client: Client, table: &str) -> Self {
+        Checkpoints { client, table: table.to_string() }
+    }
+
+    /// The table in `CHECKPOINT_TABLE`, or `None` when checkpoints are off.
+    pub async fn from_env() -> Option<Self> {
+        let table = config::var("CHECKPOINT_TABLE").filter(|t| !t.is_empty())?;
+        let client = Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await);
+        Some(Checkpoints::new(client, &table))
+    }
+
+    /// Saves `checkpoint` unless the table already holds a later book for the
+    /// symbol, e.g. from an overlapping invocation.
+    pub async fn save(&self, checkpoint: &Checkpoint) -> Result<(), Error> {
+        let result = self.client.put_item()
+            .table_name(&self.table)
+            .set_item(Some(checkpoint.to_item()))
+            .condition_expression("attribute_not_exists(last_update_id) OR last_update_id < :id")
+            .expression_attribute_values(":id", AttributeValue::N(checkpoint.last_update_id.to_string()))
+            .send().await;
+        match result {
+            Ok(_) => Ok(()),
+            Err(e) if e.as_service_error().is_some_and(|e| e.is_conditional_check_failed_exception()) => Ok(()),
+            Err(e) => Err(Error::Dynamo(Box::new(e.into()))),
+        }
+    }
+
+    pub async fn load(&self, symbol: &str) -> Result<Option<Checkpoint>, Error> {
+        let item = self.client.get_item()
+            .table_name(&self.table)
+            .key("symbol", AttributeValue::S(symbol.to_uppercase()))
+            .consistent_read(true)
+            .send().await
+            .map_err(|e| Error::Dynamo(Box::new(e.into())))?;
+        Ok(item.item().and_then(Checkpoint::from_item))
+    }
+}
@@ -0,0 +1,77 @@
+//! Book churn: how many levels the diff stream adds, removes and resizes per
+//! interval. Bursts of adds quickly cancelled are the mark of quote stuffing, so each
+//! interval is stored under `churn/` with its update rate and cancel ratio. Counting
+//! needs the local book to tell an add from a resize, so it runs in the full-depth
+//! capture (see `fulldepth`).
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::{binance, config};
+
+pub const CHURN_PREFIX: &str = "churn";
+
+const DEFAULT_EVERY: Duration = Duration::from_secs(10);
+
+/// Whether `BOOK_CHURN=1` asks for churn records.
+pub fn enabled() -> bool {
+    config::var("BOOK_CHURN").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// `CHURN_SECS`, how long each record covers; 10 seconds when unset.
+pub fn interval() -> Duration {
+    config::var("CHURN_SECS").and_then(|s| s.parse().ok()).filter(|s| *s > 0).map_or(DEFAULT_EVERY, Duration::from_secs)
+}
+
+/// Level changes counted from diffs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    /// Diff events applied.
+    pub updates: u64,
+    /// Levels that weren't in the book.
+    pub adds: u64,
+    /// Levels removed, whether cancelled or filled; the diff stream doesn't say which.
+    pub cancels: u64,
+    /// Levels whose quantity changed.
+    pub modifications: u64,
+}
+
+impl Counts {
+    pub fn changes(&self) -> u64 {
+        self.adds + self.cancels + self.modifications
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Churn {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub updates: i64,
+    pub adds: i64,
+    pub cancels: i64,
+    pub modifications: i64,
+    /// Level changes per second over the interval.
+    pub update_rate: f64,
+    /// Cancels per add, 0 without adds.
+    pub cancel_ratio: f64,
+}
+
+impl Churn {
+    pub fn new(symbol: &str, start_ms: i64, end_ms: i64, counts: Counts) -> Self {
+        let secs = (end_ms - start_ms) as f64 / 1000.0;
+        Churn {
+            start_ms,
+            end_ms,
+            exchange: binance::EXCHANGE.to_string(),
+            symbol: symbol.to_uppercase(),
+            updates: counts.updates as i64,
+            adds: counts.adds as i64,
+            cancels: counts.cancels as i64,
+            modifications: counts.modifications as i64,
+            update_rate: if secs > 0.0 { counts.changes() as f64 / secs } else { 0.0 },
+            cancel_ratio: if counts.adds > 0 { counts.cancels as f64 / counts.adds as f64 } else { 0.0 },
+        }
+    }
+}
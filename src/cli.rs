@@ -0,0 +1,56 @@
+//! Minimal argument helpers shared by the command line tools.
+
+use chrono::{DateTime, Duration, DurationRound, NaiveDateTime, TimeZone, Utc};
+
+/// Value following `--name`, if present.
+pub fn arg(name: &str) -> Option<String> {
+    let flag = format!("--{}", name);
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|a| *a == flag).and_then(|i| args.get(i + 1).cloned())
+}
+
+/// Every value following a repeated `--name`, in order.
+pub fn args(name: &str) -> Vec<String> {
+    let flag = format!("--{}", name);
+    let args: Vec<String> = std::env::args().collect();
+    args.windows(2).filter(|w| w[0] == flag).map(|w| w[1].clone()).collect()
+}
+
+pub fn flag(name: &str) -> bool {
+    let flag = format!("--{}", name);
+    std::env::args().any(|a| a == flag)
+}
+
+/// Accepts RFC 3339 or the shorter `2024-01-01`, `2024-01-01T10` and `2024-01-01T10:30` forms, as UTC.
+pub fn parse_time(s: &str) -> Option<DateTime<Utc>> {
+    if let Ok(t) = DateTime::parse_from_rfc3339(s) {
+        return Some(t.with_timezone(&Utc));
+    }
+    let padded = match s.len() {
+        10 => format!("{}T00:00:00", s),
+        13 => format!("{}:00:00", s),
+        16 => format!("{}:00", s),
+        _ => s.to_string(),
+    };
+    NaiveDateTime::parse_from_str(&padded, "%Y-%m-%dT%H:%M:%S").ok().map(|t| Utc.from_utc_datetime(&t))
+}
+
+/// `--from`/`--to` as a half-open range, exiting with `usage` if either is missing or invalid.
+pub fn time_range(usage: &str) -> (DateTime<Utc>, DateTime<Utc>) {
+    let parse = |name| arg(name).as_deref().and_then(parse_time);
+    match (parse("from"), parse("to")) {
+        (Some(from), Some(to)) if from < to => (from, to),
+        _ => {
+            eprintln!("{}", usage);
+            std::process::exit(2);
+        }
+    }
+}
+
+/// Start of every hour overlapping [from, to).
+pub fn hours(from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<DateTime<Utc>> {
+    let start = from.duration_trunc(Duration::hours(1)).unwrap_or(from);
+    std::iter::successors(Some(start), |h| Some(*h + Duration::hours(1)))
+        .take_while(|h| *h < to)
+        .collect()
+}
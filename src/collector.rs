@@ -0,0 +1,1013 @@
+//! The websocket ingest loop: connect, run each payload through the pipeline,
+//! hand encoded batches to an `Output`, and reconnect with backoff on failure.
+//! Reading and parsing run as their own `stages`, ahead of the uploads.
+
+use apache_avro::Schema;
+use chrono::Utc;
+use futures_util::stream::{FuturesUnordered, SplitStream};
+use futures_util::{FutureExt, SinkExt, StreamExt};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::task::{AbortHandle, JoinSet};
+use tokio::net::TcpStream;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, field, info, info_span, warn, Instrument, Span};
+
+use crate::alert::{self, Alert, Streak};
+use crate::anomaly::{self, Detector};
+use crate::bars::{self, Bar, BarBuilder};
+use crate::batch::{self, Batch, BookBatcher};
+use crate::checkpoint::Checkpoint;
+#[cfg(feature = "delta")]
+use crate::delta::{self, DeltaSink};
+use crate::gaps;
+use crate::handoff::{self, Window};
+use crate::metrics::{Metric, Metrics};
+use crate::pipeline::{self, Outcome};
+use crate::poll::{Fallback, Polled};
+use crate::raw::{self, RawBatcher};
+use crate::resiliency::{self, Resiliency, Tracker};
+use crate::returns::Returns;
+use crate::sample::Sampler;
+use crate::sink::{self, Delivery, Output};
+use crate::split::{self, Features, RawBook};
+use crate::stages::{self, Frame, Received};
+use crate::stats::{self, HourlyStats, StatsBuilder};
+use crate::top::{self, Quote, QuoteBatcher};
+use crate::validate::{self, Quarantined, Reason, Validator};
+use crate::{binance, config, correlation, live, proxy, schema, sweep, vpin, Error, OrderBook};
+
+pub(crate) const MAX_BACKOFF: Duration = Duration::from_secs(30);
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// How often a quiet stream checks for finished batches and due metrics.
+const IDLE_FLUSH: Duration = Duration::from_secs(10);
+
+pub const DEFAULT_UPLOAD_CONCURRENCY: usize = 4;
+
+/// Books whose upload has started but not finished: one, or a window's batch.
+struct InFlight {
+    key: String,
+    books: Vec<OrderBook>,
+    span: Span,
+}
+
+type Uploading = Pin<Box<dyn Future<Output = (InFlight, Result<Delivery, Error>)> + Send>>;
+
+/// What became of every text frame received. Each one lands in exactly one bucket,
+/// so `received` always equals the sum of the others.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Serialize)]
+pub struct MessageCounts {
+    pub received: u64,
+    pub stored: u64,
+    /// Pings, other events and one-sided books.
+    pub skipped: u64,
+    pub malformed: u64,
+    /// Failed validation and written under `quarantine/` instead (`VALIDATE_BOOKS`).
+    pub quarantined: u64,
+    /// Valid books the sampling policy chose not to persist.
+    pub downsampled: u64,
+    /// Folded into a bar without being stored on their own (`AGGREGATE=bars-only`).
+    pub aggregated: u64,
+    /// Books already handled: from an overlapping invocation or connection, or an
+    /// unchanged `lastUpdateId` between heartbeats.
+    pub duplicates: u64,
+    /// Written to the dead letter bucket instead of the main one.
+    pub dead_lettered: u64,
+    /// Lost to an encode or write error; the run stops at the first one.
+    pub dropped: u64,
+}
+
+pub struct Collector<O: Output> {
+    pub symbol: String,
+    pub url: String,
+    /// OrderBook schema version to write.
+    pub version: i32,
+    /// Reconnect when the stream ends; when false `run` returns instead.
+    pub reconnect: bool,
+    /// How long `run` keeps a connection before replacing it (Binance drops them at 24h).
+    pub rotate_after: Duration,
+    /// Stores a book whose `lastUpdateId` hasn't advanced once this long has passed
+    /// since the last stored one, so an idle market still shows up. `None` never does.
+    pub heartbeat: Option<Duration>,
+    /// Most book uploads in flight at once, for outputs that support `Output::upload`;
+    /// 1 awaits each before reading on.
+    pub upload_concurrency: usize,
+    /// Sent after every connect, for feeds that need a (signed) subscription request.
+    subscription: Option<String>,
+    shutdown: Option<watch::Receiver<bool>>,
+    /// REST polling while the websocket can't reconnect.
+    fallback: Option<Fallback>,
+    /// Set for bounded invocations, whose books are claimed while they overlap another.
+    window: Option<Window>,
+    /// The last stored book, until the next save.
+    checkpoint: Option<Checkpoint>,
+    /// Highest `lastUpdateId` handled, for dropping books delivered twice.
+    last_update_id: i64,
+    /// When the last book was stored, for heartbeats.
+    last_stored_ms: i64,
+    output: O,
+    uploads: FuturesUnordered<Uploading>,
+    metrics: Metrics,
+    raw: Option<RawBatcher>,
+    sampler: Sampler,
+    bars_mode: bars::Mode,
+    bars: BarBuilder,
+    /// Set with `HOURLY_STATS=1`, summarizing each hour of stored books.
+    stats: Option<StatsBuilder>,
+    /// Set with `BATCH_SECS`, storing a window of books per object.
+    batch: Option<BookBatcher>,
+    /// Set with `ANOMALIES=1`, scoring spread and depth against rolling baselines.
+    anomalies: Option<Detector>,
+    /// Set with `SWEEPS=1`, flagging orders that swept several levels between books.
+    sweeps: Option<sweep::Detector>,
+    /// Mids seen and stored, for the returns stamped on stored books.
+    returns: Returns,
+    /// Set with `VALIDATE_BOOKS=1`, following the mid to catch jumps.
+    validator: Option<Validator>,
+    /// Set with `RESILIENCY=1`, following depth near the mid across books.
+    resiliency: Option<Tracker>,
+    /// Set in top-of-book mode, where payloads are quotes rather than depth.
+    top: Option<QuoteBatcher>,
+    /// Set with `DELTA_TABLE_URI`, batching stored books for the Delta table.
+    #[cfg(feature = "delta")]
+    delta: Option<DeltaSink>,
+    /// Reconnects and failed writes since the last stored book, for `alert`.
+    reconnects: Streak,
+    writes: Streak,
+    /// Encode scratch space, reused so steady state doesn't allocate per message.
+    buf: Vec<u8>,
+    counts: MessageCounts,
+}
+
+impl<O: Output> Collector<O> {
+    /// A collector configured from the environment (schema version, raw archival, sampling, bars).
+    pub fn new(symbol: &str, url: &str, output: O) -> Self {
+        Collector {
+            symbol: symbol.to_string(),
+            url: url.to_string(),
+            version: schema::writer_version(),
+            reconnect: true,
+            rotate_after: rotate_after(),
+            heartbeat: heartbeat(),
+            upload_concurrency: upload_concurrency(),
+            subscription: None,
+            shutdown: None,
+            fallback: None,
+            window: None,
+            checkpoint: None,
+            last_update_id: 0,
+            last_stored_ms: 0,
+            output,
+            uploads: FuturesUnordered::new(),
+            metrics: Metrics::new(symbol),
+            raw: raw::enabled().then(|| RawBatcher::new(symbol)),
+            sampler: Sampler::from_env(),
+            bars_mode: bars::Mode::from_env(),
+            bars: BarBuilder::default(),
+            stats: stats::enabled().then(StatsBuilder::default),
+            batch: batch::window().map(BookBatcher::new),
+            anomalies: anomaly::enabled().then(|| Detector::new(anomaly::Settings::from_env())),
+            sweeps: sweep::enabled().then(sweep::Detector::from_env),
+            returns: Returns::from_env(),
+            validator: validate::enabled().then(Validator::from_env),
+            resiliency: resiliency::enabled().then(|| Tracker::new(resiliency::Settings::from_env())),
+            top: top::enabled().then(QuoteBatcher::default),
+            #[cfg(feature = "delta")]
+            delta: delta::table_uri().map(DeltaSink::new),
+            reconnects: Streak::new(alert::RECONNECT, alert::after()),
+            writes: Streak::new(alert::S3_WRITE, alert::after()),
+            buf: Vec::new(),
+            counts: MessageCounts::default(),
+        }
+    }
+
+    /// Accounting for everything received so far, including by a run that failed.
+    pub fn counts(&self) -> MessageCounts {
+        self.counts
+    }
+
+    pub fn with_sampler(mut self, sampler: Sampler) -> Self {
+        self.sampler = sampler;
+        self
+    }
+
+    /// Sends `request` each time the socket (re)connects, before reading, e.g. a
+    /// message built by `auth::Credentials::coinbase_subscribe`.
+    pub fn with_subscription(mut self, request: String) -> Self {
+        self.subscription = Some(request);
+        self
+    }
+
+    /// Makes `run` return once `shutdown` turns true, after writing partial batches.
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Polls `fallback` for books between reconnect attempts once enough have failed in a row.
+    pub fn with_fallback(mut self, fallback: Fallback) -> Self {
+        self.fallback = Some(fallback);
+        self
+    }
+
+    /// Claims each book by its `lastUpdateId` before storing it while `window` overlaps
+    /// another invocation, counting those already claimed as `duplicates`.
+    pub fn with_window(mut self, window: Window) -> Self {
+        self.window = Some(window);
+        self
+    }
+
+    /// Reads `bookTicker` quotes and stores them per minute under `top/` instead of
+    /// depth snapshots; connect it to `top::stream`.
+    pub fn with_top_of_book(mut self) -> Self {
+        self.top = Some(QuoteBatcher::default());
+        self
+    }
+
+    pub fn with_bars(mut self, mode: bars::Mode) -> Self {
+        self.bars_mode = mode;
+        self
+    }
+
+    /// Stores the books of each `window` together in one object instead of one each.
+    pub fn with_batch_window(mut self, window: Duration) -> Self {
+        self.batch = Some(BookBatcher::new(window));
+        self
+    }
+
+    pub fn output(&self) -> &O {
+        &self.output
+    }
+
+    pub fn into_output(self) -> O {
+        self.output
+    }
+
+    pub async fn run(&mut self) -> Result<(), Error> {
+        let book_schema = schema::parsed(schema::orderbook(self.version).unwrap_or(schema::ORDERBOOK))?;
+        let mut backoff = Duration::from_secs(1);
+        let mut last_write_ms = Utc::now().timestamp_millis();
+        let mut reconnected = false;
+        let mut failures = 0;
+        let mut shutdown = self.shutdown.clone();
+        let mut idle = tokio::time::interval_at(tokio::time::Instant::now() + IDLE_FLUSH, IDLE_FLUSH);
+
+        // every connection's reader feeds the one compute stage, so frames a rotated-out
+        // connection already read are still stored; dropping `tasks` stops them all
+        let (frames, to_compute) = mpsc::channel(stages::buffer());
+        let (computed, mut staged) = mpsc::channel(stages::buffer());
+        let mut tasks = JoinSet::new();
+        tasks.spawn(stages::compute(to_compute, computed, self.version, self.top.is_some()));
+        let mut connection = 0;
+
+        loop {
+            let rx = match connect(&self.url, self.subscription.clone()).await {
+                Ok(rx) => {
+                    self.metrics.connected(true);
+                    backoff = Duration::from_secs(1);
+                    failures = 0;
+                    rx
+                }
+                Err(e) if self.reconnect => {
+                    warn!(error = %e, backoff_s = backoff.as_secs(), "connect failed");
+                    failures += 1;
+                    let alert = self.reconnects.fail(&self.symbol, &e.to_string(), Utc::now().timestamp_millis());
+                    raise(alert).await;
+                    if self.wait_to_reconnect(book_schema, backoff, failures, &mut shutdown, &mut last_write_ms).await? {
+                        self.finish().await?;
+                        self.metrics.flush();
+                        return Ok(());
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let mut rotate_at = tokio::time::Instant::now() + self.rotate_after;
+            connection += 1;
+            let mut reader = tasks.spawn(stages::read(connection, rx, frames.clone()));
+            // during a rotation, the connection being replaced and its reader: both keep
+            // streaming until the new one delivers a book as recent as any handled
+            let mut retiring: Option<(u64, AbortHandle)> = None;
+
+            loop {
+                let frame = tokio::select! {
+                    frame = staged.recv() => match frame {
+                        Some(frame) => frame,
+                        // it only stops early by panicking, with `frames` still open
+                        None => return Err(Error::Io(std::io::Error::other("compute stage stopped"))),
+                    },
+                    _ = idle.tick() => {
+                        self.flush_ended().await?;
+                        continue;
+                    }
+                    _ = tokio::time::sleep_until(rotate_at), if retiring.is_none() => {
+                        match connect(&self.url, self.subscription.clone()).await {
+                            Ok(next) => {
+                                retiring = Some((connection, reader));
+                                connection += 1;
+                                reader = tasks.spawn(stages::read(connection, next, frames.clone()));
+                                rotate_at = tokio::time::Instant::now() + self.rotate_after;
+                                info!(symbol = %self.symbol, "rotating connection ahead of the 24h limit");
+                            }
+                            Err(e) => {
+                                rotate_at = tokio::time::Instant::now() + MAX_BACKOFF;
+                                warn!(error = %e, "rotation failed, keeping the current connection");
+                            }
+                        }
+                        continue;
+                    }
+                    _ = stopped(&mut shutdown) => {
+                        // stop reading, then handle what was already read so every frame is counted
+                        reader.abort();
+                        if let Some((_, old_reader)) = retiring.take() {
+                            old_reader.abort();
+                        }
+                        drop(frames);
+                        while let Some(frame) = staged.recv().await {
+                            if let Frame::Text(received) = frame {
+                                self.handle(book_schema, received).await?;
+                            }
+                        }
+                        self.finish().await?;
+                        self.metrics.connected(false);
+                        self.metrics.flush();
+                        return Ok(());
+                    }
+                };
+                self.metrics.maybe_flush();
+                // the reader ends a connection on a transport error; a bad payload only skips the message
+                let received = match frame {
+                    Frame::Text(received) => received,
+                    Frame::Closed { connection: closed } if closed == connection => match retiring.take() {
+                        // the replacement ended before catching up, so the old connection stays
+                        Some((old, old_reader)) => {
+                            (connection, reader) = (old, old_reader);
+                            rotate_at = tokio::time::Instant::now() + MAX_BACKOFF;
+                            warn!(symbol = %self.symbol, "rotation's new connection closed, keeping the current one");
+                            continue;
+                        }
+                        None => break,
+                    },
+                    Frame::Closed { connection: closed } => {
+                        if retiring.as_ref().is_some_and(|(old, _)| *old == closed) {
+                            retiring = None;
+                            info!(symbol = %self.symbol, "rotated connection ahead of the 24h limit");
+                        }
+                        continue;
+                    }
+                };
+                // books both connections deliver are dropped as duplicates by lastUpdateId
+                if received.connection == connection && self.caught_up(&received) {
+                    if let Some((_, old_reader)) = retiring.take() {
+                        old_reader.abort();
+                        info!(symbol = %self.symbol, "rotated connection ahead of the 24h limit");
+                    }
+                }
+                if self.handle(book_schema, received).await? {
+                    let now_ms = Utc::now().timestamp_millis();
+                    self.reconnects.succeed();
+                    if reconnected {
+                        self.metrics.record(Metric::DataGapSeconds, (now_ms - last_write_ms) as f64 / 1000.0);
+                        self.mark_gap(last_write_ms, now_ms).await;
+                        reconnected = false;
+                    }
+                    last_write_ms = now_ms;
+                }
+            }
+
+            while tasks.try_join_next().is_some() {}
+            self.finish().await?;
+            self.metrics.connected(false);
+            self.metrics.flush();
+            if !self.reconnect {
+                return Ok(());
+            }
+            warn!("websocket closed, reconnecting");
+            self.metrics.incr(Metric::Reconnects, 1.0);
+            let alert = self.reconnects.fail(&self.symbol, "websocket closed", Utc::now().timestamp_millis());
+            raise(alert).await;
+            reconnected = true;
+        }
+    }
+
+    /// Consumes payloads forwarded by a `Multiplexer` instead of connecting itself,
+    /// until the sending side is dropped.
+    pub async fn run_channel(&mut self, mut rx: mpsc::Receiver<String>) -> Result<(), Error> {
+        let book_schema = schema::parsed(schema::orderbook(self.version).unwrap_or(schema::ORDERBOOK))?;
+        let mut idle = tokio::time::interval_at(tokio::time::Instant::now() + IDLE_FLUSH, IDLE_FLUSH);
+        loop {
+            let text = tokio::select! {
+                text = rx.recv() => match text {
+                    Some(text) => text,
+                    None => break,
+                },
+                _ = idle.tick() => {
+                    self.flush_ended().await?;
+                    continue;
+                }
+            };
+            self.metrics.maybe_flush();
+            self.handle(book_schema, Received::new(Utc::now().timestamp_millis(), text)).await?;
+        }
+        self.finish().await?;
+        self.metrics.flush();
+        Ok(())
+    }
+
+    /// Waits `backoff` before the next connect attempt, storing books polled over REST
+    /// meanwhile once `failures` connects in a row have failed. Returns whether
+    /// `shutdown` fired instead.
+    async fn wait_to_reconnect(&mut self, book_schema: &'static Schema, backoff: Duration, failures: u32,
+                               shutdown: &mut Option<watch::Receiver<bool>>, last_write_ms: &mut i64) -> Result<bool, Error> {
+        let deadline = tokio::time::Instant::now() + backoff;
+        let Some(fallback) = self.fallback.clone().filter(|f| failures >= f.after) else {
+            return Ok(tokio::select! {
+                _ = tokio::time::sleep_until(deadline) => false,
+                _ = stopped(shutdown) => true,
+            });
+        };
+        if failures == fallback.after {
+            info!(symbol = %self.symbol, interval_ms = fallback.interval.as_millis() as u64, "websocket down, polling REST depth");
+        }
+        while tokio::time::Instant::now() < deadline {
+            let wait = match fallback.fetch().await {
+                Ok(Polled::Book(text)) => {
+                    if self.handle(book_schema, Received::new(Utc::now().timestamp_millis(), text)).await? {
+                        *last_write_ms = Utc::now().timestamp_millis();
+                    }
+                    fallback.interval
+                }
+                Ok(Polled::Limited(wait)) => {
+                    warn!(symbol = %self.symbol, wait_s = wait.as_secs(), "REST depth rate limited");
+                    wait
+                }
+                Err(e) => {
+                    warn!(error = %e, symbol = %self.symbol, "REST depth poll failed");
+                    fallback.interval
+                }
+            };
+            tokio::select! {
+                _ = tokio::time::sleep(wait) => {}
+                _ = stopped(shutdown) => return Ok(true),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Whether a frame from a rotation's new connection shows it has caught up with
+    /// the old one: a book at or past the last update handled, or any quote.
+    fn caught_up(&self, received: &Received) -> bool {
+        match &received.outcome {
+            Some(Outcome::Book(book)) => book.last_update_id >= self.last_update_id,
+            Some(_) => false,
+            None => true,
+        }
+    }
+
+    /// Runs one received payload through the pipeline, unless the compute stage
+    /// already has, and stores the book, returning whether a snapshot was written.
+    async fn handle(&mut self, book_schema: &'static Schema, received: Received) -> Result<bool, Error> {
+        self.counts.received += 1;
+        self.metrics.incr(Metric::MessagesReceived, 1.0);
+        let span = info_span!("message", symbol = %self.symbol, exchange_latency_ms = field::Empty);
+        let Received { received_ms, text, outcome, .. } = received;
+        if let Err(e) = self.archive_raw(received_ms, &text).await {
+            return Err(self.dropped(e));
+        }
+        if self.top.is_some() {
+            return self.handle_quote(&text, received_ms, &span).await;
+        }
+        let outcome = outcome.unwrap_or_else(|| pipeline::process(&text, received_ms, self.version));
+        let mut book = match outcome {
+            Outcome::Book(book) => (*book)
+                .with_source(binance::EXCHANGE, &self.symbol)
+                .with_tick_size(binance::tick_size(&self.symbol).unwrap_or_default())
+                .with_vpin(vpin::estimate(&self.symbol).unwrap_or_default()),
+            Outcome::Skipped => {
+                self.counts.skipped += 1;
+                self.metrics.incr(Metric::MessagesSkipped, 1.0);
+                span.in_scope(|| debug!("skipping message without a two-sided book"));
+                return Ok(false);
+            }
+            Outcome::Malformed(e) => {
+                self.counts.malformed += 1;
+                self.metrics.incr(Metric::ParseFailures, 1.0);
+                span.in_scope(|| warn!(error = %e, "skipping malformed message"));
+                return Ok(false);
+            }
+            Outcome::Invalid(reason) => return self.quarantine(received_ms, text, reason, &span).await,
+        };
+        if book.last_update_id > 0 {
+            let heartbeat_due = self.heartbeat.is_some_and(|every| Utc::now().timestamp_millis() - self.last_stored_ms >= every.as_millis() as i64);
+            if book.last_update_id < self.last_update_id || (book.last_update_id == self.last_update_id && !heartbeat_due) {
+                self.counts.duplicates += 1;
+                span.in_scope(|| debug!(last_update_id = book.last_update_id, "skipping book already handled"));
+                return Ok(false);
+            }
+            self.last_update_id = book.last_update_id;
+        }
+        if let Some(reason) = self.validator.as_mut().and_then(|v| v.check(book.mid_price)) {
+            return self.quarantine(received_ms, text, reason, &span).await;
+        }
+        if let Some(detector) = self.anomalies.as_mut() {
+            book = detector.flag(book);
+        }
+        if let Some(detector) = self.sweeps.as_mut() {
+            let trades = sweep::take(&self.symbol, book.timestamp_ms);
+            book = detector.flag(book, &trades);
+        }
+        self.returns.observe(&book);
+        correlation::publish_mid(&self.symbol, book.mid_price);
+        if let Some(tracker) = self.resiliency.as_mut() {
+            let events = tracker.push(&book, resiliency::large_trades(&self.symbol));
+            if let Err(e) = self.write_resiliency(book.timestamp_ms, &events).await {
+                return Err(self.dropped(e));
+            }
+        }
+        if self.bars_mode != bars::Mode::Off {
+            if let Some(bar) = self.bars.push(&book) {
+                if let Err(e) = self.write_bar(bar).await {
+                    return Err(self.dropped(e));
+                }
+            }
+            if self.bars_mode == bars::Mode::Only {
+                self.counts.aggregated += 1;
+                return Ok(false);
+            }
+        }
+        if !self.sampler.keep(&book) {
+            self.counts.downsampled += 1;
+            self.metrics.incr(Metric::MessagesDownsampled, 1.0);
+            return Ok(false);
+        }
+        if self.window.is_some_and(|w| w.overlapping(tokio::time::Instant::now())) && book.last_update_id > 0 {
+            match self.output.claim(&handoff::claim_key(&self.symbol, book.last_update_id)).await {
+                Ok(true) => {}
+                Ok(false) => {
+                    self.counts.duplicates += 1;
+                    span.in_scope(|| debug!(last_update_id = book.last_update_id, "book already stored by an overlapping invocation"));
+                    return Ok(false);
+                }
+                Err(e) => return Err(self.dropped(e)),
+            }
+        }
+        book = self.returns.stamp(book);
+        if split::enabled() {
+            let (raw, features) = split::records(&mut book);
+            if let Err(e) = self.write_split(&raw, &features).await {
+                return Err(self.dropped(e));
+            }
+        }
+        if let Some(done) = self.stats.as_mut().and_then(|stats| stats.push(&book)) {
+            if let Err(e) = self.write_stats(done).await {
+                return Err(self.dropped(e));
+            }
+        }
+
+        self.store(book_schema, book, span).await
+    }
+
+    /// Writes a payload that failed validation under `quarantine/` instead of storing it.
+    async fn quarantine(&mut self, received_ms: i64, payload: String, reason: Reason, span: &Span) -> Result<bool, Error> {
+        span.in_scope(|| warn!(%reason, "quarantining book"));
+        let quarantined = Quarantined { symbol: self.symbol.to_uppercase(), received_ms, reason: reason.to_string(), payload };
+        let written = match quarantined.object() {
+            Ok((key, body)) => self.output.write(&key, &body).await,
+            Err(e) => Err(e),
+        };
+        if let Err(e) = written {
+            return Err(self.dropped(e));
+        }
+        self.counts.quarantined += 1;
+        self.metrics.incr(Metric::Quarantined, 1.0);
+        Ok(false)
+    }
+
+    /// Adds a `bookTicker` quote to its minute's batch, writing the previous minute
+    /// once it rolls over. Quotes count as stored once batched.
+    async fn handle_quote(&mut self, text: &str, received_ms: i64, span: &Span) -> Result<bool, Error> {
+        let quote = match top::parse(text, received_ms) {
+            Ok(Some(quote)) => quote,
+            Ok(None) => {
+                self.counts.skipped += 1;
+                self.metrics.incr(Metric::MessagesSkipped, 1.0);
+                return Ok(false);
+            }
+            Err(e) => {
+                self.counts.malformed += 1;
+                self.metrics.incr(Metric::ParseFailures, 1.0);
+                span.in_scope(|| warn!(error = %e, "skipping malformed quote"));
+                return Ok(false);
+            }
+        };
+        if quote.update_id <= self.last_update_id {
+            self.counts.duplicates += 1;
+            return Ok(false);
+        }
+        self.last_update_id = quote.update_id;
+        if let Some((minute_ms, quotes)) = self.top.as_mut().and_then(|batch| batch.push(quote)) {
+            if let Err(e) = self.write_quotes(minute_ms, &quotes).await {
+                return Err(self.dropped(e));
+            }
+        }
+        self.counts.stored += 1;
+        self.metrics.incr(Metric::MessagesProcessed, 1.0);
+        Ok(true)
+    }
+
+    /// Writes raw batches, bars and statistics whose minute or hour is over and flushes
+    /// due metrics, for when the stream goes quiet.
+    async fn flush_ended(&mut self) -> Result<(), Error> {
+        self.reap_uploads().await?;
+        let now_ms = Utc::now().timestamp_millis();
+        if let Some((key, body)) = self.raw.as_mut().map(|raw| raw.flush_ended(now_ms)).transpose()?.flatten() {
+            self.output.write(&key, &body).await?;
+        }
+        if let Some(batch) = self.batch.as_mut().map(|b| b.flush_ended(now_ms)).transpose()?.flatten() {
+            self.write_batch(batch).await?;
+        }
+        if let Some(bar) = self.bars.flush_ended(now_ms) {
+            self.write_bar(bar).await?;
+        }
+        if let Some(done) = self.stats.as_mut().and_then(|stats| stats.flush_ended(now_ms)) {
+            self.write_stats(done).await?;
+        }
+        if let Some((minute_ms, quotes)) = self.top.as_mut().and_then(|batch| batch.flush_ended(now_ms)) {
+            self.write_quotes(minute_ms, &quotes).await?;
+        }
+        #[cfg(feature = "delta")]
+        {
+            let books = self.delta.as_mut().and_then(|delta| delta.flush_ended(now_ms));
+            self.append_delta(books).await?;
+        }
+        self.save_checkpoint().await;
+        self.metrics.maybe_flush();
+        Ok(())
+    }
+
+    /// Writes the partial batch of books and waits out the uploads in flight, then
+    /// writes the partial raw batch, bar and hour's statistics when the stream ends.
+    async fn finish(&mut self) -> Result<(), Error> {
+        if let Some(batch) = self.batch.as_mut().map(BookBatcher::flush).transpose()?.flatten() {
+            self.write_batch(batch).await?;
+        }
+        while let Some((flight, result)) = self.uploads.next().await {
+            self.delivered(flight, result).await?;
+        }
+        if let Some((key, body)) = self.raw.as_mut().map(RawBatcher::flush).transpose()?.flatten() {
+            self.output.write(&key, &body).await?;
+        }
+        if let Some(bar) = self.bars.flush() {
+            self.write_bar(bar).await?;
+        }
+        if let Some(done) = self.stats.as_mut().and_then(StatsBuilder::flush) {
+            self.write_stats(done).await?;
+        }
+        if let Some((minute_ms, quotes)) = self.top.as_mut().and_then(QuoteBatcher::flush) {
+            self.write_quotes(minute_ms, &quotes).await?;
+        }
+        #[cfg(feature = "delta")]
+        {
+            let books = self.delta.as_mut().and_then(DeltaSink::flush);
+            self.append_delta(books).await?;
+        }
+        self.save_checkpoint().await;
+        Ok(())
+    }
+
+    /// Records the stretch a reconnect lost as a gap marker, if it was long enough.
+    async fn mark_gap(&mut self, last_write_ms: i64, now_ms: i64) {
+        let Some(gap) = gaps::detect(&self.symbol, last_write_ms, now_ms, gaps::threshold_ms(), "reconnect") else { return };
+        warn!(symbol = %self.symbol, gap_ms = gap.duration_ms(), "gap while reconnecting");
+        if let Err(e) = gaps::record(&mut self.output, &gap).await {
+            warn!(error = %e, symbol = %self.symbol, "failed to write gap marker");
+        }
+    }
+
+    /// Saves the last stored book as the checkpoint. A failed save only costs
+    /// precision in gap detection, so it doesn't stop the run.
+    async fn save_checkpoint(&mut self) {
+        let Some(checkpoint) = self.checkpoint.take() else { return };
+        if let Err(e) = self.output.checkpoint(&checkpoint).await {
+            warn!(error = %e, symbol = %self.symbol, "failed to save checkpoint");
+        }
+    }
+
+    /// Adds the payload to the raw batch, writing the previous minute once it rolls over.
+    async fn archive_raw(&mut self, received_ms: i64, payload: &str) -> Result<(), Error> {
+        if let Some(batch) = self.raw.as_mut() {
+            if let Some((key, body)) = batch.push(received_ms, payload)? {
+                self.output.write(&key, &body).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes a finished bar under `aggregates/`, keyed by its first book, so the
+    /// parts of a minute stored by overlapping or successive runs each keep their own.
+    async fn write_bar(&mut self, bar: Bar) -> Result<(), Error> {
+        let key = sink::partition_key(bars::BARS_PREFIX, &self.symbol, sink::at_ms(bar.start_ms), bar.first_ms)?;
+        let body = sink::encode(schema::BAR, &[bar])?;
+        self.output.write(&key, &body).await?;
+        Ok(())
+    }
+
+    /// Writes an hour's statistics under `stats/`, keyed by its first book, so the
+    /// parts of an hour stored by overlapping or successive runs each keep their own.
+    async fn write_stats(&mut self, stats: HourlyStats) -> Result<(), Error> {
+        let key = sink::partition_key(stats::STATS_PREFIX, &self.symbol, sink::at_ms(stats.hour_ms), stats.first_ms)?;
+        let body = sink::encode(schema::HOURLY_STATS, &[stats])?;
+        self.output.write(&key, &body).await?;
+        Ok(())
+    }
+
+    /// Writes the depletion events a book closed under `resiliency/`, keyed by that book.
+    async fn write_resiliency(&mut self, at_ms: i64, events: &[Resiliency]) -> Result<(), Error> {
+        if events.is_empty() {
+            return Ok(());
+        }
+        let key = sink::partition_key(resiliency::RESILIENCY_PREFIX, &self.symbol, sink::at_ms(at_ms), at_ms)?;
+        let body = sink::encode(schema::RESILIENCY, events)?;
+        self.output.write(&key, &body).await?;
+        Ok(())
+    }
+
+    /// Writes a book's raw levels under `books/` and its features under `features/`,
+    /// keyed like the book.
+    async fn write_split(&mut self, raw: &RawBook, features: &Features) -> Result<(), Error> {
+        let at = sink::at_ms(raw.timestamp_ms);
+        let key = sink::partition_key(split::RAW_BOOK_PREFIX, &self.symbol, at, raw.timestamp_ms)?;
+        self.output.write(&key, &sink::encode(schema::RAW_BOOK, std::slice::from_ref(raw))?).await?;
+        let key = sink::partition_key(split::FEATURES_PREFIX, &self.symbol, at, features.timestamp_ms)?;
+        self.output.write(&key, &sink::encode(schema::FEATURES, std::slice::from_ref(features))?).await?;
+        Ok(())
+    }
+
+    /// Writes a minute of quotes under `top/`, keyed by its first quote, so the parts
+    /// of a minute stored by overlapping or successive runs each keep their own.
+    async fn write_quotes(&mut self, minute_ms: i64, quotes: &[Quote]) -> Result<(), Error> {
+        let first_ms = quotes.first().map_or(minute_ms, Quote::partition_time_ms);
+        let key = sink::partition_key(top::TOP_PREFIX, &self.symbol, sink::at_ms(minute_ms), first_ms)?;
+        let body = sink::encode(schema::QUOTE, quotes)?;
+        self.output.write(&key, &body).await?;
+        Ok(())
+    }
+
+    /// Commits a finished batch of books to the Delta table.
+    #[cfg(feature = "delta")]
+    async fn append_delta(&mut self, books: Option<Vec<OrderBook>>) -> Result<(), Error> {
+        let (Some(delta), Some(books)) = (self.delta.as_mut(), books) else { return Ok(()) };
+        let version = delta.append(&books).await?;
+        debug!(symbol = %self.symbol, version, records = books.len(), "appended to delta table");
+        Ok(())
+    }
+
+    /// Counts the current message as lost to `e` before the run stops on it.
+    fn dropped(&mut self, e: Error) -> Error {
+        self.counts.dropped += 1;
+        self.metrics.incr(Metric::MessagesDropped, 1.0);
+        self.metrics.flush();
+        e
+    }
+
+    /// Encodes one snapshot and uploads it, or adds it to the window's batch and
+    /// uploads the batch before once the window rolls over. Returns whether it was
+    /// stored or handed off.
+    async fn store(&mut self, schema: &'static Schema, book: OrderBook, span: Span) -> Result<bool, Error> {
+        if let Some(batch) = self.batch.as_mut() {
+            // batched books would reach subscribers a window late
+            live::publish(&book);
+            return match batch.push(schema, book) {
+                Ok(Some(done)) => self.write_batch(done).await,
+                Ok(None) => Ok(true),
+                Err(e) => Err(self.write_failed(e, 1).await),
+            };
+        }
+        if let Err(e) = sink::encode_into(schema, std::slice::from_ref(&book), &mut self.buf) {
+            return Err(self.write_failed(e, 1).await);
+        }
+        self.upload(book.partition_time_ms(), key_id(&book), vec![book], span).await
+    }
+
+    /// Uploads a finished window of books as one object, keyed by its first book like
+    /// a book of its own, so runs that each batched part of a window keep their parts.
+    async fn write_batch(&mut self, batch: Batch) -> Result<bool, Error> {
+        let span = info_span!("batch", symbol = %self.symbol, books = batch.books.len(), exchange_latency_ms = field::Empty);
+        self.buf.clear();
+        self.buf.extend_from_slice(&batch.body);
+        let id_ms = batch.books.first().map_or(batch.start_ms, key_id);
+        self.upload(batch.start_ms, id_ms, batch.books, span).await
+    }
+
+    /// Writes `books`, encoded in `buf`, under the key for `at_ms` and `id_ms`:
+    /// alongside up to `upload_concurrency - 1` others when the output supports it,
+    /// else before returning.
+    async fn upload(&mut self, at_ms: i64, id_ms: i64, books: Vec<OrderBook>, span: Span) -> Result<bool, Error> {
+        let key = match sink::partition_key("orderbook", &self.symbol, sink::at_ms(at_ms), id_ms) {
+            Ok(key) => key,
+            Err(e) => return Err(self.write_failed(e, books.len()).await),
+        };
+        let id = sink::idempotent().then(|| match &books[..] {
+            [book] => sink::book_id(book.last_update_id, &self.buf),
+            _ => sink::book_id(0, &self.buf),
+        });
+
+        if self.upload_concurrency > 1 {
+            if let Some(upload) = self.output.upload(&key, self.buf.clone(), id.clone()) {
+                while self.uploads.len() >= self.upload_concurrency {
+                    let Some((flight, result)) = self.uploads.next().await else { break };
+                    self.delivered(flight, result).await?;
+                }
+                let flight = InFlight { key, books, span: span.clone() };
+                self.uploads.push(Box::pin(upload.instrument(span).map(move |result| (flight, result))));
+                self.reap_uploads().await?;
+                return Ok(true);
+            }
+        }
+        let result = match &id {
+            Some(id) => self.output.write_once(&key, &self.buf, id).instrument(span.clone()).await,
+            None => self.output.write(&key, &self.buf).instrument(span.clone()).await,
+        };
+        self.delivered(InFlight { key, books, span }, result).await
+    }
+
+    /// Accounts for the uploads that have already finished, without waiting on the rest.
+    async fn reap_uploads(&mut self) -> Result<(), Error> {
+        while let Some(Some((flight, result))) = self.uploads.next().now_or_never() {
+            self.delivered(flight, result).await?;
+        }
+        Ok(())
+    }
+
+    /// Records a finished upload: put and latency metrics, counts, the checkpoint and
+    /// alerts. Returns whether the books were written, stopping the run on a failed write.
+    async fn delivered(&mut self, flight: InFlight, result: Result<Delivery, Error>) -> Result<bool, Error> {
+        let InFlight { key, books, span } = flight;
+        let n = books.len() as u64;
+        let delivery = match result {
+            Ok(delivery) => delivery,
+            Err(e) => return Err(self.write_failed(e, books.len()).await),
+        };
+        match delivery {
+            Delivery::Stored { latency, retries } => {
+                self.counts.stored += n;
+                self.writes.succeed();
+                self.metrics.record(Metric::S3PutLatency, latency.as_millis() as f64);
+                self.metrics.incr(Metric::S3Retries, retries as f64);
+
+                let stored_ms = Utc::now().timestamp_millis();
+                self.last_stored_ms = stored_ms;
+                // uploads finish out of order; the checkpoint only moves forward
+                let last_update_id = books.iter().map(|b| b.last_update_id).max().unwrap_or_default();
+                if self.checkpoint.as_ref().is_none_or(|c| c.last_update_id <= last_update_id) {
+                    self.checkpoint = Some(Checkpoint {
+                        symbol: self.symbol.to_uppercase(),
+                        last_update_id,
+                        last_flush_ms: stored_ms,
+                        last_key: key,
+                    });
+                }
+                for book in &books {
+                    // spot partial depth has no exchange clock, so only ingest latency is known for it
+                    let (received_ms, event_ms) = (book.timestamp_ms, (book.event_time_ms > 0).then_some(book.event_time_ms));
+                    self.metrics.record(Metric::IngestLatency, (stored_ms - received_ms) as f64);
+                    if let Some(event_ms) = event_ms {
+                        span.record("exchange_latency_ms", received_ms - event_ms);
+                        self.metrics.record(Metric::ExchangeLatency, (received_ms - event_ms) as f64);
+                        self.metrics.record(Metric::EndToEndLatency, (stored_ms - event_ms) as f64);
+                    }
+                    if self.batch.is_none() {
+                        live::publish(book);
+                    }
+                    span.in_scope(|| debug!(
+                        ingest_latency_ms = stored_ms - received_ms,
+                        end_to_end_latency_ms = event_ms.map(|e| stored_ms - e),
+                        "snapshot stored"
+                    ));
+                }
+                #[cfg(feature = "delta")]
+                for book in books {
+                    let books = self.delta.as_mut().and_then(|delta| delta.push(book));
+                    self.append_delta(books).await?;
+                }
+            }
+            Delivery::DeadLettered => {
+                self.counts.dead_lettered += n;
+                self.metrics.incr(Metric::DeadLetters, 1.0);
+                let alert = self.writes.fail(&self.symbol, "dead-lettered after retries", Utc::now().timestamp_millis());
+                raise(alert).await;
+            }
+            Delivery::Duplicate => {
+                self.counts.duplicates += n;
+                self.writes.succeed();
+                span.in_scope(|| debug!(key, "already stored under its key"));
+                return Ok(false);
+            }
+        }
+        self.metrics.incr(Metric::MessagesProcessed, n as f64);
+        Ok(true)
+    }
+
+    /// Alerts on a failed write and counts its `books` as dropped.
+    async fn write_failed(&mut self, e: Error, books: usize) -> Error {
+        let alert = self.writes.fail(&self.symbol, &e.to_string(), Utc::now().timestamp_millis());
+        raise(alert).await;
+        self.counts.dropped += books as u64;
+        self.metrics.incr(Metric::MessagesDropped, books as f64);
+        self.metrics.flush();
+        e
+    }
+}
+
+/// Publishes the alert a streak just raised, if any; failing to is only logged.
+async fn raise(alert: Option<Alert>) {
+    let Some(alert) = alert else { return };
+    warn!(symbol = alert.symbol, error_class = alert.error_class, failures = alert.failures, duration_ms = alert.duration_ms, "repeated failures, alerting");
+    #[cfg(feature = "alerts")]
+    if let Err(e) = alert::publish(&alert).await {
+        warn!(error = %e, "alert not published");
+    }
+}
+
+/// Connects to `url` and sends `subscription`, if any, returning the read half.
+async fn connect(url: &str, subscription: Option<String>) -> Result<SplitStream<Socket>, Error> {
+    let ws = proxy::connect(url).await?;
+    let (mut tx, rx) = ws.split();
+    if let Some(request) = subscription {
+        tx.send(Message::Text(request)).await?;
+    }
+    Ok(rx)
+}
+
+/// `ROTATE_AFTER_SECS`, or 23 hours: a new connection replaces the current one
+/// before Binance closes it at 24.
+pub(crate) fn rotate_after() -> Duration {
+    Duration::from_secs(config::var("ROTATE_AFTER_SECS").and_then(|s| s.parse().ok()).filter(|&s| s > 0).unwrap_or(23 * 3600))
+}
+
+/// `UPLOAD_CONCURRENCY`, or 4 book uploads in flight at once.
+fn upload_concurrency() -> usize {
+    config::var("UPLOAD_CONCURRENCY").and_then(|n| n.parse().ok()).filter(|&n| n > 0).unwrap_or(DEFAULT_UPLOAD_CONCURRENCY)
+}
+
+/// `HEARTBEAT_SECS`, or `None` when unset or not a number.
+fn heartbeat() -> Option<Duration> {
+    config::var("HEARTBEAT_SECS").and_then(|s| s.parse().ok()).map(Duration::from_secs)
+}
+
+/// Resolves once `shutdown` turns true; never without a shutdown channel or once its
+/// sender is gone.
+/// The id `book` is keyed by. With idempotent writes it comes from the exchange, so
+/// every instance receiving the book picks the same one: its event time, or for spot
+/// partial depth, which has none, its lastUpdateId.
+fn key_id(book: &OrderBook) -> i64 {
+    match (sink::idempotent(), book.event_time_ms) {
+        (true, 0) => book.last_update_id,
+        (true, event_ms) => event_ms,
+        (false, _) => book.timestamp_ms,
+    }
+}
+
+pub(crate) async fn stopped(shutdown: &mut Option<watch::Receiver<bool>>) {
+    if let Some(rx) = shutdown {
+        if rx.wait_for(|stop| *stop).await.is_ok() {
+            return;
+        }
+    }
+    std::future::pending().await
+}
+
+/// Runs a collector per symbol, each on its own task with its own connection, batching
+/// and uploads, so a slow upload for one symbol never holds up another. The first
+/// failure stops the rest and is returned; each finished collector logs its counts.
+pub async fn run_each<O, F>(symbols: &[String], mut collector_for: F) -> Result<BTreeMap<String, MessageCounts>, Error>
+where
+    O: Output + Send + 'static,
+    F: FnMut(&str) -> Collector<O>,
+{
+    let mut tasks = JoinSet::new();
+    for symbol in symbols {
+        let mut collector = collector_for(symbol);
+        tasks.spawn(async move {
+            let result = collector.run().await;
+            (collector, result)
+        });
+    }
+
+    let mut counts = BTreeMap::new();
+    while let Some(joined) = tasks.join_next().await {
+        let (collector, result) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        info!(symbol = %collector.symbol, counts = ?collector.counts(), "message accounting");
+        counts.insert(collector.symbol.clone(), collector.counts());
+        // dropping the set aborts the collectors still running
+        result?;
+    }
+    Ok(counts)
+}
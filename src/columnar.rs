@@ -0,0 +1,173 @@
+//! Arrow/Parquet encoding of OrderBook records.
+
+use arrow_array::builder::{Float64Builder, Int64Builder, ListBuilder, StructBuilder};
+use arrow_array::{ArrayRef, BooleanArray, Float64Array, Int32Array, Int64Array, RecordBatch, StringArray};
+use arrow_schema::{DataType, Field, Fields, Schema};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+use std::sync::Arc;
+
+use crate::book::{ExactLevel, Level};
+use crate::{Error, OrderBook};
+
+fn level_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("price", DataType::Float64, false),
+        Field::new("qty", DataType::Float64, false),
+    ])
+}
+
+fn exact_level_fields() -> Fields {
+    Fields::from(vec![
+        Field::new("price", DataType::Int64, false),
+        Field::new("qty", DataType::Int64, false),
+    ])
+}
+
+/// Arrow schema mirroring the current Avro OrderBook schema.
+pub fn orderbook_schema() -> Arc<Schema> {
+    let levels = DataType::List(Arc::new(Field::new("item", DataType::Struct(level_fields()), true)));
+    let exact_levels = DataType::List(Arc::new(Field::new("item", DataType::Struct(exact_level_fields()), true)));
+    let amounts = DataType::List(Arc::new(Field::new("item", DataType::Float64, true)));
+    Arc::new(Schema::new(vec![
+        Field::new("timestamp_ms", DataType::Int64, false),
+        Field::new("bids", levels.clone(), false),
+        Field::new("asks", levels.clone(), false),
+        Field::new("spread", DataType::Float64, false),
+        Field::new("mid_price", DataType::Float64, false),
+        Field::new("imbalance_ratio", DataType::Float64, false),
+        Field::new("schema_version", DataType::Int32, false),
+        Field::new("exchange", DataType::Utf8, false),
+        Field::new("symbol", DataType::Utf8, false),
+        Field::new("event_time_ms", DataType::Int64, false),
+        Field::new("last_update_id", DataType::Int64, false),
+        Field::new("exact_bids", exact_levels.clone(), false),
+        Field::new("exact_asks", exact_levels, false),
+        Field::new("price_scale", DataType::Int32, false),
+        Field::new("qty_scale", DataType::Int32, false),
+        Field::new("bid_notional", amounts.clone(), false),
+        Field::new("ask_notional", amounts.clone(), false),
+        Field::new("bid_sweeps", levels.clone(), false),
+        Field::new("ask_sweeps", levels, false),
+        Field::new("spread_bps", DataType::Float64, false),
+        Field::new("tick_size", DataType::Float64, false),
+        Field::new("spread_in_ticks", DataType::Float64, false),
+        Field::new("vpin", DataType::Float64, false),
+        Field::new("vpin_buckets", DataType::Int32, false),
+        Field::new("bid_fill_prices", amounts.clone(), false),
+        Field::new("ask_fill_prices", amounts.clone(), false),
+        Field::new("bid_slippage_bps", amounts.clone(), false),
+        Field::new("ask_slippage_bps", amounts, false),
+        Field::new("spread_mads", DataType::Float64, false),
+        Field::new("depth_mads", DataType::Float64, false),
+        Field::new("spread_anomaly", DataType::Boolean, false),
+        Field::new("depth_anomaly", DataType::Boolean, false),
+        Field::new("mid_return", DataType::Float64, false),
+        Field::new("mid_return_window", DataType::Float64, false),
+        Field::new("return_window_secs", DataType::Int32, false),
+        Field::new("sweep", DataType::Boolean, false),
+        Field::new("sweep_side", DataType::Utf8, false),
+        Field::new("sweep_levels", DataType::Int32, false),
+        Field::new("sweep_notional", DataType::Float64, false),
+    ]))
+}
+
+fn levels_column(books: &[OrderBook], side: impl Fn(&OrderBook) -> &[Level]) -> ArrayRef {
+    let values = StructBuilder::new(level_fields(), vec![
+        Box::new(Float64Builder::new()),
+        Box::new(Float64Builder::new()),
+    ]);
+    let mut list = ListBuilder::new(values);
+    for book in books {
+        let levels = list.values();
+        for level in side(book) {
+            levels.field_builder::<Float64Builder>(0).expect("price column").append_value(level.price);
+            levels.field_builder::<Float64Builder>(1).expect("qty column").append_value(level.qty);
+            levels.append(true);
+        }
+        list.append(true);
+    }
+    Arc::new(list.finish())
+}
+
+fn exact_levels_column(books: &[OrderBook], side: impl Fn(&OrderBook) -> &[ExactLevel]) -> ArrayRef {
+    let values = StructBuilder::new(exact_level_fields(), vec![
+        Box::new(Int64Builder::new()),
+        Box::new(Int64Builder::new()),
+    ]);
+    let mut list = ListBuilder::new(values);
+    for book in books {
+        let levels = list.values();
+        for level in side(book) {
+            levels.field_builder::<Int64Builder>(0).expect("price column").append_value(level.price);
+            levels.field_builder::<Int64Builder>(1).expect("qty column").append_value(level.qty);
+            levels.append(true);
+        }
+        list.append(true);
+    }
+    Arc::new(list.finish())
+}
+
+fn amounts_column(books: &[OrderBook], side: impl Fn(&OrderBook) -> &[f64]) -> ArrayRef {
+    let mut list = ListBuilder::new(Float64Builder::new());
+    for book in books {
+        list.values().append_slice(side(book));
+        list.append(true);
+    }
+    Arc::new(list.finish())
+}
+
+pub fn to_record_batch(books: &[OrderBook]) -> Result<RecordBatch, Error> {
+    let float = |f: fn(&OrderBook) -> f64| -> ArrayRef { Arc::new(books.iter().map(f).collect::<Float64Array>()) };
+    let columns: Vec<ArrayRef> = vec![
+        Arc::new(books.iter().map(|b| b.timestamp_ms).collect::<Int64Array>()),
+        levels_column(books, |b| &b.bids),
+        levels_column(books, |b| &b.asks),
+        float(|b| b.spread),
+        float(|b| b.mid_price),
+        float(|b| b.imbalance_ratio),
+        Arc::new(books.iter().map(|b| b.schema_version).collect::<Int32Array>()),
+        Arc::new(books.iter().map(|b| Some(b.exchange.as_str())).collect::<StringArray>()),
+        Arc::new(books.iter().map(|b| Some(b.symbol.as_str())).collect::<StringArray>()),
+        Arc::new(books.iter().map(|b| b.event_time_ms).collect::<Int64Array>()),
+        Arc::new(books.iter().map(|b| b.last_update_id).collect::<Int64Array>()),
+        exact_levels_column(books, |b| &b.exact_bids),
+        exact_levels_column(books, |b| &b.exact_asks),
+        Arc::new(books.iter().map(|b| b.price_scale).collect::<Int32Array>()),
+        Arc::new(books.iter().map(|b| b.qty_scale).collect::<Int32Array>()),
+        amounts_column(books, |b| &b.bid_notional),
+        amounts_column(books, |b| &b.ask_notional),
+        levels_column(books, |b| &b.bid_sweeps),
+        levels_column(books, |b| &b.ask_sweeps),
+        float(|b| b.spread_bps),
+        float(|b| b.tick_size),
+        float(|b| b.spread_in_ticks),
+        float(|b| b.vpin),
+        Arc::new(books.iter().map(|b| b.vpin_buckets).collect::<Int32Array>()),
+        amounts_column(books, |b| &b.bid_fill_prices),
+        amounts_column(books, |b| &b.ask_fill_prices),
+        amounts_column(books, |b| &b.bid_slippage_bps),
+        amounts_column(books, |b| &b.ask_slippage_bps),
+        float(|b| b.spread_mads),
+        float(|b| b.depth_mads),
+        Arc::new(books.iter().map(|b| Some(b.spread_anomaly)).collect::<BooleanArray>()),
+        Arc::new(books.iter().map(|b| Some(b.depth_anomaly)).collect::<BooleanArray>()),
+        float(|b| b.mid_return),
+        float(|b| b.mid_return_window),
+        Arc::new(books.iter().map(|b| b.return_window_secs).collect::<Int32Array>()),
+        Arc::new(books.iter().map(|b| Some(b.sweep)).collect::<BooleanArray>()),
+        Arc::new(books.iter().map(|b| Some(b.sweep_side.as_str())).collect::<StringArray>()),
+        Arc::new(books.iter().map(|b| b.sweep_levels).collect::<Int32Array>()),
+        float(|b| b.sweep_notional),
+    ];
+    Ok(RecordBatch::try_new(orderbook_schema(), columns)?)
+}
+
+/// Snappy-compressed Parquet file holding `books`.
+pub fn write_parquet(books: &[OrderBook]) -> Result<Vec<u8>, Error> {
+    let props = WriterProperties::builder().set_compression(Compression::SNAPPY).build();
+    let mut writer = ArrowWriter::try_new(Vec::new(), orderbook_schema(), Some(props))?;
+    writer.write(&to_record_batch(books)?)?;
+    Ok(writer.into_inner()?)
+}
@@ -0,0 +1,62 @@
+//! Merges an hour of small per-snapshot objects into one compressed file, and
+//! writes the hour's manifest pointing at it along with any files compacted before.
+
+use apache_avro::Codec;
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::info;
+
+use crate::archive::Archive;
+use crate::manifest::{self, FileEntry, Manifest};
+use crate::{migrate, schema, sink, Error, OrderBook};
+
+#[derive(Debug, Serialize)]
+pub struct Compaction {
+    pub source_objects: usize,
+    pub records: usize,
+    pub key: Option<String>,
+    pub manifest: Option<String>,
+}
+
+/// Compacts `symbol`'s `orderbook/` partition for the hour containing `hour` into a
+/// file in the same partition, so the Glue table reads it in place of the objects
+/// it replaces. Originals are deleted only after the merged file is written, and
+/// the manifest naming it goes into the partition last.
+pub async fn compact_hour(s3: &Client, symbol: &str, hour: DateTime<Utc>) -> Result<Compaction, Error> {
+    let archive = Archive::S3(s3.clone());
+    let dir = sink::hour_dir("orderbook", symbol, hour)?;
+    // an hour compacted before keeps the files its manifest already named; only the
+    // objects that landed since are merged
+    let mut files = manifest::read(&archive, "orderbook", symbol, hour).await?.map_or_else(Vec::new, |m| m.files);
+    let mut keys: Vec<String> = sink::list_keys(s3, &format!("{}/", dir)).await?.into_iter()
+        .filter(|k| k.ends_with(".avro") && !files.iter().any(|f| &f.key == k))
+        .collect();
+    if keys.is_empty() {
+        return Ok(Compaction { source_objects: 0, records: 0, key: None, manifest: None });
+    }
+
+    let mut books: Vec<OrderBook> = Vec::new();
+    for key in &keys {
+        books.extend(migrate::read_orderbooks(&sink::get(s3, key).await?)?);
+    }
+    // a merged file left by a run that failed before its manifest is among the
+    // sources, along with the originals it failed to delete
+    books.sort_by_key(|b| (b.timestamp_ms, b.last_update_id));
+    books.dedup_by_key(|b| (b.timestamp_ms, b.last_update_id));
+
+    let (first, last) = (books.first().map_or(0, |b| b.timestamp_ms), books.last().map_or(0, |b| b.timestamp_ms));
+    let mut key = format!("{}/{}-{}.compacted.avro", dir, first, last);
+    if files.iter().any(|f| f.key == key) {
+        key = format!("{}/{}-{}-{}.compacted.avro", dir, first, last, files.len());
+    }
+    let body = sink::encode_with_codec(schema::ORDERBOOK, &books, Codec::Deflate)?;
+    sink::put(s3, &key, body).await?;
+    keys.retain(|k| k != &key);
+    sink::delete_keys(s3, &keys).await?;
+    files.push(FileEntry::new(&key, &books));
+    let manifest = manifest::write(&archive, &Manifest::new("orderbook", symbol, hour, files)).await?;
+
+    info!(key, manifest, source_objects = keys.len(), records = books.len(), "compacted hour");
+    Ok(Compaction { source_objects: keys.len(), records: books.len(), key: Some(key), manifest: Some(manifest) })
+}
@@ -0,0 +1,38 @@
+use chrono::{DateTime, Duration, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use orderbook::{compact, config, layout, logging, params};
+use std::collections::BTreeMap;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    logging::init();
+    params::init().await?;
+    // fail the cold start on bad storage settings rather than the first invocation
+    config::storage()?;
+    layout::template()?;
+    config::symbols()?;
+    run(service_fn(handler)).await
+}
+
+/// Compacts the hour given as `{"hour": "<RFC 3339>", "symbol": "BTCUSDT"}`, or the
+/// previous hour of each of `SYMBOLS` when scheduled.
+async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<BTreeMap<String, compact::Compaction>, Error> {
+    let s3 = config::s3_client().await?;
+
+    let hour = match event.payload["hour"].as_str() {
+        Some(h) => DateTime::parse_from_rfc3339(h)?.with_timezone(&Utc),
+        None => Utc::now() - Duration::hours(1),
+    };
+
+    let symbols = match event.payload["symbol"].as_str() {
+        Some(symbol) => vec![symbol.to_string()],
+        None => config::symbols()?,
+    };
+
+    let mut compactions = BTreeMap::new();
+    for symbol in symbols {
+        let compaction = compact::compact_hour(&s3, &symbol, hour).await?;
+        compactions.insert(symbol, compaction);
+    }
+    Ok(compactions)
+}
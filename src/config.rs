@@ -0,0 +1,213 @@
+//! Where data is stored, read from the environment so the same build can run in
+//! dev, staging and prod accounts. Settings loaded from Parameter Store (see
+//! `params`) take precedence over the environment.
+
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_s3::Client;
+use std::collections::BTreeMap;
+use std::sync::{OnceLock, RwLock};
+
+use crate::Error;
+
+pub const DEFAULT_BUCKET: &str = "orderbook-data";
+pub const DEFAULT_SYMBOLS: &str = "btcusdt";
+
+static OVERRIDES: RwLock<BTreeMap<String, String>> = RwLock::new(BTreeMap::new());
+
+/// A setting by its environment variable name: the loaded override when there is
+/// one, else the environment.
+pub fn var(name: &str) -> Option<String> {
+    let overridden = OVERRIDES.read().ok().and_then(|o| o.get(name).cloned());
+    overridden.or_else(|| std::env::var(name).ok())
+}
+
+/// Replaces every override. Settings read once per process (storage, key template,
+/// depth buckets) only see values set before their first use.
+pub fn set_overrides(values: BTreeMap<String, String>) {
+    if let Ok(mut overrides) = OVERRIDES.write() {
+        *overrides = values;
+    }
+}
+
+/// Storage classes objects can be written with: those that can be read back straight
+/// away, since compaction, recovery and the tools read what the collector wrote.
+pub const STORAGE_CLASSES: [&str; 5] = ["STANDARD", "INTELLIGENT_TIERING", "STANDARD_IA", "ONEZONE_IA", "GLACIER_IR"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Storage {
+    pub bucket: String,
+    /// Prepended to every key in the main bucket, without surrounding slashes.
+    /// Empty writes at the bucket root.
+    pub prefix: String,
+    /// Overrides the SDK's usual region lookup when set.
+    pub region: Option<String>,
+    /// Customer managed KMS key every write is encrypted with (SSE-KMS); the bucket's
+    /// default encryption applies when unset.
+    pub kms_key: Option<String>,
+    /// Storage class for writes, by dataset (the key's first segment) and otherwise;
+    /// S3's default (`STANDARD`) when neither is set.
+    pub storage_class: Option<String>,
+    pub storage_classes: BTreeMap<String, String>,
+}
+
+impl Storage {
+    /// Validates the bucket name and region; slashes around `prefix` are dropped.
+    pub fn new(bucket: &str, prefix: &str, region: Option<&str>) -> Result<Self, Error> {
+        if !valid_bucket(bucket) {
+            return Err(Error::Config(format!("invalid bucket name {:?}", bucket)));
+        }
+        let prefix = prefix.trim_matches('/');
+        let empty_segment = !prefix.is_empty() && prefix.split('/').any(str::is_empty);
+        if empty_segment || prefix.chars().any(char::is_control) {
+            return Err(Error::Config(format!("invalid key prefix {:?}", prefix)));
+        }
+        if let Some(region) = region.filter(|r| !valid_region(r)) {
+            return Err(Error::Config(format!("invalid region {:?}", region)));
+        }
+        Ok(Storage { bucket: bucket.to_string(), prefix: prefix.to_string(), region: region.map(str::to_string), kms_key: None, storage_class: None, storage_classes: BTreeMap::new() })
+    }
+
+    /// Encrypts writes with `kms_key`, a KMS key or alias ARN.
+    pub fn with_kms_key(mut self, kms_key: Option<&str>) -> Result<Self, Error> {
+        if let Some(key) = kms_key.filter(|k| !valid_kms_arn(k)) {
+            return Err(Error::Config(format!("invalid KMS key ARN {:?}", key)));
+        }
+        self.kms_key = kms_key.map(str::to_string);
+        Ok(self)
+    }
+
+    /// Writes with storage class `default`, and with the classes of a list such as
+    /// `raw:ONEZONE_IA,fulldepth:INTELLIGENT_TIERING` for those datasets.
+    pub fn with_storage_classes(mut self, default: Option<&str>, per_dataset: Option<&str>) -> Result<Self, Error> {
+        let class = |c: &str| {
+            let c = c.trim().to_uppercase();
+            if !STORAGE_CLASSES.contains(&c.as_str()) {
+                return Err(Error::Config(format!("unsupported storage class {:?}, expected one of {}", c, STORAGE_CLASSES.join(", "))));
+            }
+            Ok(c)
+        };
+        self.storage_class = default.map(class).transpose()?;
+        for entry in per_dataset.unwrap_or_default().split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let (dataset, name) = entry.split_once(':')
+                .filter(|(d, _)| !d.trim().is_empty() && !d.contains('/'))
+                .ok_or_else(|| Error::Config(format!("invalid storage class entry {:?}, expected dataset:CLASS", entry)))?;
+            self.storage_classes.insert(dataset.trim().to_string(), class(name)?);
+        }
+        Ok(self)
+    }
+
+    /// The storage class to write `key` (relative to the prefix) with, if any.
+    pub fn storage_class(&self, key: &str) -> Option<&str> {
+        let dataset = key.split('/').next().unwrap_or_default();
+        self.storage_classes.get(dataset).or(self.storage_class.as_ref()).map(String::as_str)
+    }
+
+    /// Reads `BUCKET_NAME` (default `orderbook-data`), `S3_PREFIX`, `S3_REGION`,
+    /// `S3_KMS_KEY_ARN`, `S3_STORAGE_CLASS` and `S3_STORAGE_CLASSES`.
+    pub fn from_env() -> Result<Self, Error> {
+        let var = |name: &str| var(name).filter(|v| !v.is_empty());
+        Storage::new(
+            &var("BUCKET_NAME").unwrap_or_else(|| DEFAULT_BUCKET.into()),
+            &var("S3_PREFIX").unwrap_or_default(),
+            var("S3_REGION").as_deref(),
+        )?
+        .with_kms_key(var("S3_KMS_KEY_ARN").as_deref())?
+        .with_storage_classes(var("S3_STORAGE_CLASS").as_deref(), var("S3_STORAGE_CLASSES").as_deref())
+    }
+
+    /// Object key in the bucket for a key relative to the prefix.
+    pub fn key(&self, key: &str) -> String {
+        if self.prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", self.prefix, key)
+        }
+    }
+
+    /// Inverse of `key`; keys outside the prefix come back unchanged.
+    pub fn relative<'a>(&self, key: &'a str) -> &'a str {
+        if self.prefix.is_empty() {
+            return key;
+        }
+        key.strip_prefix(&self.prefix).and_then(|k| k.strip_prefix('/')).unwrap_or(key)
+    }
+}
+
+/// The process-wide settings, read and validated on first use. Binaries call this
+/// at startup so a bad value fails the cold start instead of the first write.
+pub fn storage() -> Result<&'static Storage, Error> {
+    static STORAGE: OnceLock<Storage> = OnceLock::new();
+
+    if let Some(storage) = STORAGE.get() {
+        return Ok(storage);
+    }
+    let storage = Storage::from_env()?;
+    Ok(STORAGE.get_or_init(|| storage))
+}
+
+/// An S3 client for the configured region.
+pub async fn s3_client() -> Result<Client, Error> {
+    let mut loader = aws_config::defaults(BehaviorVersion::latest());
+    if let Some(region) = &storage()?.region {
+        loader = loader.region(Region::new(region.clone()));
+    }
+    Ok(Client::new(&loader.load().await))
+}
+
+/// Lower-cased stream names from a comma-separated list such as `BTCUSDT, ethusdt`,
+/// without duplicates. An empty list or a name with anything but letters, digits and
+/// `_` is rejected; `binance::Venue::validate_symbol` checks the venue's own rules.
+pub fn parse_symbols(list: &str) -> Result<Vec<String>, Error> {
+    let mut symbols: Vec<String> = Vec::new();
+    for symbol in list.split(',').map(str::trim).filter(|s| !s.is_empty()) {
+        if !symbol.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(Error::Config(format!("invalid symbol {:?}", symbol)));
+        }
+        let symbol = symbol.to_ascii_lowercase();
+        if !symbols.contains(&symbol) {
+            symbols.push(symbol);
+        }
+    }
+    if symbols.is_empty() {
+        return Err(Error::Config(format!("no symbols in {:?}", list)));
+    }
+    Ok(symbols)
+}
+
+/// The symbols to collect, from `SYMBOLS` (default `btcusdt`).
+pub fn symbols() -> Result<Vec<String>, Error> {
+    parse_symbols(&var("SYMBOLS").unwrap_or_else(|| DEFAULT_SYMBOLS.into()))
+}
+
+/// S3 naming rules: 3-63 lowercase letters, digits, dots and hyphens, starting and
+/// ending with a letter or digit, with no empty dot-separated labels.
+fn valid_bucket(name: &str) -> bool {
+    let alnum = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    (3..=63).contains(&name.len())
+        && name.chars().all(|c| alnum(c) || c == '.' || c == '-')
+        && name.starts_with(alnum)
+        && name.ends_with(alnum)
+        && !name.contains("..")
+}
+
+/// `arn:<partition>:kms:<region>:<account>:key/<id>` or `...:alias/<name>`.
+fn valid_kms_arn(arn: &str) -> bool {
+    let parts: Vec<&str> = arn.splitn(6, ':').collect();
+    let resource = parts.get(5).and_then(|r| r.strip_prefix("key/").or_else(|| r.strip_prefix("alias/")));
+    parts.len() == 6
+        && parts[0] == "arn"
+        && parts[1].starts_with("aws")
+        && parts[2] == "kms"
+        && valid_region(parts[3])
+        && parts[4].len() == 12
+        && parts[4].chars().all(|c| c.is_ascii_digit())
+        && resource.is_some_and(|r| !r.is_empty())
+}
+
+/// `us-east-1`, `ap-southeast-2`, `us-gov-west-1` and so on.
+fn valid_region(region: &str) -> bool {
+    let parts: Vec<&str> = region.split('-').collect();
+    parts.len() >= 3
+        && parts.last().is_some_and(|n| !n.is_empty() && n.chars().all(|c| c.is_ascii_digit()))
+        && parts[..parts.len() - 1].iter().all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_lowercase()))
+}
@@ -0,0 +1,116 @@
+//! Consolidation of one instrument's books across venues: a merged best bid and
+//! offer, and depth summed per bucket, with each venue's contribution alongside.
+//! Venues' streams are first joined on a shared clock (`align`), so every record
+//! compares books as they stood at the same instant.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::book::{self, Level, DEPTHS};
+use crate::OrderBook;
+
+pub const CONSOLIDATED_PREFIX: &str = "consolidated";
+
+/// One venue's part of a consolidated record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Contribution {
+    pub venue: String,
+    /// The venue's book that went in, by its partition time.
+    pub timestamp_ms: i64,
+    pub best_bid: f64,
+    pub best_ask: f64,
+    /// Cumulative volume per depth bucket, nearest mid first.
+    pub bid_depth: Vec<f64>,
+    pub ask_depth: Vec<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Consolidated {
+    /// The instant the venues' books are aligned to.
+    pub timestamp_ms: i64,
+    pub symbol: String,
+    pub best_bid: f64,
+    pub best_bid_venue: String,
+    pub best_ask: f64,
+    pub best_ask_venue: String,
+    /// Negative when the venues are crossed.
+    pub spread: f64,
+    pub mid_price: f64,
+    /// Depth buckets around the consolidated mid, each the sum of the venues' volume
+    /// in that bucket.
+    pub bids: Vec<Level>,
+    pub asks: Vec<Level>,
+    pub venues: Vec<Contribution>,
+}
+
+/// Each venue's latest book at every multiple of `window_ms` from the first book to
+/// the last, leaving out books older than `max_age_ms` at that instant. Instants no
+/// venue has a book for are skipped. `streams` are each venue's books, oldest first.
+pub fn align(streams: &BTreeMap<String, Vec<OrderBook>>, window_ms: i64, max_age_ms: i64) -> Vec<(i64, Vec<(&str, &OrderBook)>)> {
+    let times = streams.values().flatten().map(OrderBook::partition_time_ms);
+    let (Some(first), Some(last)) = (times.clone().min(), times.max()) else { return Vec::new() };
+    let window_ms = window_ms.max(1);
+
+    let mut next: BTreeMap<&str, usize> = streams.keys().map(|venue| (venue.as_str(), 0)).collect();
+    let mut aligned = Vec::new();
+    // the first instant at or after the first book, through the first at or after the last
+    let mut at = first + (window_ms - first.rem_euclid(window_ms)) % window_ms;
+    while at < last + window_ms {
+        let mut books = Vec::new();
+        for (venue, stream) in streams {
+            let i = next.get_mut(venue.as_str()).expect("every venue has a cursor");
+            while stream.get(*i).is_some_and(|b| b.partition_time_ms() <= at) {
+                *i += 1;
+            }
+            if let Some(book) = i.checked_sub(1).map(|i| &stream[i]) {
+                if at - book.partition_time_ms() <= max_age_ms {
+                    books.push((venue.as_str(), book));
+                }
+            }
+        }
+        if !books.is_empty() {
+            aligned.push((at, books));
+        }
+        at += window_ms;
+    }
+    aligned
+}
+
+/// The consolidated record of `books` aligned at `at_ms`, or `None` without any.
+pub fn consolidate(symbol: &str, at_ms: i64, books: &[(&str, &OrderBook)]) -> Option<Consolidated> {
+    let by = |price: fn(&OrderBook) -> f64, better: fn(f64, f64) -> bool| {
+        books.iter().map(|(venue, b)| (price(b), *venue)).reduce(|best, next| if better(next.0, best.0) { next } else { best })
+    };
+    let (best_bid, best_bid_venue) = by(OrderBook::best_bid, |a, b| a > b)?;
+    let (best_ask, best_ask_venue) = by(OrderBook::best_ask, |a, b| a < b)?;
+    let mid_price = (best_bid + best_ask) / 2.0;
+
+    let buckets = book::depth_buckets().unwrap_or(&DEPTHS);
+    let summed = |side: fn(&OrderBook) -> &[Level], sign: f64| -> Vec<Level> {
+        buckets.iter().enumerate().map(|(i, d)| {
+            let qty = books.iter().filter_map(|(_, b)| side(b).get(i)).map(|l| l.qty).sum();
+            Level::new(mid_price * (1.0 + sign * d), qty)
+        }).collect()
+    };
+
+    Some(Consolidated {
+        timestamp_ms: at_ms,
+        symbol: symbol.to_uppercase(),
+        best_bid,
+        best_bid_venue: best_bid_venue.to_string(),
+        best_ask,
+        best_ask_venue: best_ask_venue.to_string(),
+        spread: best_ask - best_bid,
+        mid_price,
+        bids: summed(|b| &b.bids, -1.0),
+        asks: summed(|b| &b.asks, 1.0),
+        venues: books.iter().map(|(venue, b)| Contribution {
+            venue: venue.to_string(),
+            timestamp_ms: b.partition_time_ms(),
+            best_bid: b.best_bid(),
+            best_ask: b.best_ask(),
+            bid_depth: b.bids.iter().map(|l| l.qty).collect(),
+            ask_depth: b.asks.iter().map(|l| l.qty).collect(),
+        }).collect(),
+    })
+}
@@ -0,0 +1,228 @@
+//! Rolling correlation and lead-lag of mid returns between pairs of collected symbols.
+//! Collectors publish each book's mid; a background task samples the latest mid of
+//! every paired symbol on a fixed step, and on a fixed cadence correlates the log
+//! returns of each pair over the trailing window, at lag zero and at each lag up to
+//! `CORRELATION_MAX_LAG_MS` either way. Results go under `correlation/`.
+
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::{info_span, Instrument};
+
+use crate::{binance, config, schema, sink, Error};
+
+pub const CORRELATION_PREFIX: &str = "correlation";
+
+const DEFAULT_STEP_MS: i64 = 1_000;
+const DEFAULT_WINDOW_SECS: i64 = 300;
+const DEFAULT_EVERY_SECS: u64 = 60;
+const DEFAULT_MAX_LAG_MS: i64 = 5_000;
+
+/// Parses a comma-separated pair list such as `btcusdt:ethusdt,btcusdt:solusdt`,
+/// lowercased, each pair of two different symbols and listed once.
+pub fn parse_pairs(list: &str) -> Result<Vec<(String, String)>, Error> {
+    let mut pairs: Vec<(String, String)> = Vec::new();
+    for pair in list.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        let invalid = || Error::Config(format!("invalid correlation pair {:?}, expected symbol:symbol", pair));
+        let (a, b) = pair.split_once(':').ok_or_else(invalid)?;
+        let (a, b) = (a.trim().to_lowercase(), b.trim().to_lowercase());
+        let symbol = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric());
+        if !symbol(&a) || !symbol(&b) || a == b {
+            return Err(invalid());
+        }
+        if !pairs.contains(&(a.clone(), b.clone())) {
+            pairs.push((a, b));
+        }
+    }
+    if pairs.is_empty() {
+        return Err(Error::Config(format!("no correlation pairs in {:?}", list)));
+    }
+    Ok(pairs)
+}
+
+/// `CORRELATION_PAIRS`, checked against the symbols being collected; empty when unset.
+pub fn pairs(symbols: &[String]) -> Result<Vec<(String, String)>, Error> {
+    let Some(list) = config::var("CORRELATION_PAIRS").filter(|v| !v.is_empty()) else {
+        return Ok(Vec::new());
+    };
+    let pairs = parse_pairs(&list)?;
+    for symbol in pairs.iter().flat_map(|(a, b)| [a, b]) {
+        if !symbols.contains(symbol) {
+            return Err(Error::Config(format!("correlation pair symbol {} isn't in SYMBOLS", symbol)));
+        }
+    }
+    Ok(pairs)
+}
+
+/// Sampling step, window, cadence and lag range.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    pub step_ms: i64,
+    pub window_ms: i64,
+    pub every: Duration,
+    pub max_lag_ms: i64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            step_ms: DEFAULT_STEP_MS,
+            window_ms: DEFAULT_WINDOW_SECS * 1000,
+            every: Duration::from_secs(DEFAULT_EVERY_SECS),
+            max_lag_ms: DEFAULT_MAX_LAG_MS,
+        }
+    }
+}
+
+impl Settings {
+    /// `CORRELATION_STEP_MS` (default 1000), `CORRELATION_WINDOW_SECS` (default 300),
+    /// `CORRELATION_SECS` (default 60) and `CORRELATION_MAX_LAG_MS` (default 5000).
+    pub fn from_env() -> Self {
+        let defaults = Settings::default();
+        let number = |name: &str| config::var(name).and_then(|v| v.parse::<i64>().ok()).filter(|v| *v > 0);
+        Settings {
+            step_ms: number("CORRELATION_STEP_MS").unwrap_or(defaults.step_ms),
+            window_ms: number("CORRELATION_WINDOW_SECS").map_or(defaults.window_ms, |s| s * 1000),
+            every: number("CORRELATION_SECS").map_or(defaults.every, |s| Duration::from_secs(s as u64)),
+            max_lag_ms: config::var("CORRELATION_MAX_LAG_MS").and_then(|v| v.parse().ok()).filter(|v: &i64| *v >= 0).unwrap_or(defaults.max_lag_ms),
+        }
+    }
+
+    /// Returns in the window, at least two.
+    pub fn window_steps(&self) -> usize {
+        (self.window_ms / self.step_ms).max(2) as usize
+    }
+
+    pub fn max_lag_steps(&self) -> usize {
+        (self.max_lag_ms / self.step_ms) as usize
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Correlation {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub exchange: String,
+    /// The pair as configured; a positive lag means `symbol` leads `other`.
+    pub symbol: String,
+    pub other: String,
+    pub step_ms: i64,
+    /// Returns correlated at lag zero.
+    pub samples: i64,
+    pub correlation: f64,
+    /// The lag with the strongest correlation, either sign, and that correlation.
+    pub best_lag_ms: i64,
+    pub lag_correlation: f64,
+}
+
+/// Pearson correlation; `None` with fewer than two points or when either side is flat.
+pub fn pearson(x: &[f64], y: &[f64]) -> Option<f64> {
+    let n = x.len().min(y.len());
+    if n < 2 {
+        return None;
+    }
+    let (x, y) = (&x[..n], &y[..n]);
+    let mean_x = x.iter().sum::<f64>() / n as f64;
+    let mean_y = y.iter().sum::<f64>() / n as f64;
+    let sxy: f64 = x.iter().zip(y).map(|(a, b)| (a - mean_x) * (b - mean_y)).sum();
+    let sxx: f64 = x.iter().map(|a| (a - mean_x).powi(2)).sum();
+    let syy: f64 = y.iter().map(|b| (b - mean_y).powi(2)).sum();
+    (sxx > 0.0 && syy > 0.0).then(|| sxy / (sxx * syy).sqrt())
+}
+
+/// Log returns between consecutive mids.
+pub fn returns(mids: &[f64]) -> Vec<f64> {
+    mids.windows(2).map(|w| (w[1] / w[0]).ln()).collect()
+}
+
+/// The lag, in steps, at which `x`'s returns best correlate with `y`'s later ones, and
+/// that correlation. Positive lags compare `x[t]` with `y[t + lag]`, so `x` leads.
+/// `None` when no lag gives a correlation.
+pub fn lead_lag(x: &[f64], y: &[f64], max_lag: usize) -> Option<(i64, f64)> {
+    let n = x.len().min(y.len());
+    let shifted = |lag: i64| {
+        let k = lag.unsigned_abs() as usize;
+        if k >= n {
+            return None;
+        }
+        let corr = if lag >= 0 { pearson(&x[..n - k], &y[k..n]) } else { pearson(&x[k..n], &y[..n - k]) };
+        corr.map(|c| (lag, c))
+    };
+    (-(max_lag as i64)..=max_lag as i64)
+        .filter_map(shifted)
+        .fold(None, |best: Option<(i64, f64)>, (lag, c)| match best {
+            Some((_, b)) if b.abs() >= c.abs() => best,
+            _ => Some((lag, c)),
+        })
+}
+
+/// The pair's correlation over `mids` sampled every `settings.step_ms` and ending at
+/// `end_ms`, or `None` while there are too few samples.
+pub fn correlate(pair: &(String, String), mids: &[(f64, f64)], end_ms: i64, settings: &Settings) -> Option<Correlation> {
+    let (a, b): (Vec<f64>, Vec<f64>) = mids.iter().copied().unzip();
+    let (x, y) = (returns(&a), returns(&b));
+    let correlation = pearson(&x, &y)?;
+    let (lag, lag_correlation) = lead_lag(&x, &y, settings.max_lag_steps()).unwrap_or((0, correlation));
+    Some(Correlation {
+        start_ms: end_ms - (mids.len() as i64 - 1) * settings.step_ms,
+        end_ms,
+        exchange: binance::EXCHANGE.to_string(),
+        symbol: pair.0.to_uppercase(),
+        other: pair.1.to_uppercase(),
+        step_ms: settings.step_ms,
+        samples: x.len() as i64,
+        correlation,
+        best_lag_ms: lag * settings.step_ms,
+        lag_correlation,
+    })
+}
+
+/// The latest mid per upper-cased symbol, published by the collectors.
+static MIDS: LazyLock<Mutex<HashMap<String, f64>>> = LazyLock::new(Default::default);
+
+/// Records `symbol`'s latest mid.
+pub fn publish_mid(symbol: &str, mid: f64) {
+    if mid > 0.0 && mid.is_finite() {
+        MIDS.lock().unwrap_or_else(|e| e.into_inner()).insert(symbol.to_uppercase(), mid);
+    }
+}
+
+fn latest_mid(symbol: &str) -> Option<f64> {
+    MIDS.lock().unwrap_or_else(|e| e.into_inner()).get(&symbol.to_uppercase()).copied()
+}
+
+/// Samples `pairs` every step and stores their correlations on the cadence until the
+/// task is dropped. A step where either symbol has no mid yet is skipped.
+pub async fn run(s3: Client, pairs: Vec<(String, String)>) -> Result<(), Error> {
+    let settings = Settings::from_env();
+    let keep = settings.window_steps() + 1;
+    let mut samples: Vec<VecDeque<(f64, f64)>> = vec![VecDeque::with_capacity(keep); pairs.len()];
+    let mut step = tokio::time::interval(Duration::from_millis(settings.step_ms as u64));
+    let mut every = tokio::time::interval_at(tokio::time::Instant::now() + settings.every, settings.every);
+
+    loop {
+        tokio::select! {
+            _ = step.tick() => {
+                for (pair, series) in pairs.iter().zip(samples.iter_mut()) {
+                    let (Some(a), Some(b)) = (latest_mid(&pair.0), latest_mid(&pair.1)) else { continue };
+                    if series.len() == keep {
+                        series.pop_front();
+                    }
+                    series.push_back((a, b));
+                }
+            }
+            _ = every.tick() => {
+                let end_ms = Utc::now().timestamp_millis();
+                for (pair, series) in pairs.iter().zip(samples.iter_mut()) {
+                    let Some(record) = correlate(pair, series.make_contiguous(), end_ms, &settings) else { continue };
+                    let name = format!("{}-{}", record.symbol, record.other);
+                    let key = sink::partition_key(CORRELATION_PREFIX, &name, sink::at_ms(end_ms), end_ms)?;
+                    sink::write(&s3, &key, schema::CORRELATION, &[record]).instrument(info_span!("correlation", pair = name)).await?;
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Delta Lake output: stored books also appended to a Delta table at `DELTA_TABLE_URI`,
+//! one Parquet file and commit per symbol and minute, so Databricks and other Delta
+//! readers query the stream directly instead of waiting for a conversion job.
+//!
+//! Files are written with the `columnar` schema and committed as plain appends. The
+//! table is created on first use with writer version 1, the highest this build can
+//! commit to without datafusion, so leave its protocol alone. On S3, data files and
+//! commits are encrypted with `S3_KMS_KEY_ARN` like every other write.
+
+use chrono::Utc;
+use deltalake::kernel::{Action, Add, Protocol, StructType};
+use deltalake::operations::transaction::CommitBuilder;
+use deltalake::protocol::{DeltaOperation, SaveMode};
+use deltalake::{DeltaOps, DeltaTable, DeltaTableError};
+use std::collections::HashMap;
+use std::sync::{Once, OnceLock};
+
+use crate::{columnar, config, Error, OrderBook};
+
+/// `DELTA_TABLE_URI`, e.g. `s3://bucket/delta/orderbook` or a local directory; read
+/// once per process. Unset leaves Delta output off.
+pub fn table_uri() -> Option<&'static str> {
+    static URI: OnceLock<Option<String>> = OnceLock::new();
+
+    URI.get_or_init(|| config::var("DELTA_TABLE_URI").filter(|v| !v.is_empty())).as_deref()
+}
+
+/// A minute of one symbol's books on their way to the table.
+pub struct DeltaSink {
+    uri: String,
+    table: Option<DeltaTable>,
+    minute_ms: i64,
+    books: Vec<OrderBook>,
+}
+
+impl DeltaSink {
+    pub fn new(uri: &str) -> Self {
+        DeltaSink { uri: uri.to_string(), table: None, minute_ms: 0, books: Vec::new() }
+    }
+
+    /// Adds a stored book, returning the previous minute's books once the minute rolls over.
+    pub fn push(&mut self, book: OrderBook) -> Option<Vec<OrderBook>> {
+        let minute_ms = book.timestamp_ms - book.timestamp_ms.rem_euclid(60_000);
+        let done = if minute_ms != self.minute_ms { self.flush() } else { None };
+        self.minute_ms = minute_ms;
+        self.books.push(book);
+        done
+    }
+
+    /// `flush` once the buffered minute is over.
+    pub fn flush_ended(&mut self, now_ms: i64) -> Option<Vec<OrderBook>> {
+        if now_ms - self.minute_ms < 60_000 {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// Takes whatever is buffered.
+    pub fn flush(&mut self) -> Option<Vec<OrderBook>> {
+        (!self.books.is_empty()).then(|| std::mem::take(&mut self.books))
+    }
+
+    /// Writes `books` as one Parquet file and commits it to the table, creating the
+    /// table first if there is none. Returns the committed table version.
+    pub async fn append(&mut self, books: &[OrderBook]) -> Result<i64, Error> {
+        if self.table.is_none() {
+            self.table = Some(open(&self.uri).await?);
+        }
+        let table = self.table.as_mut().expect("opened above");
+        let first = books.first().ok_or(Error::EmptyBook)?;
+
+        let path = format!("{}/{}-{:08x}.parquet", first.symbol, first.timestamp_ms, fastrand::u32(..));
+        let body = columnar::write_parquet(books)?;
+        let size = body.len() as i64;
+        table.object_store().put(&path.as_str().into(), body.into()).await.map_err(DeltaTableError::from)?;
+
+        let add = Add {
+            path,
+            size,
+            modification_time: Utc::now().timestamp_millis(),
+            data_change: true,
+            stats: Some(stats(books).to_string()),
+            ..Default::default()
+        };
+        let operation = DeltaOperation::Write { mode: SaveMode::Append, partition_by: None, predicate: None };
+        let version = CommitBuilder::default()
+            .with_actions(vec![Action::Add(add)])
+            .build(Some(table.snapshot()?), table.log_store(), operation)
+            .await?
+            .version();
+        table.update().await?;
+        Ok(version)
+    }
+}
+
+/// The table at `uri`, created with the `columnar` schema if it doesn't exist yet.
+pub async fn open(uri: &str) -> Result<DeltaTable, Error> {
+    static HANDLERS: Once = Once::new();
+    HANDLERS.call_once(|| deltalake::aws::register_handlers(None));
+
+    let options = storage_options(uri)?;
+    match deltalake::open_table_with_storage_options(uri, options.clone()).await {
+        Ok(table) => Ok(table),
+        Err(DeltaTableError::NotATable(_)) => {
+            let schema = StructType::try_from(columnar::orderbook_schema().as_ref())?;
+            let protocol = Protocol { min_reader_version: 1, min_writer_version: 1, reader_features: None, writer_features: None };
+            Ok(DeltaOps::try_from_uri_with_storage_options(uri, options).await?
+                .create()
+                .with_table_name("orderbook")
+                .with_columns(schema.fields().cloned())
+                .with_actions([Action::Protocol(protocol)])
+                .await?)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// SSE-KMS with the configured key for an S3 table; nothing for a local one, or
+/// without a key, so the bucket's default applies.
+pub fn storage_options(uri: &str) -> Result<HashMap<String, String>, Error> {
+    let mut options = HashMap::new();
+    if !uri.starts_with("s3://") && !uri.starts_with("s3a://") {
+        return Ok(options);
+    }
+    if let Some(key) = &config::storage()?.kms_key {
+        options.insert("aws_server_side_encryption".to_string(), "aws:kms".to_string());
+        options.insert("aws_sse_kms_key_id".to_string(), key.clone());
+    }
+    Ok(options)
+}
+
+/// File statistics readers skip files with: record count and the time range.
+fn stats(books: &[OrderBook]) -> serde_json::Value {
+    let ts = books.iter().map(|b| b.timestamp_ms);
+    serde_json::json!({
+        "numRecords": books.len(),
+        "minValues": { "timestamp_ms": ts.clone().min() },
+        "maxValues": { "timestamp_ms": ts.max() },
+        "nullCount": { "timestamp_ms": 0 },
+    })
+}
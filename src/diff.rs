@@ -0,0 +1,88 @@
+//! Field-by-field differences between two books, for `snapshot-diff`. Both books are
+//! flattened to paths such as `bids[2].qty` or `spread_bps`, so levels line up by
+//! position and every feature the schema grows is compared without listing it here.
+
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::{Error, OrderBook};
+
+/// One path whose value differs, `Null` on the side that doesn't have it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    pub field: String,
+    pub before: Value,
+    pub after: Value,
+}
+
+impl Change {
+    /// `after - before`, when both are numbers.
+    pub fn delta(&self) -> Option<f64> {
+        Some(self.after.as_f64()? - self.before.as_f64()?)
+    }
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let show = |v: &Value| if v.is_null() { "-".to_string() } else { v.to_string() };
+        write!(f, "{}: {} -> {}", self.field, show(&self.before), show(&self.after))?;
+        match self.delta() {
+            Some(d) => write!(f, " ({:+})", d),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Every path that differs between `before` and `after`, in field order with levels
+/// in book order. A level only one book has shows up with the other side `Null`.
+pub fn books(before: &OrderBook, after: &OrderBook) -> Result<Vec<Change>, Error> {
+    let before = flatten(&serde_json::to_value(before)?);
+    let after = flatten(&serde_json::to_value(after)?);
+
+    // before's paths, with after's extra ones slotted in behind their predecessor
+    let mut paths: Vec<&str> = before.iter().map(|(p, _)| p.as_str()).collect();
+    let known: HashSet<&str> = paths.iter().copied().collect();
+    for (i, (path, _)) in after.iter().enumerate() {
+        if known.contains(path.as_str()) {
+            continue;
+        }
+        let at = i.checked_sub(1)
+            .and_then(|prev| paths.iter().position(|p| *p == after[prev].0))
+            .map_or(paths.len(), |p| p + 1);
+        paths.insert(at, path);
+    }
+
+    let (old, new): (HashMap<&str, &Value>, HashMap<&str, &Value>) = (
+        before.iter().map(|(p, v)| (p.as_str(), v)).collect(),
+        after.iter().map(|(p, v)| (p.as_str(), v)).collect(),
+    );
+    let value = |side: &HashMap<&str, &Value>, path| side.get(path).map_or(Value::Null, |v| (*v).clone());
+    Ok(paths.into_iter()
+        .map(|path| Change { field: path.to_string(), before: value(&old, path), after: value(&new, path) })
+        .filter(|c| c.before != c.after)
+        .collect())
+}
+
+/// `(path, scalar)` for every leaf of `value`.
+fn flatten(value: &Value) -> Vec<(String, Value)> {
+    fn walk(path: String, value: &Value, out: &mut Vec<(String, Value)>) {
+        match value {
+            Value::Object(fields) => {
+                for (name, v) in fields {
+                    let path = if path.is_empty() { name.clone() } else { format!("{}.{}", path, name) };
+                    walk(path, v, out);
+                }
+            }
+            Value::Array(items) => {
+                for (i, v) in items.iter().enumerate() {
+                    walk(format!("{}[{}]", path, i), v, out);
+                }
+            }
+            _ => out.push((path, value.clone())),
+        }
+    }
+    let mut out = Vec::new();
+    walk(String::new(), value, &mut out);
+    out
+}
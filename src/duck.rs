@@ -0,0 +1,84 @@
+//! DuckDB output for local development: books appended to an `orderbook` table in a
+//! single database file, so ad-hoc analysis is one `SELECT` away instead of a walk
+//! over thousands of JSON files.
+
+use duckdb::{params, Connection};
+use std::fmt::Write;
+use std::path::Path;
+
+use crate::book::Level;
+use crate::{Error, OrderBook};
+
+const LEVELS: &str = "STRUCT(price DOUBLE, qty DOUBLE)[]";
+
+/// An open database with the `orderbook` table.
+pub struct DuckSink {
+    conn: Connection,
+}
+
+impl DuckSink {
+    /// Opens `path`, creating the file and the table if they don't exist yet.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, Error> {
+        let conn = Connection::open(path)?;
+        conn.execute_batch(&format!(
+            "CREATE TABLE IF NOT EXISTS orderbook (
+                timestamp_ms BIGINT NOT NULL,
+                event_time_ms BIGINT NOT NULL,
+                exchange VARCHAR NOT NULL,
+                symbol VARCHAR NOT NULL,
+                last_update_id BIGINT NOT NULL,
+                mid_price DOUBLE NOT NULL,
+                spread DOUBLE NOT NULL,
+                spread_bps DOUBLE NOT NULL,
+                imbalance_ratio DOUBLE NOT NULL,
+                bids {LEVELS} NOT NULL,
+                asks {LEVELS} NOT NULL
+            )"
+        ))?;
+        Ok(DuckSink { conn })
+    }
+
+    /// Appends `books` in one transaction, returning how many were written.
+    pub fn append(&mut self, books: &[OrderBook]) -> Result<usize, Error> {
+        let tx = self.conn.transaction()?;
+        {
+            let mut insert = tx.prepare(&format!(
+                "INSERT INTO orderbook VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?::{LEVELS}, ?::{LEVELS})"
+            ))?;
+            for book in books {
+                insert.execute(params![
+                    book.timestamp_ms,
+                    book.event_time_ms,
+                    book.exchange,
+                    book.symbol,
+                    book.last_update_id,
+                    book.mid_price,
+                    book.spread,
+                    book.spread_bps,
+                    book.imbalance_ratio,
+                    levels_literal(&book.bids),
+                    levels_literal(&book.asks),
+                ])?;
+            }
+        }
+        tx.commit()?;
+        Ok(books.len())
+    }
+
+    /// The connection, for queries.
+    pub fn connection(&self) -> &Connection {
+        &self.conn
+    }
+}
+
+/// `levels` as a DuckDB list-of-structs literal, cast to `LEVELS` on insert since
+/// nested values can't be bound as parameters.
+fn levels_literal(levels: &[Level]) -> String {
+    let mut out = String::from("[");
+    for (i, level) in levels.iter().enumerate() {
+        let sep = if i == 0 { "" } else { ", " };
+        let _ = write!(out, "{sep}{{'price': {:?}, 'qty': {:?}}}", level.price, level.qty);
+    }
+    out.push(']');
+    out
+}
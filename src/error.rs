@@ -0,0 +1,104 @@
+use aws_sdk_s3::error::SdkError;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum Error {
+    #[error("websocket: {0}")]
+    WebSocket(#[from] Box<tokio_tungstenite::tungstenite::Error>),
+    #[error("parse: {0}")]
+    Parse(#[from] serde_json::Error),
+    #[error("avro: {0}")]
+    Avro(#[from] Box<apache_avro::Error>),
+    #[error("s3: {0}")]
+    S3(#[from] Box<aws_sdk_s3::Error>),
+    #[error("ssm: {0}")]
+    Ssm(Box<aws_sdk_ssm::Error>),
+    #[cfg(feature = "userdata")]
+    #[error("secrets manager: {0}")]
+    Secrets(Box<aws_sdk_secretsmanager::Error>),
+    #[error("dynamodb: {0}")]
+    Dynamo(Box<aws_sdk_dynamodb::Error>),
+    #[cfg(feature = "alerts")]
+    #[error("sns: {0}")]
+    Sns(Box<aws_sdk_sns::Error>),
+    #[error("s3 body: {0}")]
+    Body(#[from] aws_sdk_s3::primitives::ByteStreamError),
+    #[error("s3 request: {0}")]
+    Build(#[from] aws_sdk_s3::error::BuildError),
+    #[error("http: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("proxy: {0}")]
+    Proxy(String),
+    #[error("io: {0}")]
+    Io(#[from] std::io::Error),
+    #[cfg(feature = "parquet")]
+    #[error("arrow: {0}")]
+    Arrow(#[from] arrow_schema::ArrowError),
+    #[cfg(feature = "parquet")]
+    #[error("parquet: {0}")]
+    Parquet(#[from] parquet::errors::ParquetError),
+    #[cfg(feature = "delta")]
+    #[error("delta: {0}")]
+    Delta(Box<deltalake::DeltaTableError>),
+    #[cfg(feature = "duckdb")]
+    #[error("duckdb: {0}")]
+    DuckDb(Box<duckdb::Error>),
+    #[cfg(feature = "redis")]
+    #[error("redis: {0}")]
+    Redis(#[from] redis::RedisError),
+    #[cfg(feature = "nats")]
+    #[error("nats: {0}")]
+    Nats(String),
+    #[cfg(feature = "grpc")]
+    #[error("grpc: {0}")]
+    Grpc(String),
+    #[error("order book has an empty side")]
+    EmptyBook,
+    #[error("user data stream: {0}")]
+    UserData(String),
+    #[error("full depth: {0}")]
+    FullDepth(String),
+    #[error("config: {0}")]
+    Config(String),
+    #[error("schema registry: {0}")]
+    Registry(String),
+    #[error("conflicting object: {0}")]
+    Conflict(String),
+    #[error("failed to delete {}", .0.join(", "))]
+    Delete(Vec<String>),
+}
+
+impl From<tokio_tungstenite::tungstenite::Error> for Error {
+    fn from(e: tokio_tungstenite::tungstenite::Error) -> Self {
+        Error::WebSocket(Box::new(e))
+    }
+}
+
+impl From<apache_avro::Error> for Error {
+    fn from(e: apache_avro::Error) -> Self {
+        Error::Avro(Box::new(e))
+    }
+}
+
+#[cfg(feature = "delta")]
+impl From<deltalake::DeltaTableError> for Error {
+    fn from(e: deltalake::DeltaTableError) -> Self {
+        Error::Delta(Box::new(e))
+    }
+}
+
+#[cfg(feature = "duckdb")]
+impl From<duckdb::Error> for Error {
+    fn from(e: duckdb::Error) -> Self {
+        Error::DuckDb(Box::new(e))
+    }
+}
+
+impl<E, R> From<SdkError<E, R>> for Error
+where
+    aws_sdk_s3::Error: From<SdkError<E, R>>,
+{
+    fn from(e: SdkError<E, R>) -> Self {
+        Error::S3(Box::new(e.into()))
+    }
+}
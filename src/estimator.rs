@@ -0,0 +1,42 @@
+// awaiting a compacted hour through its manifest nests deep enough to need this
+#![recursion_limit = "256"]
+
+use chrono::{DateTime, Duration, Utc};
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use orderbook::archive::Archive;
+use orderbook::impact::{self, ImpactEstimate};
+use orderbook::{binance, config, layout, logging, params};
+use tracing::warn;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    logging::init();
+    params::init().await?;
+    // fail the cold start on bad storage settings rather than the first invocation
+    config::storage()?;
+    binance::venue()?;
+    layout::template()?;
+    config::symbols()?;
+    run(service_fn(handler)).await
+}
+
+/// Estimates the hour given as `{"hour": "<RFC 3339>"}`, or the previous hour when
+/// scheduled, for each of `SYMBOLS`. A symbol that fails is logged and skipped so
+/// the others are still estimated.
+async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<Vec<ImpactEstimate>, Error> {
+    let archive = Archive::S3(config::s3_client().await?);
+    let hour = match event.payload["hour"].as_str() {
+        Some(h) => DateTime::parse_from_rfc3339(h)?.with_timezone(&Utc),
+        None => Utc::now() - Duration::hours(1),
+    };
+
+    let mut estimates = Vec::new();
+    for symbol in config::symbols()? {
+        match impact::estimate_hour(&archive, &symbol, hour).await {
+            Ok(Some(estimate)) => estimates.push(estimate),
+            Ok(None) => warn!(symbol, "not enough books in the hour for an impact estimate"),
+            Err(e) => warn!(error = %e, symbol, "impact estimate failed"),
+        }
+    }
+    Ok(estimates)
+}
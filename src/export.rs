@@ -0,0 +1,110 @@
+//! Flat CSV of chosen book fields, for spreadsheets and quick plots: one row per
+//! book, one column per field, with `depth` spread over a bid and an ask column per
+//! depth bucket.
+
+use std::io::Write;
+
+use crate::book::{self, DEPTHS};
+use crate::{Error, OrderBook};
+
+pub const DEFAULT_FIELDS: &str = "timestamp,mid,spread,imbalance,depth";
+
+/// A selectable column, or with `Depth`, group of columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Timestamp,
+    EventTime,
+    Mid,
+    Spread,
+    SpreadBps,
+    Imbalance,
+    Depth,
+    Vpin,
+    MidReturn,
+}
+
+impl Field {
+    pub fn parse(name: &str) -> Result<Self, Error> {
+        Ok(match name.trim() {
+            "timestamp" => Field::Timestamp,
+            "event_time" => Field::EventTime,
+            "mid" => Field::Mid,
+            "spread" => Field::Spread,
+            "spread_bps" => Field::SpreadBps,
+            "imbalance" => Field::Imbalance,
+            "depth" => Field::Depth,
+            "vpin" => Field::Vpin,
+            "mid_return" => Field::MidReturn,
+            other => return Err(Error::Config(format!("unknown export field {:?}", other))),
+        })
+    }
+
+    /// A comma-separated list such as `timestamp,mid,depth`.
+    pub fn parse_list(names: &str) -> Result<Vec<Self>, Error> {
+        names.split(',').filter(|n| !n.trim().is_empty()).map(Field::parse).collect()
+    }
+}
+
+/// The header row for `fields`, naming depth columns after the buckets in basis
+/// points, e.g. `bid_depth_10bps`.
+pub fn header(fields: &[Field]) -> String {
+    let mut cols: Vec<String> = Vec::new();
+    for field in fields {
+        match field {
+            Field::Timestamp => cols.push("timestamp_ms".into()),
+            Field::EventTime => cols.push("event_time_ms".into()),
+            Field::Mid => cols.push("mid_price".into()),
+            Field::Spread => cols.push("spread".into()),
+            Field::SpreadBps => cols.push("spread_bps".into()),
+            Field::Imbalance => cols.push("imbalance_ratio".into()),
+            Field::Vpin => cols.push("vpin".into()),
+            Field::MidReturn => cols.push("mid_return".into()),
+            Field::Depth => {
+                for side in ["bid", "ask"] {
+                    for bucket in buckets() {
+                        cols.push(format!("{}_depth_{}bps", side, (bucket * 1_000_000.0).round() / 100.0));
+                    }
+                }
+            }
+        }
+    }
+    cols.join(",")
+}
+
+/// `book`'s row for `fields`. A bucket the book doesn't have is left empty.
+pub fn row(fields: &[Field], book: &OrderBook) -> String {
+    let mut cols: Vec<String> = Vec::new();
+    for field in fields {
+        match field {
+            Field::Timestamp => cols.push(book.timestamp_ms.to_string()),
+            Field::EventTime => cols.push(book.event_time_ms.to_string()),
+            Field::Mid => cols.push(book.mid_price.to_string()),
+            Field::Spread => cols.push(book.spread.to_string()),
+            Field::SpreadBps => cols.push(book.spread_bps.to_string()),
+            Field::Imbalance => cols.push(book.imbalance_ratio.to_string()),
+            Field::Vpin => cols.push(book.vpin.to_string()),
+            Field::MidReturn => cols.push(book.mid_return.to_string()),
+            Field::Depth => {
+                for levels in [&book.bids, &book.asks] {
+                    for i in 0..buckets().len() {
+                        cols.push(levels.get(i).map_or_else(String::new, |l| l.qty.to_string()));
+                    }
+                }
+            }
+        }
+    }
+    cols.join(",")
+}
+
+/// Writes the header and a row per book.
+pub fn write(out: &mut impl Write, fields: &[Field], books: &[OrderBook]) -> Result<(), Error> {
+    writeln!(out, "{}", header(fields))?;
+    for book in books {
+        writeln!(out, "{}", row(fields, book))?;
+    }
+    Ok(())
+}
+
+fn buckets() -> &'static [f64] {
+    book::depth_buckets().unwrap_or(&DEPTHS)
+}
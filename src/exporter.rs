@@ -0,0 +1,144 @@
+//! Prometheus `/metrics` endpoint for long-running (non-Lambda) deployments.
+
+use prometheus::{
+    register_gauge_vec, register_histogram_vec, register_int_counter_vec, register_int_gauge_vec, Encoder, GaugeVec,
+    HistogramVec, IntCounterVec, IntGaugeVec, TextEncoder,
+};
+use std::sync::LazyLock;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tracing::{info, warn};
+
+use crate::metrics::Metric;
+use crate::Error;
+
+static MESSAGES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_messages_total", "Snapshots written", &["symbol"])
+        .expect("metric registered once")
+});
+static RECEIVED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_messages_received_total", "Text frames received", &["symbol"])
+        .expect("metric registered once")
+});
+static SKIPPED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_messages_skipped_total", "Pings, other events and one-sided books", &["symbol"])
+        .expect("metric registered once")
+});
+static DOWNSAMPLED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_messages_downsampled_total", "Books the sampling policy didn't persist", &["symbol"])
+        .expect("metric registered once")
+});
+static DROPPED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_messages_dropped_total", "Messages lost to encode or write errors", &["symbol"])
+        .expect("metric registered once")
+});
+static PARSE_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_parse_failures_total", "Messages that failed to parse", &["symbol"])
+        .expect("metric registered once")
+});
+static QUARANTINED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_quarantined_total", "Books that failed validation", &["symbol"])
+        .expect("metric registered once")
+});
+static RECONNECTS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_reconnects_total", "Websocket reconnects", &["symbol"])
+        .expect("metric registered once")
+});
+static DEAD_LETTERS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_dead_letters_total", "Batches parked in the dead letter bucket", &["symbol"])
+        .expect("metric registered once")
+});
+static S3_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!("orderbook_s3_retries_total", "S3 request retries", &["symbol"])
+        .expect("metric registered once")
+});
+static WRITE_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!("orderbook_write_latency_seconds", "Sink write latency", &["symbol"])
+        .expect("metric registered once")
+});
+static SNAPSHOT_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "orderbook_snapshot_latency_seconds",
+        "Per-snapshot delay between exchange event, receipt and storage",
+        &["symbol", "stage"],
+        vec![0.01, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    )
+    .expect("metric registered once")
+});
+static DATA_GAP: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "orderbook_data_gap_seconds",
+        "Time without writes around a reconnect",
+        &["symbol"],
+        vec![1.0, 5.0, 10.0, 30.0, 60.0, 300.0]
+    )
+    .expect("metric registered once")
+});
+static SINCE_LAST_WRITE: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!("orderbook_seconds_since_last_write", "Time since the last stored book, per checkpoint", &["symbol"])
+        .expect("metric registered once")
+});
+static CONNECTED: LazyLock<IntGaugeVec> = LazyLock::new(|| {
+    register_int_gauge_vec!("orderbook_connected", "1 while the websocket is connected", &["symbol"])
+        .expect("metric registered once")
+});
+
+/// Mirrors a metric update from `Metrics` into the Prometheus collectors.
+pub fn observe(symbol: &str, metric: Metric, value: f64) {
+    let labels = &[symbol];
+    match metric {
+        Metric::MessagesReceived => RECEIVED.with_label_values(labels).inc_by(value as u64),
+        Metric::MessagesProcessed => MESSAGES.with_label_values(labels).inc_by(value as u64),
+        Metric::MessagesSkipped => SKIPPED.with_label_values(labels).inc_by(value as u64),
+        Metric::MessagesDownsampled => DOWNSAMPLED.with_label_values(labels).inc_by(value as u64),
+        Metric::MessagesDropped => DROPPED.with_label_values(labels).inc_by(value as u64),
+        Metric::ParseFailures => PARSE_FAILURES.with_label_values(labels).inc_by(value as u64),
+        Metric::Quarantined => QUARANTINED.with_label_values(labels).inc_by(value as u64),
+        Metric::Reconnects => RECONNECTS.with_label_values(labels).inc_by(value as u64),
+        Metric::DeadLetters => DEAD_LETTERS.with_label_values(labels).inc_by(value as u64),
+        Metric::S3Retries => S3_RETRIES.with_label_values(labels).inc_by(value as u64),
+        Metric::S3PutLatency => WRITE_LATENCY.with_label_values(labels).observe(value / 1000.0),
+        Metric::DataGapSeconds => DATA_GAP.with_label_values(labels).observe(value),
+        Metric::ExchangeLatency => SNAPSHOT_LATENCY.with_label_values(&[symbol, "exchange"]).observe(value / 1000.0),
+        Metric::IngestLatency => SNAPSHOT_LATENCY.with_label_values(&[symbol, "ingest"]).observe(value / 1000.0),
+        Metric::EndToEndLatency => SNAPSHOT_LATENCY.with_label_values(&[symbol, "end_to_end"]).observe(value / 1000.0),
+        Metric::SecondsSinceLastWrite => SINCE_LAST_WRITE.with_label_values(labels).set(value),
+    }
+}
+
+pub fn set_connected(symbol: &str, up: bool) {
+    CONNECTED.with_label_values(&[symbol]).set(up as i64);
+}
+
+/// Serves the default registry at `GET /metrics` until the listener fails.
+pub async fn serve(addr: &str) -> Result<(), Error> {
+    let listener = TcpListener::bind(addr).await?;
+    info!(addr, "serving prometheus metrics");
+
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        tokio::spawn(async move {
+            let mut request = [0u8; 1024];
+            let n = stream.read(&mut request).await.unwrap_or(0);
+
+            let response = if request[..n].starts_with(b"GET /metrics") {
+                let mut body = Vec::new();
+                if let Err(e) = TextEncoder::new().encode(&prometheus::gather(), &mut body) {
+                    warn!(error = %e, "failed to encode metrics");
+                }
+                let mut head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    body.len()
+                ).into_bytes();
+                head.extend(body);
+                head
+            } else {
+                b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec()
+            };
+
+            if let Err(e) = stream.write_all(&response).await {
+                warn!(error = %e, "failed to write metrics response");
+            }
+        });
+    }
+}
@@ -0,0 +1,409 @@
+//! Full-depth capture: the whole 1000-level book, kept locally from a REST snapshot
+//! and the `@depth@100ms` diff stream as Binance documents, and persisted every
+//! `FULL_DEPTH_SECS` under `fulldepth/`. Consecutive records share most of their
+//! levels, so only every `FULL_DEPTH_KEYFRAME_EVERY`-th record is the complete book
+//! (a keyframe); the rest hold just the levels that changed since the record before.
+
+use aws_sdk_s3::Client;
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tracing::{info, info_span, warn, Instrument};
+
+use crate::archive::Archive;
+use crate::binance::{self, DepthMessage, DepthUpdate, PartialDepth, RawLevel, Venue};
+use crate::book::{self, ExactLevel};
+use crate::churn::{self, Churn, Counts};
+use crate::{cli, config, proxy, rest, schema, sink, Error};
+
+pub const FULL_DEPTH_PREFIX: &str = "fulldepth";
+
+/// Levels per side of the REST snapshot, and of every persisted book.
+pub const LIMIT: usize = 1000;
+
+/// Decimal places of every stored price and quantity; Binance never sends more.
+pub const SCALE: i32 = 8;
+
+const DEFAULT_EVERY: Duration = Duration::from_secs(60);
+const DEFAULT_KEYFRAME_EVERY: u32 = 60;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// How far `read_books` looks back for a keyframe, well past the default hour between them.
+const MAX_LOOKBACK_HOURS: usize = 6;
+
+/// Whether `FULL_DEPTH=1` asks for full-depth capture alongside the collectors.
+pub fn enabled() -> bool {
+    config::var("FULL_DEPTH").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// The diff stream the local book is kept from.
+pub fn diff_stream(symbol: &str) -> String {
+    format!("{}@depth@100ms", symbol.to_lowercase())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FullDepth {
+    /// When the book was persisted.
+    pub timestamp_ms: i64,
+    /// The event time of the last diff applied, 0 before the first.
+    pub event_time_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    /// The book's update id as persisted.
+    pub last_update_id: i64,
+    /// The complete book, or only the changes since the record before.
+    pub keyframe: bool,
+    /// For changes, the `last_update_id` of the record they apply to; 0 for keyframes.
+    pub base_update_id: i64,
+    /// Best first, in units of `10^-SCALE`. In changes a quantity of 0 removes the level.
+    pub bids: Vec<ExactLevel>,
+    pub asks: Vec<ExactLevel>,
+}
+
+/// What became of a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Applied {
+    /// Already covered by the book; dropped.
+    Stale,
+    Applied,
+    /// Updates are missing in between; the book has to be rebuilt from a new snapshot.
+    Gap,
+}
+
+/// One side's levels, price to quantity, both in units of `10^-SCALE`.
+type Side = BTreeMap<i64, i64>;
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LocalBook {
+    pub last_update_id: u64,
+    /// Whether a diff has been applied since the snapshot.
+    synced: bool,
+    bids: Side,
+    asks: Side,
+    /// Changes made by diffs since the last `take_churn`.
+    churn: Counts,
+}
+
+impl LocalBook {
+    /// The book as of a REST depth snapshot. `None` when a level isn't a plain decimal.
+    pub fn from_snapshot(depth: &PartialDepth) -> Option<Self> {
+        let mut book = LocalBook { last_update_id: depth.last_update_id, ..Self::default() };
+        let mut loaded = Counts::default();
+        set_levels(&mut book.bids, &depth.bids, &mut loaded)?;
+        set_levels(&mut book.asks, &depth.asks, &mut loaded)?;
+        Some(book)
+    }
+
+    /// Applies a diff following Binance's rules: diffs the snapshot already covers are
+    /// dropped, the first one must straddle it, and each later one must continue from
+    /// the one before (by `pu` on futures, `U` on spot).
+    pub fn apply(&mut self, update: &DepthUpdate) -> Applied {
+        let next = self.last_update_id + 1;
+        if update.final_update_id < next {
+            return Applied::Stale;
+        }
+        let continues = if self.synced && update.prev_final_update_id > 0 {
+            update.prev_final_update_id == self.last_update_id
+        } else if self.synced {
+            update.first_update_id == next
+        } else {
+            update.first_update_id <= next
+        };
+        let churn = &mut self.churn;
+        if !continues || set_levels(&mut self.bids, &update.bids, churn).and(set_levels(&mut self.asks, &update.asks, churn)).is_none() {
+            return Applied::Gap;
+        }
+        self.churn.updates += 1;
+        self.last_update_id = update.final_update_id;
+        self.synced = true;
+        Applied::Applied
+    }
+
+    /// Applies a persisted record: keyframes replace the book, changes must apply to
+    /// the book as it stands. Returns false, leaving the book as it was, when they don't.
+    pub fn apply_record(&mut self, record: &FullDepth) -> bool {
+        if !record.keyframe && record.base_update_id != self.last_update_id as i64 {
+            return false;
+        }
+        if record.keyframe {
+            *self = LocalBook::default();
+        }
+        for (side, levels) in [(&mut self.bids, &record.bids), (&mut self.asks, &record.asks)] {
+            for level in levels {
+                set(side, level.price, level.qty);
+            }
+        }
+        self.last_update_id = record.last_update_id as u64;
+        true
+    }
+
+    /// The changes diffs made since the last call.
+    pub fn take_churn(&mut self) -> Counts {
+        std::mem::take(&mut self.churn)
+    }
+
+    /// Up to `limit` levels per side, best first.
+    pub fn levels(&self, limit: usize) -> (Vec<ExactLevel>, Vec<ExactLevel>) {
+        let level = |(&price, &qty): (&i64, &i64)| ExactLevel { price, qty };
+        (self.bids.iter().rev().take(limit).map(level).collect(), self.asks.iter().take(limit).map(level).collect())
+    }
+}
+
+/// Sets a level, returning the quantity it had.
+fn set(side: &mut Side, price: i64, qty: i64) -> Option<i64> {
+    if qty == 0 {
+        side.remove(&price)
+    } else {
+        side.insert(price, qty)
+    }
+}
+
+fn set_levels(side: &mut Side, levels: &[RawLevel], churn: &mut Counts) -> Option<()> {
+    for [price, qty] in levels {
+        let qty = fixed(qty)?;
+        match (set(side, fixed(price)?, qty), qty) {
+            (None, 0) => {}
+            (None, _) => churn.adds += 1,
+            (Some(_), 0) => churn.cancels += 1,
+            (Some(before), _) if before != qty => churn.modifications += 1,
+            (Some(_), _) => {}
+        }
+    }
+    Some(())
+}
+
+/// `"65000.10"` in units of `10^-SCALE`.
+fn fixed(s: &str) -> Option<i64> {
+    let (units, scale) = book::parse_fixed(s)?;
+    units.checked_mul(10i64.checked_pow(SCALE.checked_sub(scale)? as u32)?)
+}
+
+/// Turns the local book into records: a keyframe first and every `keyframe_every`
+/// records after, and the changes since the last record in between.
+#[derive(Debug, Clone)]
+pub struct Encoder {
+    symbol: String,
+    keyframe_every: u32,
+    /// The last record's book, what the next changes are taken against.
+    last: Option<LocalBook>,
+    since_keyframe: u32,
+}
+
+impl Encoder {
+    pub fn new(symbol: &str, keyframe_every: u32) -> Self {
+        Encoder { symbol: symbol.to_uppercase(), keyframe_every: keyframe_every.max(1), last: None, since_keyframe: 0 }
+    }
+
+    /// Makes the next record a keyframe, as after rebuilding the book.
+    pub fn reset(&mut self) {
+        self.last = None;
+    }
+
+    /// The record of `book` at `now_ms`, or `None` when nothing changed since the last.
+    pub fn encode(&mut self, book: &LocalBook, event_time_ms: i64, now_ms: i64) -> Option<FullDepth> {
+        let (bids, asks) = book.levels(LIMIT);
+        let mut persisted = LocalBook { last_update_id: book.last_update_id, ..LocalBook::default() };
+        for (side, levels) in [(&mut persisted.bids, &bids), (&mut persisted.asks, &asks)] {
+            side.extend(levels.iter().map(|l| (l.price, l.qty)));
+        }
+
+        let keyframe = self.last.is_none() || self.since_keyframe + 1 >= self.keyframe_every;
+        let record = match self.last.as_ref().filter(|_| !keyframe) {
+            Some(last) if last.bids == persisted.bids && last.asks == persisted.asks => return None,
+            Some(last) => FullDepth {
+                base_update_id: last.last_update_id as i64,
+                keyframe: false,
+                bids: changes(&last.bids, &persisted.bids, true),
+                asks: changes(&last.asks, &persisted.asks, false),
+                ..self.record(book, event_time_ms, now_ms)
+            },
+            None => FullDepth { keyframe: true, bids, asks, ..self.record(book, event_time_ms, now_ms) },
+        };
+        self.since_keyframe = if keyframe { 0 } else { self.since_keyframe + 1 };
+        self.last = Some(persisted);
+        Some(record)
+    }
+
+    fn record(&self, book: &LocalBook, event_time_ms: i64, now_ms: i64) -> FullDepth {
+        FullDepth {
+            timestamp_ms: now_ms,
+            event_time_ms,
+            exchange: binance::EXCHANGE.to_string(),
+            symbol: self.symbol.clone(),
+            last_update_id: book.last_update_id as i64,
+            keyframe: true,
+            base_update_id: 0,
+            bids: Vec::new(),
+            asks: Vec::new(),
+        }
+    }
+}
+
+/// Levels of `now` that differ from `before`, and those gone from it at quantity 0,
+/// best first.
+fn changes(before: &Side, now: &Side, descending: bool) -> Vec<ExactLevel> {
+    let mut changed: Vec<ExactLevel> = now.iter()
+        .filter(|(price, qty)| before.get(price) != Some(qty))
+        .chain(before.keys().filter(|price| !now.contains_key(price)).map(|price| (price, &0)))
+        .map(|(&price, &qty)| ExactLevel { price, qty })
+        .collect();
+    changed.sort_by_key(|l| if descending { -l.price } else { l.price });
+    changed
+}
+
+/// The full book after each of `records` (oldest first) as a keyframe. Changes that
+/// don't apply to the book rebuilt so far, as after a lost record, are dropped until
+/// the next keyframe.
+pub fn reconstruct(records: &[FullDepth]) -> Vec<FullDepth> {
+    let mut book = LocalBook::default();
+    let mut synced = false;
+    records.iter().filter_map(|record| {
+        synced = (record.keyframe || synced) && book.apply_record(record);
+        if !synced {
+            return None;
+        }
+        let (bids, asks) = book.levels(LIMIT);
+        Some(FullDepth { keyframe: true, base_update_id: 0, bids, asks, ..record.clone() })
+    }).collect()
+}
+
+/// `symbol`'s records stored in the hour containing `hour`, oldest first.
+async fn read_hour(archive: &Archive, symbol: &str, hour: DateTime<Utc>) -> Result<Vec<FullDepth>, Error> {
+    let mut records: Vec<FullDepth> = Vec::new();
+    for key in archive.list(&format!("{}/", sink::hour_dir(FULL_DEPTH_PREFIX, symbol, hour)?)).await? {
+        for value in apache_avro::Reader::new(&archive.get(&key).await?[..])? {
+            records.push(apache_avro::from_value(&value?)?);
+        }
+    }
+    records.sort_by_key(|r| r.timestamp_ms);
+    Ok(records)
+}
+
+/// `symbol`'s full books persisted in [from, to), rebuilt from the archive. Reads back
+/// from `from` an hour at a time, up to `MAX_LOOKBACK_HOURS`, for the keyframe the
+/// first of them builds on.
+pub async fn read_books(archive: &Archive, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<FullDepth>, Error> {
+    let (from_ms, to_ms) = (from.timestamp_millis(), to.timestamp_millis());
+    let mut records = Vec::new();
+    for hour in cli::hours(from, to) {
+        records.extend(read_hour(archive, symbol, hour).await?);
+    }
+    let mut earliest = cli::hours(from, to).first().copied().unwrap_or(from);
+    for _ in 0..MAX_LOOKBACK_HOURS {
+        if records.iter().any(|r| r.keyframe && r.timestamp_ms <= from_ms) {
+            break;
+        }
+        earliest -= chrono::Duration::hours(1);
+        records.splice(0..0, read_hour(archive, symbol, earliest).await?);
+    }
+
+    let mut books = reconstruct(&records);
+    books.retain(|b| (from_ms..to_ms).contains(&b.timestamp_ms));
+    Ok(books)
+}
+
+/// Keeps `symbol`'s full book until the task is dropped, persisting it with
+/// `FULL_DEPTH` on and its churn with `BOOK_CHURN` on, and rebuilding from a new
+/// snapshot after a sequence gap or a lost connection.
+pub async fn capture(s3: Client, venue: &Venue, symbol: String) -> Result<(), Error> {
+    let url = format!("{}/{}", venue.ws, diff_stream(&symbol));
+    let snapshot = format!("{}?symbol={}&limit={}", venue.rest_url("depth"), symbol.to_uppercase(), LIMIT);
+    let weight = venue.weight("depth", LIMIT as u32);
+    let every = config::var("FULL_DEPTH_SECS").and_then(|s| s.parse().ok()).map_or(DEFAULT_EVERY, Duration::from_secs);
+    let keyframe_every = config::var("FULL_DEPTH_KEYFRAME_EVERY").and_then(|n| n.parse().ok()).unwrap_or(DEFAULT_KEYFRAME_EVERY);
+    let mut outputs = Outputs {
+        symbol: symbol.clone(),
+        depth: enabled().then(|| (every, Encoder::new(&symbol, keyframe_every))),
+        churn: churn::enabled().then(churn::interval),
+    };
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        if let Some((_, encoder)) = &mut outputs.depth {
+            encoder.reset();
+        }
+        match keep(&s3, &url, (&snapshot, weight), &mut outputs).instrument(info_span!("full_depth", symbol)).await {
+            Ok(()) => backoff = Duration::from_secs(1),
+            Err(e) => {
+                warn!(error = %e, symbol, "full depth capture failed, rebuilding");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// What the local book is persisted as: full-depth records every so often, churn
+/// records every so often, or both.
+struct Outputs {
+    symbol: String,
+    depth: Option<(Duration, Encoder)>,
+    churn: Option<Duration>,
+}
+
+fn ticks(every: Option<Duration>) -> Option<tokio::time::Interval> {
+    every.map(|every| tokio::time::interval_at(tokio::time::Instant::now() + every, every))
+}
+
+/// The next tick, or never without an interval.
+async fn next_tick(tick: &mut Option<tokio::time::Interval>) {
+    match tick {
+        Some(tick) => {
+            tick.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+/// One life of the local book: connects, loads the snapshot and applies diffs until
+/// a gap (`Ok`) or the connection fails.
+async fn keep(s3: &Client, url: &str, (snapshot, weight): (&str, u32), outputs: &mut Outputs) -> Result<(), Error> {
+    // connect first so diffs queue up while the snapshot loads
+    let ws = proxy::connect(url).await?;
+    let (_, mut rx) = ws.split();
+    let body = rest::get(snapshot, weight).await?.error_for_status()?.text().await?;
+    let depth: PartialDepth = serde_json::from_str(&body)?;
+    let mut book = LocalBook::from_snapshot(&depth).ok_or_else(|| Error::FullDepth("snapshot levels aren't decimals".into()))?;
+    info!(last_update_id = book.last_update_id, "full depth snapshot loaded");
+
+    let mut event_time_ms = 0;
+    let mut depth_tick = ticks(outputs.depth.as_ref().map(|(every, _)| *every));
+    let mut churn_tick = ticks(outputs.churn);
+    let mut churn_start_ms = chrono::Utc::now().timestamp_millis();
+    loop {
+        tokio::select! {
+            msg = rx.next() => {
+                let Some(msg) = msg else { return Err(Error::FullDepth("diff stream closed".into())) };
+                let msg = msg?;
+                if !msg.is_text() {
+                    continue;
+                }
+                let mut bytes = msg.into_data();
+                let Ok(Some(DepthMessage::Update(update))) = DepthMessage::parse(&mut bytes) else { continue };
+                match book.apply(&update) {
+                    Applied::Gap => {
+                        warn!(last_update_id = book.last_update_id, first_update_id = update.first_update_id, "diff stream gap");
+                        return Ok(());
+                    }
+                    Applied::Applied => event_time_ms = update.event_time_ms,
+                    Applied::Stale => {}
+                }
+            }
+            _ = next_tick(&mut depth_tick) => {
+                let Some((_, encoder)) = &mut outputs.depth else { continue };
+                let now = chrono::Utc::now().timestamp_millis();
+                let Some(record) = encoder.encode(&book, event_time_ms, now) else { continue };
+                let key = sink::partition_key(FULL_DEPTH_PREFIX, &record.symbol, sink::at_ms(now), now)?;
+                sink::write(s3, &key, schema::FULL_DEPTH, &[record]).await?;
+            }
+            _ = next_tick(&mut churn_tick) => {
+                let now = chrono::Utc::now().timestamp_millis();
+                let record = Churn::new(&outputs.symbol, churn_start_ms, now, book.take_churn());
+                churn_start_ms = now;
+                let key = sink::partition_key(churn::CHURN_PREFIX, &record.symbol, sink::at_ms(record.start_ms), record.start_ms)?;
+                sink::write(s3, &key, schema::CHURN, &[record]).await?;
+            }
+        }
+    }
+}
@@ -0,0 +1,166 @@
+//! USD-M futures extras: liquidation capture and funding/open interest polling.
+
+use aws_sdk_s3::Client;
+use chrono::Utc;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::{info_span, warn, Instrument};
+
+use crate::{binance, config, handoff, proxy, rest, schema, sink, Error};
+
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Liquidation {
+    pub event_time_ms: i64,
+    pub trade_time_ms: i64,
+    pub symbol: String,
+    pub side: String,
+    pub price: f64,
+    pub avg_price: f64,
+    pub qty: f64,
+    pub filled_qty: f64,
+    pub status: String,
+}
+
+impl Liquidation {
+    /// Maps a `forceOrder` event onto the archived record.
+    pub fn from_event(v: &Value) -> Self {
+        let o = &v["o"];
+        let text = |k: &str| -> String { o[k].as_str().unwrap_or_default().to_string() };
+
+        Liquidation {
+            event_time_ms: v["E"].as_i64().unwrap_or_default(),
+            trade_time_ms: o["T"].as_i64().unwrap_or_default(),
+            symbol: text("s"),
+            side: text("S"),
+            price: num(o, "p"),
+            avg_price: num(o, "ap"),
+            qty: num(o, "q"),
+            filled_qty: num(o, "z"),
+            status: text("X"),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct FundingSnapshot {
+    pub timestamp_ms: i64,
+    pub symbol: String,
+    pub funding_rate: f64,
+    pub next_funding_time_ms: i64,
+    pub mark_price: f64,
+    pub index_price: f64,
+    pub open_interest: f64,
+}
+
+fn num(v: &Value, k: &str) -> f64 {
+    v[k].as_str().and_then(|x| x.parse().ok()).unwrap_or(0.0)
+}
+
+// Liquidations can't be backfilled, so each event is written as soon as it arrives,
+// and a lost connection or failed write is retried rather than ending the capture
+pub async fn capture_liquidations(s3: Client, url: &str) -> Result<(), Error> {
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match follow_liquidations(&s3, url).await {
+            Ok(()) => backoff = Duration::from_secs(1),
+            Err(e) => {
+                warn!(error = %e, url, "liquidation stream failed, reconnecting");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+async fn follow_liquidations(s3: &Client, url: &str) -> Result<(), Error> {
+    let ws = proxy::connect(url).await?;
+    let (_, mut rx) = ws.split();
+
+    while let Some(msg) = rx.next().await {
+        let msg = msg?;
+        if !msg.is_text() {
+            continue;
+        }
+        let v: Value = match serde_json::from_str(msg.to_text()?) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(error = %e, "skipping malformed liquidation event");
+                continue;
+            }
+        };
+        let liq = Liquidation::from_event(&v);
+
+        let key = sink::partition_key("liquidations", &liq.symbol, sink::at_ms(liq.event_time_ms), liq.event_time_ms)?;
+        let span = info_span!("liquidation", symbol = %liq.symbol);
+        sink::write(s3, &key, schema::LIQUIDATION, &[liq]).instrument(span).await?;
+    }
+    Ok(())
+}
+
+pub async fn fetch_funding(symbol: &str) -> Result<FundingSnapshot, Error> {
+    let venue = binance::venue()?;
+    let premium: Value = rest::get(&format!("{}?symbol={}", venue.rest_url("premiumIndex"), symbol), venue.weight("premiumIndex", 0))
+        .await?
+        .json()
+        .await?;
+    let oi: Value = rest::get(&format!("{}?symbol={}", venue.rest_url("openInterest"), symbol), venue.weight("openInterest", 0))
+        .await?
+        .json()
+        .await?;
+
+    Ok(FundingSnapshot {
+        timestamp_ms: premium["time"].as_i64().unwrap_or_else(|| Utc::now().timestamp_millis()),
+        symbol: symbol.to_string(),
+        funding_rate: num(&premium, "lastFundingRate"),
+        next_funding_time_ms: premium["nextFundingTime"].as_i64().unwrap_or_default(),
+        mark_price: num(&premium, "markPrice"),
+        index_price: num(&premium, "indexPrice"),
+        open_interest: num(&oi, "openInterest"),
+    })
+}
+
+/// The first wall-clock multiple of `period_ms` after `now_ms`.
+pub fn next_poll_ms(now_ms: i64, period_ms: i64) -> i64 {
+    now_ms - now_ms.rem_euclid(period_ms) + period_ms
+}
+
+/// Polls funding and open interest for `symbols`, which move slowly, at each wall
+/// clock multiple of `FUNDING_POLL_MINUTES` the task lives through. It lives as long
+/// as the invocation, so one that ends before the next multiple doesn't poll, and of
+/// overlapping invocations reaching the same one only the instance claiming it does.
+/// A failed fetch or write is logged and the next multiple tries again.
+pub async fn poll_funding(s3: Client, symbols: Vec<String>) -> Result<(), Error> {
+    let minutes: i64 = config::var("FUNDING_POLL_MINUTES").and_then(|m| m.parse().ok()).filter(|&m| m > 0).unwrap_or(5);
+
+    loop {
+        let now_ms = Utc::now().timestamp_millis();
+        let poll_ms = next_poll_ms(now_ms, minutes * 60_000);
+        tokio::time::sleep(Duration::from_millis((poll_ms - now_ms) as u64)).await;
+        for symbol in symbols.iter().map(|s| s.to_uppercase()) {
+            match sink::claim(&s3, &format!("{}/{}/funding-{}", handoff::CLAIMS_PREFIX, symbol, poll_ms)).await {
+                Ok(true) => {}
+                Ok(false) => continue,
+                Err(e) => {
+                    warn!(error = %e, symbol, "funding claim failed");
+                    continue;
+                }
+            }
+            let snap = match fetch_funding(&symbol).await {
+                Ok(snap) => snap,
+                Err(e) => {
+                    warn!(error = %e, symbol, "funding fetch failed");
+                    continue;
+                }
+            };
+
+            let key = sink::partition_key("funding", &symbol, sink::at_ms(poll_ms), poll_ms)?;
+            if let Err(e) = sink::write(&s3, &key, schema::FUNDING, &[snap]).instrument(info_span!("funding", symbol)).await {
+                warn!(error = %e, symbol, key, "funding write failed");
+            }
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Gaps in collection: stretches with no stored books, measured from the last
+//! stored book to now. Each detected gap is written as a marker record under
+//! `gaps/`, copied into every hour it covers, so a consumer reading any hour can
+//! mask the missing stretch instead of treating it as a quiet market.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::archive::Archive;
+use crate::checkpoint::{Checkpoint, Checkpoints};
+use crate::sink::{self, Output};
+use crate::{cli, config, migrate, schema, Error};
+
+pub const GAPS_PREFIX: &str = "gaps";
+
+/// Longer than this without a stored book counts as a gap.
+pub const DEFAULT_THRESHOLD_MS: i64 = 5000;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Gap {
+    /// Upper-cased.
+    pub symbol: String,
+    /// When the last book before the gap was stored.
+    pub from_ms: i64,
+    /// When collection resumed, or when the gap was found.
+    pub to_ms: i64,
+    /// What found it: `reconnect`, `recovery` or `repair`; `trades-missing` for the
+    /// part of a gap recovery ran out of time to fetch trades for.
+    pub reason: String,
+}
+
+impl Gap {
+    pub fn duration_ms(&self) -> i64 {
+        self.to_ms - self.from_ms
+    }
+
+    /// The hours the gap covers, each of which gets a copy of its marker.
+    pub fn hours(&self) -> Vec<DateTime<Utc>> {
+        cli::hours(sink::at_ms(self.from_ms), sink::at_ms(self.to_ms))
+    }
+
+    pub fn overlaps(&self, from_ms: i64, to_ms: i64) -> bool {
+        self.from_ms < to_ms && from_ms < self.to_ms
+    }
+}
+
+/// `GAP_THRESHOLD_MS`, or the 5 second default.
+pub fn threshold_ms() -> i64 {
+    config::var("GAP_THRESHOLD_MS").and_then(|t| t.parse().ok()).unwrap_or(DEFAULT_THRESHOLD_MS)
+}
+
+/// The gap from the last stored book at `last_ms` to `now_ms`, if longer than `threshold_ms`.
+pub fn detect(symbol: &str, last_ms: i64, now_ms: i64, threshold_ms: i64, reason: &str) -> Option<Gap> {
+    (now_ms - last_ms > threshold_ms).then(|| Gap {
+        symbol: symbol.to_uppercase(),
+        from_ms: last_ms,
+        to_ms: now_ms,
+        reason: reason.to_string(),
+    })
+}
+
+/// Seconds from `checkpoint`'s last stored book to `now_ms`, never negative, for the
+/// `SecondsSinceLastWrite` heartbeat.
+pub fn since_last_write_secs(checkpoint: &Checkpoint, now_ms: i64) -> f64 {
+    (now_ms - checkpoint.last_flush_ms).max(0) as f64 / 1000.0
+}
+
+/// Writes `gap`'s marker into every hour it covers, keyed by where it starts so a
+/// gap found twice overwrites its own markers.
+pub async fn record(output: &mut impl Output, gap: &Gap) -> Result<(), Error> {
+    for (key, body) in markers(gap)? {
+        output.write(&key, &body).await?;
+    }
+    Ok(())
+}
+
+/// `gap`'s marker objects as (key, body), one per hour it covers.
+pub fn markers(gap: &Gap) -> Result<Vec<(String, Vec<u8>)>, Error> {
+    let body = sink::encode(schema::GAP, std::slice::from_ref(gap))?;
+    gap.hours().into_iter()
+        .map(|hour| Ok((sink::partition_key(GAPS_PREFIX, &gap.symbol, hour, gap.from_ms)?, body.clone())))
+        .collect()
+}
+
+/// The gap manifest for `symbol` between `from` and `to`: every recorded gap
+/// overlapping the window, oldest first.
+pub async fn manifest(archive: &Archive, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Gap>, Error> {
+    let mut gaps: Vec<Gap> = Vec::new();
+    for hour in cli::hours(from, to) {
+        for key in archive.list(&format!("{}/", sink::hour_dir(GAPS_PREFIX, symbol, hour)?)).await? {
+            for value in apache_avro::Reader::new(&archive.get(&key).await?[..])? {
+                gaps.push(apache_avro::from_value(&value?)?);
+            }
+        }
+    }
+    gaps.retain(|g| g.overlaps(from.timestamp_millis(), to.timestamp_millis()));
+    // a gap has a marker in each hour it covers; the longest report of it wins
+    gaps.sort_by_key(|g| (g.from_ms, std::cmp::Reverse(g.to_ms)));
+    gaps.dedup_by_key(|g| g.from_ms);
+    Ok(gaps)
+}
+
+/// When `symbol`'s last book was stored, from the books in the newest object in the
+/// archive. Its key isn't trusted for the time: idempotently written partial depth
+/// is keyed by lastUpdateId. `None` when nothing has been stored for it.
+pub async fn last_stored_ms(archive: &Archive, symbol: &str) -> Result<Option<i64>, Error> {
+    let Some((_, key)) = archive.latest("orderbook", symbol).await? else { return Ok(None) };
+    Ok(migrate::read_orderbooks(&archive.get(&key).await?)?.iter().map(|b| b.timestamp_ms).max())
+}
+
+/// The gap since `symbol`'s last stored book, if any. The checkpoint is saved every
+/// few seconds, so a gap it shows is confirmed against the newest stored object.
+pub async fn find(archive: &Archive, checkpoints: Option<&Checkpoints>, symbol: &str, now_ms: i64, threshold_ms: i64) -> Result<Option<Gap>, Error> {
+    let checkpointed = match checkpoints {
+        Some(checkpoints) => checkpoints.load(symbol).await?.map(|c| c.last_flush_ms),
+        None => None,
+    };
+    if let Some(last_ms) = checkpointed {
+        if detect(symbol, last_ms, now_ms, threshold_ms, "recovery").is_none() {
+            return Ok(None);
+        }
+    }
+    let Some(last_ms) = checkpointed.max(last_stored_ms(archive, symbol).await?) else {
+        info!(symbol, "nothing stored yet, no gap to measure");
+        return Ok(None);
+    };
+    Ok(detect(symbol, last_ms, now_ms, threshold_ms, "recovery"))
+}
@@ -0,0 +1,127 @@
+//! gRPC streaming of stored books for service deployments: `SubscribeBook(symbol)`
+//! streams every book stored for the symbol from then on, as the `OrderBook` message
+//! of `proto/orderbook.proto`. A subscriber that falls behind skips the oldest books.
+
+use futures_util::Stream;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use tokio::sync::broadcast::error::RecvError;
+use tonic::{Request, Response, Status};
+use tracing::{info, warn};
+
+use crate::book::{ExactLevel, Level};
+use crate::{config, live, Error};
+
+/// Code generated from `proto/orderbook.proto`.
+pub mod proto {
+    tonic::include_proto!("orderbook.v1");
+}
+
+use proto::order_book_service_server::{OrderBookService, OrderBookServiceServer};
+
+impl From<&Level> for proto::Level {
+    fn from(level: &Level) -> Self {
+        proto::Level { price: level.price, qty: level.qty }
+    }
+}
+
+impl From<&ExactLevel> for proto::ExactLevel {
+    fn from(level: &ExactLevel) -> Self {
+        proto::ExactLevel { price: level.price, qty: level.qty }
+    }
+}
+
+impl From<&crate::OrderBook> for proto::OrderBook {
+    fn from(book: &crate::OrderBook) -> Self {
+        let levels = |levels: &[Level]| levels.iter().map(proto::Level::from).collect();
+        let exact = |levels: &[ExactLevel]| levels.iter().map(proto::ExactLevel::from).collect();
+        proto::OrderBook {
+            timestamp_ms: book.timestamp_ms,
+            bids: levels(&book.bids),
+            asks: levels(&book.asks),
+            spread: book.spread,
+            mid_price: book.mid_price,
+            imbalance_ratio: book.imbalance_ratio,
+            schema_version: book.schema_version,
+            exchange: book.exchange.clone(),
+            symbol: book.symbol.clone(),
+            event_time_ms: book.event_time_ms,
+            last_update_id: book.last_update_id,
+            exact_bids: exact(&book.exact_bids),
+            exact_asks: exact(&book.exact_asks),
+            price_scale: book.price_scale,
+            qty_scale: book.qty_scale,
+            bid_notional: book.bid_notional.clone(),
+            ask_notional: book.ask_notional.clone(),
+            bid_sweeps: levels(&book.bid_sweeps),
+            ask_sweeps: levels(&book.ask_sweeps),
+            spread_bps: book.spread_bps,
+            tick_size: book.tick_size,
+            spread_in_ticks: book.spread_in_ticks,
+            vpin: book.vpin,
+            vpin_buckets: book.vpin_buckets,
+            bid_fill_prices: book.bid_fill_prices.clone(),
+            ask_fill_prices: book.ask_fill_prices.clone(),
+            bid_slippage_bps: book.bid_slippage_bps.clone(),
+            ask_slippage_bps: book.ask_slippage_bps.clone(),
+            spread_mads: book.spread_mads,
+            depth_mads: book.depth_mads,
+            spread_anomaly: book.spread_anomaly,
+            depth_anomaly: book.depth_anomaly,
+            mid_return: book.mid_return,
+            mid_return_window: book.mid_return_window,
+            return_window_secs: book.return_window_secs,
+            sweep: book.sweep,
+            sweep_side: book.sweep_side.clone(),
+            sweep_levels: book.sweep_levels,
+            sweep_notional: book.sweep_notional,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct BookService;
+
+type BookStream = Pin<Box<dyn Stream<Item = Result<proto::OrderBook, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl OrderBookService for BookService {
+    type SubscribeBookStream = BookStream;
+
+    async fn subscribe_book(&self, request: Request<proto::SubscribeBookRequest>) -> Result<Response<BookStream>, Status> {
+        let symbol = request.into_inner().symbol.trim().to_uppercase();
+        if symbol.is_empty() || !symbol.chars().all(|c| c.is_ascii_alphanumeric()) {
+            return Err(Status::invalid_argument(format!("invalid symbol {:?}", symbol)));
+        }
+        let feed = live::subscribe();
+        let stream = futures_util::stream::unfold((feed, symbol), |(mut feed, symbol)| async move {
+            loop {
+                match feed.recv().await {
+                    Ok(book) if book.symbol == symbol => return Some((Ok(proto::OrderBook::from(&*book)), (feed, symbol))),
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(missed)) => warn!(missed, symbol, "grpc subscriber fell behind, skipped books"),
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(stream)))
+    }
+}
+
+/// Serves `OrderBookService` on `addr` until the server fails.
+pub async fn serve(addr: SocketAddr) -> Result<(), Error> {
+    info!(%addr, "serving books over grpc");
+    tonic::transport::Server::builder()
+        .add_service(OrderBookServiceServer::new(BookService))
+        .serve(addr)
+        .await
+        .map_err(|e| Error::Grpc(e.to_string()))
+}
+
+/// `GRPC_ADDR`, e.g. `0.0.0.0:50051`, when set.
+pub fn addr() -> Result<Option<SocketAddr>, Error> {
+    let Some(addr) = config::var("GRPC_ADDR").filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    addr.parse().map(Some).map_err(|_| Error::Config(format!("invalid GRPC_ADDR {:?}", addr)))
+}
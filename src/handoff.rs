@@ -0,0 +1,78 @@
+//! Bounded invocations for overlapping schedules. An EventBridge rule invokes the
+//! collector with `{"duration_minutes": N, "overlap_secs": M}` more often than every
+//! N minutes, so the next invocation is already streaming when the previous one
+//! flushes and exits. During those overlaps both instances receive the same books;
+//! each is claimed by its `lastUpdateId` before it is written, so only one of them
+//! stores it.
+
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::Duration;
+use tokio::time::Instant;
+
+use crate::Error;
+
+pub const CLAIMS_PREFIX: &str = "claims";
+
+const DEFAULT_OVERLAP_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct Bounds {
+    pub duration_minutes: u64,
+    /// How long this invocation shares the stream with its neighbours, at each end.
+    #[serde(default = "default_overlap")]
+    pub overlap_secs: u64,
+}
+
+fn default_overlap() -> u64 {
+    DEFAULT_OVERLAP_SECS
+}
+
+impl Bounds {
+    /// The bounds in an invocation's payload. Payloads without `duration_minutes`,
+    /// such as EventBridge's own scheduled event, run unbounded.
+    pub fn from_event(event: &Value) -> Result<Option<Self>, Error> {
+        if event.get("duration_minutes").is_none() {
+            return Ok(None);
+        }
+        let bounds: Bounds = serde_json::from_value(event.clone())
+            .map_err(|e| Error::Config(format!("invocation bounds: {}", e)))?;
+        if bounds.duration_minutes == 0 || bounds.overlap() >= bounds.duration() {
+            return Err(Error::Config(format!("invocation bounds: overlap of {}s doesn't fit in {} minutes",
+                                             bounds.overlap_secs, bounds.duration_minutes)));
+        }
+        Ok(Some(bounds))
+    }
+
+    pub fn duration(&self) -> Duration {
+        Duration::from_secs(self.duration_minutes * 60)
+    }
+
+    pub fn overlap(&self) -> Duration {
+        Duration::from_secs(self.overlap_secs)
+    }
+}
+
+/// When one bounded invocation may be streaming alongside another.
+#[derive(Debug, Clone, Copy)]
+pub struct Window {
+    started: Instant,
+    deadline: Instant,
+    overlap: Duration,
+}
+
+impl Window {
+    pub fn new(bounds: &Bounds, started: Instant) -> Self {
+        Window { started, deadline: started + bounds.duration(), overlap: bounds.overlap() }
+    }
+
+    /// Whether `now` falls in the first or last `overlap` of the invocation.
+    pub fn overlapping(&self, now: Instant) -> bool {
+        now < self.started + self.overlap || now + self.overlap >= self.deadline
+    }
+}
+
+/// Key claiming `symbol`'s book at `last_update_id` for a single writer.
+pub fn claim_key(symbol: &str, last_update_id: i64) -> String {
+    format!("{}/{}/{}", CLAIMS_PREFIX, symbol.to_uppercase(), last_update_id)
+}
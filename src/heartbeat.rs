@@ -0,0 +1,34 @@
+use chrono::Utc;
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use orderbook::checkpoint::Checkpoints;
+use orderbook::metrics::{Metric, Metrics};
+use orderbook::{config, gaps, logging, params};
+use std::collections::BTreeMap;
+use tracing::warn;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    logging::init();
+    params::init().await?;
+    config::symbols()?;
+    let checkpoints = Checkpoints::from_env().await.ok_or("CHECKPOINT_TABLE is not set")?;
+    run(service_fn(|event| handler(&checkpoints, event))).await
+}
+
+/// Emits `SecondsSinceLastWrite` for each of `SYMBOLS` from its checkpoint and returns
+/// the values. A symbol without a checkpoint emits nothing, which the alarm counts
+/// as missing data.
+async fn handler(checkpoints: &Checkpoints, _: LambdaEvent<serde_json::Value>) -> Result<BTreeMap<String, f64>, Error> {
+    let now_ms = Utc::now().timestamp_millis();
+    let mut since = BTreeMap::new();
+    for symbol in config::symbols()? {
+        let Some(checkpoint) = checkpoints.load(&symbol).await? else {
+            warn!(symbol, "no checkpoint, nothing stored yet");
+            continue;
+        };
+        let secs = gaps::since_last_write_secs(&checkpoint, now_ms);
+        Metrics::new(&symbol).record(Metric::SecondsSinceLastWrite, secs);
+        since.insert(symbol, secs);
+    }
+    Ok(since)
+}
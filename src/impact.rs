@@ -0,0 +1,141 @@
+//! Kyle's lambda: how far the mid moves per unit of signed trade flow. An hour is cut
+//! into intervals; each gives the mid's change across it (from the stored books) and
+//! the taker buy minus sell quantity traded in it (from the venue's aggregated
+//! trades). An ordinary least-squares fit of one on the other gives the impact
+//! coefficient, stored per symbol and hour under `analytics/impact/`.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::archive::Archive;
+use crate::trades::{self, AggTrade};
+use crate::{binance, config, lookup, schema, sink, Error, OrderBook};
+
+pub const ANALYTICS_PREFIX: &str = "analytics";
+
+const DEFAULT_INTERVAL_SECS: i64 = 60;
+
+/// `IMPACT_INTERVAL_SECS`, the length of each regression interval; a minute when unset.
+pub fn interval_ms() -> i64 {
+    config::var("IMPACT_INTERVAL_SECS").and_then(|s| s.parse().ok()).filter(|s| *s > 0).unwrap_or(DEFAULT_INTERVAL_SECS) * 1000
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImpactEstimate {
+    pub start_ms: i64,
+    pub end_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub interval_ms: i64,
+    /// Intervals in the fit: those with a book at both ends.
+    pub intervals: i64,
+    /// Mid change per unit of signed base quantity.
+    pub lambda: f64,
+    /// `lambda` rescaled to basis points of the mean mid per unit of quote notional,
+    /// so symbols compare; times an order's notional, its expected impact.
+    pub lambda_bps: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+    /// `lambda` over its standard error, 0 with fewer than three intervals.
+    pub t_stat: f64,
+    pub mean_mid: f64,
+}
+
+/// An ordinary least-squares fit of `y = intercept + slope * x`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fit {
+    pub slope: f64,
+    pub intercept: f64,
+    pub r_squared: f64,
+    pub t_stat: f64,
+}
+
+/// Fits `(x, y)` points; `None` with fewer than two or when `x` doesn't vary.
+pub fn regress(points: &[(f64, f64)]) -> Option<Fit> {
+    let n = points.len() as f64;
+    if points.len() < 2 {
+        return None;
+    }
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let sxx: f64 = points.iter().map(|p| (p.0 - mean_x).powi(2)).sum();
+    let sxy: f64 = points.iter().map(|p| (p.0 - mean_x) * (p.1 - mean_y)).sum();
+    let syy: f64 = points.iter().map(|p| (p.1 - mean_y).powi(2)).sum();
+    if sxx <= 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let intercept = mean_y - slope * mean_x;
+    let residual = (syy - slope * sxy).max(0.0);
+    let r_squared = if syy > 0.0 { 1.0 - residual / syy } else { 0.0 };
+    let t_stat = match points.len() {
+        0..=2 => 0.0,
+        _ if residual == 0.0 => 0.0,
+        len => slope / (residual / (len as f64 - 2.0) / sxx).sqrt(),
+    };
+    Some(Fit { slope, intercept, r_squared, t_stat })
+}
+
+/// (signed flow, mid change) for each `interval_ms` of [start_ms, end_ms) with a book
+/// received at or before both of its ends. `books` and `trades` are oldest first; a trade
+/// whose buyer was the maker counts as a sell.
+pub fn intervals(books: &[OrderBook], trades: &[AggTrade], start_ms: i64, end_ms: i64, interval_ms: i64) -> Vec<(f64, f64)> {
+    let mid_at = |at: i64| {
+        let i = books.partition_point(|b| b.timestamp_ms <= at);
+        i.checked_sub(1).map(|i| books[i].mid_price)
+    };
+    (start_ms..end_ms).step_by(interval_ms.max(1) as usize).filter_map(|from| {
+        let to = (from + interval_ms).min(end_ms);
+        let (before, after) = (mid_at(from)?, mid_at(to)?);
+        let flow = trades.iter()
+            .filter(|t| (from..to).contains(&t.trade_time_ms))
+            .map(|t| if t.is_buyer_maker { -t.qty } else { t.qty })
+            .sum();
+        Some((flow, after - before))
+    }).collect()
+}
+
+/// The estimate for `symbol` over `books` and `trades` in [start_ms, end_ms), or
+/// `None` when the intervals don't support a fit.
+pub fn estimate(symbol: &str, books: &[OrderBook], trades: &[AggTrade], start_ms: i64, end_ms: i64, interval_ms: i64) -> Option<ImpactEstimate> {
+    let points = intervals(books, trades, start_ms, end_ms, interval_ms);
+    let fit = regress(&points)?;
+    let mids: Vec<f64> = books.iter().map(|b| b.mid_price).collect();
+    let mean_mid = mids.iter().sum::<f64>() / mids.len() as f64;
+    Some(ImpactEstimate {
+        start_ms,
+        end_ms,
+        exchange: binance::EXCHANGE.to_string(),
+        symbol: symbol.to_uppercase(),
+        interval_ms,
+        intervals: points.len() as i64,
+        lambda: fit.slope,
+        lambda_bps: if mean_mid > 0.0 { fit.slope / (mean_mid * mean_mid) * 10_000.0 } else { 0.0 },
+        intercept: fit.intercept,
+        r_squared: fit.r_squared,
+        t_stat: fit.t_stat,
+        mean_mid,
+    })
+}
+
+/// Estimates `symbol`'s impact over the hour containing `hour` from its stored books,
+/// compacted or not, and the trades the venue serves for it, and stores the estimate.
+/// Returns `None`, writing nothing, when the hour has too few books for a fit.
+pub async fn estimate_hour(archive: &Archive, symbol: &str, hour: DateTime<Utc>) -> Result<Option<ImpactEstimate>, Error> {
+    let start = hour.duration_trunc(Duration::hours(1)).unwrap_or(hour);
+    let end = start + Duration::hours(1);
+    let books = lookup::hour_books(archive, symbol, start).await?;
+    if books.len() < 2 {
+        return Ok(None);
+    }
+    let trades = trades::fetch_agg_trades(&symbol.to_uppercase(), start, end).await?;
+    let Some(estimate) = estimate(symbol, &books, &trades, start.timestamp_millis(), end.timestamp_millis(), interval_ms()) else {
+        return Ok(None);
+    };
+
+    let key = sink::partition_key(&format!("{}/impact", ANALYTICS_PREFIX), &estimate.symbol, start, estimate.start_ms)?;
+    archive.put(&key, sink::encode(schema::IMPACT, std::slice::from_ref(&estimate))?).await?;
+    info!(key, symbol, lambda = estimate.lambda, intervals = estimate.intervals, "stored impact estimate");
+    Ok(Some(estimate))
+}
@@ -0,0 +1,154 @@
+//! Object key layout. `KEY_TEMPLATE` lets a bucket follow an existing lake convention,
+//! e.g. `{prefix}/exchange={exchange}/symbol={symbol}/dt={date}/hour={hour}/{ts}.avro`.
+//!
+//! Placeholders: `{prefix}` (the dataset, such as `orderbook`), `{exchange}`, `{symbol}`
+//! (upper-cased), `{date}` (`2025-09-03`), `{year}`, `{month}`, `{day}`, `{hour}` and
+//! `{ts}` (the record's id, usually a millisecond timestamp). Everything before the
+//! last `/` is the partition directory readers list by hour, so `{ts}` may only
+//! appear in the file name.
+
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use std::sync::OnceLock;
+
+use crate::{config, Error};
+
+/// Hive-style partitions by source, then hour.
+pub const DEFAULT_TEMPLATE: &str = "{prefix}/exchange={exchange}/symbol={symbol}/year={year}/month={month}/day={day}/hour={hour}/{ts}.avro";
+
+const PLACEHOLDERS: [&str; 9] = ["prefix", "exchange", "symbol", "date", "year", "month", "day", "hour", "ts"];
+const TIME_PLACEHOLDERS: [&str; 5] = ["{date}", "{year}", "{month}", "{day}", "{hour}"];
+
+/// What a key is rendered from, apart from the record id.
+#[derive(Debug, Clone, Copy)]
+pub struct KeyParts<'a> {
+    pub prefix: &'a str,
+    pub exchange: &'a str,
+    pub symbol: &'a str,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyTemplate {
+    template: String,
+}
+
+impl Default for KeyTemplate {
+    fn default() -> Self {
+        KeyTemplate { template: DEFAULT_TEMPLATE.to_string() }
+    }
+}
+
+impl KeyTemplate {
+    /// Rejects unknown or unclosed placeholders, and templates that could put two
+    /// records at the same key or two datasets in the same directory: `{ts}` must
+    /// be in the file name only, `{prefix}` in the directory and `{symbol}` somewhere.
+    pub fn new(template: &str) -> Result<Self, Error> {
+        let invalid = |why: &str| Err(Error::Config(format!("key template {:?}: {}", template, why)));
+
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let Some(len) = rest[start..].find('}') else { return invalid("unclosed placeholder") };
+            let name = &rest[start + 1..start + len];
+            if !PLACEHOLDERS.contains(&name) {
+                return invalid(&format!("unknown placeholder {{{}}}", name));
+            }
+            rest = &rest[start + len + 1..];
+        }
+        let (dir, file) = template.rsplit_once('/').unwrap_or(("", template));
+        if !file.contains("{ts}") || dir.contains("{ts}") {
+            return invalid("{ts} must appear in the file name only");
+        }
+        if !dir.contains("{prefix}") {
+            return invalid("the directory must include {prefix}");
+        }
+        // one exchange is collected, but many symbols share each dataset
+        if !template.contains("{symbol}") {
+            return invalid("{symbol} must appear, or symbols overwrite each other");
+        }
+        Ok(KeyTemplate { template: template.to_string() })
+    }
+
+    /// `KEY_TEMPLATE`, or the default layout when unset.
+    pub fn from_env() -> Result<Self, Error> {
+        match config::var("KEY_TEMPLATE") {
+            Some(template) if !template.is_empty() => KeyTemplate::new(&template),
+            _ => Ok(KeyTemplate::default()),
+        }
+    }
+
+    pub fn key(&self, parts: &KeyParts, ts: i64) -> String {
+        render(&self.template, parts, ts)
+    }
+
+    /// The partition directory for `parts`, without a trailing slash.
+    pub fn dir(&self, parts: &KeyParts) -> String {
+        render(self.split().0, parts, 0)
+    }
+
+    /// The partition directory one segment at a time: rendered where the segment
+    /// doesn't depend on the time, `None` where it does. Time segments are zero
+    /// padded, so their names sort in time order.
+    pub fn dir_levels(&self, parts: &KeyParts) -> Vec<Option<String>> {
+        self.split().0.split('/')
+            .map(|segment| (!TIME_PLACEHOLDERS.iter().any(|p| segment.contains(p))).then(|| render(segment, parts, 0)))
+            .collect()
+    }
+
+    /// The record id `{ts}` stands for in `key`'s file name.
+    pub fn record_id(&self, key: &str) -> Option<i64> {
+        let file = key.rsplit('/').next()?;
+        let (_, after) = self.split().1.split_once("{ts}")?;
+        // a literal suffix is stripped exactly; otherwise the id runs up to the first dot
+        let rest = if after.contains('{') { file.split('.').next()? } else { file.strip_suffix(after)? };
+        let digits = rest.trim_end_matches(|c: char| c.is_ascii_digit());
+        rest[digits.len()..].parse().ok()
+    }
+
+    /// The key among `keys` with the largest record id, compared as numbers rather
+    /// than in listing order. Ids are event times or, for idempotently written partial
+    /// depth, lastUpdateIds, so they order a directory's keys but aren't a time.
+    pub fn latest<'a>(&self, keys: impl IntoIterator<Item = &'a String>) -> Option<(i64, &'a String)> {
+        keys.into_iter().filter_map(|key| Some((self.record_id(key)?, key))).max()
+    }
+
+    /// (directory, file name) templates.
+    fn split(&self) -> (&str, &str) {
+        self.template.rsplit_once('/').unwrap_or(("", &self.template))
+    }
+}
+
+fn render(template: &str, parts: &KeyParts, ts: i64) -> String {
+    let at = parts.at;
+    let mut out = String::with_capacity(template.len() + 32);
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        let Some(len) = rest[start..].find('}') else { break };
+        out.push_str(&rest[..start]);
+        match &rest[start + 1..start + len] {
+            "prefix" => out.push_str(parts.prefix),
+            "exchange" => out.push_str(parts.exchange),
+            "symbol" => out.push_str(&parts.symbol.to_uppercase()),
+            "date" => out.push_str(&format!("{}-{:02}-{:02}", at.year(), at.month(), at.day())),
+            "year" => out.push_str(&at.year().to_string()),
+            "month" => out.push_str(&format!("{:02}", at.month())),
+            "day" => out.push_str(&format!("{:02}", at.day())),
+            "hour" => out.push_str(&format!("{:02}", at.hour())),
+            "ts" => out.push_str(&ts.to_string()),
+            other => out.push_str(&format!("{{{}}}", other)),
+        }
+        rest = &rest[start + len + 1..];
+    }
+    out.push_str(rest);
+    out
+}
+
+/// The process-wide template, read and validated on first use.
+pub fn template() -> Result<&'static KeyTemplate, Error> {
+    static TEMPLATE: OnceLock<KeyTemplate> = OnceLock::new();
+
+    if let Some(template) = TEMPLATE.get() {
+        return Ok(template);
+    }
+    let template = KeyTemplate::from_env()?;
+    Ok(TEMPLATE.get_or_init(|| template))
+}
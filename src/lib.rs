@@ -0,0 +1,84 @@
+//! Shared orderbook ingestion logic used by the Lambda handlers and local test binaries.
+
+pub mod alert;
+pub mod anomaly;
+pub mod archive;
+#[cfg(feature = "userdata")]
+pub mod auth;
+pub mod bars;
+pub mod batch;
+pub mod binance;
+pub mod book;
+pub mod checkpoint;
+pub mod churn;
+pub mod cli;
+pub mod collector;
+#[cfg(feature = "parquet")]
+pub mod columnar;
+pub mod compact;
+pub mod config;
+pub mod consolidate;
+pub mod correlation;
+#[cfg(feature = "delta")]
+pub mod delta;
+pub mod diff;
+#[cfg(feature = "duckdb")]
+pub mod duck;
+pub mod error;
+pub mod export;
+#[cfg(feature = "prometheus")]
+pub mod exporter;
+pub mod fulldepth;
+#[cfg(feature = "futures")]
+pub mod futures;
+pub mod gaps;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod handoff;
+pub mod impact;
+pub mod layout;
+pub mod live;
+pub mod logging;
+pub mod lookup;
+pub mod manifest;
+pub mod metrics;
+pub mod migrate;
+pub mod mux;
+#[cfg(feature = "nats")]
+pub mod nats;
+pub mod params;
+pub mod pipeline;
+pub mod poll;
+pub mod proxy;
+#[cfg(feature = "python")]
+pub mod python;
+#[cfg(feature = "redis")]
+pub mod pubsub;
+pub mod raw;
+pub mod registry;
+pub mod relay;
+pub mod repair;
+pub mod replay;
+pub mod resiliency;
+pub mod rest;
+pub mod retry;
+pub mod returns;
+pub mod sample;
+pub mod schema;
+pub mod sink;
+pub mod split;
+pub mod stages;
+pub mod stats;
+pub mod sweep;
+pub mod spread;
+pub mod top;
+pub mod trades;
+#[cfg(feature = "userdata")]
+pub mod userdata;
+pub mod validate;
+pub mod verify;
+pub mod vpin;
+pub mod wal;
+
+pub use book::OrderBook;
+pub use error::Error;
@@ -0,0 +1,25 @@
+//! In-process feed of stored books for real-time consumers. Collectors publish each
+//! book they store; fan-out tasks subscribe and forward them elsewhere. A subscriber
+//! that falls behind loses the oldest books rather than slowing collection down.
+
+use std::sync::{Arc, LazyLock};
+use tokio::sync::broadcast;
+
+use crate::OrderBook;
+
+/// Books a subscriber may fall behind by before it starts losing them.
+const CAPACITY: usize = 4096;
+
+static FEED: LazyLock<broadcast::Sender<Arc<OrderBook>>> = LazyLock::new(|| broadcast::channel(CAPACITY).0);
+
+/// Hands `book` to every subscriber; free when there are none.
+pub fn publish(book: &OrderBook) {
+    if FEED.receiver_count() > 0 {
+        let _ = FEED.send(Arc::new(book.clone()));
+    }
+}
+
+/// Every book published from now on.
+pub fn subscribe() -> broadcast::Receiver<Arc<OrderBook>> {
+    FEED.subscribe()
+}
@@ -0,0 +1,13 @@
+use tracing_subscriber::EnvFilter;
+
+/// JSON logs to stdout so CloudWatch can index span and event fields.
+/// Verbosity follows `RUST_LOG`, defaulting to `info`.
+pub fn init() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with_current_span(true)
+        .with_span_list(false)
+        .without_time()
+        .init();
+}
@@ -0,0 +1,69 @@
+//! Point lookups over the archive: the stored book nearest a moment, or the latest
+//! one, for the query Lambda and anything else that needs a single snapshot rather
+//! than a range.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+
+use crate::archive::Archive;
+use crate::checkpoint::Checkpoints;
+use crate::{migrate, sink, verify, Error, OrderBook};
+
+const PREFIX: &str = "orderbook";
+
+/// Every book of `symbol` for the hour containing `hour`, oldest first: those in the
+/// files its manifest names, once compacted, and in the hour's own objects, with a
+/// book found in both kept once.
+pub async fn hour_books(archive: &Archive, symbol: &str, hour: DateTime<Utc>) -> Result<Vec<OrderBook>, Error> {
+    let symbol = symbol.to_uppercase();
+    let mut books = Vec::new();
+    for key in verify::hour_keys(archive, &symbol, hour).await? {
+        books.extend(migrate::read_orderbooks(&archive.get(&key).await?)?.into_iter().filter(|b| b.symbol.is_empty() || b.symbol == symbol));
+    }
+    books.sort_by_key(|b| (b.timestamp_ms, b.last_update_id));
+    books.dedup_by_key(|b| (b.timestamp_ms, b.last_update_id));
+    Ok(books)
+}
+
+/// The book received closest to `at_ms`, the earlier one on a tie.
+pub fn nearest(books: &[OrderBook], at_ms: i64) -> Option<&OrderBook> {
+    books.iter().min_by_key(|b| ((b.timestamp_ms - at_ms).abs(), b.timestamp_ms))
+}
+
+/// `symbol`'s stored book nearest `at_ms`. Looks in the hour holding `at_ms`, and in
+/// the neighbouring hour whenever that hour's boundary is closer than the best match.
+pub async fn nearest_book(archive: &Archive, symbol: &str, at_ms: i64) -> Result<Option<OrderBook>, Error> {
+    let at = sink::at_ms(at_ms);
+    let hour = at.duration_trunc(Duration::hours(1)).unwrap_or(at);
+    let mut books = hour_books(archive, symbol, hour).await?;
+
+    let best = nearest(&books, at_ms).map(|b| (b.timestamp_ms - at_ms).abs());
+    let (since_start, until_end) = (at_ms - hour.timestamp_millis(), (hour + Duration::hours(1)).timestamp_millis() - at_ms);
+    let neighbour = if since_start <= until_end { hour - Duration::hours(1) } else { hour + Duration::hours(1) };
+    if best.is_none_or(|d| d > since_start.min(until_end)) {
+        books.extend(hour_books(archive, symbol, neighbour).await?);
+    }
+    Ok(nearest(&books, at_ms).cloned())
+}
+
+/// `symbol`'s newest stored book: the one its checkpoint names when there is a
+/// checkpoint store (found by time once its object has been compacted away),
+/// otherwise the newest object in the archive.
+pub async fn latest_book(archive: &Archive, checkpoints: Option<&Checkpoints>, symbol: &str) -> Result<Option<OrderBook>, Error> {
+    let checkpoint = match checkpoints {
+        Some(checkpoints) => checkpoints.load(symbol).await?,
+        None => None,
+    };
+    let listed = |key: &str| {
+        let dir = key.rsplit_once('/').map_or(String::new(), |(dir, _)| format!("{}/", dir));
+        async move { archive.list(&dir).await }
+    };
+    let key = match checkpoint {
+        Some(checkpoint) if listed(&checkpoint.last_key).await?.contains(&checkpoint.last_key) => checkpoint.last_key,
+        Some(checkpoint) => return nearest_book(archive, symbol, checkpoint.last_flush_ms).await,
+        None => match archive.latest(PREFIX, symbol).await? {
+            Some((_, key)) => key,
+            None => return Ok(None),
+        },
+    };
+    Ok(migrate::read_orderbooks(&archive.get(&key).await?)?.into_iter().max_by_key(|b| b.timestamp_ms))
+}
@@ -1,102 +1,323 @@
-use aws_config::BehaviorVersion;
 use aws_sdk_s3::Client;
-use chrono::{Datelike, Timelike, Utc};
-use futures_util::StreamExt;
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
-use serde::{Deserialize, Serialize};
-use tokio_tungstenite::connect_async;
-
-#[derive(Serialize, Deserialize)]
-struct OrderBook {
-    timestamp_ms: i64,
-    bids: Vec<(f64, f64)>,
-    asks: Vec<(f64, f64)>,
-    spread: f64,
-    mid_price: f64,
-    imbalance_ratio: f64,
-}
-
-const SCHEMA: &str = r#"
-{
-  "type": "record",
-  "name": "OrderBook",
-  "fields": [
-    {"name": "timestamp_ms", "type": "long"},
-    {"name": "bids", "type": {"type": "array", "items": {"type": "array", "items": "double"}}},
-    {"name": "asks", "type": {"type": "array", "items": {"type": "array", "items": "double"}}},
-    {"name": "spread", "type": "double"},
-    {"name": "mid_price", "type": "double"},
-    {"name": "imbalance_ratio", "type": "double"}
-  ]
-}
-"#;
+use orderbook::checkpoint::Checkpoints;
+use orderbook::collector::{self, Collector, MessageCounts};
+use orderbook::handoff::{Bounds, Window};
+use orderbook::mux::{self, Control, Multiplexer};
+use orderbook::params::{self, Params};
+use orderbook::sink::S3Output;
+use orderbook::{binance, book, churn, config, correlation, fulldepth, layout, logging, poll, proxy, relay, resiliency, sink, sweep, top, vpin};
+use std::collections::BTreeMap;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::{mpsc, watch, Mutex};
+use tokio::task::JoinSet;
+use tracing::{error, info, warn};
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
-    run(service_fn(handler)).await
+    logging::init();
+    // parameter store settings go in first so the process-wide ones below see them
+    let params = Mutex::new(params::init().await?);
+    // built once per container and shared by every invocation it serves
+    let s3 = config::s3_client().await?;
+    layout::template()?;
+    book::depth_buckets()?;
+    book::notional_thresholds()?;
+    binance::venue()?;
+    config::symbols()?;
+    proxy::check()?;
+    // REDIS_URL publishes every stored book on a per-symbol channel, from one task per
+    // process however many invocations it serves
+    #[cfg(feature = "redis")]
+    if let Some(settings) = orderbook::pubsub::Settings::from_env() {
+        tokio::spawn(async move {
+            if let Err(e) = orderbook::pubsub::run(settings).await {
+                error!(error = %e, "redis publisher failed");
+            }
+        });
+    }
+    // NATS_URL publishes every stored book to JetStream, acknowledged
+    #[cfg(feature = "nats")]
+    if let Some(settings) = orderbook::nats::Settings::from_env() {
+        tokio::spawn(async move {
+            if let Err(e) = orderbook::nats::run(settings).await {
+                error!(error = %e, "jetstream publisher failed");
+            }
+        });
+    }
+    // RUN_MODE=service runs as a plain long-lived process (ECS/Fargate) instead of
+    // serving Lambda invocations
+    if config::var("RUN_MODE").is_some_and(|m| m.eq_ignore_ascii_case("service")) {
+        return service(&s3, &params).await;
+    }
+    run(service_fn(|event| handler(&s3, &params, event))).await
 }
 
-async fn handler(_: LambdaEvent<serde_json::Value>) -> Result<(), Error> {
-    let s3 = Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await);
-    let (ws, _) = connect_async("wss://stream.binance.us:9443/ws/btcusdt@depth20@100ms").await?;
-    let (_, mut rx) = ws.split();
-    
-    while let Some(msg) = rx.next().await {
-        let txt = msg?.to_text()?.to_string();  // handles all message types
-        let v: serde_json::Value = serde_json::from_str(&txt)?;
-        
-        let parse_book = |key| -> Vec<(f64, f64)> {
-            v[key].as_array().unwrap().iter().take(20)
-                .map(|x| (x[0].as_str().unwrap().parse().unwrap(), 
-                         x[1].as_str().unwrap().parse().unwrap()))
-                .collect()
-        };
-        let (bids, asks) = (parse_book("bids"), parse_book("asks"));
-        
-        // core metrics
-        let mid = (bids[0].0 + asks[0].0) / 2.0;
-        let spread = asks[0].0 - bids[0].0;
-        let vol = |book: &[(f64, f64)]| -> f64 { book[..5].iter().map(|x| x.1).sum() };
-        let (bid_vol, ask_vol) = (vol(&bids), vol(&asks));
-        
-        // normalize to depth levels
-        let depths = [0.0001, 0.0005, 0.001, 0.005, 0.01];
-        let norm = |book: &[(f64, f64)], is_ask: bool| -> Vec<(f64, f64)> {
-            depths.iter().map(|&d| {
-                let target = mid * (1.0 + if is_ask { d } else { -d });
-                let cum: f64 = book.iter()
-                    .filter(|(p, _)| (is_ask && *p <= target) || (!is_ask && *p >= target))
-                    .map(|(_, q)| q)
-                    .sum();
-                (target, cum)
-            }).collect()
-        };
-        
-        let book = OrderBook {
-            timestamp_ms: Utc::now().timestamp_millis(),
-            bids: norm(&bids, false),
-            asks: norm(&asks, true),
-            spread,
-            mid_price: mid,
-            imbalance_ratio: (bid_vol - ask_vol) / (bid_vol + ask_vol),
-        };
-
-        let schema = apache_avro::Schema::parse_str(SCHEMA)?;
-        let mut writer = apache_avro::Writer::new(&schema, Vec::new());
-        writer.append_ser(&book)?;
-        
-        let now = Utc::now();
-        let key = format!("orderbook/year={}/month={:02}/day={:02}/hour={:02}/{}.avro",
-                         now.year(), now.month(), now.day(), now.hour(), now.timestamp_millis());
-        
-        s3.put_object()
-            .bucket("orderbook-data")
-            .key(&key)
-            .body(writer.into_inner()?.into())
-            .send()
-            .await?;
-        
-        println!("Written: {}", key);
+async fn handler(s3: &Client, params: &Mutex<Option<Params>>, event: LambdaEvent<serde_json::Value>) -> Result<BTreeMap<String, MessageCounts>, Error> {
+    // {"duration_minutes": N} runs for N minutes, then flushes and returns
+    let Some(bounds) = Bounds::from_event(&event.payload)? else {
+        return collect(s3, params, None, None).await;
+    };
+    let (stop, shutdown) = watch::channel(false);
+    let window = Window::new(&bounds, tokio::time::Instant::now());
+    tokio::spawn(async move {
+        tokio::time::sleep(bounds.duration()).await;
+        stop.send(true).ok();
+    });
+    info!(duration_minutes = bounds.duration_minutes, overlap_secs = bounds.overlap_secs, "bounded invocation");
+    collect(s3, params, Some(shutdown), Some(window)).await
+}
+
+/// Collects until SIGTERM (what ECS sends on stop) or SIGINT, then writes partial
+/// batches and exits. Parameters are refreshed on their own timer since there are
+/// no invocations to do it.
+async fn service(s3: &Client, params: &Mutex<Option<Params>>) -> Result<(), Error> {
+    let (stop, shutdown) = watch::channel(false);
+    let (mut term, mut int) = (signal(SignalKind::terminate())?, signal(SignalKind::interrupt())?);
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = term.recv() => info!("SIGTERM, shutting down"),
+            _ = int.recv() => info!("SIGINT, shutting down"),
+        }
+        stop.send(true).ok();
+    });
+
+    // RELAY_ADDR re-broadcasts stored books to websocket clients, e.g. 0.0.0.0:9100
+    if let Some(listener) = relay::bind().await? {
+        tokio::spawn(async move {
+            if let Err(e) = relay::serve(listener).await {
+                error!(error = %e, "relay server failed");
+            }
+        });
+    }
+
+    // GRPC_ADDR serves SubscribeBook streams of stored books, e.g. 0.0.0.0:50051
+    #[cfg(feature = "grpc")]
+    if let Some(addr) = orderbook::grpc::addr()? {
+        tokio::spawn(async move {
+            if let Err(e) = orderbook::grpc::serve(addr).await {
+                error!(error = %e, "grpc server failed");
+            }
+        });
     }
+
+    let every = params.lock().await.as_ref().map(|p| p.every);
+    let refresh = async {
+        let Some(every) = every else { return std::future::pending().await };
+        let mut tick = tokio::time::interval_at(tokio::time::Instant::now() + every, every);
+        loop {
+            tick.tick().await;
+            if let Some(params) = params.lock().await.as_mut() {
+                if let Err(e) = params.refresh().await {
+                    warn!(error = %e, "parameter refresh failed, keeping previous values");
+                }
+            }
+        }
+    };
+
+    let counts = tokio::select! {
+        counts = collect(s3, params, Some(shutdown), None) => counts?,
+        () = refresh => unreachable!("parameter refresh never returns"),
+    };
+    info!(?counts, "collection stopped");
     Ok(())
-}
\ No newline at end of file
+}
+
+/// One collection run: a Lambda invocation, or the whole life of a service process.
+/// Stops once `shutdown` fires; collectors with a `window` claim overlapping books.
+async fn collect(
+    s3: &Client,
+    params: &Mutex<Option<Params>>,
+    shutdown: Option<watch::Receiver<bool>>,
+    window: Option<Window>,
+) -> Result<BTreeMap<String, MessageCounts>, Error> {
+    // per-invocation settings (symbols, sampling, bars, ...) pick up parameter changes here
+    if let Some(params) = params.lock().await.as_mut() {
+        params.refresh().await?;
+    }
+    // VENUE picks the deployment (binance.us, binance.com, futures, testnets); USD-M
+    // futures also archive liquidations and funding
+    let venue = binance::venue()?;
+    let symbols = config::symbols()?;
+    for symbol in &symbols {
+        venue.validate_symbol(&symbol.to_uppercase())?;
+    }
+    let pairs = correlation::pairs(&symbols)?;
+
+    // tick sizes put spreads in ticks; without them books are still stored, with 0 ticks
+    if let Err(e) = binance::load_tick_sizes(venue).await {
+        warn!(error = %e, "failed to load tick sizes from exchangeInfo");
+    }
+
+    // batches spilled by a run that died mid-upload go out before anything new
+    let recovered = sink::recover_spilled(s3).await?;
+    if recovered > 0 {
+        info!(recovered, "uploaded spilled batches from a previous run");
+    }
+
+    // CHECKPOINT_TABLE records each symbol's last stored book, so we know exactly
+    // how long collection was down
+    let checkpoints = Checkpoints::from_env().await;
+    if let Some(checkpoints) = &checkpoints {
+        let now = chrono::Utc::now().timestamp_millis();
+        for symbol in &symbols {
+            match checkpoints.load(symbol).await {
+                Ok(Some(last)) => info!(symbol, gap_ms = now - last.last_flush_ms, last_update_id = last.last_update_id, "resuming after checkpoint"),
+                Ok(None) => info!(symbol, "no checkpoint, first run for symbol"),
+                Err(e) => warn!(error = %e, symbol, "failed to load checkpoint"),
+            }
+        }
+    }
+    let output = || S3Output::new(s3.clone()).with_checkpoints(checkpoints.clone());
+
+    // followers, pollers and servers belong to this run and are aborted when it returns
+    // (or fails), so a warm container's next invocation doesn't pile more onto them
+    let mut tasks = JoinSet::new();
+    let base = venue.ws.as_str();
+    #[cfg(feature = "futures")]
+    if venue.futures {
+        for symbol in &symbols {
+            let (client, url) = (s3.clone(), format!("{}/{}@forceOrder", base, symbol));
+            tasks.spawn(async move {
+                if let Err(e) = orderbook::futures::capture_liquidations(client, &url).await {
+                    error!(error = %e, "liquidation stream failed");
+                }
+            });
+        }
+
+        let (client, symbols) = (s3.clone(), symbols.clone());
+        tasks.spawn(async move {
+            if let Err(e) = orderbook::futures::poll_funding(client, symbols).await {
+                error!(error = %e, "funding poller failed");
+            }
+        });
+    }
+
+    // USER_DATA_STREAM=1 also archives the account's own executions, encrypted
+    #[cfg(feature = "userdata")]
+    if config::var("USER_DATA_STREAM").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")) {
+        let creds = orderbook::auth::credentials().await?
+            .ok_or_else(|| orderbook::Error::Config("USER_DATA_STREAM needs API_SECRET_ID".into()))?;
+        let client = s3.clone();
+        tasks.spawn(async move {
+            if let Err(e) = orderbook::userdata::capture_user_data(client, creds, venue).await {
+                error!(error = %e, "user data stream failed");
+            }
+        });
+    }
+
+    // FULL_DEPTH=1 also keeps each symbol's 1000-level book and persists it periodically;
+    // BOOK_CHURN=1 counts the changes to that book
+    if fulldepth::enabled() || churn::enabled() {
+        for symbol in &symbols {
+            let (client, symbol) = (s3.clone(), symbol.clone());
+            tasks.spawn(async move {
+                if let Err(e) = fulldepth::capture(client, venue, symbol).await {
+                    error!(error = %e, "full depth capture failed");
+                }
+            });
+        }
+    }
+
+    // VPIN=1 follows each symbol's trades to stamp a toxicity estimate on its books
+    if vpin::enabled() {
+        for symbol in &symbols {
+            let symbol = symbol.clone();
+            tasks.spawn(async move {
+                if let Err(e) = vpin::capture(venue, symbol).await {
+                    error!(error = %e, "VPIN estimator failed");
+                }
+            });
+        }
+    }
+
+    // RESILIENCY=1 follows each symbol's trades to tell trade depletions from removals
+    if resiliency::enabled() {
+        for symbol in &symbols {
+            let symbol = symbol.clone();
+            tasks.spawn(async move {
+                if let Err(e) = resiliency::capture(venue, symbol).await {
+                    error!(error = %e, "large trade follower failed");
+                }
+            });
+        }
+    }
+
+    // SWEEPS=1 follows each symbol's trades to flag books after multi-level sweeps
+    if sweep::enabled() {
+        for symbol in &symbols {
+            let symbol = symbol.clone();
+            tasks.spawn(async move {
+                if let Err(e) = sweep::capture(venue, symbol).await {
+                    error!(error = %e, "sweep trade follower failed");
+                }
+            });
+        }
+    }
+
+    // CORRELATION_PAIRS correlates the mids of collected symbols, e.g. btcusdt:ethusdt
+    if !pairs.is_empty() {
+        let client = s3.clone();
+        tasks.spawn(async move {
+            if let Err(e) = correlation::run(client, pairs).await {
+                error!(error = %e, "correlation task failed");
+            }
+        });
+    }
+
+    #[cfg(feature = "prometheus")]
+    if let Some(addr) = config::var("METRICS_ADDR") {
+        tasks.spawn(async move {
+            if let Err(e) = orderbook::exporter::serve(&addr).await {
+                error!(error = %e, "metrics endpoint failed");
+            }
+        });
+    }
+
+    // BOOK_MODE=top stores best bid/ask quotes from bookTicker instead of depth
+    let stream_for = if top::enabled() { top::stream } else { binance::depth_stream };
+
+    // SYMBOLS_KEY names a symbol list in the bucket that can change while running;
+    // symbols then share one connection and are (un)subscribed in place
+    let counts = match config::var("SYMBOLS_KEY") {
+        None => collector::run_each(&symbols, |symbol| {
+            let url = format!("{}/{}", base, stream_for(symbol));
+            let mut collector = Collector::new(symbol, &url, output());
+            // REST depth replies are books, so quotes have no fallback
+            let depth = format!("{}?symbol={}&limit=20", venue.rest_url("depth"), symbol.to_uppercase());
+            if let Some(fallback) = poll::Fallback::from_env(&depth).filter(|_| !top::enabled()) {
+                collector = collector.with_fallback(fallback);
+            }
+            if let Some(shutdown) = &shutdown {
+                collector = collector.with_shutdown(shutdown.clone());
+            }
+            if let Some(window) = window {
+                collector = collector.with_window(window);
+            }
+            collector
+        }).await?,
+        Some(key) => {
+            let (control, changes) = mpsc::channel(16);
+            control.send(Control::Subscribe(symbols.clone())).await?;
+            let every = Duration::from_secs(config::var("SYMBOLS_REFRESH_SECS").and_then(|s| s.parse().ok()).unwrap_or(60));
+            tasks.spawn(mux::watch_symbols(s3.clone(), key, symbols, every, control));
+
+            let url = binance::combined_url(base);
+            let mut mux = Multiplexer::new(&url, |symbol| {
+                let collector = Collector::new(symbol, &url, output());
+                match window {
+                    Some(window) => collector.with_window(window),
+                    None => collector,
+                }
+            });
+            mux.stream_for = stream_for;
+            if let Some(shutdown) = shutdown {
+                mux = mux.with_shutdown(shutdown);
+            }
+            mux.run(changes).await?
+        }
+    };
+    tasks.shutdown().await;
+    Ok(counts)
+}
@@ -0,0 +1,93 @@
+//! Per-hour manifests: a `_manifest.json` in each compacted hour's `orderbook/`
+//! partition naming the files that hold its books, with record counts and update id
+//! ranges, so completeness checks and incremental loads read one object instead of
+//! listing the partition.
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::archive::Archive;
+use crate::{binance, sink, Error, OrderBook};
+
+pub const MANIFEST_FILE: &str = "_manifest.json";
+
+/// One data file of the hour.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileEntry {
+    /// Relative to the bucket prefix, like every other key.
+    pub key: String,
+    pub records: usize,
+    pub first_ms: i64,
+    pub last_ms: i64,
+    /// `lastUpdateId` range of the file's books; 0 for books without one.
+    pub min_last_update_id: i64,
+    pub max_last_update_id: i64,
+}
+
+impl FileEntry {
+    /// The entry for `key` holding `books`, oldest first.
+    pub fn new(key: &str, books: &[OrderBook]) -> Self {
+        let ids = books.iter().map(|b| b.last_update_id);
+        FileEntry {
+            key: key.to_string(),
+            records: books.len(),
+            first_ms: books.first().map_or(0, |b| b.timestamp_ms),
+            last_ms: books.last().map_or(0, |b| b.timestamp_ms),
+            min_last_update_id: ids.clone().min().unwrap_or(0),
+            max_last_update_id: ids.max().unwrap_or(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Manifest {
+    pub dataset: String,
+    pub exchange: String,
+    pub symbol: String,
+    /// Start of the hour.
+    pub hour_ms: i64,
+    pub created_ms: i64,
+    pub records: usize,
+    pub min_last_update_id: i64,
+    pub max_last_update_id: i64,
+    pub files: Vec<FileEntry>,
+}
+
+impl Manifest {
+    pub fn new(dataset: &str, symbol: &str, hour: DateTime<Utc>, files: Vec<FileEntry>) -> Self {
+        let hour = hour.duration_trunc(Duration::hours(1)).unwrap_or(hour);
+        let with_records = || files.iter().filter(|f| f.records > 0);
+        Manifest {
+            dataset: dataset.to_string(),
+            exchange: binance::EXCHANGE.to_string(),
+            symbol: symbol.to_uppercase(),
+            hour_ms: hour.timestamp_millis(),
+            created_ms: Utc::now().timestamp_millis(),
+            records: files.iter().map(|f| f.records).sum(),
+            min_last_update_id: with_records().map(|f| f.min_last_update_id).min().unwrap_or(0),
+            max_last_update_id: with_records().map(|f| f.max_last_update_id).max().unwrap_or(0),
+            files,
+        }
+    }
+}
+
+/// Where the manifest of `dataset`'s partition for `symbol` and `hour` lives.
+pub fn key(dataset: &str, symbol: &str, hour: DateTime<Utc>) -> Result<String, Error> {
+    Ok(format!("{}/{}", sink::hour_dir(dataset, symbol, hour)?, MANIFEST_FILE))
+}
+
+/// Writes `manifest` into its partition, replacing any earlier one.
+pub async fn write(archive: &Archive, manifest: &Manifest) -> Result<String, Error> {
+    let key = key(&manifest.dataset, &manifest.symbol, sink::at_ms(manifest.hour_ms))?;
+    archive.put(&key, serde_json::to_vec_pretty(manifest)?).await?;
+    Ok(key)
+}
+
+/// The manifest of `dataset` for `symbol` and `hour`, `None` when none was written.
+pub async fn read(archive: &Archive, dataset: &str, symbol: &str, hour: DateTime<Utc>) -> Result<Option<Manifest>, Error> {
+    let key = key(dataset, symbol, hour)?;
+    if !archive.list(&format!("{}/", sink::hour_dir(dataset, symbol, hour)?)).await?.contains(&key) {
+        return Ok(None);
+    }
+    Ok(Some(serde_json::from_slice(&archive.get(&key).await?)?))
+}
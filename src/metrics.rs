@@ -0,0 +1,155 @@
+//! CloudWatch metrics via the Embedded Metric Format: each flush prints one JSON
+//! document to stdout, which CloudWatch Logs turns into metrics without API calls.
+
+use chrono::Utc;
+use serde_json::{json, Map, Value};
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+
+pub const NAMESPACE: &str = "OrderBook";
+
+/// EMF allows at most 100 values per metric in a single document.
+const MAX_VALUES: usize = 100;
+const FLUSH_INTERVAL: Duration = Duration::from_secs(10);
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Metric {
+    MessagesReceived,
+    MessagesProcessed,
+    MessagesSkipped,
+    MessagesDownsampled,
+    MessagesDropped,
+    ParseFailures,
+    /// Books that failed validation and were quarantined.
+    Quarantined,
+    S3PutLatency,
+    Reconnects,
+    DataGapSeconds,
+    DeadLetters,
+    S3Retries,
+    /// Exchange event time to receipt by the collector.
+    ExchangeLatency,
+    /// Receipt to the snapshot being stored in S3.
+    IngestLatency,
+    /// Exchange event time to the snapshot being stored in S3.
+    EndToEndLatency,
+    /// Since the symbol's last stored book, per its checkpoint.
+    SecondsSinceLastWrite,
+}
+
+impl Metric {
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::MessagesReceived => "MessagesReceived",
+            Metric::MessagesProcessed => "MessagesProcessed",
+            Metric::MessagesSkipped => "MessagesSkipped",
+            Metric::MessagesDownsampled => "MessagesDownsampled",
+            Metric::MessagesDropped => "MessagesDropped",
+            Metric::ParseFailures => "ParseFailures",
+            Metric::Quarantined => "Quarantined",
+            Metric::S3PutLatency => "S3PutLatency",
+            Metric::Reconnects => "Reconnects",
+            Metric::DataGapSeconds => "DataGapSeconds",
+            Metric::DeadLetters => "DeadLetters",
+            Metric::S3Retries => "S3Retries",
+            Metric::ExchangeLatency => "ExchangeLatency",
+            Metric::IngestLatency => "IngestLatency",
+            Metric::EndToEndLatency => "EndToEndLatency",
+            Metric::SecondsSinceLastWrite => "SecondsSinceLastWrite",
+        }
+    }
+
+    pub fn unit(self) -> &'static str {
+        match self {
+            Metric::MessagesReceived | Metric::MessagesProcessed | Metric::MessagesSkipped | Metric::MessagesDownsampled
+            | Metric::MessagesDropped | Metric::ParseFailures | Metric::Quarantined | Metric::Reconnects | Metric::DeadLetters | Metric::S3Retries => "Count",
+            Metric::S3PutLatency | Metric::ExchangeLatency | Metric::IngestLatency | Metric::EndToEndLatency => "Milliseconds",
+            Metric::DataGapSeconds | Metric::SecondsSinceLastWrite => "Seconds",
+        }
+    }
+}
+
+/// Buffers counters and samples for one symbol and flushes them as EMF.
+pub struct Metrics {
+    symbol: String,
+    values: BTreeMap<Metric, Vec<f64>>,
+    last_flush: Instant,
+}
+
+impl Metrics {
+    pub fn new(symbol: &str) -> Self {
+        Metrics { symbol: symbol.to_string(), values: BTreeMap::new(), last_flush: Instant::now() }
+    }
+
+    /// Adds to a counter; counters are summed locally and emitted as one value.
+    pub fn incr(&mut self, metric: Metric, n: f64) {
+        #[cfg(feature = "prometheus")]
+        crate::exporter::observe(&self.symbol, metric, n);
+        let values = self.values.entry(metric).or_default();
+        match values.first_mut() {
+            Some(total) => *total += n,
+            None => values.push(n),
+        }
+    }
+
+    /// Records an individual sample (latency, gap length) so CloudWatch keeps the distribution.
+    pub fn record(&mut self, metric: Metric, value: f64) {
+        #[cfg(feature = "prometheus")]
+        crate::exporter::observe(&self.symbol, metric, value);
+        self.values.entry(metric).or_default().push(value);
+        if self.values[&metric].len() >= MAX_VALUES {
+            self.flush();
+        }
+    }
+
+    /// Connection status is only exported as a gauge; EMF has no use for it.
+    pub fn connected(&self, up: bool) {
+        #[cfg(feature = "prometheus")]
+        crate::exporter::set_connected(&self.symbol, up);
+        #[cfg(not(feature = "prometheus"))]
+        let _ = up;
+    }
+
+    pub fn maybe_flush(&mut self) {
+        if self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush();
+        }
+    }
+
+    pub fn flush(&mut self) {
+        self.last_flush = Instant::now();
+        if self.values.is_empty() {
+            return;
+        }
+        println!("{}", self.document());
+        self.values.clear();
+    }
+
+    fn document(&self) -> Value {
+        let definitions: Vec<Value> = self.values.keys()
+            .map(|m| json!({"Name": m.name(), "Unit": m.unit()}))
+            .collect();
+
+        let mut doc = Map::new();
+        doc.insert("_aws".into(), json!({
+            "Timestamp": Utc::now().timestamp_millis(),
+            "CloudWatchMetrics": [{
+                "Namespace": NAMESPACE,
+                "Dimensions": [["Symbol"]],
+                "Metrics": definitions,
+            }],
+        }));
+        doc.insert("Symbol".into(), json!(self.symbol));
+        for (metric, values) in &self.values {
+            let value = if values.len() == 1 { json!(values[0]) } else { json!(values) };
+            doc.insert(metric.name().into(), value);
+        }
+        Value::Object(doc)
+    }
+}
+
+impl Drop for Metrics {
+    fn drop(&mut self) {
+        self.flush();
+    }
+}
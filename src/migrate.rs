@@ -0,0 +1,65 @@
+//! Conversion between the legacy `[price, qty]` pair encoding of book levels (schema
+//! v1/v2) and the `Level` record encoding (v3+), plus a reader for any version.
+
+use apache_avro::schema::Schema;
+use apache_avro::types::Value;
+
+use crate::{Error, OrderBook};
+
+const LEVEL_FIELDS: [&str; 2] = ["bids", "asks"];
+
+/// Reads every OrderBook in an Avro container, whatever schema version wrote it.
+pub fn read_orderbooks(bytes: &[u8]) -> Result<Vec<OrderBook>, Error> {
+    apache_avro::Reader::new(bytes)?
+        .map(|value| Ok(apache_avro::from_value(&pairs_to_levels(value?))?))
+        .collect()
+}
+
+/// Rewrites legacy pair arrays in bids/asks as `Level` records; other values pass through.
+pub fn pairs_to_levels(value: Value) -> Value {
+    map_level_fields(value, |level| match level {
+        Value::Array(pair) if pair.len() == 2 => Value::Record(vec![
+            ("price".to_string(), pair[0].clone()),
+            ("qty".to_string(), pair[1].clone()),
+        ]),
+        other => other,
+    })
+}
+
+/// Rewrites `Level` records in bids/asks as legacy `[price, qty]` pairs.
+pub fn levels_to_pairs(value: Value) -> Value {
+    map_level_fields(value, |level| match level {
+        Value::Record(fields) => Value::Array(fields.into_iter().map(|(_, v)| v).collect()),
+        other => other,
+    })
+}
+
+/// Whether `schema` is a v1/v2 OrderBook that stores levels as pairs.
+pub fn uses_pairs(schema: &Schema) -> bool {
+    let Schema::Record(record) = schema else { return false };
+    record.fields.iter()
+        .find(|f| f.name == "bids")
+        .is_some_and(|f| matches!(&f.schema, Schema::Array(items) if matches!(**items, Schema::Array(_))))
+}
+
+/// Shapes a record's levels to match `schema`, so newer records can be written with older schemas.
+pub fn conform(value: Value, schema: &Schema) -> Value {
+    if uses_pairs(schema) {
+        levels_to_pairs(value)
+    } else {
+        value
+    }
+}
+
+fn map_level_fields(value: Value, f: impl Fn(Value) -> Value) -> Value {
+    let Value::Record(fields) = value else { return value };
+    Value::Record(fields.into_iter().map(|(name, v)| {
+        let v = match v {
+            Value::Array(levels) if LEVEL_FIELDS.contains(&name.as_str()) => {
+                Value::Array(levels.into_iter().map(&f).collect())
+            }
+            other => other,
+        };
+        (name, v)
+    }).collect())
+}
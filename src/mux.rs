@@ -0,0 +1,315 @@
+//! One combined-stream connection shared by many symbols. Symbols are added and
+//! removed at runtime with Binance's `SUBSCRIBE`/`UNSUBSCRIBE` requests on the open
+//! socket, and each symbol's payloads go to its own collector task over a channel,
+//! so per-symbol batching and uploads stay independent. The channels hold
+//! `INGEST_BUFFER` payloads like a lone collector's stages; a symbol whose uploads
+//! fall that far behind pauses reading for all of them.
+
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+use aws_sdk_s3::Client;
+use tracing::{info, warn};
+
+use crate::binance;
+use crate::collector::{self, Collector, MessageCounts, MAX_BACKOFF};
+use crate::sink::{self, Output};
+use crate::{config, proxy, stages, Error};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// A change to the set of symbols, e.g. from a control queue or a config re-read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Control {
+    Subscribe(Vec<String>),
+    Unsubscribe(Vec<String>),
+}
+
+pub struct Multiplexer<O: Output, F> {
+    /// Combined-stream URL, see `binance::combined_url`.
+    pub url: String,
+    /// Reconnect when the stream ends; when false `run` returns once the collectors finish.
+    pub reconnect: bool,
+    /// How long `run` keeps a connection before replacing it (Binance drops them at 24h).
+    pub rotate_after: Duration,
+    /// Stream name per symbol: depth by default, `top::stream` for quotes.
+    pub stream_for: fn(&str) -> String,
+    collector_for: F,
+    /// Stream name to the symbol's collector.
+    routes: HashMap<String, mpsc::Sender<String>>,
+    tasks: JoinSet<(Collector<O>, Result<(), Error>)>,
+    counts: BTreeMap<String, MessageCounts>,
+    next_id: u64,
+    shutdown: Option<watch::Receiver<bool>>,
+}
+
+impl<O, F> Multiplexer<O, F>
+where
+    O: Output + Send + 'static,
+    F: FnMut(&str) -> Collector<O>,
+{
+    /// `collector_for` builds the collector a newly subscribed symbol's payloads go to.
+    pub fn new(url: &str, collector_for: F) -> Self {
+        Multiplexer {
+            url: url.to_string(),
+            reconnect: true,
+            rotate_after: collector::rotate_after(),
+            stream_for: binance::depth_stream,
+            collector_for,
+            routes: HashMap::new(),
+            tasks: JoinSet::new(),
+            counts: BTreeMap::new(),
+            next_id: 1,
+            shutdown: None,
+        }
+    }
+
+    /// Makes `run` stop the collectors and return once `shutdown` turns true.
+    pub fn with_shutdown(mut self, shutdown: watch::Receiver<bool>) -> Self {
+        self.shutdown = Some(shutdown);
+        self
+    }
+
+    /// Streams until a collector fails, `shutdown` fires, or with `reconnect` off
+    /// until the socket closes, applying `control` changes as they arrive. Returns
+    /// each finished collector's counts, including those of symbols unsubscribed
+    /// along the way.
+    pub async fn run(&mut self, mut control: mpsc::Receiver<Control>) -> Result<BTreeMap<String, MessageCounts>, Error> {
+        let mut backoff = Duration::from_secs(1);
+        let mut control_open = true;
+        let mut shutdown = self.shutdown.clone();
+
+        loop {
+            let (mut tx, mut rx) = match self.connect().await {
+                Ok(socket) => {
+                    backoff = Duration::from_secs(1);
+                    socket
+                }
+                Err(e) if self.reconnect => {
+                    warn!(error = %e, backoff_s = backoff.as_secs(), "connect failed");
+                    tokio::select! {
+                        _ = tokio::time::sleep(backoff) => {}
+                        _ = collector::stopped(&mut shutdown) => return self.shutdown().await,
+                    }
+                    backoff = (backoff * 2).min(MAX_BACKOFF);
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            let mut rotate_at = tokio::time::Instant::now() + self.rotate_after;
+            // during a rotation, the connection being replaced: it's read alongside the
+            // new one until that has delivered a frame on every routed stream in `behind`
+            let mut retiring: Option<(SplitSink<Socket, Message>, SplitStream<Socket>)> = None;
+            let mut behind: HashSet<String> = HashSet::new();
+
+            loop {
+                tokio::select! {
+                    msg = rx.next() => match msg {
+                        Some(Ok(msg)) if msg.is_text() => {
+                            let routed = self.route(msg.to_text()?).await;
+                            if retiring.is_some() {
+                                behind.retain(|stream| routed.as_ref() != Some(stream) && self.routes.contains_key(stream));
+                                if behind.is_empty() {
+                                    retiring = None;
+                                    info!(streams = self.routes.len(), "rotated connection ahead of the 24h limit");
+                                }
+                            }
+                        }
+                        Some(Ok(_)) => {}
+                        ended => {
+                            if let Some(Err(e)) = ended {
+                                warn!(error = %e, "websocket error");
+                            }
+                            // the replacement ended before catching up, so the old connection stays
+                            let Some(old) = retiring.take() else { break };
+                            (tx, rx) = old;
+                            rotate_at = tokio::time::Instant::now() + MAX_BACKOFF;
+                            warn!("rotation's new connection closed, keeping the current one");
+                        }
+                    },
+                    msg = async { retiring.as_mut().expect("guarded").1.next().await }, if retiring.is_some() => match msg {
+                        Some(Ok(msg)) if msg.is_text() => {
+                            self.route(msg.to_text()?).await;
+                        }
+                        Some(Ok(_)) => {}
+                        // the old connection went first, leaving the new one on its own
+                        _ => {
+                            retiring = None;
+                            info!(streams = self.routes.len(), "rotated connection ahead of the 24h limit");
+                        }
+                    },
+                    change = control.recv(), if control_open => match change {
+                        Some(change) => self.apply(&mut tx, change).await?,
+                        None => control_open = false,
+                    },
+                    Some(joined) = self.tasks.join_next(), if !self.tasks.is_empty() => {
+                        self.finished(joined)?;
+                    }
+                    _ = collector::stopped(&mut shutdown) => return self.shutdown().await,
+                    // while both are read, collectors drop the books they both deliver by
+                    // lastUpdateId, so the overlap neither loses nor stores a book twice
+                    _ = tokio::time::sleep_until(rotate_at), if retiring.is_none() => match self.connect().await {
+                        Ok(next) => {
+                            let (next_tx, next_rx) = next;
+                            retiring = Some((std::mem::replace(&mut tx, next_tx), std::mem::replace(&mut rx, next_rx)));
+                            behind = self.routes.keys().cloned().collect();
+                            rotate_at = tokio::time::Instant::now() + self.rotate_after;
+                            info!(streams = self.routes.len(), "rotating connection ahead of the 24h limit");
+                        }
+                        Err(e) => {
+                            rotate_at = tokio::time::Instant::now() + MAX_BACKOFF;
+                            warn!(error = %e, "rotation failed, keeping the current connection");
+                        }
+                    },
+                }
+            }
+
+            if !self.reconnect {
+                return self.shutdown().await;
+            }
+            warn!("websocket closed, reconnecting");
+        }
+    }
+
+    /// Hands a payload to its symbol's collector, returning the stream it was routed
+    /// from; request replies are only logged.
+    async fn route(&mut self, text: &str) -> Option<String> {
+        let Some(msg) = binance::Control::parse(text) else {
+            warn!("unroutable combined-stream payload");
+            return None;
+        };
+        match (msg.stream, msg.error) {
+            (Some(stream), _) => {
+                let route = self.routes.get(stream)?;
+                // the collector only goes away once its route is removed
+                match route.try_send(text.to_string()) {
+                    // warned once as the channel fills, not for every payload that waits
+                    Ok(()) if route.capacity() == 0 => {
+                        warn!(stream, capacity = route.max_capacity(), "collector behind, reading paused until it catches up");
+                    }
+                    Err(TrySendError::Full(text)) => {
+                        route.send(text).await.ok();
+                    }
+                    _ => {}
+                }
+                Some(stream.to_string())
+            }
+            (None, Some(error)) => {
+                warn!(id = msg.id, %error, "subscription request rejected");
+                None
+            }
+            (None, None) => None,
+        }
+    }
+
+    async fn apply(&mut self, tx: &mut SplitSink<Socket, Message>, change: Control) -> Result<(), Error> {
+        match change {
+            Control::Subscribe(symbols) => {
+                let mut added = Vec::new();
+                for symbol in symbols {
+                    let stream = (self.stream_for)(&symbol);
+                    if self.routes.contains_key(&stream) {
+                        continue;
+                    }
+                    let (route, payloads) = mpsc::channel(stages::buffer());
+                    let mut collector = (self.collector_for)(&symbol);
+                    self.tasks.spawn(async move {
+                        let result = collector.run_channel(payloads).await;
+                        (collector, result)
+                    });
+                    self.routes.insert(stream.clone(), route);
+                    added.push(stream);
+                }
+                info!(streams = ?added, "subscribing");
+                self.request(tx, "SUBSCRIBE", &added).await
+            }
+            Control::Unsubscribe(symbols) => {
+                // dropping the route lets the collector drain, flush and finish
+                let removed: Vec<String> = symbols.iter()
+                    .map(|s| (self.stream_for)(s))
+                    .filter(|stream| self.routes.remove(stream).is_some())
+                    .collect();
+                info!(streams = ?removed, "unsubscribing");
+                self.request(tx, "UNSUBSCRIBE", &removed).await
+            }
+        }
+    }
+
+    /// Connects and subscribes to every routed stream; a new connection starts with none.
+    async fn connect(&mut self) -> Result<(SplitSink<Socket, Message>, SplitStream<Socket>), Error> {
+        let ws = proxy::connect(&self.url).await?;
+        let (mut tx, rx) = ws.split();
+        let streams: Vec<String> = self.routes.keys().cloned().collect();
+        self.request(&mut tx, "SUBSCRIBE", &streams).await?;
+        Ok((tx, rx))
+    }
+
+    async fn request(&mut self, tx: &mut SplitSink<Socket, Message>, method: &str, streams: &[String]) -> Result<(), Error> {
+        if streams.is_empty() {
+            return Ok(());
+        }
+        let request = binance::stream_request(method, streams, self.next_id);
+        self.next_id += 1;
+        tx.send(Message::Text(request)).await?;
+        Ok(())
+    }
+
+    fn finished(&mut self, joined: Result<(Collector<O>, Result<(), Error>), tokio::task::JoinError>) -> Result<(), Error> {
+        let (collector, result) = joined.unwrap_or_else(|e| std::panic::resume_unwind(e.into_panic()));
+        info!(symbol = %collector.symbol, counts = ?collector.counts(), "message accounting");
+        self.counts.insert(collector.symbol.clone(), collector.counts());
+        // on an error the caller drops the multiplexer, aborting the collectors still running
+        result
+    }
+
+    /// Closes every route and waits for the collectors to flush.
+    async fn shutdown(&mut self) -> Result<BTreeMap<String, MessageCounts>, Error> {
+        self.routes.clear();
+        while let Some(joined) = self.tasks.join_next().await {
+            self.finished(joined)?;
+        }
+        Ok(std::mem::take(&mut self.counts))
+    }
+}
+
+/// The changes that take `current` to `desired`.
+pub fn diff(current: &[String], desired: &[String]) -> Vec<Control> {
+    let added: Vec<String> = desired.iter().filter(|s| !current.contains(s)).cloned().collect();
+    let removed: Vec<String> = current.iter().filter(|s| !desired.contains(s)).cloned().collect();
+    let mut changes = Vec::new();
+    if !added.is_empty() {
+        changes.push(Control::Subscribe(added));
+    }
+    if !removed.is_empty() {
+        changes.push(Control::Unsubscribe(removed));
+    }
+    changes
+}
+
+/// Re-reads the comma-separated symbol list stored at `key` in the main bucket every
+/// `every`, sending what changed since `current`. A failed read keeps the current set.
+pub async fn watch_symbols(s3: Client, key: String, mut current: Vec<String>, every: Duration, control: mpsc::Sender<Control>) {
+    loop {
+        tokio::time::sleep(every).await;
+        let desired = match sink::get(&s3, &key).await.and_then(|body| config::parse_symbols(&String::from_utf8_lossy(&body))) {
+            Ok(desired) => desired,
+            Err(e) => {
+                warn!(error = %e, key, "symbol list re-read failed");
+                continue;
+            }
+        };
+        for change in diff(&current, &desired) {
+            if control.send(change).await.is_err() {
+                return;
+            }
+        }
+        current = desired;
+    }
+}
@@ -0,0 +1,94 @@
+//! NATS JetStream publishing: every stored book sent as JSON to the subject
+//! `{prefix}.{exchange}.{SYMBOL}` and acknowledged by the stream, as a lighter-weight
+//! alternative to Kafka. Unacknowledged books are resent, so delivery is at least
+//! once; each carries a `Nats-Msg-Id` so the stream drops resends within its
+//! duplicate window. With `SCHEMA_REGISTRY_URL` set, books go out as Confluent-framed
+//! Avro instead, the schema registered under `{prefix}-value`.
+
+use async_nats::jetstream::{self, context::Publish, stream};
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::registry::SchemaRegistry;
+use crate::retry::RetryPolicy;
+use crate::{config, live, schema, Error, OrderBook};
+
+const DEFAULT_PREFIX: &str = "orderbook";
+const DEFAULT_STREAM: &str = "ORDERBOOK";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub url: String,
+    pub prefix: String,
+    /// Created over `{prefix}.>` if it doesn't exist.
+    pub stream: String,
+}
+
+impl Settings {
+    /// `NATS_URL`, `NATS_SUBJECT_PREFIX` (default `orderbook`) and `NATS_STREAM`
+    /// (default `ORDERBOOK`); `None` without a URL.
+    pub fn from_env() -> Option<Self> {
+        let url = config::var("NATS_URL").filter(|v| !v.is_empty())?;
+        let var = |name: &str, default: &str| config::var(name).filter(|v| !v.is_empty()).unwrap_or_else(|| default.to_string());
+        Some(Settings { url, prefix: var("NATS_SUBJECT_PREFIX", DEFAULT_PREFIX), stream: var("NATS_STREAM", DEFAULT_STREAM) })
+    }
+
+    pub fn subject(&self, book: &OrderBook) -> String {
+        format!("{}.{}.{}", self.prefix, book.exchange, book.symbol.to_uppercase())
+    }
+}
+
+/// The book's deduplication id: its `lastUpdateId`, or receive time for books without one.
+pub fn message_id(book: &OrderBook) -> String {
+    let id = if book.last_update_id > 0 { book.last_update_id } else { book.timestamp_ms };
+    format!("{}.{}.{}", book.exchange, book.symbol.to_uppercase(), id)
+}
+
+/// Publishes every book stored in this process until the task is dropped, waiting
+/// for each acknowledgement. A book still unacknowledged after the retries is logged
+/// and skipped.
+pub async fn run(settings: Settings) -> Result<(), Error> {
+    let mut feed = live::subscribe();
+    let client = async_nats::connect(settings.url.as_str()).await.map_err(|e| Error::Nats(e.to_string()))?;
+    let js = jetstream::new(client);
+    js.get_or_create_stream(stream::Config {
+        name: settings.stream.clone(),
+        subjects: vec![format!("{}.>", settings.prefix)],
+        ..Default::default()
+    })
+    .await
+    .map_err(|e| Error::Nats(e.to_string()))?;
+    let registry = SchemaRegistry::from_env();
+    info!(stream = %settings.stream, prefix = %settings.prefix, avro = registry.is_some(), "publishing books to jetstream");
+
+    let policy = RetryPolicy { max_attempts: 5, base_delay: Duration::from_millis(200), max_delay: Duration::from_secs(5) };
+    loop {
+        let book = match feed.recv().await {
+            Ok(book) => book,
+            Err(RecvError::Lagged(missed)) => {
+                warn!(missed, "jetstream publisher fell behind, skipped books");
+                continue;
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        };
+        let payload = match &registry {
+            Some(registry) => match registry.encode(&settings.prefix, schema::ORDERBOOK, &*book).await {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!(error = %e, symbol = %book.symbol, "book not encoded for the schema registry");
+                    continue;
+                }
+            },
+            None => serde_json::to_vec(&*book)?,
+        };
+        let (subject, payload) = (settings.subject(&book), bytes::Bytes::from(payload));
+        let publish = || async {
+            let message = Publish::build().payload(payload.clone()).message_id(message_id(&book));
+            js.send_publish(subject.clone(), message).await.map_err(|e| e.to_string())?.await.map_err(|e| e.to_string())
+        };
+        if let (Err(e), retries) = policy.run(publish, |_| true).await {
+            warn!(error = %e, retries, subject, "book not acknowledged by jetstream");
+        }
+    }
+}
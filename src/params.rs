@@ -0,0 +1,86 @@
+//! Settings kept in SSM Parameter Store, so they can change without redeploying.
+//! `CONFIG_PARAMETER_PATH` (e.g. `/orderbook/prod`) names a path whose parameters
+//! are named after the environment variables they replace, such as
+//! `/orderbook/prod/SYMBOLS` or `/orderbook/prod/SAMPLE_INTERVAL_MS`. Loaded values
+//! take precedence over the environment through `config::var`.
+
+use aws_config::BehaviorVersion;
+use aws_sdk_ssm::Client;
+use std::collections::BTreeMap;
+use std::time::{Duration, Instant};
+use tracing::info;
+
+use crate::{config, Error};
+
+const DEFAULT_REFRESH: Duration = Duration::from_secs(300);
+
+pub struct Params {
+    client: Client,
+    path: String,
+    /// How long loaded values are used before `refresh` reloads them.
+    pub every: Duration,
+    loaded: Option<Instant>,
+}
+
+impl Params {
+    pub fn new(client: Client, path: &str, every: Duration) -> Self {
+        Params { client, path: path.trim_end_matches('/').to_string(), every, loaded: None }
+    }
+
+    /// Reads `CONFIG_PARAMETER_PATH` and `CONFIG_REFRESH_SECS` (default 300) from the
+    /// environment itself; `None` when no path is set.
+    pub async fn from_env() -> Option<Self> {
+        let path = std::env::var("CONFIG_PARAMETER_PATH").ok().filter(|p| !p.is_empty())?;
+        let every = std::env::var("CONFIG_REFRESH_SECS").ok().and_then(|s| s.parse().ok()).map_or(DEFAULT_REFRESH, Duration::from_secs);
+        let client = Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await);
+        Some(Params::new(client, &path, every))
+    }
+
+    /// Replaces the overrides with every parameter directly under the path,
+    /// decrypting `SecureString`s. Returns how many were loaded.
+    pub async fn load(&mut self) -> Result<usize, Error> {
+        let mut values = BTreeMap::new();
+        let mut pages = self.client.get_parameters_by_path()
+            .path(&self.path)
+            .with_decryption(true)
+            .into_paginator()
+            .send();
+        while let Some(page) = pages.next().await {
+            let page = page.map_err(|e| Error::Ssm(Box::new(e.into())))?;
+            for param in page.parameters() {
+                if let (Some(name), Some(value)) = (param.name().and_then(|n| setting_name(&self.path, n)), param.value()) {
+                    values.insert(name.to_string(), value.to_string());
+                }
+            }
+        }
+        let loaded = values.len();
+        config::set_overrides(values);
+        self.loaded = Some(Instant::now());
+        info!(path = %self.path, loaded, "loaded settings from parameter store");
+        Ok(loaded)
+    }
+
+    /// Reloads once the values are older than `every`, returning whether it did.
+    pub async fn refresh(&mut self) -> Result<bool, Error> {
+        if self.loaded.is_some_and(|at| at.elapsed() < self.every) {
+            return Ok(false);
+        }
+        self.load().await?;
+        Ok(true)
+    }
+}
+
+/// The setting a parameter under `path` stands for: its name relative to the path.
+/// Parameters in nested paths are ignored.
+pub fn setting_name<'a>(path: &str, parameter: &'a str) -> Option<&'a str> {
+    let name = parameter.strip_prefix(path.trim_end_matches('/'))?.strip_prefix('/')?;
+    (!name.is_empty() && !name.contains('/')).then_some(name)
+}
+
+/// Loads the parameters at cold start, when a path is configured, so settings read
+/// once per process see them.
+pub async fn init() -> Result<Option<Params>, Error> {
+    let Some(mut params) = Params::from_env().await else { return Ok(None) };
+    params.load().await?;
+    Ok(Some(params))
+}
@@ -0,0 +1,53 @@
+//! The per-message path shared by the live handler and offline replay:
+//! raw websocket text in, normalized OrderBook out.
+
+use crate::binance::{self, DepthMessage};
+use crate::validate::{self, Reason};
+use crate::{book, split, OrderBook};
+
+#[derive(Debug)]
+pub enum Outcome {
+    Book(Box<OrderBook>),
+    /// Valid JSON that doesn't yield a book (pings, one-sided books, other events).
+    Skipped,
+    Malformed(simd_json::Error),
+    /// A depth payload that failed `validate::levels`, with `VALIDATE_BOOKS` on.
+    Invalid(Reason),
+}
+
+/// Parses and normalizes one payload received at `received_ms`, stamping `version`
+/// and the exchange. Event time and symbol come from the payload when it has them;
+/// partial depth streams carry neither, so event time stays 0 and the caller fills
+/// in the symbol from the subscription.
+pub fn process(text: &str, received_ms: i64, version: i32) -> Outcome {
+    if binance::is_ping(text) {
+        return Outcome::Skipped;
+    }
+    // simd-json parses in place, so it needs its own mutable copy
+    let mut bytes = text.as_bytes().to_vec();
+    let message = match DepthMessage::parse(&mut bytes) {
+        Ok(Some(message)) => message,
+        Ok(None) => return Outcome::Skipped,
+        Err(e) => return Outcome::Malformed(e),
+    };
+
+    let (bids, asks) = message.levels();
+    if validate::enabled() {
+        if let Some(reason) = validate::levels(&bids, &asks) {
+            return Outcome::Invalid(reason);
+        }
+    }
+    let Some(book) = OrderBook::from_levels(received_ms, &bids, &asks) else {
+        return Outcome::Skipped;
+    };
+    let book = book
+        .with_version(version)
+        .with_exchange_clock(message.event_time_ms().unwrap_or_default(), message.last_update_id() as i64)
+        .with_source(binance::EXCHANGE, message.symbol().unwrap_or_default());
+    // split output stores the fixed-point levels as the raw record
+    if !book::decimal_prices() && !split::enabled() {
+        return Outcome::Book(Box::new(book));
+    }
+    let (raw_bids, raw_asks) = message.raw_levels();
+    Outcome::Book(Box::new(book.with_exact_levels(raw_bids, raw_asks)))
+}
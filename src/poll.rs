@@ -0,0 +1,58 @@
+//! REST polling for when the websocket stays down. After a few failed connects in a
+//! row the collector polls the depth endpoint between attempts, so the archive
+//! drops to a lower frequency instead of going dark. The REST reply has the same
+//! shape as a partial depth payload and goes through the same pipeline.
+
+use reqwest::StatusCode;
+use std::time::Duration;
+
+use crate::{config, rest, Error};
+
+/// Consecutive failed connects before polling starts.
+const DEFAULT_AFTER: u32 = 3;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+/// Floor on `REST_POLL_MS`. Every collector polls on its own, and Binance limits
+/// request weight per IP, so a lower interval across many symbols gets banned.
+pub const MIN_INTERVAL: Duration = Duration::from_millis(500);
+/// Weight of a depth request up to 100 levels on spot; futures charge less.
+const WEIGHT: u32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct Fallback {
+    /// The symbol's depth endpoint, query included.
+    pub url: String,
+    pub after: u32,
+    pub interval: Duration,
+}
+
+/// One poll's result.
+#[derive(Debug)]
+pub enum Polled {
+    Book(String),
+    /// Rate limited; nothing may be sent for this long.
+    Limited(Duration),
+}
+
+impl Fallback {
+    pub fn new(url: &str, after: u32, interval: Duration) -> Self {
+        Fallback { url: url.to_string(), after, interval }
+    }
+
+    /// Polling `url` after `REST_FALLBACK_AFTER` (default 3) failed connects, every
+    /// `REST_POLL_MS` (default 1000, at least 500). `None` when `REST_FALLBACK_AFTER=0`.
+    pub fn from_env(url: &str) -> Option<Self> {
+        let after = config::var("REST_FALLBACK_AFTER").and_then(|a| a.parse().ok()).unwrap_or(DEFAULT_AFTER);
+        let interval = config::var("REST_POLL_MS").and_then(|ms| ms.parse().ok())
+            .map_or(DEFAULT_INTERVAL, Duration::from_millis)
+            .max(MIN_INTERVAL);
+        (after > 0).then(|| Fallback::new(url, after, interval))
+    }
+
+    pub async fn fetch(&self) -> Result<Polled, Error> {
+        let response = rest::get(&self.url, WEIGHT).await?;
+        if matches!(response.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT) {
+            return Ok(Polled::Limited(rest::retry_after(&response)));
+        }
+        Ok(Polled::Book(response.error_for_status()?.text().await?))
+    }
+}
@@ -0,0 +1,195 @@
+//! Outbound proxies, for VPCs whose only way out is a proxy. Websockets and REST calls
+//! go through `HTTPS_PROXY` (`HTTP_PROXY` for plain `ws://` and `http://`), else
+//! `ALL_PROXY`, except to hosts in `NO_PROXY`. An `http://` proxy is tunnelled through
+//! with CONNECT; `socks5://` and `socks5h://` proxies resolve the host themselves.
+//! Either takes `user:password@` credentials. AWS clients don't use these settings;
+//! S3 and DynamoDB are reached through VPC endpoints.
+
+use base64::engine::general_purpose::STANDARD as BASE64;
+use base64::Engine;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_socks::tcp::Socks5Stream;
+use tokio_tungstenite::tungstenite::http::Uri;
+use tokio_tungstenite::{client_async_tls, connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::{config, Error};
+
+pub type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Longest CONNECT response head read before giving up on the proxy.
+const MAX_HEAD: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    /// An HTTP proxy, tunnelled through with CONNECT.
+    Http,
+    Socks5,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Proxy {
+    pub scheme: Scheme,
+    pub host: String,
+    pub port: u16,
+    pub credentials: Option<(String, String)>,
+}
+
+impl Proxy {
+    /// Parses `http://[user:password@]host[:port]` (port 80 by default) or the same
+    /// with `socks5://` or `socks5h://` (port 1080). Credentials are percent-decoded.
+    pub fn parse(url: &str) -> Result<Self, Error> {
+        let invalid = |why: &str| Error::Config(format!("proxy {:?}: {}", url, why));
+        let (scheme, rest) = url.split_once("://").ok_or_else(|| invalid("no scheme"))?;
+        let (scheme, default_port) = match scheme.to_ascii_lowercase().as_str() {
+            "http" => (Scheme::Http, 80),
+            "socks5" | "socks5h" => (Scheme::Socks5, 1080),
+            _ => return Err(invalid("only http, socks5 and socks5h proxies are supported")),
+        };
+        let authority = rest.split('/').next().unwrap_or_default();
+        let (credentials, address) = match authority.rsplit_once('@') {
+            Some((userinfo, address)) => {
+                let (user, password) = userinfo.split_once(':').unwrap_or((userinfo, ""));
+                (Some((percent_decode(user), percent_decode(password))), address)
+            }
+            None => (None, authority),
+        };
+        // a bracketed IPv6 address has colons of its own
+        let (host, port) = match address.rsplit_once(':').filter(|(_, port)| !port.contains(']')) {
+            Some((host, port)) => (host, port.parse().map_err(|_| invalid("bad port"))?),
+            None => (address, default_port),
+        };
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        if host.is_empty() {
+            return Err(invalid("no host"));
+        }
+        Ok(Proxy { scheme, host: host.to_string(), port, credentials })
+    }
+
+    /// The proxy the settings name for connections to `url`; None to connect directly.
+    pub fn for_url(url: &str) -> Result<Option<Self>, Error> {
+        let uri: Uri = url.parse().map_err(|e| Error::Config(format!("url {:?}: {}", url, e)))?;
+        if bypass(uri.host().unwrap_or_default(), &config::var("NO_PROXY").unwrap_or_default()) {
+            return Ok(None);
+        }
+        let setting = if secure(&uri) { "HTTPS_PROXY" } else { "HTTP_PROXY" };
+        config::var(setting).or_else(|| config::var("ALL_PROXY"))
+            .filter(|p| !p.is_empty())
+            .map(|p| Self::parse(&p))
+            .transpose()
+    }
+
+    /// Opens a connection to `host:port` through the proxy.
+    pub async fn tunnel(&self, host: &str, port: u16) -> Result<TcpStream, Error> {
+        let proxy = (self.host.as_str(), self.port);
+        if self.scheme == Scheme::Socks5 {
+            let stream = match &self.credentials {
+                Some((user, password)) => Socks5Stream::connect_with_password(proxy, (host, port), user, password).await,
+                None => Socks5Stream::connect(proxy, (host, port)).await,
+            };
+            return Ok(stream.map_err(|e| Error::Proxy(format!("socks5 to {}:{}: {}", host, port, e)))?.into_inner());
+        }
+
+        let mut stream = TcpStream::connect(proxy).await?;
+        let mut request = format!("CONNECT {host}:{port} HTTP/1.1\r\nHost: {host}:{port}\r\n");
+        if let Some((user, password)) = &self.credentials {
+            request += &format!("Proxy-Authorization: Basic {}\r\n", BASE64.encode(format!("{}:{}", user, password)));
+        }
+        request += "\r\n";
+        stream.write_all(request.as_bytes()).await?;
+
+        // a byte at a time, so nothing the target sends after the head is consumed
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            if head.len() > MAX_HEAD {
+                return Err(Error::Proxy(format!("CONNECT {}:{}: response head too long", host, port)));
+            }
+            head.push(stream.read_u8().await?);
+        }
+        let status_line = String::from_utf8_lossy(&head).lines().next().unwrap_or_default().to_string();
+        if status_line.split_whitespace().nth(1) != Some("200") {
+            return Err(Error::Proxy(format!("CONNECT {}:{}: {}", host, port, status_line)));
+        }
+        Ok(stream)
+    }
+}
+
+/// Whether `host` is in `no_proxy`: comma-separated hosts, each also matching its
+/// subdomains, or `*` for every host.
+pub fn bypass(host: &str, no_proxy: &str) -> bool {
+    let host = host.trim_start_matches('[').trim_end_matches(']').to_ascii_lowercase();
+    no_proxy.split(',').map(|e| e.trim().trim_start_matches('.').to_ascii_lowercase()).filter(|e| !e.is_empty()).any(|entry| {
+        entry == "*" || host == entry || host.strip_suffix(&entry).is_some_and(|sub| sub.ends_with('.'))
+    })
+}
+
+/// Fails on a proxy setting that doesn't parse, so a bad one stops startup rather
+/// than every connection.
+pub fn check() -> Result<(), Error> {
+    for setting in ["HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"] {
+        if let Some(url) = config::var(setting).filter(|p| !p.is_empty()) {
+            Proxy::parse(&url)?;
+        }
+    }
+    Ok(())
+}
+
+/// Opens a websocket to `url` through the proxy the settings name for it, if any.
+pub async fn connect(url: &str) -> Result<Socket, Error> {
+    connect_via(url, Proxy::for_url(url)?.as_ref()).await
+}
+
+/// Opens a websocket to `url` through `proxy`, or directly without one.
+pub async fn connect_via(url: &str, proxy: Option<&Proxy>) -> Result<Socket, Error> {
+    let Some(proxy) = proxy else {
+        return Ok(connect_async(url).await?.0);
+    };
+    let uri: Uri = url.parse().map_err(|e| Error::Config(format!("url {:?}: {}", url, e)))?;
+    let host = uri.host().ok_or_else(|| Error::Config(format!("url {:?} has no host", url)))?;
+    let port = uri.port_u16().unwrap_or(if secure(&uri) { 443 } else { 80 });
+    let stream = proxy.tunnel(host.trim_start_matches('[').trim_end_matches(']'), port).await?;
+    Ok(client_async_tls(url, stream).await?.0)
+}
+
+/// A REST client going through the same proxies as the websockets.
+pub fn http_client() -> reqwest::Client {
+    let mut builder = reqwest::Client::builder();
+    let no_proxy = config::var("NO_PROXY").and_then(|list| reqwest::NoProxy::from_string(&list));
+    for setting in ["HTTPS_PROXY", "HTTP_PROXY", "ALL_PROXY"] {
+        let Some(url) = config::var(setting).filter(|p| !p.is_empty()) else { continue };
+        let proxy = match setting {
+            "HTTPS_PROXY" => reqwest::Proxy::https(url),
+            "HTTP_PROXY" => reqwest::Proxy::http(url),
+            _ => reqwest::Proxy::all(url),
+        };
+        // `check` has already turned away settings that don't parse
+        if let Ok(proxy) = proxy {
+            builder = builder.proxy(proxy.no_proxy(no_proxy.clone()));
+        }
+    }
+    builder.build().unwrap_or_default()
+}
+
+fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let escaped = bytes.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        match escaped {
+            Some(byte) if bytes[i] == b'%' => {
+                out.push(byte);
+                i += 3;
+            }
+            _ => {
+                out.push(bytes[i]);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&out).into_owned()
+}
+
+fn secure(uri: &Uri) -> bool {
+    matches!(uri.scheme_str(), Some("wss" | "https"))
+}
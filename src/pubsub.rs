@@ -0,0 +1,73 @@
+//! Redis pub/sub fan-out: every stored book published as JSON on a per-symbol channel,
+//! and optionally kept under a per-symbol key, so trading services consume the
+//! normalized feed in real time. Publishing is best effort and never holds up storage.
+
+use redis::aio::ConnectionManager;
+use tokio::sync::broadcast::error::RecvError;
+use tracing::{info, warn};
+
+use crate::{config, live, Error, OrderBook};
+
+const DEFAULT_PREFIX: &str = "orderbook";
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub url: String,
+    /// Channels are `{prefix}:{SYMBOL}` and latest keys `{prefix}:latest:{SYMBOL}`.
+    pub prefix: String,
+    /// Also `SET` the latest book of each symbol.
+    pub latest: bool,
+}
+
+impl Settings {
+    /// `REDIS_URL`, `REDIS_PREFIX` (default `orderbook`) and `REDIS_LATEST=1`; `None`
+    /// without a URL.
+    pub fn from_env() -> Option<Self> {
+        let url = config::var("REDIS_URL").filter(|v| !v.is_empty())?;
+        Some(Settings {
+            url,
+            prefix: config::var("REDIS_PREFIX").filter(|v| !v.is_empty()).unwrap_or_else(|| DEFAULT_PREFIX.to_string()),
+            latest: config::var("REDIS_LATEST").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")),
+        })
+    }
+
+    pub fn channel(&self, symbol: &str) -> String {
+        format!("{}:{}", self.prefix, symbol.to_uppercase())
+    }
+
+    pub fn latest_key(&self, symbol: &str) -> String {
+        format!("{}:latest:{}", self.prefix, symbol.to_uppercase())
+    }
+
+    /// The commands that publish `book`, sent as one round trip.
+    pub fn commands(&self, book: &OrderBook) -> Result<redis::Pipeline, Error> {
+        let payload = serde_json::to_vec(book)?;
+        let mut pipe = redis::pipe();
+        pipe.publish(self.channel(&book.symbol), &payload).ignore();
+        if self.latest {
+            pipe.set(self.latest_key(&book.symbol), &payload).ignore();
+        }
+        Ok(pipe)
+    }
+}
+
+/// Publishes every book stored in this process until the task is dropped. The
+/// connection reconnects on its own; books that fail to go out are skipped.
+pub async fn run(settings: Settings) -> Result<(), Error> {
+    let mut feed = live::subscribe();
+    let mut conn = ConnectionManager::new(redis::Client::open(settings.url.as_str())?).await?;
+    info!(prefix = %settings.prefix, latest = settings.latest, "publishing books to redis");
+    loop {
+        let book = match feed.recv().await {
+            Ok(book) => book,
+            Err(RecvError::Lagged(missed)) => {
+                warn!(missed, "redis publisher fell behind, skipped books");
+                continue;
+            }
+            Err(RecvError::Closed) => return Ok(()),
+        };
+        if let Err(e) = settings.commands(&book)?.query_async::<()>(&mut conn).await {
+            warn!(error = %e, symbol = %book.symbol, "failed to publish book to redis");
+        }
+    }
+}
@@ -0,0 +1,79 @@
+//! Python bindings, with the `python` feature: `import orderbook` reads Avro files of
+//! any schema version into a list of dicts, or into one pyarrow `RecordBatch` with the
+//! same columns as the Parquet output, so pandas users skip the schema handling.
+//! Wheels are built by maturin from `pyproject.toml`, with `extension-module` on top.
+
+use std::path::PathBuf;
+
+use arrow::pyarrow::ToPyArrow;
+use pyo3::exceptions::{PyIOError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList, PyString};
+use serde_json::Value;
+
+use crate::{columnar, migrate, Error, OrderBook};
+
+impl From<Error> for PyErr {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Io(e) => PyIOError::new_err(e.to_string()),
+            e => PyValueError::new_err(e.to_string()),
+        }
+    }
+}
+
+/// Every book in `paths`, in order.
+fn read(paths: &[PathBuf]) -> Result<Vec<OrderBook>, Error> {
+    let mut books = Vec::new();
+    for path in paths {
+        books.extend(migrate::read_orderbooks(&std::fs::read(path)?)?);
+    }
+    Ok(books)
+}
+
+/// `read_orderbook_files(paths) -> list[dict]`, a dict per book with levels as
+/// `{"price": ..., "qty": ...}` dicts.
+#[pyfunction]
+fn read_orderbook_files(py: Python<'_>, paths: Vec<PathBuf>) -> PyResult<Vec<PyObject>> {
+    let books = py.allow_threads(|| read(&paths))?;
+    books.iter()
+        .map(|book| to_python(py, &serde_json::to_value(book).map_err(Error::from)?))
+        .collect()
+}
+
+/// `read_orderbook_arrow(paths) -> pyarrow.RecordBatch`, for `.to_pandas()`.
+#[pyfunction]
+fn read_orderbook_arrow(py: Python<'_>, paths: Vec<PathBuf>) -> PyResult<PyObject> {
+    let batch = py.allow_threads(|| read(&paths).and_then(|books| columnar::to_record_batch(&books)))?;
+    batch.to_pyarrow(py)
+}
+
+fn to_python(py: Python<'_>, value: &Value) -> PyResult<PyObject> {
+    Ok(match value {
+        Value::Null => py.None(),
+        Value::Bool(b) => b.into_pyobject(py)?.to_owned().into_any().unbind(),
+        Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into_pyobject(py)?.into_any().unbind(),
+            None => n.as_f64().unwrap_or(f64::NAN).into_pyobject(py)?.into_any().unbind(),
+        },
+        Value::String(s) => PyString::new(py, s).into_any().unbind(),
+        Value::Array(items) => {
+            let items = items.iter().map(|v| to_python(py, v)).collect::<PyResult<Vec<_>>>()?;
+            PyList::new(py, items)?.into_any().unbind()
+        }
+        Value::Object(fields) => {
+            let dict = PyDict::new(py);
+            for (name, v) in fields {
+                dict.set_item(name, to_python(py, v)?)?;
+            }
+            dict.into_any().unbind()
+        }
+    })
+}
+
+#[pymodule]
+pub fn orderbook(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(read_orderbook_files, m)?)?;
+    m.add_function(wrap_pyfunction!(read_orderbook_arrow, m)?)?;
+    Ok(())
+}
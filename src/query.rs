@@ -0,0 +1,60 @@
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use orderbook::archive::Archive;
+use orderbook::checkpoint::Checkpoints;
+use orderbook::{cli, config, layout, logging, lookup, params, OrderBook};
+use serde_json::{json, Value};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    logging::init();
+    params::init().await?;
+    config::storage()?;
+    layout::template()?;
+    let archive = Archive::S3(config::s3_client().await?);
+    let checkpoints = Checkpoints::from_env().await;
+    run(service_fn(|event| handler(&archive, checkpoints.as_ref(), event))).await
+}
+
+/// Finds `{"symbol": "BTCUSDT", "at": "<time>"}`'s stored book nearest `at`, or the
+/// latest one without it. `at` takes RFC 3339, the shorter `cli` forms or epoch
+/// milliseconds. Through the function URL the same fields come as query parameters
+/// and the answer is an HTTP response; invoked directly it's the book or `null`.
+async fn handler(archive: &Archive, checkpoints: Option<&Checkpoints>, event: LambdaEvent<Value>) -> Result<Value, Error> {
+    let payload = event.payload;
+    let url = payload.get("requestContext").is_some();
+    let fields = if url { &payload["queryStringParameters"] } else { &payload };
+
+    let book = match find(archive, checkpoints, fields).await {
+        Ok(book) => book,
+        Err(e) if url => return Ok(response(400, &json!({ "error": e.to_string() }))),
+        Err(e) => return Err(e),
+    };
+    if !url {
+        return Ok(serde_json::to_value(book)?);
+    }
+    Ok(match book {
+        Some(book) => response(200, &serde_json::to_value(book)?),
+        None => response(404, &json!({ "error": "no stored book" })),
+    })
+}
+
+async fn find(archive: &Archive, checkpoints: Option<&Checkpoints>, fields: &Value) -> Result<Option<OrderBook>, Error> {
+    let symbol = fields["symbol"].as_str().filter(|s| !s.contains(',')).unwrap_or_default();
+    let symbol = config::parse_symbols(symbol)?.pop().ok_or("missing symbol")?;
+    let Some(at) = fields["at"].as_str().filter(|s| !s.is_empty()) else {
+        return Ok(lookup::latest_book(archive, checkpoints, &symbol).await?);
+    };
+    let at_ms = match at.parse::<i64>() {
+        Ok(ms) => ms,
+        Err(_) => cli::parse_time(at).ok_or_else(|| format!("invalid time {:?}", at))?.timestamp_millis(),
+    };
+    Ok(lookup::nearest_book(archive, &symbol, at_ms).await?)
+}
+
+fn response(status: u16, body: &Value) -> Value {
+    json!({
+        "statusCode": status,
+        "headers": { "content-type": "application/json" },
+        "body": body.to_string(),
+    })
+}
@@ -0,0 +1,82 @@
+//! Raw payload archival: untouched websocket text, batched per minute as gzipped
+//! JSON lines of `RawMessage` (the format `replay` reads).
+
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use std::io::{Read, Write};
+
+use crate::replay::RawMessage;
+use crate::{config, sink, Error};
+
+pub const RAW_PREFIX: &str = "raw";
+
+/// Whether `ARCHIVE_RAW` asks for raw payloads to be kept.
+pub fn enabled() -> bool {
+    config::var("ARCHIVE_RAW").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Accumulates payloads for the current minute.
+pub struct RawBatcher {
+    symbol: String,
+    minute_ms: i64,
+    messages: Vec<RawMessage>,
+}
+
+impl RawBatcher {
+    /// Batches `symbol`'s payloads, filed under `raw/` by `KEY_TEMPLATE`.
+    pub fn new(symbol: &str) -> Self {
+        RawBatcher { symbol: symbol.to_string(), minute_ms: 0, messages: Vec::new() }
+    }
+
+    /// Adds a payload, returning the previous minute's (key, gzipped body) once the minute rolls over.
+    pub fn push(&mut self, received_ms: i64, payload: &str) -> Result<Option<(String, Vec<u8>)>, Error> {
+        let minute_ms = received_ms - received_ms.rem_euclid(60_000);
+        let done = if minute_ms != self.minute_ms { self.flush()? } else { None };
+        self.minute_ms = minute_ms;
+        self.messages.push(RawMessage { received_ms, payload: payload.to_string() });
+        Ok(done)
+    }
+
+    /// `flush` once the buffered minute is over, so a stream that goes quiet doesn't
+    /// hold its last batch until the next payload.
+    pub fn flush_ended(&mut self, now_ms: i64) -> Result<Option<(String, Vec<u8>)>, Error> {
+        if now_ms - self.minute_ms < 60_000 {
+            return Ok(None);
+        }
+        self.flush()
+    }
+
+    /// Takes whatever is buffered as a (key, gzipped body) pair. The key is the first
+    /// payload's receive time, so a minute split across runs keeps every part.
+    pub fn flush(&mut self) -> Result<Option<(String, Vec<u8>)>, Error> {
+        let Some(first) = self.messages.first() else { return Ok(None) };
+        let key = sink::partition_key(RAW_PREFIX, &self.symbol, sink::at_ms(self.minute_ms), first.received_ms)?;
+        let key = match key.strip_suffix(".avro") {
+            Some(stem) => format!("{}.jsonl.gz", stem),
+            None => key,
+        };
+        let body = gzip_lines(&std::mem::take(&mut self.messages))?;
+        Ok(Some((key, body)))
+    }
+}
+
+fn gzip_lines(messages: &[RawMessage]) -> Result<Vec<u8>, Error> {
+    let mut gz = GzEncoder::new(Vec::new(), Compression::default());
+    for msg in messages {
+        serde_json::to_writer(&mut gz, msg)?;
+        gz.write_all(b"\n")?;
+    }
+    Ok(gz.finish()?)
+}
+
+/// Text of a recording, transparently un-gzipping archived raw batches.
+pub fn decode_recording(bytes: &[u8]) -> Result<String, Error> {
+    let mut text = String::new();
+    if bytes.starts_with(&[0x1f, 0x8b]) {
+        GzDecoder::new(bytes).read_to_string(&mut text)?;
+    } else {
+        text = String::from_utf8_lossy(bytes).into_owned();
+    }
+    Ok(text)
+}
@@ -1,103 +1,102 @@
-use aws_config::BehaviorVersion;
-use aws_sdk_s3::Client;
-use chrono::{Datelike, Timelike, Utc};
+use chrono::{Duration, Utc};
 use lambda_runtime::{run, service_fn, Error, LambdaEvent};
-use serde::{Deserialize, Serialize};
+use orderbook::archive::Archive;
+use orderbook::checkpoint::Checkpoints;
+use orderbook::gaps::{self, Gap};
+use orderbook::sink::{Output, S3Output};
+use orderbook::{binance, config, layout, logging, params, rest, schema, sink, trades, OrderBook};
+use orderbook::Error as IngestError;
+use tokio::time::Instant;
+use tracing::{info, warn};
 
-#[derive(Serialize, Deserialize)]
-struct OrderBook {
-    timestamp_ms: i64,
-    bids: Vec<(f64, f64)>,
-    asks: Vec<(f64, f64)>,
-    spread: f64,
-    mid_price: f64,
-    imbalance_ratio: f64,
-}
-
-const SCHEMA: &str = r#"{"type":"record","name":"OrderBook","fields":[{"name":"timestamp_ms","type":"long"},{"name":"bids","type":{"type":"array","items":{"type":"array","items":"double"}}},{"name":"asks","type":{"type":"array","items":{"type":"array","items":"double"}}},{"name":"spread","type":"double"},{"name":"mid_price","type":"double"},{"name":"imbalance_ratio","type":"double"}]}"#;
+const MAX_BACKFILL_HOURS: i64 = 24;
+/// Trades are fetched and written this much at a time, so a deadline cuts in between.
+const BACKFILL_CHUNK_MINUTES: i64 = 5;
+/// Left before the function's deadline for the invocation to return.
+const DEADLINE_MARGIN: std::time::Duration = std::time::Duration::from_secs(2);
 
 #[tokio::main]
 async fn main() -> Result<(), Error> {
+    logging::init();
+    params::init().await?;
+    // fail the cold start on bad storage settings rather than the first invocation
+    config::storage()?;
+    binance::venue()?;
+    layout::template()?;
+    config::symbols()?;
     run(service_fn(handler)).await
 }
 
-async fn handler(_: LambdaEvent<serde_json::Value>) -> Result<(), Error> {
-    let s3 = Client::new(&aws_config::load_defaults(BehaviorVersion::latest()).await);
-    
-    // Check gap from last write
-    let objs = s3.list_objects_v2()
-        .bucket("orderbook-data")
-        .prefix("orderbook/")
-        .send()
-        .await?;
-    
-    let now = Utc::now().timestamp_millis();
-    let last_ts = objs.contents()
-        .last()  // actually get the LAST one
-        .and_then(|obj| obj.key()?.split('/').last()?.strip_suffix(".avro"))
-        .and_then(|s| s.parse::<i64>().ok())
-        .unwrap_or(0);
-    
-    if now - last_ts > 5000 {  // 5 second gap
-        println!("Backfilling {}ms gap", now - last_ts);
-        
-        // Fetch REST snapshot
-        let depth: serde_json::Value = reqwest::get("https://api.binance.com/api/v3/depth?symbol=BTCUSDT&limit=1000")
+async fn handler(event: LambdaEvent<serde_json::Value>) -> Result<Vec<Gap>, Error> {
+    let remaining_ms = (event.context.deadline as i64 - Utc::now().timestamp_millis()).max(0);
+    let deadline = Instant::now() + std::time::Duration::from_millis(remaining_ms as u64);
+    let s3 = config::s3_client().await?;
+    let archive = Archive::S3(s3.clone());
+    let mut output = S3Output::new(s3.clone());
+    let checkpoints = Checkpoints::from_env().await;
+    let (now, threshold) = (Utc::now(), gaps::threshold_ms());
+
+    let mut found = Vec::new();
+    for symbol in config::symbols()? {
+        let symbol = symbol.to_uppercase();
+        let Some(gap) = gaps::find(&archive, checkpoints.as_ref(), &symbol, now.timestamp_millis(), threshold).await? else { continue };
+        info!(symbol, from_ms = gap.from_ms, to_ms = gap.to_ms, gap_ms = gap.duration_ms(), "backfilling gap");
+
+        // Binance has no historical depth, so the book resumes from a snapshot of now
+        let venue = binance::venue()?;
+        let body = rest::get(&format!("{}?symbol={}&limit=1000", venue.rest_url("depth"), symbol), venue.weight("depth", 1000))
             .await?
-            .json()
+            .bytes()
             .await?;
-        
-        // Parse books
-        let parse = |key| -> Vec<(f64, f64)> {
-            depth[key].as_array().unwrap().iter().take(20)
-                .map(|x| (x[0].as_str().unwrap().parse().unwrap(),
-                         x[1].as_str().unwrap().parse().unwrap()))
-                .collect()
-        };
-        let (bids, asks) = (parse("bids"), parse("asks"));
-        
-        // Metrics
-        let mid = (bids[0].0 + asks[0].0) / 2.0;
-        let vol = |b: &[(f64, f64)]| -> f64 { b[..5].iter().map(|x| x.1).sum() };
-        let (bv, av) = (vol(&bids), vol(&asks));
-        
-        // Normalize
-        let norm = |book: &[(f64, f64)], is_ask| -> Vec<(f64, f64)> {
-            [0.0001, 0.0005, 0.001, 0.005, 0.01].iter().map(|&d| {
-                let target = mid * (1.0 + if is_ask { d } else { -d });
-                (target, book.iter()
-                    .filter(|(p, _)| (is_ask && *p <= target) || (!is_ask && *p >= target))
-                    .map(|(_, q)| q)
-                    .sum())
-            }).collect()
-        };
-        
-        // Build & serialize
-        let book = OrderBook {
-            timestamp_ms: now,
-            bids: norm(&bids, false),
-            asks: norm(&asks, true),
-            spread: asks[0].0 - bids[0].0,
-            mid_price: mid,
-            imbalance_ratio: (bv - av) / (bv + av),
+        let depth: binance::PartialDepth = serde_json::from_slice(&body)?;
+
+        let (bids, asks) = depth.levels();
+        let version = schema::writer_version();
+        let now_ms = now.timestamp_millis();
+        let book = OrderBook::from_levels(now_ms, &bids, &asks).ok_or(IngestError::EmptyBook)?
+            .with_version(version)
+            .with_exchange_clock(0, depth.last_update_id as i64)
+            .with_source(binance::EXCHANGE, &symbol);
+
+        // keyed and written the way the collector stores a partial depth book, so an
+        // instance storing the same snapshot doesn't write it twice
+        let id_ms = if sink::idempotent() { book.last_update_id } else { now_ms };
+        let key = sink::partition_key("orderbook", &symbol, sink::at_ms(now_ms), id_ms)?;
+        let body = sink::encode(schema::orderbook(version).unwrap_or(schema::ORDERBOOK), &[book])?;
+        match sink::idempotent() {
+            true => output.write_once(&key, &body, &sink::book_id(depth.last_update_id as i64, &body)).await?,
+            false => output.write(&key, &body).await?,
         };
-        
-        let schema = apache_avro::Schema::parse_str(SCHEMA)?;
-        let mut writer = apache_avro::Writer::new(&schema, Vec::new());
-        writer.append_ser(&book)?;
-        
-        let t = Utc::now();
-        let key = format!("orderbook/year={}/month={:02}/day={:02}/hour={:02}/{}.avro",
-                         t.year(), t.month(), t.day(), t.hour(), now);
-        
-        s3.put_object()
-            .bucket("orderbook-data")
-            .key(&key)
-            .body(writer.into_inner()?.into())
-            .send()
-            .await?;
-        
-        println!("Recovered: {}", key);
+        gaps::record(&mut output, &gap).await?;
+        if config::var("RECOVERY_BACKFILL").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")) {
+            backfill_trades(&archive, &mut output, &gap, deadline).await?;
+        }
+        found.push(gap);
+    }
+    Ok(found)
+}
+
+/// Writes the aggTrades Binance still has for the gap into `trades/`, a few minutes
+/// at a time, tagged `source=backfill`. Only the most recent day of a longer gap is
+/// fetched. What won't fit before `deadline`, going by the slowest chunk so far, is
+/// marked as a `trades-missing` gap for the `backfill` tool to fetch later.
+async fn backfill_trades(archive: &Archive, output: &mut S3Output, gap: &Gap, deadline: Instant) -> Result<(), Error> {
+    let to = sink::at_ms(gap.to_ms);
+    let mut from = sink::at_ms(gap.from_ms).max(to - Duration::hours(MAX_BACKFILL_HOURS));
+    let mut slowest = std::time::Duration::ZERO;
+    while from < to {
+        if Instant::now() + slowest + DEADLINE_MARGIN > deadline {
+            let missing = Gap { symbol: gap.symbol.clone(), from_ms: from.timestamp_millis(), to_ms: gap.to_ms, reason: "trades-missing".to_string() };
+            warn!(symbol = gap.symbol, from_ms = missing.from_ms, to_ms = missing.to_ms, "out of time, trades left unfetched");
+            gaps::record(output, &missing).await?;
+            return Ok(());
+        }
+        let (started, end) = (Instant::now(), (from + Duration::minutes(BACKFILL_CHUNK_MINUTES)).min(to));
+        for (key, trades) in trades::backfill(archive, &gap.symbol, from, end).await? {
+            info!(symbol = gap.symbol, trades, key, "backfilled trades");
+        }
+        slowest = slowest.max(started.elapsed());
+        from = end;
     }
     Ok(())
-}
\ No newline at end of file
+}
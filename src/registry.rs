@@ -0,0 +1,111 @@
+//! Confluent Schema Registry client and wire-format framing for Kafka producers.
+//!
+//! Confluent-framed messages are a zero magic byte, the big-endian schema id, then a
+//! bare Avro datum (no object container header).
+
+use serde::Serialize;
+use serde_json::{json, Value};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::{config, migrate, proxy, schema, Error};
+
+const MAGIC_BYTE: u8 = 0;
+const CONTENT_TYPE: &str = "application/vnd.schemaregistry.v1+json";
+
+/// Frames an Avro datum with the Confluent header.
+pub fn frame(schema_id: u32, datum: &[u8]) -> Vec<u8> {
+    let mut buf = Vec::with_capacity(5 + datum.len());
+    buf.push(MAGIC_BYTE);
+    buf.extend_from_slice(&schema_id.to_be_bytes());
+    buf.extend_from_slice(datum);
+    buf
+}
+
+/// Subject name under the default TopicNameStrategy.
+pub fn value_subject(topic: &str) -> String {
+    format!("{}-value", topic)
+}
+
+/// The `id` of a registry reply; a reply without a usable one is an error rather
+/// than id 0, which would frame every message with the wrong schema.
+pub fn schema_id(subject: &str, reply: &Value) -> Result<u32, Error> {
+    reply["id"].as_u64()
+        .and_then(|id| u32::try_from(id).ok())
+        .ok_or_else(|| Error::Registry(format!("no schema id for {} in {}", subject, reply)))
+}
+
+pub struct SchemaRegistry {
+    url: String,
+    auth: Option<(String, String)>,
+    http: reqwest::Client,
+    /// Ids by subject and schema fingerprint, so a new schema version under the
+    /// same subject gets its own.
+    ids: Mutex<HashMap<(String, String), u32>>,
+}
+
+impl SchemaRegistry {
+    pub fn new(url: &str, auth: Option<(String, String)>) -> Self {
+        SchemaRegistry {
+            url: url.trim_end_matches('/').to_string(),
+            auth,
+            http: proxy::http_client(),
+            ids: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// `SCHEMA_REGISTRY_URL` plus optional `SCHEMA_REGISTRY_USER`/`SCHEMA_REGISTRY_PASSWORD`
+    /// (an API key/secret pair on Confluent Cloud). None when no URL is configured.
+    pub fn from_env() -> Option<Self> {
+        let url = config::var("SCHEMA_REGISTRY_URL")?;
+        let auth = config::var("SCHEMA_REGISTRY_USER")
+            .zip(config::var("SCHEMA_REGISTRY_PASSWORD"));
+        Some(SchemaRegistry::new(&url, auth))
+    }
+
+    async fn post(&self, path: &str, schema: &str) -> Result<Value, Error> {
+        let mut req = self.http.post(format!("{}{}", self.url, path))
+            .header(reqwest::header::CONTENT_TYPE, CONTENT_TYPE)
+            .json(&json!({ "schema": schema }));
+        if let Some((user, password)) = &self.auth {
+            req = req.basic_auth(user, Some(password));
+        }
+        Ok(req.send().await?.error_for_status()?.json().await?)
+    }
+
+    /// Registers `schema` under `subject` (a no-op server side if it already exists) and returns its id.
+    pub async fn register(&self, subject: &str, schema: &str) -> Result<u32, Error> {
+        schema_id(subject, &self.post(&format!("/subjects/{}/versions", subject), schema).await?)
+    }
+
+    /// Looks up the id of an already registered schema without registering it.
+    pub async fn lookup(&self, subject: &str, schema: &str) -> Result<u32, Error> {
+        schema_id(subject, &self.post(&format!("/subjects/{}", subject), schema).await?)
+    }
+
+    /// Schema id for `schema` under `subject`, registering on first use and caching afterwards.
+    pub async fn id_for(&self, subject: &str, schema: &str) -> Result<u32, Error> {
+        let key = (subject.to_string(), schema::parsed(schema)?.fingerprint::<Sha256>().to_string());
+        if let Some(id) = self.ids.lock().unwrap_or_else(|e| e.into_inner()).get(&key) {
+            return Ok(*id);
+        }
+        let id = self.register(subject, schema).await?;
+        self.ids.lock().unwrap_or_else(|e| e.into_inner()).insert(key, id);
+        Ok(id)
+    }
+
+    /// Serializes one record as a Confluent-framed Avro message for `topic`.
+    pub async fn encode<T: Serialize>(&self, topic: &str, schema: &str, record: &T) -> Result<Vec<u8>, Error> {
+        let id = self.id_for(&value_subject(topic), schema).await?;
+        framed(id, schema, record)
+    }
+}
+
+/// `record` as a bare Avro datum of `schema` behind the header for `schema_id`,
+/// conformed and resolved like the records `sink` writes.
+pub fn framed<T: Serialize>(schema_id: u32, schema: &str, record: &T) -> Result<Vec<u8>, Error> {
+    let parsed = schema::parsed(schema)?;
+    let value = migrate::conform(apache_avro::to_value(record)?, parsed).resolve(parsed)?;
+    Ok(frame(schema_id, &apache_avro::to_avro_datum(parsed, value)?))
+}
@@ -0,0 +1,147 @@
+//! Websocket re-broadcast of stored books for service deployments: dashboards and
+//! paper-trading bots connect and receive each normalized book as JSON, for the
+//! symbols they subscribed to.
+//!
+//! Clients pick symbols with `?symbols=btcusdt,ethusdt` on the URL (`*` for all) and
+//! change them with `{"subscribe": [...]}` and `{"unsubscribe": [...]}` messages.
+//! Each control message is answered with the client's symbols, e.g.
+//! `{"symbols": ["ETHUSDT"]}`. A client that falls behind skips the oldest books.
+
+use futures_util::{SinkExt, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::net::SocketAddr;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::broadcast::error::RecvError;
+use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+use tokio_tungstenite::tungstenite::Message;
+use tracing::{debug, info, warn};
+
+use crate::{config, live, Error};
+
+const ALL: &str = "*";
+
+/// The symbols one client receives.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Filter {
+    all: bool,
+    symbols: BTreeSet<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Control {
+    subscribe: Vec<String>,
+    unsubscribe: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+struct Subscribed {
+    symbols: Vec<String>,
+}
+
+impl Filter {
+    /// From the `symbols` parameter of a connection's query string; empty without one.
+    pub fn from_query(query: Option<&str>) -> Self {
+        let mut filter = Filter::default();
+        let list = query.into_iter().flat_map(|q| q.split('&')).filter_map(|p| p.strip_prefix("symbols="));
+        filter.subscribe(list.flat_map(|l| l.split(',')));
+        filter
+    }
+
+    /// Applies a `subscribe`/`unsubscribe` control message.
+    pub fn apply(&mut self, text: &str) -> Result<(), Error> {
+        let control: Control = serde_json::from_str(text)?;
+        self.subscribe(control.subscribe.iter().map(String::as_str));
+        for symbol in control.unsubscribe.iter().map(|s| s.trim()) {
+            if symbol == ALL {
+                *self = Filter::default();
+            } else {
+                self.symbols.remove(&symbol.to_uppercase());
+            }
+        }
+        Ok(())
+    }
+
+    fn subscribe<'a>(&mut self, symbols: impl Iterator<Item = &'a str>) {
+        for symbol in symbols.map(str::trim).filter(|s| !s.is_empty()) {
+            if symbol == ALL {
+                self.all = true;
+            } else {
+                self.symbols.insert(symbol.to_uppercase());
+            }
+        }
+    }
+
+    pub fn wants(&self, symbol: &str) -> bool {
+        self.all || self.symbols.contains(&symbol.to_uppercase())
+    }
+
+    /// The subscription as reported back to the client.
+    pub fn symbols(&self) -> Vec<String> {
+        if self.all {
+            return vec![ALL.to_string()];
+        }
+        self.symbols.iter().cloned().collect()
+    }
+}
+
+/// Accepts clients on `listener` until it fails, each served on its own task.
+pub async fn serve(listener: TcpListener) -> Result<(), Error> {
+    info!(addr = %listener.local_addr()?, "relaying books over websocket");
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        tokio::spawn(async move {
+            match client(stream).await {
+                Ok(()) => debug!(%peer, "relay client disconnected"),
+                Err(e) => debug!(error = %e, %peer, "relay client dropped"),
+            }
+        });
+    }
+}
+
+// the handshake callback's error type is tungstenite's, large or not
+#[allow(clippy::result_large_err)]
+async fn client(stream: TcpStream) -> Result<(), Error> {
+    let mut query = None;
+    let ws = tokio_tungstenite::accept_hdr_async(stream, |request: &Request, response: Response| {
+        query = request.uri().query().map(str::to_string);
+        Ok(response)
+    })
+    .await?;
+    let mut filter = Filter::from_query(query.as_deref());
+    let mut feed = live::subscribe();
+    let (mut tx, mut rx) = ws.split();
+
+    loop {
+        tokio::select! {
+            msg = rx.next() => match msg {
+                Some(Ok(Message::Text(text))) => {
+                    if let Err(e) = filter.apply(&text) {
+                        tx.send(Message::Text(serde_json::json!({ "error": e.to_string() }).to_string())).await?;
+                        continue;
+                    }
+                    tx.send(Message::Text(serde_json::to_string(&Subscribed { symbols: filter.symbols() })?)).await?;
+                }
+                Some(Ok(Message::Close(_))) | None => return Ok(()),
+                Some(Ok(_)) => {}
+                Some(Err(e)) => return Err(e.into()),
+            },
+            book = feed.recv() => match book {
+                Ok(book) if filter.wants(&book.symbol) => tx.send(Message::Text(serde_json::to_string(&*book)?)).await?,
+                Ok(_) => {}
+                Err(RecvError::Lagged(missed)) => warn!(missed, "relay client fell behind, skipped books"),
+                Err(RecvError::Closed) => return Ok(()),
+            },
+        }
+    }
+}
+
+/// `RELAY_ADDR`, bound, when set.
+pub async fn bind() -> Result<Option<TcpListener>, Error> {
+    let Some(addr) = config::var("RELAY_ADDR").filter(|v| !v.is_empty()) else {
+        return Ok(None);
+    };
+    let addr: SocketAddr = addr.parse().map_err(|_| Error::Config(format!("invalid RELAY_ADDR {:?}", addr)))?;
+    Ok(Some(TcpListener::bind(addr).await?))
+}
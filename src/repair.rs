@@ -0,0 +1,72 @@
+//! Bulk repair after an outage, from a `verify` report. Binance keeps no depth
+//! history, so a hole can't be refilled with books; each one gets what recovery
+//! gives a gap it finds live: `Gap` markers in every hour it covers, so readers mask
+//! it, and optionally the aggTrades Binance still has for it. Every hour of the
+//! report that holds books then has its manifest rewritten from what's there, the
+//! objects that failed to decode left out.
+
+use tracing::info;
+
+use crate::archive::Archive;
+use crate::gaps::{self, Gap};
+use crate::manifest::{self, FileEntry, Manifest};
+use crate::verify::{self, Report};
+use crate::{cli, migrate, sink, trades, Error};
+
+const PREFIX: &str = "orderbook";
+
+/// What repairing a report did, or would do with `dry_run`.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Repaired {
+    pub gaps: Vec<Gap>,
+    /// Keys of the `trades/` objects written.
+    pub trades: Vec<String>,
+    pub manifests: Vec<String>,
+}
+
+/// The gap marker each hole of `report` gets, reason `repair`.
+pub fn gaps_of(report: &Report) -> Vec<Gap> {
+    report.holes.iter()
+        .map(|hole| Gap { symbol: report.symbol.clone(), from_ms: hole.from_ms, to_ms: hole.to_ms, reason: "repair".to_string() })
+        .collect()
+}
+
+/// Repairs what `report` found: gap markers and, with `backfill_trades`, trades for
+/// each hole, then fresh manifests for its hours. With `dry_run` nothing is written
+/// and the result lists what would be.
+pub async fn repair(archive: &Archive, report: &Report, backfill_trades: bool, dry_run: bool) -> Result<Repaired, Error> {
+    let mut repaired = Repaired::default();
+    for gap in gaps_of(report) {
+        if !dry_run {
+            for (key, body) in gaps::markers(&gap)? {
+                archive.put(&key, body).await?;
+            }
+            if backfill_trades {
+                let written = trades::backfill(archive, &gap.symbol, sink::at_ms(gap.from_ms), sink::at_ms(gap.to_ms)).await?;
+                repaired.trades.extend(written.into_iter().map(|(key, _)| key));
+            }
+        }
+        info!(symbol = gap.symbol, from_ms = gap.from_ms, to_ms = gap.to_ms, dry_run, "repaired hole");
+        repaired.gaps.push(gap);
+    }
+
+    for hour in cli::hours(sink::at_ms(report.from_ms), sink::at_ms(report.to_ms)) {
+        let mut files = Vec::new();
+        for key in verify::hour_keys(archive, &report.symbol, hour).await? {
+            let Ok(mut books) = migrate::read_orderbooks(&archive.get(&key).await?) else { continue };
+            books.sort_by_key(|b| b.timestamp_ms);
+            files.push(FileEntry::new(&key, &books));
+        }
+        if files.is_empty() {
+            continue;
+        }
+        let manifest = Manifest::new(PREFIX, &report.symbol, hour, files);
+        let key = if dry_run {
+            manifest::key(PREFIX, &report.symbol, hour)?
+        } else {
+            manifest::write(archive, &manifest).await?
+        };
+        repaired.manifests.push(key);
+    }
+    Ok(repaired)
+}
@@ -0,0 +1,45 @@
+//! Offline reprocessing of recorded websocket payloads through `pipeline::process`.
+
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::{self, Outcome};
+use crate::OrderBook;
+
+/// One recorded payload with the time the collector received it.
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct RawMessage {
+    pub received_ms: i64,
+    pub payload: String,
+}
+
+/// Parses a recording: one JSON `RawMessage` per line. Lines that aren't wrapped are
+/// taken as bare payloads with a zero receive time, so output stays deterministic.
+pub fn parse_recording(text: &str) -> Vec<RawMessage> {
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|line| serde_json::from_str(line).unwrap_or_else(|_| RawMessage { received_ms: 0, payload: line.to_string() }))
+        .collect()
+}
+
+#[derive(Debug, Default)]
+pub struct Replay {
+    pub books: Vec<OrderBook>,
+    pub skipped: usize,
+    pub malformed: usize,
+    /// Failed validation, with `VALIDATE_BOOKS` on.
+    pub invalid: usize,
+}
+
+/// Runs every message through the production pipeline in order.
+pub fn replay(messages: &[RawMessage], version: i32) -> Replay {
+    let mut out = Replay::default();
+    for msg in messages {
+        match pipeline::process(&msg.payload, msg.received_ms, version) {
+            Outcome::Book(book) => out.books.push(*book),
+            Outcome::Skipped => out.skipped += 1,
+            Outcome::Malformed(_) => out.malformed += 1,
+            Outcome::Invalid(_) => out.invalid += 1,
+        }
+    }
+    out
+}
@@ -0,0 +1,22 @@
+use lambda_runtime::{run, service_fn, Error, LambdaEvent};
+use orderbook::{config, logging, params, sink};
+use serde_json::json;
+use tracing::info;
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    logging::init();
+    params::init().await?;
+    // fail the cold start on bad storage settings rather than the first invocation
+    config::storage()?;
+    run(service_fn(handler)).await
+}
+
+async fn handler(_: LambdaEvent<serde_json::Value>) -> Result<serde_json::Value, Error> {
+    let s3 = config::s3_client().await?;
+
+    let replayed = sink::drain_dead_letters(&s3).await?;
+    info!(replayed, "dead letter queue drained");
+
+    Ok(json!({ "replayed": replayed }))
+}
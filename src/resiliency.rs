@@ -0,0 +1,245 @@
+//! Book resiliency: how fast depth near the mid comes back after it is knocked out.
+//! The collector follows the depth within `RESILIENCY_BPS` of mid on each side from
+//! book to book. A drop of at least `RESILIENCY_DROP` of it between two books opens a
+//! depletion event, blamed on a trade when a large one hit that side in between and on
+//! levels removed otherwise. The event closes once depth is back to where it was, or
+//! after `RESILIENCY_TIMEOUT_SECS`, and is stored under `resiliency/` with a score.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::binance::{self, Venue};
+use crate::book::{self, DEPTHS};
+use crate::trades;
+use crate::{config, Error, OrderBook};
+
+pub const RESILIENCY_PREFIX: &str = "resiliency";
+
+pub const CAUSE_TRADE: &str = "trade";
+pub const CAUSE_REMOVAL: &str = "removal";
+
+const DEFAULT_BPS: f64 = 10.0;
+const DEFAULT_DROP: f64 = 0.3;
+const DEFAULT_TRADE_NOTIONAL: f64 = 100_000.0;
+const DEFAULT_TIMEOUT_SECS: i64 = 60;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Whether `RESILIENCY=1` asks for resiliency records.
+pub fn enabled() -> bool {
+    config::var("RESILIENCY").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+fn positive(name: &str) -> Option<f64> {
+    config::var(name).and_then(|v| v.parse().ok()).filter(|v: &f64| *v > 0.0 && v.is_finite())
+}
+
+/// When a side counts as depleted and how long it gets to recover.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Settings {
+    /// Distance from mid, in basis points, of the depth followed.
+    pub bps: f64,
+    /// Fraction of that depth that must go between two books to open an event.
+    pub drop: f64,
+    /// Quote notional of a trade large enough to be blamed for a depletion.
+    pub trade_notional: f64,
+    pub timeout_ms: i64,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings { bps: DEFAULT_BPS, drop: DEFAULT_DROP, trade_notional: DEFAULT_TRADE_NOTIONAL, timeout_ms: DEFAULT_TIMEOUT_SECS * 1000 }
+    }
+}
+
+impl Settings {
+    /// `RESILIENCY_BPS` (default 10), `RESILIENCY_DROP` (default 0.3),
+    /// `RESILIENCY_TRADE_NOTIONAL` (default 100,000) and `RESILIENCY_TIMEOUT_SECS`
+    /// (default 60).
+    pub fn from_env() -> Self {
+        let defaults = Settings::default();
+        Settings {
+            bps: positive("RESILIENCY_BPS").unwrap_or(defaults.bps),
+            drop: positive("RESILIENCY_DROP").filter(|d| *d < 1.0).unwrap_or(defaults.drop),
+            trade_notional: positive("RESILIENCY_TRADE_NOTIONAL").unwrap_or(defaults.trade_notional),
+            timeout_ms: positive("RESILIENCY_TIMEOUT_SECS").map_or(defaults.timeout_ms, |s| (s * 1000.0) as i64),
+        }
+    }
+}
+
+/// The cumulative quantity in one side's depth buckets (`OrderBook::bids` or `asks`)
+/// at the narrowest bucket reaching `bps` from mid, or at the widest when none does.
+pub fn depth_within(levels: &[book::Level], bps: f64) -> f64 {
+    let buckets = book::depth_buckets().unwrap_or(&DEPTHS);
+    let i = buckets.iter().position(|d| d * 10_000.0 >= bps).unwrap_or(buckets.len().saturating_sub(1));
+    levels.get(i).or(levels.last()).map_or(0.0, |l| l.qty)
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Resiliency {
+    /// Receive time of the book that showed the depletion.
+    pub start_ms: i64,
+    /// Receive time of the book that closed the event.
+    pub end_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    /// `bid` or `ask`.
+    pub side: String,
+    /// `trade` or `removal`.
+    pub cause: String,
+    pub bps: f64,
+    /// Depth before the drop, the lowest it reached and where it ended.
+    pub baseline_depth: f64,
+    pub trough_depth: f64,
+    pub end_depth: f64,
+    pub recovered: bool,
+    pub recovery_ms: i64,
+    /// Share of the lost depth restored per second, capped at all of it; 0 when none came back.
+    pub score: f64,
+}
+
+/// An open depletion on one side.
+#[derive(Debug, Clone, Copy)]
+struct Event {
+    start_ms: i64,
+    baseline: f64,
+    trough: f64,
+    by_trade: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct SideState {
+    /// Receive time and depth of the last book outside an event.
+    previous: Option<(i64, f64)>,
+    event: Option<Event>,
+}
+
+/// Depletion events on both sides of one symbol's books.
+#[derive(Debug, Clone)]
+pub struct Tracker {
+    settings: Settings,
+    bids: SideState,
+    asks: SideState,
+}
+
+impl Tracker {
+    pub fn new(settings: Settings) -> Self {
+        Tracker { settings, bids: SideState::default(), asks: SideState::default() }
+    }
+
+    /// Follows `book` on both sides and returns the events it closes. `large_trades`
+    /// holds the exchange times of the latest large taker sell and buy, which hit the
+    /// bids and asks respectively.
+    pub fn push(&mut self, book: &OrderBook, large_trades: LargeTrades) -> Vec<Resiliency> {
+        let settings = self.settings;
+        let now = book.timestamp_ms;
+        let sides = [
+            ("bid", &mut self.bids, depth_within(&book.bids, settings.bps), large_trades.sell_ms),
+            ("ask", &mut self.asks, depth_within(&book.asks, settings.bps), large_trades.buy_ms),
+        ];
+        sides.into_iter()
+            .filter_map(|(side, state, depth, trade_ms)| {
+                let closed = state.push(&settings, now, depth, trade_ms)?;
+                Some(closed.record(book, side, settings.bps, now, depth))
+            })
+            .collect()
+    }
+}
+
+impl SideState {
+    /// Returns the event `depth` closes, if any.
+    fn push(&mut self, settings: &Settings, now: i64, depth: f64, trade_ms: Option<i64>) -> Option<Event> {
+        if let Some(event) = self.event.as_mut() {
+            event.trough = event.trough.min(depth);
+            if depth < event.baseline && now - event.start_ms < settings.timeout_ms {
+                return None;
+            }
+            let closed = *event;
+            self.event = None;
+            self.previous = Some((now, depth));
+            return Some(closed);
+        }
+        if let Some((previous_ms, previous)) = self.previous {
+            if previous > 0.0 && depth <= previous * (1.0 - settings.drop) {
+                let by_trade = trade_ms.is_some_and(|t| t > previous_ms && t <= now);
+                self.event = Some(Event { start_ms: now, baseline: previous, trough: depth, by_trade });
+                return None;
+            }
+        }
+        self.previous = Some((now, depth));
+        None
+    }
+}
+
+impl Event {
+    fn record(&self, book: &OrderBook, side: &str, bps: f64, end_ms: i64, end_depth: f64) -> Resiliency {
+        let recovered = end_depth >= self.baseline;
+        let recovery_ms = end_ms - self.start_ms;
+        let lost = self.baseline - self.trough;
+        let restored = if lost > 0.0 { ((end_depth - self.trough) / lost).clamp(0.0, 1.0) } else { 1.0 };
+        let secs = recovery_ms as f64 / 1000.0;
+        Resiliency {
+            start_ms: self.start_ms,
+            end_ms,
+            exchange: if book.exchange.is_empty() { binance::EXCHANGE.to_string() } else { book.exchange.clone() },
+            symbol: book.symbol.to_uppercase(),
+            side: side.to_string(),
+            cause: if self.by_trade { CAUSE_TRADE } else { CAUSE_REMOVAL }.to_string(),
+            bps,
+            baseline_depth: self.baseline,
+            trough_depth: self.trough,
+            end_depth,
+            recovered,
+            recovery_ms,
+            score: if restored == 0.0 { 0.0 } else { restored / secs.max(0.001) },
+        }
+    }
+}
+
+/// Exchange times of the latest large taker trades on each side.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct LargeTrades {
+    pub buy_ms: Option<i64>,
+    pub sell_ms: Option<i64>,
+}
+
+/// The latest large trades per upper-cased symbol, read by the collectors.
+static LARGE_TRADES: LazyLock<Mutex<HashMap<String, LargeTrades>>> = LazyLock::new(Default::default);
+
+/// `symbol`'s latest large trades, empty until one is seen.
+pub fn large_trades(symbol: &str) -> LargeTrades {
+    LARGE_TRADES.lock().unwrap_or_else(|e| e.into_inner()).get(&symbol.to_uppercase()).copied().unwrap_or_default()
+}
+
+/// Follows `symbol`'s trade stream and notes its large trades until the task is
+/// dropped, reconnecting after a lost connection.
+pub async fn capture(venue: &Venue, symbol: String) -> Result<(), Error> {
+    let threshold = Settings::from_env().trade_notional;
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        let followed = trades::follow(venue, &symbol, |trade| {
+            if trade.price * trade.qty < threshold {
+                return;
+            }
+            let mut large = LARGE_TRADES.lock().unwrap_or_else(|e| e.into_inner());
+            let seen = large.entry(symbol.to_uppercase()).or_default();
+            // a buyer-maker trade was an aggressive sell
+            if trade.is_buyer_maker {
+                seen.sell_ms = Some(trade.trade_time_ms);
+            } else {
+                seen.buy_ms = Some(trade.trade_time_ms);
+            }
+        }).await;
+        match followed {
+            Ok(()) => backoff = Duration::from_secs(1),
+            Err(e) => {
+                warn!(error = %e, symbol, "trade stream failed, reconnecting");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
@@ -0,0 +1,131 @@
+//! The one HTTP client every Binance REST call goes through. Binance limits request
+//! weight per IP per minute and bans IPs that keep going after a 429, so calls wait
+//! for room in a shared per-minute budget (`REST_WEIGHT_PER_MINUTE`, default 1200),
+//! take the weight Binance reports in `X-MBX-USED-WEIGHT-1M` as the floor of what's
+//! been spent, and all hold off for `Retry-After` once any of them is limited. A
+//! backfill then slows down instead of getting the IP banned under the collector.
+
+use chrono::Utc;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+use crate::{config, proxy, Error};
+
+pub const DEFAULT_WEIGHT_PER_MINUTE: u32 = 1200;
+/// How long to hold off after a 429 or 418 without a `Retry-After`.
+pub const DEFAULT_RETRY_AFTER: Duration = Duration::from_secs(60);
+const USED_WEIGHT: &str = "x-mbx-used-weight-1m";
+const MINUTE_MS: i64 = 60_000;
+
+static HTTP: LazyLock<reqwest::Client> = LazyLock::new(proxy::http_client);
+static BUDGET: LazyLock<Mutex<Budget>> = LazyLock::new(|| Mutex::new(Budget::new(weight_per_minute())));
+
+/// Request weight spent in the current minute, against a fixed allowance. Binance's
+/// windows are clock minutes, so these are too.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Budget {
+    per_minute: u32,
+    minute: i64,
+    used: u32,
+    blocked_until_ms: i64,
+}
+
+impl Budget {
+    pub fn new(per_minute: u32) -> Self {
+        Budget { per_minute, minute: 0, used: 0, blocked_until_ms: 0 }
+    }
+
+    /// Spends `weight` at `now_ms` and returns zero, or returns how long to wait before
+    /// asking again. A request heavier than the whole allowance still goes first
+    /// thing in a minute.
+    pub fn acquire(&mut self, weight: u32, now_ms: i64) -> Duration {
+        if now_ms < self.blocked_until_ms {
+            return Duration::from_millis((self.blocked_until_ms - now_ms) as u64);
+        }
+        self.roll(now_ms);
+        if self.used > 0 && self.used + weight > self.per_minute {
+            return Duration::from_millis(((self.minute + 1) * MINUTE_MS - now_ms) as u64);
+        }
+        self.used += weight;
+        Duration::ZERO
+    }
+
+    /// Takes the weight Binance reports used this minute, which counts every
+    /// process on the IP, when it's more than this one has spent.
+    pub fn observe(&mut self, used: u32, now_ms: i64) {
+        self.roll(now_ms);
+        self.used = self.used.max(used);
+    }
+
+    /// Holds every request until `until_ms`.
+    pub fn block(&mut self, until_ms: i64) {
+        self.blocked_until_ms = self.blocked_until_ms.max(until_ms);
+    }
+
+    pub fn used(&self) -> u32 {
+        self.used
+    }
+
+    fn roll(&mut self, now_ms: i64) {
+        let minute = now_ms.div_euclid(MINUTE_MS);
+        if minute != self.minute {
+            (self.minute, self.used) = (minute, 0);
+        }
+    }
+}
+
+/// `REST_WEIGHT_PER_MINUTE`, or 1200: a share of Binance's per-IP limit that leaves
+/// room for whatever else runs from the same address.
+pub fn weight_per_minute() -> u32 {
+    config::var("REST_WEIGHT_PER_MINUTE").and_then(|w| w.parse().ok()).filter(|&w| w > 0).unwrap_or(DEFAULT_WEIGHT_PER_MINUTE)
+}
+
+/// The shared client, for building requests to `send`.
+pub fn http() -> &'static reqwest::Client {
+    &HTTP
+}
+
+/// A GET of `url` through `send`.
+pub async fn get(url: &str, weight: u32) -> Result<Response, Error> {
+    send(HTTP.get(url), weight).await
+}
+
+/// Sends `request` once the budget has room for `weight`, and returns the response
+/// whatever its status. A 429 or 418 holds off every later request for its
+/// `Retry-After`.
+pub async fn send(request: RequestBuilder, weight: u32) -> Result<Response, Error> {
+    loop {
+        let wait = lock().acquire(weight, Utc::now().timestamp_millis());
+        if wait.is_zero() {
+            break;
+        }
+        debug!(weight, wait_ms = wait.as_millis() as u64, "waiting for rest weight");
+        tokio::time::sleep(wait).await;
+    }
+
+    let response = request.send().await?;
+    let now_ms = Utc::now().timestamp_millis();
+    let mut budget = lock();
+    if let Some(used) = response.headers().get(USED_WEIGHT).and_then(|v| v.to_str().ok()?.parse().ok()) {
+        budget.observe(used, now_ms);
+    }
+    if matches!(response.status(), StatusCode::TOO_MANY_REQUESTS | StatusCode::IM_A_TEAPOT) {
+        let wait = retry_after(&response);
+        warn!(status = response.status().as_u16(), wait_s = wait.as_secs(), "rest rate limited, holding off");
+        budget.block(now_ms + wait.as_millis() as i64);
+    }
+    Ok(response)
+}
+
+/// A limited response's `Retry-After`, or a minute.
+pub fn retry_after(response: &Response) -> Duration {
+    response.headers().get(reqwest::header::RETRY_AFTER)
+        .and_then(|v| v.to_str().ok()?.parse().ok())
+        .map_or(DEFAULT_RETRY_AFTER, Duration::from_secs)
+}
+
+fn lock() -> std::sync::MutexGuard<'static, Budget> {
+    BUDGET.lock().unwrap_or_else(|e| e.into_inner())
+}
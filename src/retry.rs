@@ -0,0 +1,82 @@
+//! Exponential backoff with full jitter for transient S3 failures.
+
+use aws_sdk_s3::error::SdkError;
+use aws_sdk_s3::config::http::HttpResponse;
+use std::future::Future;
+use std::time::Duration;
+use tracing::warn;
+
+use crate::config;
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    /// Total attempts including the first one.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// Reads `S3_MAX_ATTEMPTS`, `S3_RETRY_BASE_MS` and `S3_RETRY_MAX_MS`, keeping defaults for unset values.
+    pub fn from_env() -> Self {
+        let var = |name: &str| config::var(name).and_then(|v| v.parse::<u64>().ok());
+        let default = RetryPolicy::default();
+        RetryPolicy {
+            max_attempts: var("S3_MAX_ATTEMPTS").map_or(default.max_attempts, |v| v.max(1) as u32),
+            base_delay: var("S3_RETRY_BASE_MS").map_or(default.base_delay, Duration::from_millis),
+            max_delay: var("S3_RETRY_MAX_MS").map_or(default.max_delay, Duration::from_millis),
+        }
+    }
+
+    /// Full jitter: uniform in [0, min(max_delay, base_delay * 2^retry)].
+    pub fn delay(&self, retry: u32) -> Duration {
+        let ceiling = self.base_delay
+            .saturating_mul(1u32.checked_shl(retry).unwrap_or(u32::MAX))
+            .min(self.max_delay);
+        ceiling.mul_f64(fastrand::f64())
+    }
+
+    /// Runs `op` until it succeeds, fails permanently, or attempts run out.
+    /// Returns the final result together with the number of retries performed.
+    pub async fn run<T, E, F, Fut>(&self, mut op: F, is_transient: impl Fn(&E) -> bool) -> (Result<T, E>, u32)
+    where
+        E: std::fmt::Display,
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut retries = 0;
+        loop {
+            match op().await {
+                Err(e) if retries + 1 < self.max_attempts && is_transient(&e) => {
+                    let delay = self.delay(retries);
+                    warn!(error = %e, retry = retries + 1, delay_ms = delay.as_millis() as u64, "transient failure, retrying");
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                result => return (result, retries),
+            }
+        }
+    }
+}
+
+/// Timeouts, connection failures, throttling and 5xx responses are worth retrying.
+pub fn is_transient<E>(e: &SdkError<E, HttpResponse>) -> bool {
+    match e {
+        SdkError::TimeoutError(_) | SdkError::DispatchFailure(_) | SdkError::ResponseError(_) => true,
+        SdkError::ServiceError(ctx) => {
+            let status = ctx.raw().status().as_u16();
+            status == 429 || status >= 500
+        }
+        _ => false,
+    }
+}
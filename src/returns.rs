@@ -0,0 +1,66 @@
+//! Log-returns of mid, so readers don't rebuild them from neighbouring rows. Each
+//! stored book carries the return since the symbol's previous stored book, and the
+//! return over the last `RETURN_WINDOW_SECS` from a rolling buffer of every book
+//! seen, sampled or not.
+
+use std::collections::VecDeque;
+
+use crate::{config, OrderBook};
+
+pub const DEFAULT_WINDOW_SECS: u64 = 60;
+
+/// `RETURN_WINDOW_SECS`, default 60.
+pub fn window_secs() -> u64 {
+    config::var("RETURN_WINDOW_SECS").and_then(|s| s.parse().ok()).filter(|&s| s > 0).unwrap_or(DEFAULT_WINDOW_SECS)
+}
+
+/// A symbol's recent mids and its last stored one.
+#[derive(Debug, Clone)]
+pub struct Returns {
+    window_secs: u64,
+    /// (timestamp_ms, mid) of every book seen, keeping the newest one at least a
+    /// window old at the front.
+    mids: VecDeque<(i64, f64)>,
+    stored: Option<f64>,
+}
+
+impl Returns {
+    pub fn new(window_secs: u64) -> Self {
+        Returns { window_secs, mids: VecDeque::new(), stored: None }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(window_secs())
+    }
+
+    /// Adds a book's mid to the buffer, whether or not it's stored.
+    pub fn observe(&mut self, book: &OrderBook) {
+        self.mids.push_back((book.timestamp_ms, book.mid_price));
+        let cutoff = book.timestamp_ms - self.window_ms();
+        while self.mids.get(1).is_some_and(|&(t, _)| t <= cutoff) {
+            self.mids.pop_front();
+        }
+    }
+
+    /// Stamps returns on a book about to be stored, and remembers its mid as the
+    /// previous stored one. Each return is 0 until there's a mid to measure from.
+    pub fn stamp(&mut self, book: OrderBook) -> OrderBook {
+        let cutoff = book.timestamp_ms - self.window_ms();
+        let then = self.mids.front().filter(|&&(t, _)| t <= cutoff).map(|&(_, mid)| mid);
+        let since_stored = log_return(self.stored, book.mid_price);
+        let over_window = log_return(then, book.mid_price);
+        self.stored = Some(book.mid_price);
+        book.with_returns(since_stored, over_window, self.window_secs as i32)
+    }
+
+    fn window_ms(&self) -> i64 {
+        self.window_secs as i64 * 1000
+    }
+}
+
+fn log_return(from: Option<f64>, to: f64) -> f64 {
+    match from {
+        Some(from) if from > 0.0 && to > 0.0 => (to / from).ln(),
+        _ => 0.0,
+    }
+}
@@ -0,0 +1,47 @@
+//! Downsampling: persist fewer snapshots than the stream delivers, trading resolution
+//! for storage without changing the subscription.
+
+use crate::{config, OrderBook};
+
+/// Keeps a snapshot only when every configured condition holds against the last kept
+/// one. With nothing configured every snapshot is kept.
+#[derive(Debug, Clone, Default)]
+pub struct Sampler {
+    /// Minimum time between kept snapshots.
+    pub min_interval_ms: Option<i64>,
+    /// Minimum mid price move since the last kept snapshot, in basis points.
+    pub min_move_bps: Option<f64>,
+    last: Option<(i64, f64)>,
+}
+
+impl Sampler {
+    pub fn new(min_interval_ms: Option<i64>, min_move_bps: Option<f64>) -> Self {
+        Sampler { min_interval_ms, min_move_bps, last: None }
+    }
+
+    /// Reads `SAMPLE_INTERVAL_MS` and `SAMPLE_MIN_MOVE_BPS`; unset or non-positive values disable that condition.
+    pub fn from_env() -> Self {
+        let var = |name: &str| config::var(name).and_then(|v| v.parse::<f64>().ok()).filter(|v| *v > 0.0);
+        Sampler::new(var("SAMPLE_INTERVAL_MS").map(|ms| ms as i64), var("SAMPLE_MIN_MOVE_BPS"))
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.min_interval_ms.is_some() || self.min_move_bps.is_some()
+    }
+
+    /// Whether `book` should be persisted; kept books become the new reference.
+    pub fn keep(&mut self, book: &OrderBook) -> bool {
+        let at = book.partition_time_ms();
+        let keep = match self.last {
+            None => true,
+            Some((last_at, last_mid)) => {
+                self.min_interval_ms.is_none_or(|min| at - last_at >= min)
+                    && self.min_move_bps.is_none_or(|min| ((book.mid_price - last_mid) / last_mid).abs() * 10_000.0 >= min)
+            }
+        };
+        if keep {
+            self.last = Some((at, book.mid_price));
+        }
+        keep
+    }
+}
@@ -0,0 +1,1040 @@
+//! Avro schemas for every record type written to S3.
+//!
+//! The OrderBook schema is versioned. New versions append fields, and every
+//! added field carries a default, so readers can resolve files written by other
+//! versions (see `ORDERBOOK_V3` for the one exception). Writers can pin an older
+//! version with `ORDERBOOK_SCHEMA_VERSION`; fields that version doesn't know about
+//! are dropped at encode time.
+
+use apache_avro::Schema;
+use std::collections::HashMap;
+use std::sync::{LazyLock, Mutex};
+
+use crate::{config, Error};
+
+/// Version stamped into newly built OrderBook records.
+pub const ORDERBOOK_VERSION: i32 = 14;
+
+/// v1: the original layout, without a version field.
+pub const ORDERBOOK_V1: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {"type": "array", "items": "double"}}},
+    {"name": "asks", "type": {"type": "array", "items": {"type": "array", "items": "double"}}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"}
+  ]
+}
+"#;
+
+/// v2: adds `schema_version`; v1 files resolve with it defaulted to 1.
+pub const ORDERBOOK_V2: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {"type": "array", "items": "double"}}},
+    {"name": "asks", "type": {"type": "array", "items": {"type": "array", "items": "double"}}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1}
+  ]
+}
+"#;
+
+/// v3: bids/asks become arrays of named `Level` records instead of `[price, qty]`
+/// pairs. This is the one break in resolution; `migrate` reads v1/v2 files into v3
+/// records and converts back when a writer is pinned to an older version.
+pub const ORDERBOOK_V3: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1}
+  ]
+}
+"#;
+
+/// v4: adds `exchange` and `symbol`, so books from several streams can share a table.
+/// Older files resolve with both empty.
+pub const ORDERBOOK_V4: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""}
+  ]
+}
+"#;
+
+/// v5: adds `event_time_ms`, the time the book describes, next to `timestamp_ms`
+/// (when it was received). Older files resolve with it at 0.
+pub const ORDERBOOK_V5: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0}
+  ]
+}
+"#;
+
+/// v6: adds `last_update_id`, the exchange's book sequence number. From v6 on
+/// `event_time_ms` is only ever the exchange's own clock, 0 when it sent none.
+pub const ORDERBOOK_V6: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0}
+  ]
+}
+"#;
+
+/// v7: adds `exact_bids`/`exact_asks`, the exchange's own levels in fixed point
+/// (`price / 10^price_scale`, `qty / 10^qty_scale`) for exact tick arithmetic.
+/// They're only filled with `DECIMAL_PRICES` on and are empty otherwise.
+pub const ORDERBOOK_V7: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0}
+  ]
+}
+"#;
+
+/// v8: adds `bid_notional`/`ask_notional`, the cumulative notional within each depth
+/// bucket, and `bid_sweeps`/`ask_sweeps`, how far a sweep of each notional threshold
+/// reaches. Older files resolve with all four empty.
+pub const ORDERBOOK_V8: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []}
+  ]
+}
+"#;
+
+/// v9: adds `spread_bps` and, from the symbol's `exchangeInfo` tick size,
+/// `tick_size` and `spread_in_ticks`, so spreads compare across price scales. Older
+/// files resolve with all three at 0.
+pub const ORDERBOOK_V9: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "spread_bps", "type": "double", "default": 0.0},
+    {"name": "tick_size", "type": "double", "default": 0.0},
+    {"name": "spread_in_ticks", "type": "double", "default": 0.0}
+  ]
+}
+"#;
+
+/// v10: adds `vpin`, the symbol's trade-flow toxicity estimate, and `vpin_buckets`,
+/// how many volume buckets it covers (0 for no estimate). Older files resolve with
+/// both at 0.
+pub const ORDERBOOK_V10: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "spread_bps", "type": "double", "default": 0.0},
+    {"name": "tick_size", "type": "double", "default": 0.0},
+    {"name": "spread_in_ticks", "type": "double", "default": 0.0},
+    {"name": "vpin", "type": "double", "default": 0.0},
+    {"name": "vpin_buckets", "type": "int", "default": 0}
+  ]
+}
+"#;
+
+/// v11: adds the average fill price and slippage from mid in basis points of a
+/// market order of each notional threshold, per side. Older files resolve with the
+/// four arrays empty.
+pub const ORDERBOOK_V11: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "spread_bps", "type": "double", "default": 0.0},
+    {"name": "tick_size", "type": "double", "default": 0.0},
+    {"name": "spread_in_ticks", "type": "double", "default": 0.0},
+    {"name": "vpin", "type": "double", "default": 0.0},
+    {"name": "vpin_buckets", "type": "int", "default": 0},
+    {"name": "bid_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []}
+  ]
+}
+"#;
+
+/// v12: adds how many MADs the spread and top-of-book depth are from their rolling
+/// baselines, and whether either is past the anomaly threshold. Older files resolve
+/// with the scores 0 and the flags false.
+pub const ORDERBOOK_V12: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "spread_bps", "type": "double", "default": 0.0},
+    {"name": "tick_size", "type": "double", "default": 0.0},
+    {"name": "spread_in_ticks", "type": "double", "default": 0.0},
+    {"name": "vpin", "type": "double", "default": 0.0},
+    {"name": "vpin_buckets", "type": "int", "default": 0},
+    {"name": "bid_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "spread_mads", "type": "double", "default": 0.0},
+    {"name": "depth_mads", "type": "double", "default": 0.0},
+    {"name": "spread_anomaly", "type": "boolean", "default": false},
+    {"name": "depth_anomaly", "type": "boolean", "default": false}
+  ]
+}
+"#;
+
+/// v13: adds log-returns of mid since the previous stored book and over a trailing
+/// window, and the window's length. Older files resolve with all three 0.
+pub const ORDERBOOK_V13: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "spread_bps", "type": "double", "default": 0.0},
+    {"name": "tick_size", "type": "double", "default": 0.0},
+    {"name": "spread_in_ticks", "type": "double", "default": 0.0},
+    {"name": "vpin", "type": "double", "default": 0.0},
+    {"name": "vpin_buckets", "type": "int", "default": 0},
+    {"name": "bid_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "spread_mads", "type": "double", "default": 0.0},
+    {"name": "depth_mads", "type": "double", "default": 0.0},
+    {"name": "spread_anomaly", "type": "boolean", "default": false},
+    {"name": "depth_anomaly", "type": "boolean", "default": false},
+    {"name": "mid_return", "type": "double", "default": 0.0},
+    {"name": "mid_return_window", "type": "double", "default": 0.0},
+    {"name": "return_window_secs", "type": "int", "default": 0}
+  ]
+}
+"#;
+
+/// v14: adds whether an aggressive order swept several levels since the previous
+/// book, the side it hit, the levels it reached and its notional. Older files resolve
+/// with no sweep.
+pub const ORDERBOOK_V14: &str = r#"
+{
+  "type": "record",
+  "name": "OrderBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "schema_version", "type": "int", "default": 1},
+    {"name": "exchange", "type": "string", "default": ""},
+    {"name": "symbol", "type": "string", "default": ""},
+    {"name": "event_time_ms", "type": "long", "default": 0},
+    {"name": "last_update_id", "type": "long", "default": 0},
+    {"name": "exact_bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}, "default": []},
+    {"name": "exact_asks", "type": {"type": "array", "items": "ExactLevel"}, "default": []},
+    {"name": "price_scale", "type": "int", "default": 0},
+    {"name": "qty_scale", "type": "int", "default": 0},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}, "default": []},
+    {"name": "spread_bps", "type": "double", "default": 0.0},
+    {"name": "tick_size", "type": "double", "default": 0.0},
+    {"name": "spread_in_ticks", "type": "double", "default": 0.0},
+    {"name": "vpin", "type": "double", "default": 0.0},
+    {"name": "vpin_buckets", "type": "int", "default": 0},
+    {"name": "bid_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_fill_prices", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "bid_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "ask_slippage_bps", "type": {"type": "array", "items": "double"}, "default": []},
+    {"name": "spread_mads", "type": "double", "default": 0.0},
+    {"name": "depth_mads", "type": "double", "default": 0.0},
+    {"name": "spread_anomaly", "type": "boolean", "default": false},
+    {"name": "depth_anomaly", "type": "boolean", "default": false},
+    {"name": "mid_return", "type": "double", "default": 0.0},
+    {"name": "mid_return_window", "type": "double", "default": 0.0},
+    {"name": "return_window_secs", "type": "int", "default": 0},
+    {"name": "sweep", "type": "boolean", "default": false},
+    {"name": "sweep_side", "type": "string", "default": ""},
+    {"name": "sweep_levels", "type": "int", "default": 0},
+    {"name": "sweep_notional", "type": "double", "default": 0.0}
+  ]
+}
+"#;
+
+pub const ORDERBOOK: &str = ORDERBOOK_V14;
+
+/// OrderBook schema for a given version, if it exists.
+pub fn orderbook(version: i32) -> Option<&'static str> {
+    match version {
+        1 => Some(ORDERBOOK_V1),
+        2 => Some(ORDERBOOK_V2),
+        3 => Some(ORDERBOOK_V3),
+        4 => Some(ORDERBOOK_V4),
+        5 => Some(ORDERBOOK_V5),
+        6 => Some(ORDERBOOK_V6),
+        7 => Some(ORDERBOOK_V7),
+        8 => Some(ORDERBOOK_V8),
+        9 => Some(ORDERBOOK_V9),
+        10 => Some(ORDERBOOK_V10),
+        11 => Some(ORDERBOOK_V11),
+        12 => Some(ORDERBOOK_V12),
+        13 => Some(ORDERBOOK_V13),
+        14 => Some(ORDERBOOK_V14),
+        _ => None,
+    }
+}
+
+/// Writer version selected by `ORDERBOOK_SCHEMA_VERSION`, falling back to the latest
+/// when unset or unknown. Pair it with `orderbook` to get the schema.
+pub fn writer_version() -> i32 {
+    config::var("ORDERBOOK_SCHEMA_VERSION")
+        .and_then(|v| v.parse().ok())
+        .filter(|v| orderbook(*v).is_some())
+        .unwrap_or(ORDERBOOK_VERSION)
+}
+
+/// Parses `schema` on first use and returns the cached copy afterwards. There are
+/// only a handful of distinct schemas, so entries live for the whole process.
+pub fn parsed(schema: &str) -> Result<&'static Schema, Error> {
+    static PARSED: LazyLock<Mutex<HashMap<String, &'static Schema>>> = LazyLock::new(Default::default);
+
+    let mut cache = PARSED.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(parsed) = cache.get(schema) {
+        return Ok(parsed);
+    }
+    let parsed: &'static Schema = Box::leak(Box::new(Schema::parse_str(schema)?));
+    cache.insert(schema.to_string(), parsed);
+    Ok(parsed)
+}
+
+pub const LIQUIDATION: &str = r#"
+{
+  "type": "record",
+  "name": "Liquidation",
+  "fields": [
+    {"name": "event_time_ms", "type": "long"},
+    {"name": "trade_time_ms", "type": "long"},
+    {"name": "symbol", "type": "string"},
+    {"name": "side", "type": "string"},
+    {"name": "price", "type": "double"},
+    {"name": "avg_price", "type": "double"},
+    {"name": "qty", "type": "double"},
+    {"name": "filled_qty", "type": "double"},
+    {"name": "status", "type": "string"}
+  ]
+}
+"#;
+
+pub const FUNDING: &str = r#"
+{
+  "type": "record",
+  "name": "FundingSnapshot",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "symbol", "type": "string"},
+    {"name": "funding_rate", "type": "double"},
+    {"name": "next_funding_time_ms", "type": "long"},
+    {"name": "mark_price", "type": "double"},
+    {"name": "index_price", "type": "double"},
+    {"name": "open_interest", "type": "double"}
+  ]
+}
+"#;
+
+pub const AGG_TRADE: &str = r#"
+{
+  "type": "record",
+  "name": "AggTrade",
+  "fields": [
+    {"name": "symbol", "type": "string"},
+    {"name": "agg_id", "type": "long"},
+    {"name": "price", "type": "double"},
+    {"name": "qty", "type": "double"},
+    {"name": "first_trade_id", "type": "long"},
+    {"name": "last_trade_id", "type": "long"},
+    {"name": "trade_time_ms", "type": "long"},
+    {"name": "is_buyer_maker", "type": "boolean"},
+    {"name": "source", "type": "string", "default": "backfill"}
+  ]
+}
+"#;
+
+pub const BAR: &str = r#"
+{
+  "type": "record",
+  "name": "Bar",
+  "fields": [
+    {"name": "start_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "snapshots", "type": "long"},
+    {"name": "open_mid", "type": "double"},
+    {"name": "high_mid", "type": "double"},
+    {"name": "low_mid", "type": "double"},
+    {"name": "close_mid", "type": "double"},
+    {"name": "mean_spread", "type": "double"},
+    {"name": "max_spread", "type": "double"},
+    {"name": "mean_imbalance", "type": "double"},
+    {"name": "mean_bid_depth", "type": "double"},
+    {"name": "min_bid_depth", "type": "double"},
+    {"name": "mean_ask_depth", "type": "double"},
+    {"name": "min_ask_depth", "type": "double"},
+    {"name": "first_ms", "type": "long", "default": 0},
+    {"name": "last_ms", "type": "long", "default": 0}
+  ]
+}
+"#;
+
+pub const EXECUTION: &str = r#"
+{
+  "type": "record",
+  "name": "Execution",
+  "fields": [
+    {"name": "event_time_ms", "type": "long"},
+    {"name": "trade_time_ms", "type": "long"},
+    {"name": "symbol", "type": "string"},
+    {"name": "client_order_id", "type": "string"},
+    {"name": "order_id", "type": "long"},
+    {"name": "trade_id", "type": "long"},
+    {"name": "side", "type": "string"},
+    {"name": "order_type", "type": "string"},
+    {"name": "execution_type", "type": "string"},
+    {"name": "order_status", "type": "string"},
+    {"name": "price", "type": "double"},
+    {"name": "qty", "type": "double"},
+    {"name": "last_price", "type": "double"},
+    {"name": "last_qty", "type": "double"},
+    {"name": "cumulative_qty", "type": "double"},
+    {"name": "commission", "type": "double"},
+    {"name": "commission_asset", "type": "string"},
+    {"name": "is_maker", "type": "boolean"}
+  ]
+}
+"#;
+
+pub const CONSOLIDATED: &str = r#"
+{
+  "type": "record",
+  "name": "Consolidated",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "symbol", "type": "string"},
+    {"name": "best_bid", "type": "double"},
+    {"name": "best_bid_venue", "type": "string"},
+    {"name": "best_ask", "type": "double"},
+    {"name": "best_ask_venue", "type": "string"},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "Level"}},
+    {"name": "venues", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Contribution",
+      "fields": [
+        {"name": "venue", "type": "string"},
+        {"name": "timestamp_ms", "type": "long"},
+        {"name": "best_bid", "type": "double"},
+        {"name": "best_ask", "type": "double"},
+        {"name": "bid_depth", "type": {"type": "array", "items": "double"}},
+        {"name": "ask_depth", "type": {"type": "array", "items": "double"}}
+      ]
+    }}}
+  ]
+}
+"#;
+
+pub const VENUE_SPREAD: &str = r#"
+{
+  "type": "record",
+  "name": "VenueSpread",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "symbol", "type": "string"},
+    {"name": "venue_a", "type": "string"},
+    {"name": "venue_b", "type": "string"},
+    {"name": "mid_diff", "type": "double"},
+    {"name": "mid_diff_bps", "type": "double"},
+    {"name": "arbs", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Arb",
+      "fields": [
+        {"name": "size", "type": "double"},
+        {"name": "buy_venue", "type": "string"},
+        {"name": "sell_venue", "type": "string"},
+        {"name": "buy_price", "type": "double"},
+        {"name": "sell_price", "type": "double"},
+        {"name": "edge_bps", "type": "double"}
+      ]
+    }}}
+  ]
+}
+"#;
+
+pub const QUOTE: &str = r#"
+{
+  "type": "record",
+  "name": "Quote",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "event_time_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "update_id", "type": "long"},
+    {"name": "bid_price", "type": "double"},
+    {"name": "bid_qty", "type": "double"},
+    {"name": "ask_price", "type": "double"},
+    {"name": "ask_qty", "type": "double"},
+    {"name": "spread", "type": "double"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "micro_price", "type": "double"}
+  ]
+}
+"#;
+
+/// Prices and quantities are in units of `10^-8`; see `fulldepth::SCALE`.
+pub const FULL_DEPTH: &str = r#"
+{
+  "type": "record",
+  "name": "FullDepth",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "event_time_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "last_update_id", "type": "long"},
+    {"name": "keyframe", "type": "boolean"},
+    {"name": "base_update_id", "type": "long"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "ExactLevel"}}
+  ]
+}
+"#;
+
+pub const CHURN: &str = r#"
+{
+  "type": "record",
+  "name": "Churn",
+  "fields": [
+    {"name": "start_ms", "type": "long"},
+    {"name": "end_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "updates", "type": "long"},
+    {"name": "adds", "type": "long"},
+    {"name": "cancels", "type": "long"},
+    {"name": "modifications", "type": "long"},
+    {"name": "update_rate", "type": "double"},
+    {"name": "cancel_ratio", "type": "double"}
+  ]
+}
+"#;
+
+pub const IMPACT: &str = r#"
+{
+  "type": "record",
+  "name": "ImpactEstimate",
+  "fields": [
+    {"name": "start_ms", "type": "long"},
+    {"name": "end_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "interval_ms", "type": "long"},
+    {"name": "intervals", "type": "long"},
+    {"name": "lambda", "type": "double"},
+    {"name": "lambda_bps", "type": "double"},
+    {"name": "intercept", "type": "double"},
+    {"name": "r_squared", "type": "double"},
+    {"name": "t_stat", "type": "double"},
+    {"name": "mean_mid", "type": "double"}
+  ]
+}
+"#;
+
+pub const RESILIENCY: &str = r#"
+{
+  "type": "record",
+  "name": "Resiliency",
+  "fields": [
+    {"name": "start_ms", "type": "long"},
+    {"name": "end_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "side", "type": "string"},
+    {"name": "cause", "type": "string"},
+    {"name": "bps", "type": "double"},
+    {"name": "baseline_depth", "type": "double"},
+    {"name": "trough_depth", "type": "double"},
+    {"name": "end_depth", "type": "double"},
+    {"name": "recovered", "type": "boolean"},
+    {"name": "recovery_ms", "type": "long"},
+    {"name": "score", "type": "double"}
+  ]
+}
+"#;
+
+pub const CORRELATION: &str = r#"
+{
+  "type": "record",
+  "name": "Correlation",
+  "fields": [
+    {"name": "start_ms", "type": "long"},
+    {"name": "end_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "other", "type": "string"},
+    {"name": "step_ms", "type": "long"},
+    {"name": "samples", "type": "long"},
+    {"name": "correlation", "type": "double"},
+    {"name": "best_lag_ms", "type": "long"},
+    {"name": "lag_correlation", "type": "double"}
+  ]
+}
+"#;
+
+pub const RAW_BOOK: &str = r#"
+{
+  "type": "record",
+  "name": "RawBook",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "event_time_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "last_update_id", "type": "long"},
+    {"name": "price_scale", "type": "int"},
+    {"name": "qty_scale", "type": "int"},
+    {"name": "bids", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "ExactLevel",
+      "fields": [
+        {"name": "price", "type": "long"},
+        {"name": "qty", "type": "long"}
+      ]
+    }}},
+    {"name": "asks", "type": {"type": "array", "items": "ExactLevel"}}
+  ]
+}
+"#;
+
+/// Features of a book, versioned on their own (`split::FEATURES_VERSION`); fields are
+/// added with defaults so older records still read.
+pub const FEATURES: &str = r#"
+{
+  "type": "record",
+  "name": "Features",
+  "fields": [
+    {"name": "timestamp_ms", "type": "long"},
+    {"name": "event_time_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "last_update_id", "type": "long"},
+    {"name": "features_version", "type": "int"},
+    {"name": "mid_price", "type": "double"},
+    {"name": "spread", "type": "double"},
+    {"name": "spread_bps", "type": "double"},
+    {"name": "tick_size", "type": "double"},
+    {"name": "spread_in_ticks", "type": "double"},
+    {"name": "imbalance_ratio", "type": "double"},
+    {"name": "bid_depth", "type": {"type": "array", "items": {
+      "type": "record",
+      "name": "Level",
+      "fields": [
+        {"name": "price", "type": "double"},
+        {"name": "qty", "type": "double"}
+      ]
+    }}},
+    {"name": "ask_depth", "type": {"type": "array", "items": "Level"}},
+    {"name": "bid_notional", "type": {"type": "array", "items": "double"}},
+    {"name": "ask_notional", "type": {"type": "array", "items": "double"}},
+    {"name": "bid_sweeps", "type": {"type": "array", "items": "Level"}},
+    {"name": "ask_sweeps", "type": {"type": "array", "items": "Level"}},
+    {"name": "bid_fill_prices", "type": {"type": "array", "items": "double"}},
+    {"name": "ask_fill_prices", "type": {"type": "array", "items": "double"}},
+    {"name": "bid_slippage_bps", "type": {"type": "array", "items": "double"}},
+    {"name": "ask_slippage_bps", "type": {"type": "array", "items": "double"}},
+    {"name": "vpin", "type": "double"},
+    {"name": "vpin_buckets", "type": "int"}
+  ]
+}
+"#;
+
+pub const HOURLY_STATS: &str = r#"
+{
+  "type": "record",
+  "name": "HourlyStats",
+  "fields": [
+    {"name": "hour_ms", "type": "long"},
+    {"name": "exchange", "type": "string"},
+    {"name": "symbol", "type": "string"},
+    {"name": "first_ms", "type": "long"},
+    {"name": "last_ms", "type": "long"},
+    {"name": "snapshots", "type": "long"},
+    {"name": "mean_spread", "type": "double"},
+    {"name": "min_spread", "type": "double"},
+    {"name": "max_spread", "type": "double"},
+    {"name": "open_mid", "type": "double"},
+    {"name": "high_mid", "type": "double"},
+    {"name": "low_mid", "type": "double"},
+    {"name": "close_mid", "type": "double"},
+    {"name": "gap_ms", "type": "long"}
+  ]
+}
+"#;
+
+pub const QUARANTINE: &str = r#"
+{
+  "type": "record",
+  "name": "Quarantined",
+  "fields": [
+    {"name": "symbol", "type": "string"},
+    {"name": "received_ms", "type": "long"},
+    {"name": "reason", "type": "string"},
+    {"name": "payload", "type": "string"}
+  ]
+}
+"#;
+
+pub const GAP: &str = r#"
+{
+  "type": "record",
+  "name": "Gap",
+  "fields": [
+    {"name": "symbol", "type": "string"},
+    {"name": "from_ms", "type": "long"},
+    {"name": "to_ms", "type": "long"},
+    {"name": "reason", "type": "string"}
+  ]
+}
+"#;
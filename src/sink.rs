@@ -0,0 +1,604 @@
+use aws_sdk_s3::operation::list_objects_v2::ListObjectsV2Output;
+use aws_sdk_s3::types::{Delete, ObjectIdentifier, ServerSideEncryption, StorageClass};
+use aws_sdk_s3::Client;
+use apache_avro::{Codec, Schema};
+use bytes::Bytes;
+use chrono::{DateTime, Datelike, TimeZone, Timelike, Utc};
+use serde::Serialize;
+use std::future::Future;
+use std::pin::Pin;
+use sha2::{Digest, Sha256};
+use std::sync::{LazyLock, OnceLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info, instrument, warn};
+
+use crate::checkpoint::{Checkpoint, Checkpoints};
+use crate::config::Storage;
+use crate::layout::{self, KeyParts};
+use crate::{binance, config, migrate, schema};
+use crate::retry::{self, RetryPolicy};
+use crate::wal::Wal;
+use crate::Error;
+
+/// Failed writes land under this prefix with their original key appended.
+pub const DLQ_PREFIX: &str = "dlq/";
+
+/// User metadata holding a conditionally written object's `book_id`.
+const BOOK_ID: &str = "book-id";
+
+static RETRY: LazyLock<RetryPolicy> = LazyLock::new(RetryPolicy::from_env);
+static WAL: LazyLock<Wal> = LazyLock::new(Wal::from_env);
+
+/// What happened to a batch handed to `write`.
+#[derive(Debug, Clone, Copy)]
+pub enum Delivery {
+    Stored { latency: Duration, retries: u32 },
+    /// The main put failed and the batch was parked in the dead letter bucket.
+    DeadLettered,
+    /// The key already held the same book, from an overlapping invocation or an
+    /// earlier attempt, so nothing was written.
+    Duplicate,
+}
+
+/// A write started by `Output::upload`, running apart from the output.
+pub type Upload = Pin<Box<dyn Future<Output = Result<Delivery, Error>> + Send>>;
+
+/// Destination for encoded batches. The collector only talks to this, so tests
+/// can capture output in memory.
+pub trait Output {
+    fn write(&mut self, key: &str, body: &[u8]) -> impl Future<Output = Result<Delivery, Error>> + Send;
+
+    /// `write`, unless `key` already holds the book `id` names (see `book_id`); then
+    /// it's a `Delivery::Duplicate`. Outputs no other instance writes to just write.
+    fn write_once(&mut self, key: &str, body: &[u8], id: &str) -> impl Future<Output = Result<Delivery, Error>> + Send {
+        let _ = id;
+        self.write(key, body)
+    }
+
+    /// Claims `key` for this writer, returning false when another got there first.
+    /// Outputs no other instance writes to always win.
+    fn claim(&mut self, key: &str) -> impl Future<Output = Result<bool, Error>> + Send {
+        let _ = key;
+        async { Ok(true) }
+    }
+
+    /// Records how far collection has got once the books up to `checkpoint` are stored.
+    fn checkpoint(&mut self, checkpoint: &Checkpoint) -> impl Future<Output = Result<(), Error>> + Send {
+        let _ = checkpoint;
+        async { Ok(()) }
+    }
+
+    /// Starts `write` (`write_once` with an `id`) as a future that doesn't borrow the
+    /// output, so several can be in flight at once. Outputs that can't return None and
+    /// are written one at a time.
+    fn upload(&self, key: &str, body: Vec<u8>, id: Option<String>) -> Option<Upload> {
+        let _ = (key, body, id);
+        None
+    }
+}
+
+/// The main bucket, with write-ahead spill and dead-lettering.
+pub struct S3Output {
+    s3: Client,
+    checkpoints: Option<Checkpoints>,
+}
+
+impl S3Output {
+    pub fn new(s3: Client) -> Self {
+        S3Output { s3, checkpoints: None }
+    }
+
+    /// Saves checkpoints to `checkpoints`; without it they are dropped.
+    pub fn with_checkpoints(mut self, checkpoints: Option<Checkpoints>) -> Self {
+        self.checkpoints = checkpoints;
+        self
+    }
+}
+
+impl Output for S3Output {
+    async fn write(&mut self, key: &str, body: &[u8]) -> Result<Delivery, Error> {
+        write_bytes(&self.s3, key, body).await
+    }
+
+    async fn write_once(&mut self, key: &str, body: &[u8], id: &str) -> Result<Delivery, Error> {
+        write_bytes_once(&self.s3, key, body, id).await
+    }
+
+    async fn claim(&mut self, key: &str) -> Result<bool, Error> {
+        claim(&self.s3, key).await
+    }
+
+    async fn checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), Error> {
+        match &self.checkpoints {
+            Some(checkpoints) => checkpoints.save(checkpoint).await,
+            None => Ok(()),
+        }
+    }
+
+    fn upload(&self, key: &str, body: Vec<u8>, id: Option<String>) -> Option<Upload> {
+        let (s3, key) = (self.s3.clone(), key.to_string());
+        Some(Box::pin(async move {
+            match id {
+                Some(id) => write_bytes_once(&s3, &key, &body, &id).await,
+                None => write_bytes(&s3, &key, &body).await,
+            }
+        }))
+    }
+}
+
+/// Whether `IDEMPOTENT_WRITES=1` asks for books to be keyed by exchange time and
+/// written with conditional puts; read once per process.
+pub fn idempotent() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+
+    *ON.get_or_init(|| config::var("IDEMPOTENT_WRITES").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")))
+}
+
+/// What a conditionally written object is tagged with to tell whether a key holds
+/// the same book: its `lastUpdateId`, or for books without one a hash of the object.
+pub fn book_id(last_update_id: i64, body: &[u8]) -> String {
+    if last_update_id > 0 {
+        return format!("update:{}", last_update_id);
+    }
+    format!("sha256:{}", hex::encode(Sha256::digest(body)))
+}
+
+/// Secondary bucket for failed writes (`DLQ_BUCKET`), defaulting to the main bucket.
+pub fn dlq_bucket() -> Result<String, Error> {
+    match config::var("DLQ_BUCKET") {
+        Some(bucket) => Ok(bucket),
+        None => Ok(config::storage()?.bucket.clone()),
+    }
+}
+
+/// Hive-style hourly partition directory, e.g. `orderbook/year=2024/month=01/day=01/hour=10`.
+pub fn partition_dir(prefix: &str, at: DateTime<Utc>) -> String {
+    format!("{}/year={}/month={:02}/day={:02}/hour={:02}",
+            prefix, at.year(), at.month(), at.day(), at.hour())
+}
+
+/// The instant of an epoch-millisecond timestamp, for partitioning records by their
+/// own time rather than the clock at write time. Out of range values map to now.
+pub fn at_ms(ms: i64) -> DateTime<Utc> {
+    Utc.timestamp_millis_opt(ms).single().unwrap_or_else(Utc::now)
+}
+
+/// Key for record `id` of `symbol` under the configured `layout::template`.
+pub fn partition_key(prefix: &str, symbol: &str, at: DateTime<Utc>, id: i64) -> Result<String, Error> {
+    let parts = KeyParts { prefix, exchange: binance::EXCHANGE, symbol, at };
+    Ok(layout::template()?.key(&parts, id))
+}
+
+/// Directory `partition_key` files `symbol` under for the hour containing `at`.
+pub fn hour_dir(prefix: &str, symbol: &str, at: DateTime<Utc>) -> Result<String, Error> {
+    let parts = KeyParts { prefix, exchange: binance::EXCHANGE, symbol, at };
+    Ok(layout::template()?.dir(&parts))
+}
+
+/// Serializes records into a single Avro object container. Records are conformed and
+/// resolved against `schema` first, so writing with an older schema version works.
+pub fn encode<T: Serialize>(schema: &str, records: &[T]) -> Result<Vec<u8>, Error> {
+    encode_with_codec(schema, records, Codec::Null)
+}
+
+/// `encode` with block compression, for large files such as compacted hours.
+pub fn encode_with_codec<T: Serialize>(schema: &str, records: &[T], codec: Codec) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    encode_inner(schema::parsed(schema)?, records, codec, None, &mut buf)?;
+    Ok(buf)
+}
+
+/// `encode` with a fixed sync marker, so identical records give identical bytes.
+pub fn encode_with_marker<T: Serialize>(schema: &str, records: &[T], marker: [u8; 16]) -> Result<Vec<u8>, Error> {
+    let mut buf = Vec::new();
+    encode_inner(schema::parsed(schema)?, records, Codec::Null, Some(marker), &mut buf)?;
+    Ok(buf)
+}
+
+/// `encode` against an already parsed schema into a caller-owned buffer, replacing
+/// its contents but keeping its capacity.
+pub fn encode_into<T: Serialize>(schema: &Schema, records: &[T], buf: &mut Vec<u8>) -> Result<(), Error> {
+    buf.clear();
+    encode_inner(schema, records, Codec::Null, None, buf)
+}
+
+fn encode_inner<T: Serialize>(schema: &Schema, records: &[T], codec: Codec, marker: Option<[u8; 16]>, buf: &mut Vec<u8>) -> Result<(), Error> {
+    let mut writer = match marker {
+        Some(marker) => {
+            write_header(buf, schema, codec, marker)?;
+            apache_avro::Writer::append_to_with_codec(schema, buf, codec, marker)
+        }
+        None => apache_avro::Writer::with_codec(schema, buf, codec),
+    };
+    for record in records {
+        append(&mut writer, schema, record)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Appends one record to an open container, conformed and resolved like `encode`'s.
+pub fn append<T: Serialize, W: std::io::Write>(writer: &mut apache_avro::Writer<'_, W>, schema: &Schema, record: &T) -> Result<(), Error> {
+    let value = migrate::conform(apache_avro::to_value(record)?, schema);
+    writer.append(value.resolve(schema)?)?;
+    Ok(())
+}
+
+/// Container header with metadata in a fixed order; the library writes it from a
+/// HashMap, so its own header bytes vary between runs.
+fn write_header(buf: &mut Vec<u8>, schema: &Schema, codec: Codec, marker: [u8; 16]) -> Result<(), Error> {
+    use apache_avro::{to_avro_datum, types::Value};
+
+    buf.extend(b"Obj\x01");
+    buf.extend(to_avro_datum(&Schema::Long, Value::Long(2))?);
+    for (key, value) in [("avro.codec", Value::from(codec)), ("avro.schema", Value::Bytes(serde_json::to_vec(schema)?))] {
+        buf.extend(to_avro_datum(&Schema::String, Value::String(key.to_string()))?);
+        buf.extend(to_avro_datum(&Schema::Bytes, value)?);
+    }
+    buf.push(0);
+    buf.extend(marker);
+    Ok(())
+}
+
+/// Uploads to the main bucket under the retry policy, returning how many retries it took.
+/// Keys here and in the other main bucket helpers are relative to the configured prefix.
+pub async fn put(s3: &Client, key: &str, body: impl Into<Bytes>) -> Result<u32, Error> {
+    let storage = config::storage()?;
+    put_to(s3, &storage.bucket, &storage.key(key), body.into()).await
+}
+
+/// Server-side encryption for a write: SSE-KMS with the configured key, or nothing
+/// so the bucket's default applies.
+fn encryption(storage: &Storage) -> (Option<ServerSideEncryption>, Option<String>) {
+    match &storage.kms_key {
+        Some(key) => (Some(ServerSideEncryption::AwsKms), Some(key.clone())),
+        None => (None, None),
+    }
+}
+
+/// Retries clone `body`, which for `Bytes` is a refcount bump rather than a copy.
+async fn put_to(s3: &Client, bucket: &str, key: &str, body: Bytes) -> Result<u32, Error> {
+    let storage = config::storage()?;
+    let (sse, kms_key) = encryption(storage);
+    let class = storage.storage_class(storage.relative(key)).map(StorageClass::from);
+    let (result, retries) = RETRY.run(
+        || s3.put_object()
+            .bucket(bucket)
+            .key(key)
+            .set_server_side_encryption(sse.clone())
+            .set_ssekms_key_id(kms_key.clone())
+            .set_storage_class(class.clone())
+            .body(body.clone().into())
+            .send(),
+        retry::is_transient,
+    ).await;
+    result?;
+    Ok(retries)
+}
+
+/// Uploads account data to the main bucket encrypted with KMS (`PRIVATE_KMS_KEY_ID`,
+/// `S3_KMS_KEY_ARN` or the bucket's AWS managed key). It skips the write-ahead spill and dead letter
+/// bucket so it never lands anywhere unencrypted; a failed put is an error.
+pub async fn put_private(s3: &Client, key: &str, body: impl Into<Bytes>) -> Result<u32, Error> {
+    let storage = config::storage()?;
+    let kms_key = config::var("PRIVATE_KMS_KEY_ID").or_else(|| storage.kms_key.clone());
+    let (key, body) = (storage.key(key), body.into());
+    let (result, retries) = RETRY.run(
+        || s3.put_object()
+            .bucket(&storage.bucket)
+            .key(&key)
+            .server_side_encryption(ServerSideEncryption::AwsKms)
+            .set_ssekms_key_id(kms_key.clone())
+            .body(body.clone().into())
+            .send(),
+        retry::is_transient,
+    ).await;
+    result?;
+    Ok(retries)
+}
+
+/// Creates an empty object at `key` unless one exists, returning whether this call
+/// created it. Of several writers racing for the same key exactly one gets `true`.
+pub async fn claim(s3: &Client, key: &str) -> Result<bool, Error> {
+    let storage = config::storage()?;
+    let key = storage.key(key);
+    let (sse, kms_key) = encryption(storage);
+    let (result, _) = RETRY.run(
+        || s3.put_object()
+            .bucket(&storage.bucket)
+            .key(&key)
+            .set_server_side_encryption(sse.clone())
+            .set_ssekms_key_id(kms_key.clone())
+            .if_none_match("*")
+            .send(),
+        retry::is_transient,
+    ).await;
+    match result {
+        Ok(_) => Ok(true),
+        // 412 when the object exists, 409 when a concurrent claim is still in flight
+        Err(e) if matches!(e.raw_response().map(|r| r.status().as_u16()), Some(409 | 412)) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// How a conditional put went.
+enum Conditional {
+    Written(u32),
+    /// The key was taken, by an object tagged with this `book_id` if it's readable
+    /// yet; `None` while another write of it is still in flight.
+    Taken { id: Option<String>, retries: u32 },
+}
+
+/// `put`, but only if nothing is at `key`, tagging the object with `id`.
+async fn put_if_absent(s3: &Client, key: &str, body: Bytes, id: &str) -> Result<Conditional, Error> {
+    let storage = config::storage()?;
+    let key = storage.key(key);
+    let (sse, kms_key) = encryption(storage);
+    let class = storage.storage_class(storage.relative(&key)).map(StorageClass::from);
+    let (result, retries) = RETRY.run(
+        || s3.put_object()
+            .bucket(&storage.bucket)
+            .key(&key)
+            .set_server_side_encryption(sse.clone())
+            .set_ssekms_key_id(kms_key.clone())
+            .set_storage_class(class.clone())
+            .metadata(BOOK_ID, id)
+            .if_none_match("*")
+            .body(body.clone().into())
+            .send(),
+        retry::is_transient,
+    ).await;
+    match result {
+        Ok(_) => Ok(Conditional::Written(retries)),
+        Err(e) => match e.raw_response().map(|r| r.status().as_u16()) {
+            Some(409) => Ok(Conditional::Taken { id: None, retries }),
+            Some(412) => {
+                let (head, head_retries) = RETRY.run(
+                    || s3.head_object().bucket(&storage.bucket).key(&key).send(),
+                    retry::is_transient,
+                ).await;
+                let id = head?.metadata().and_then(|m| m.get(BOOK_ID)).cloned();
+                Ok(Conditional::Taken { id, retries: retries + head_retries })
+            }
+            _ => Err(e.into()),
+        },
+    }
+}
+
+async fn exists(s3: &Client, bucket: &str, key: &str) -> Result<bool, Error> {
+    match s3.head_object().bucket(bucket).key(key).send().await {
+        Ok(_) => Ok(true),
+        Err(e) if e.as_service_error().is_some_and(|e| e.is_not_found()) => Ok(false),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// One page of a listing under the retry policy.
+pub async fn list_page(s3: &Client, bucket: &str, prefix: &str, token: Option<String>) -> Result<ListObjectsV2Output, Error> {
+    let (result, _) = RETRY.run(
+        || s3.list_objects_v2().bucket(bucket).prefix(prefix).set_continuation_token(token.clone()).send(),
+        retry::is_transient,
+    ).await;
+    Ok(result?)
+}
+
+/// Every main bucket key under `prefix`, following continuation tokens.
+pub async fn list_keys(s3: &Client, prefix: &str) -> Result<Vec<String>, Error> {
+    let storage = config::storage()?;
+    let mut keys = Vec::new();
+    let mut token = None;
+    loop {
+        let page = list_page(s3, &storage.bucket, &storage.key(prefix), token).await?;
+        keys.extend(page.contents().iter().filter_map(|o| o.key().map(|k| storage.relative(k).to_string())));
+        token = page.next_continuation_token().map(str::to_string);
+        if token.is_none() {
+            return Ok(keys);
+        }
+    }
+}
+
+/// Names of the main bucket "directories" directly under `prefix`: the common
+/// prefixes of a `/`-delimited listing, without `prefix` or the trailing slash.
+pub async fn list_dirs(s3: &Client, prefix: &str) -> Result<Vec<String>, Error> {
+    let storage = config::storage()?;
+    let full = storage.key(prefix);
+    let mut dirs = Vec::new();
+    let mut token = None;
+    loop {
+        let (result, _) = RETRY.run(
+            || s3.list_objects_v2().bucket(&storage.bucket).prefix(&full).delimiter("/").set_continuation_token(token.clone()).send(),
+            retry::is_transient,
+        ).await;
+        let page = result?;
+        dirs.extend(page.common_prefixes().iter()
+            .filter_map(|p| p.prefix()?.strip_prefix(full.as_str())?.strip_suffix('/').map(str::to_string)));
+        token = page.next_continuation_token().map(str::to_string);
+        if token.is_none() {
+            return Ok(dirs);
+        }
+    }
+}
+
+/// Downloads an object from the main bucket under the retry policy.
+pub async fn get(s3: &Client, key: &str) -> Result<Vec<u8>, Error> {
+    let storage = config::storage()?;
+    let key = storage.key(key);
+    let (result, _) = RETRY.run(|| s3.get_object().bucket(&storage.bucket).key(&key).send(), retry::is_transient).await;
+    Ok(result?.body.collect().await?.into_bytes().to_vec())
+}
+
+/// Deletes keys from the main bucket, 1000 per request (the S3 limit). Keys S3
+/// reports it couldn't delete are returned together as `Error::Delete` once every
+/// request has been sent.
+pub async fn delete_keys(s3: &Client, keys: &[String]) -> Result<(), Error> {
+    let storage = config::storage()?;
+    let mut failed = Vec::new();
+    for chunk in keys.chunks(1000) {
+        let objects = chunk.iter()
+            .map(|k| ObjectIdentifier::builder().key(storage.key(k)).build())
+            .collect::<Result<Vec<_>, _>>()?;
+        let output = s3.delete_objects()
+            .bucket(&storage.bucket)
+            .delete(Delete::builder().set_objects(Some(objects)).quiet(true).build()?)
+            .send()
+            .await?;
+        for error in output.errors() {
+            let key = error.key().map_or_else(|| "?".to_string(), |k| storage.relative(k).to_string());
+            warn!(key, code = error.code(), message = error.message(), "delete failed");
+            failed.push(key);
+        }
+    }
+    if !failed.is_empty() {
+        return Err(Error::Delete(failed));
+    }
+    Ok(())
+}
+
+/// Encodes `records` and uploads them as one object. The batch is spilled to the
+/// write-ahead directory first and only removed from it once delivered.
+#[instrument(skip(s3, schema, records), fields(batch_size = records.len()))]
+pub async fn write<T: Serialize>(s3: &Client, key: &str, schema: &str, records: &[T]) -> Result<Delivery, Error> {
+    write_bytes(s3, key, &encode(schema, records)?).await
+}
+
+/// `write` for an already encoded body, with the same spill and dead letter handling.
+pub async fn write_bytes(s3: &Client, key: &str, body: &[u8]) -> Result<Delivery, Error> {
+    let entry = WAL.append(key, None, body)
+        .inspect_err(|e| warn!(error = %e, "write-ahead spill failed, uploading without it"))
+        .ok();
+
+    let delivery = deliver(s3, key, Bytes::copy_from_slice(body)).await?;
+    if let Some(path) = entry {
+        if let Err(e) = WAL.remove(&path) {
+            warn!(error = %e, "failed to clear write-ahead entry");
+        }
+    }
+    Ok(delivery)
+}
+
+/// `write_bytes` with a conditional put: a key already holding book `id` is a
+/// `Delivery::Duplicate`, and one holding a different book dead-letters this one
+/// rather than overwrite it.
+pub async fn write_bytes_once(s3: &Client, key: &str, body: &[u8], id: &str) -> Result<Delivery, Error> {
+    let entry = WAL.append(key, Some(id), body)
+        .inspect_err(|e| warn!(error = %e, "write-ahead spill failed, uploading without it"))
+        .ok();
+
+    let delivery = deliver_once(s3, key, Bytes::copy_from_slice(body), id).await?;
+    if let Some(path) = entry {
+        if let Err(e) = WAL.remove(&path) {
+            warn!(error = %e, "failed to clear write-ahead entry");
+        }
+    }
+    Ok(delivery)
+}
+
+/// Uploads batches a previous run spilled but never delivered, conditionally for those
+/// first written that way. Returns how many were recovered.
+pub async fn recover_spilled(s3: &Client) -> Result<usize, Error> {
+    let mut recovered = 0;
+    for entry in WAL.pending()? {
+        match &entry.id {
+            Some(id) => deliver_once(s3, &entry.key, entry.body.into(), id).await?,
+            None => deliver(s3, &entry.key, entry.body.into()).await?,
+        };
+        WAL.remove(&entry.path)?;
+        info!(key = entry.key, "recovered spilled batch");
+        recovered += 1;
+    }
+    Ok(recovered)
+}
+
+/// Puts `body` at `key`; if that fails it goes to the dead letter bucket instead,
+/// and only losing it there is an error.
+async fn deliver(s3: &Client, key: &str, body: Bytes) -> Result<Delivery, Error> {
+    let bytes = body.len();
+    let started = Instant::now();
+
+    match put(s3, key, body.clone()).await {
+        Ok(retries) => {
+            let latency = started.elapsed();
+            info!(key, bytes, retries, latency_ms = latency.as_millis() as u64, "uploaded");
+            Ok(Delivery::Stored { latency, retries })
+        }
+        Err(e) => dead_letter(s3, key, body, e).await,
+    }
+}
+
+async fn deliver_once(s3: &Client, key: &str, body: Bytes, id: &str) -> Result<Delivery, Error> {
+    let bytes = body.len();
+    let started = Instant::now();
+
+    let retries = match put_if_absent(s3, key, body.clone(), id).await {
+        Ok(Conditional::Written(retries)) => retries,
+        // a retry finding its own book means an earlier attempt landed after all
+        Ok(Conditional::Taken { id: Some(taken), retries }) if taken == id && retries > 0 => retries,
+        Ok(Conditional::Taken { id: taken, .. }) if taken.as_deref().is_none_or(|taken| taken == id) => {
+            debug!(key, id, "already stored");
+            return Ok(Delivery::Duplicate);
+        }
+        Ok(Conditional::Taken { id: taken, .. }) => {
+            let e = Error::Conflict(format!("{} holds {}, not {}", key, taken.unwrap_or_default(), id));
+            return dead_letter(s3, key, body, e).await;
+        }
+        Err(e) => return dead_letter(s3, key, body, e).await,
+    };
+    let latency = started.elapsed();
+    info!(key, bytes, retries, latency_ms = latency.as_millis() as u64, "uploaded");
+    Ok(Delivery::Stored { latency, retries })
+}
+
+/// Parks `body` in the dead letter bucket after its main put failed with `e`; only
+/// losing it there too is an error.
+async fn dead_letter(s3: &Client, key: &str, body: Bytes, e: Error) -> Result<Delivery, Error> {
+    warn!(key, error = %e, "upload failed, dead-lettering batch");
+    let dlq_key = config::storage()?.key(&format!("{}{}", DLQ_PREFIX, key));
+    if let Err(dlq_err) = put_to(s3, &dlq_bucket()?, &dlq_key, body).await {
+        error!(key, error = %dlq_err, "dead letter write failed, batch lost");
+        return Err(e);
+    }
+    Ok(Delivery::DeadLettered)
+}
+
+/// Moves every dead-lettered object back to its original key in the main bucket.
+/// Returns the number of objects replayed.
+pub async fn drain_dead_letters(s3: &Client) -> Result<usize, Error> {
+    let storage = config::storage()?;
+    let bucket = dlq_bucket()?;
+    let dlq_prefix = storage.key(DLQ_PREFIX);
+    let (sse, kms_key) = encryption(storage);
+    let mut token = None;
+    let mut replayed = 0;
+
+    loop {
+        let page = list_page(s3, &bucket, &dlq_prefix, token).await?;
+        for obj in page.contents() {
+            let Some(key) = obj.key() else { continue };
+            let target = storage.key(&key[dlq_prefix.len()..]);
+            // with conditional writes, a key already holding a book is never overwritten
+            if idempotent() && exists(s3, &storage.bucket, &target).await? {
+                warn!(key = target, "main bucket already holds the key, leaving the dead letter");
+                continue;
+            }
+
+            s3.copy_object()
+                .copy_source(format!("{}/{}", bucket, key))
+                .bucket(&storage.bucket)
+                .key(&target)
+                .set_server_side_encryption(sse.clone())
+                .set_ssekms_key_id(kms_key.clone())
+                .set_storage_class(storage.storage_class(&key[dlq_prefix.len()..]).map(StorageClass::from))
+                .send()
+                .await?;
+            s3.delete_object().bucket(&bucket).key(key).send().await?;
+
+            info!(key = target, "replayed dead letter");
+            replayed += 1;
+        }
+
+        token = page.next_continuation_token().map(str::to_string);
+        if token.is_none() {
+            return Ok(replayed);
+        }
+    }
+}
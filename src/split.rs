@@ -0,0 +1,120 @@
+//! Split output: each stored book also written as two records with schemas of their
+//! own. `books/` holds what the exchange sent (levels in fixed point and update ids)
+//! and `features/` what was derived from it, so new features change only the
+//! features schema and readers of the raw records never see them.
+
+use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
+
+use crate::book::{self, ExactLevel, Level};
+use crate::{config, OrderBook};
+
+pub const RAW_BOOK_PREFIX: &str = "books";
+pub const FEATURES_PREFIX: &str = "features";
+
+/// Version of the `Features` schema, raised whenever it gains a field.
+pub const FEATURES_VERSION: i32 = 1;
+
+/// Whether `SPLIT_OUTPUT=1` asks for the split records; read once per process.
+pub fn enabled() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+
+    *ON.get_or_init(|| config::var("SPLIT_OUTPUT").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")))
+}
+
+/// The exchange's levels for one book, best first, up to 20 a side.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RawBook {
+    pub timestamp_ms: i64,
+    pub event_time_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub last_update_id: i64,
+    /// Decimal places of the prices and quantities.
+    pub price_scale: i32,
+    pub qty_scale: i32,
+    pub bids: Vec<ExactLevel>,
+    pub asks: Vec<ExactLevel>,
+}
+
+/// Everything computed from one book, joined to its `RawBook` by `symbol` and
+/// `timestamp_ms` (or `last_update_id`).
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Features {
+    pub timestamp_ms: i64,
+    pub event_time_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub last_update_id: i64,
+    pub features_version: i32,
+    pub mid_price: f64,
+    pub spread: f64,
+    pub spread_bps: f64,
+    pub tick_size: f64,
+    pub spread_in_ticks: f64,
+    pub imbalance_ratio: f64,
+    /// Cumulative depth per bucket, as `OrderBook::bids` and `asks`.
+    pub bid_depth: Vec<Level>,
+    pub ask_depth: Vec<Level>,
+    pub bid_notional: Vec<f64>,
+    pub ask_notional: Vec<f64>,
+    pub bid_sweeps: Vec<Level>,
+    pub ask_sweeps: Vec<Level>,
+    pub bid_fill_prices: Vec<f64>,
+    pub ask_fill_prices: Vec<f64>,
+    pub bid_slippage_bps: Vec<f64>,
+    pub ask_slippage_bps: Vec<f64>,
+    pub vpin: f64,
+    pub vpin_buckets: i32,
+}
+
+/// The split records of `book`. Its fixed-point levels move into the `RawBook`
+/// unless `DECIMAL_PRICES` keeps them on the book too.
+pub fn records(book: &mut OrderBook) -> (RawBook, Features) {
+    let (bids, asks) = if book::decimal_prices() {
+        (book.exact_bids.clone(), book.exact_asks.clone())
+    } else {
+        (std::mem::take(&mut book.exact_bids), std::mem::take(&mut book.exact_asks))
+    };
+    let raw = RawBook {
+        timestamp_ms: book.timestamp_ms,
+        event_time_ms: book.event_time_ms,
+        exchange: book.exchange.clone(),
+        symbol: book.symbol.clone(),
+        last_update_id: book.last_update_id,
+        price_scale: book.price_scale,
+        qty_scale: book.qty_scale,
+        bids,
+        asks,
+    };
+    if !book::decimal_prices() {
+        (book.price_scale, book.qty_scale) = (0, 0);
+    }
+    let features = Features {
+        timestamp_ms: book.timestamp_ms,
+        event_time_ms: book.event_time_ms,
+        exchange: book.exchange.clone(),
+        symbol: book.symbol.clone(),
+        last_update_id: book.last_update_id,
+        features_version: FEATURES_VERSION,
+        mid_price: book.mid_price,
+        spread: book.spread,
+        spread_bps: book.spread_bps,
+        tick_size: book.tick_size,
+        spread_in_ticks: book.spread_in_ticks,
+        imbalance_ratio: book.imbalance_ratio,
+        bid_depth: book.bids.clone(),
+        ask_depth: book.asks.clone(),
+        bid_notional: book.bid_notional.clone(),
+        ask_notional: book.ask_notional.clone(),
+        bid_sweeps: book.bid_sweeps.clone(),
+        ask_sweeps: book.ask_sweeps.clone(),
+        bid_fill_prices: book.bid_fill_prices.clone(),
+        ask_fill_prices: book.ask_fill_prices.clone(),
+        bid_slippage_bps: book.bid_slippage_bps.clone(),
+        ask_slippage_bps: book.ask_slippage_bps.clone(),
+        vpin: book.vpin,
+        vpin_buckets: book.vpin_buckets,
+    };
+    (raw, features)
+}
@@ -0,0 +1,123 @@
+//! Cross-venue spreads: for every pair of venues at each aligned instant (see
+//! `consolidate::align`), the signed difference between their mids and what buying
+//! on one and selling on the other would net after fees at configured sizes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::book::Level;
+use crate::OrderBook;
+
+pub const SPREADS_PREFIX: &str = "spreads";
+
+/// Taker fee assumed for venues without one of their own.
+pub const DEFAULT_FEE_BPS: f64 = 10.0;
+
+/// Buying `size` on one venue and selling it on the other at once.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Arb {
+    /// In the base asset.
+    pub size: f64,
+    pub buy_venue: String,
+    pub sell_venue: String,
+    /// Average fill prices, walking each book's depth buckets.
+    pub buy_price: f64,
+    pub sell_price: f64,
+    /// Net of both venues' fees, in basis points of the buy price; negative when
+    /// there is nothing to take.
+    pub edge_bps: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenueSpread {
+    pub timestamp_ms: i64,
+    pub symbol: String,
+    /// The pair, in name order.
+    pub venue_a: String,
+    pub venue_b: String,
+    /// `venue_a`'s mid minus `venue_b`'s.
+    pub mid_diff: f64,
+    /// `mid_diff` in basis points of the pair's average mid.
+    pub mid_diff_bps: f64,
+    /// The better direction at each size. Sizes beyond either book's depth buckets
+    /// are left out.
+    pub arbs: Vec<Arb>,
+}
+
+/// Taker fees per venue in basis points, with a default for the rest.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fees {
+    pub default_bps: f64,
+    pub venues: BTreeMap<String, f64>,
+}
+
+impl Default for Fees {
+    fn default() -> Self {
+        Fees { default_bps: DEFAULT_FEE_BPS, venues: BTreeMap::new() }
+    }
+}
+
+impl Fees {
+    pub fn bps(&self, venue: &str) -> f64 {
+        self.venues.get(venue).copied().unwrap_or(self.default_bps)
+    }
+}
+
+/// Average price of taking `size` from cumulative depth buckets, nearest first, with
+/// each bucket's volume priced at its far edge. `None` when the buckets hold less.
+pub fn fill_price(buckets: &[Level], size: f64) -> Option<f64> {
+    let (mut filled, mut cost, mut below) = (0.0, 0.0, 0.0);
+    for bucket in buckets {
+        let take = (bucket.qty - below).min(size - filled).max(0.0);
+        filled += take;
+        cost += take * bucket.price;
+        below = bucket.qty;
+        if filled >= size {
+            return (size > 0.0).then(|| cost / size);
+        }
+    }
+    None
+}
+
+/// One record per pair of `books`, all aligned at `at_ms`.
+pub fn spreads(symbol: &str, at_ms: i64, books: &[(&str, &OrderBook)], sizes: &[f64], fees: &Fees) -> Vec<VenueSpread> {
+    let mut pairs = Vec::new();
+    for (i, &(venue_a, a)) in books.iter().enumerate() {
+        for &(venue_b, b) in &books[i + 1..] {
+            let ((venue_a, a), (venue_b, b)) = if venue_a <= venue_b { ((venue_a, a), (venue_b, b)) } else { ((venue_b, b), (venue_a, a)) };
+            let mid_diff = a.mid_price - b.mid_price;
+            pairs.push(VenueSpread {
+                timestamp_ms: at_ms,
+                symbol: symbol.to_uppercase(),
+                venue_a: venue_a.to_string(),
+                venue_b: venue_b.to_string(),
+                mid_diff,
+                mid_diff_bps: mid_diff / ((a.mid_price + b.mid_price) / 2.0) * 10_000.0,
+                arbs: sizes.iter().filter_map(|&size| {
+                    let one_way = arb(size, (venue_a, a), (venue_b, b), fees);
+                    let other_way = arb(size, (venue_b, b), (venue_a, a), fees);
+                    match (one_way, other_way) {
+                        (Some(x), Some(y)) => Some(if x.edge_bps >= y.edge_bps { x } else { y }),
+                        (x, y) => x.or(y),
+                    }
+                }).collect(),
+            });
+        }
+    }
+    pairs
+}
+
+/// Buying `size` on `buy` and selling it on `sell`.
+fn arb(size: f64, (buy_venue, buy): (&str, &OrderBook), (sell_venue, sell): (&str, &OrderBook), fees: &Fees) -> Option<Arb> {
+    let buy_price = fill_price(&buy.asks, size)?;
+    let sell_price = fill_price(&sell.bids, size)?;
+    let net = sell_price * (1.0 - fees.bps(sell_venue) / 10_000.0) - buy_price * (1.0 + fees.bps(buy_venue) / 10_000.0);
+    Some(Arb {
+        size,
+        buy_venue: buy_venue.to_string(),
+        sell_venue: sell_venue.to_string(),
+        buy_price,
+        sell_price,
+        edge_bps: net / buy_price * 10_000.0,
+    })
+}
@@ -0,0 +1,98 @@
+//! The stages a collector's connection runs as, so a slow upload doesn't stall the
+//! socket: a reader task takes frames off the websocket as they arrive and stamps
+//! them, a compute task runs them through the pipeline, and the collector stores the
+//! books. Bounded channels of `INGEST_BUFFER` frames (default 1024) join them. While
+//! uploads are behind the frames queue up, and only a full queue pauses reading,
+//! which TCP passes back to the server as backpressure.
+
+use chrono::Utc;
+use futures_util::{Stream, StreamExt};
+use tokio::sync::mpsc::{self, error::TrySendError};
+use tokio_tungstenite::tungstenite::{self, Message};
+use tracing::warn;
+
+use crate::config;
+use crate::pipeline::{self, Outcome};
+
+pub const DEFAULT_BUFFER: usize = 1024;
+
+/// A text frame as it came off the socket.
+#[derive(Debug)]
+pub struct Received {
+    /// When the reader took it off the socket, however long it then queued.
+    pub received_ms: i64,
+    /// The connection it came in on, 0 when it didn't come from a reader.
+    pub connection: u64,
+    pub text: String,
+    /// The pipeline's result, once the compute stage has run; quotes are left for
+    /// the collector.
+    pub outcome: Option<Outcome>,
+}
+
+impl Received {
+    pub fn new(received_ms: i64, text: String) -> Self {
+        Received { received_ms, connection: 0, text, outcome: None }
+    }
+}
+
+#[derive(Debug)]
+pub enum Frame {
+    Text(Received),
+    /// The connection numbered so ended; it sent nothing after.
+    Closed { connection: u64 },
+}
+
+/// `INGEST_BUFFER`, or 1024 frames: at 10 a second, over a minute and a half of
+/// uploads falling behind before reading pauses.
+pub fn buffer() -> usize {
+    config::var("INGEST_BUFFER").and_then(|n| n.parse().ok()).filter(|&n| n > 0).unwrap_or(DEFAULT_BUFFER)
+}
+
+/// Reads text frames from `socket` into `tx` until the connection ends or fails, then
+/// sends `Closed`. Stops early once nothing receives.
+pub async fn read<S>(connection: u64, mut socket: S, tx: mpsc::Sender<Frame>)
+where
+    S: Stream<Item = Result<Message, tungstenite::Error>> + Unpin,
+{
+    let mut full = false;
+    while let Some(msg) = socket.next().await {
+        let text = match msg {
+            Ok(Message::Text(text)) => text,
+            Ok(_) => continue,
+            Err(e) => {
+                warn!(error = %e, "websocket error");
+                break;
+            }
+        };
+        let frame = Frame::Text(Received { connection, ..Received::new(Utc::now().timestamp_millis(), text) });
+        match tx.try_send(frame) {
+            Ok(()) => full = false,
+            Err(TrySendError::Full(frame)) => {
+                if !full {
+                    warn!(connection, capacity = tx.max_capacity(), "ingest buffer full, reading paused until uploads catch up");
+                    full = true;
+                }
+                if tx.send(frame).await.is_err() {
+                    return;
+                }
+            }
+            Err(TrySendError::Closed(_)) => return,
+        }
+    }
+    tx.send(Frame::Closed { connection }).await.ok();
+}
+
+/// Runs each depth frame from `rx` through the pipeline at `version` and passes every
+/// frame on, in order, to `tx`. Quote frames (`quotes`) go on as they are.
+pub async fn compute(mut rx: mpsc::Receiver<Frame>, tx: mpsc::Sender<Frame>, version: i32, quotes: bool) {
+    while let Some(mut frame) = rx.recv().await {
+        if let Frame::Text(received) = &mut frame {
+            if !quotes {
+                received.outcome = Some(pipeline::process(&received.text, received.received_ms, version));
+            }
+        }
+        if tx.send(frame).await.is_err() {
+            return;
+        }
+    }
+}
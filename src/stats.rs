@@ -0,0 +1,112 @@
+//! Hourly statistics per symbol, written under `stats/` with `HOURLY_STATS=1`: a
+//! record per hour of stored books, so data quality over months of data reads a
+//! record an hour rather than every book.
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config, gaps, OrderBook};
+
+pub const STATS_PREFIX: &str = "stats";
+
+const HOUR_MS: i64 = 3_600_000;
+
+/// Whether `HOURLY_STATS` asks for hourly statistics.
+pub fn enabled() -> bool {
+    config::var("HOURLY_STATS").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// An hour of stored books, or the part of it one run stored. `gap_ms` is the time
+/// between consecutive books further apart than `GAP_THRESHOLD_MS`.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct HourlyStats {
+    pub hour_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    pub first_ms: i64,
+    pub last_ms: i64,
+    pub snapshots: i64,
+    pub mean_spread: f64,
+    pub min_spread: f64,
+    pub max_spread: f64,
+    pub open_mid: f64,
+    pub high_mid: f64,
+    pub low_mid: f64,
+    pub close_mid: f64,
+    pub gap_ms: i64,
+}
+
+impl HourlyStats {
+    fn open(hour_ms: i64, book: &OrderBook) -> Self {
+        let at = book.partition_time_ms();
+        HourlyStats {
+            hour_ms,
+            exchange: book.exchange.clone(),
+            symbol: book.symbol.clone(),
+            first_ms: at,
+            last_ms: at,
+            snapshots: 1,
+            mean_spread: book.spread,
+            min_spread: book.spread,
+            max_spread: book.spread,
+            open_mid: book.mid_price,
+            high_mid: book.mid_price,
+            low_mid: book.mid_price,
+            close_mid: book.mid_price,
+            gap_ms: 0,
+        }
+    }
+
+    fn add(&mut self, book: &OrderBook, threshold_ms: i64) {
+        let at = book.partition_time_ms();
+        let since = at - self.last_ms;
+        if since > threshold_ms {
+            self.gap_ms += since;
+        }
+        self.last_ms = self.last_ms.max(at);
+        self.snapshots += 1;
+        self.mean_spread += (book.spread - self.mean_spread) / self.snapshots as f64;
+        self.min_spread = self.min_spread.min(book.spread);
+        self.max_spread = self.max_spread.max(book.spread);
+        self.high_mid = self.high_mid.max(book.mid_price);
+        self.low_mid = self.low_mid.min(book.mid_price);
+        self.close_mid = book.mid_price;
+    }
+}
+
+/// Folds stored books into the statistics for their hour (by `partition_time_ms`).
+#[derive(Debug)]
+pub struct StatsBuilder {
+    threshold_ms: i64,
+    current: Option<HourlyStats>,
+}
+
+impl Default for StatsBuilder {
+    fn default() -> Self {
+        StatsBuilder { threshold_ms: gaps::threshold_ms(), current: None }
+    }
+}
+
+impl StatsBuilder {
+    /// Adds a book, returning the previous hour's statistics once the hour rolls over.
+    pub fn push(&mut self, book: &OrderBook) -> Option<HourlyStats> {
+        let at = book.partition_time_ms();
+        let hour_ms = at - at.rem_euclid(HOUR_MS);
+        match self.current.as_mut() {
+            Some(stats) if stats.hour_ms == hour_ms => {
+                stats.add(book, self.threshold_ms);
+                None
+            }
+            _ => self.current.replace(HourlyStats::open(hour_ms, book)),
+        }
+    }
+
+    /// Takes the hour in progress once it's over.
+    pub fn flush_ended(&mut self, now_ms: i64) -> Option<HourlyStats> {
+        self.current.take_if(|stats| now_ms - stats.hour_ms >= HOUR_MS)
+    }
+
+    /// Takes the hour in progress, if any.
+    pub fn flush(&mut self) -> Option<HourlyStats> {
+        self.current.take()
+    }
+}
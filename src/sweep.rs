@@ -0,0 +1,142 @@
+//! Sweeps: aggressive orders that consume several price levels at once. With
+//! `SWEEPS=1` each symbol's trade stream is followed alongside its books. Binance
+//! aggregates an order's fills per price, so the trades of one taker order share a
+//! trade time and side and carry one price per level. The collector groups the
+//! trades since the previous book that way, and an order whose fills reach at least
+//! `SWEEP_MIN_LEVELS` prices at or through the previous book's best on that side
+//! flags the book, with its notional. The largest such order wins when there are
+//! several.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::binance::Venue;
+use crate::trades::{self, AggTrade};
+use crate::{config, Error, OrderBook};
+
+pub const DEFAULT_MIN_LEVELS: usize = 2;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+/// Trades held per symbol for its collector, dropping the oldest past this.
+const MAX_PENDING: usize = 10_000;
+
+/// Whether `SWEEPS=1` asks for sweep detection.
+pub fn enabled() -> bool {
+    config::var("SWEEPS").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// `SWEEP_MIN_LEVELS`, default 2.
+pub fn min_levels() -> usize {
+    config::var("SWEEP_MIN_LEVELS").and_then(|s| s.parse().ok()).filter(|&n| n > 0).unwrap_or(DEFAULT_MIN_LEVELS)
+}
+
+/// The largest sweep between two books.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Sweep {
+    /// `ask` for an aggressive buy, `bid` for an aggressive sell.
+    pub side: &'static str,
+    /// Prices the order filled at, at or through the previous best.
+    pub levels: usize,
+    /// Quote notional of the whole order.
+    pub notional: f64,
+}
+
+/// One symbol's best prices as of the previous book.
+#[derive(Debug, Clone)]
+pub struct Detector {
+    min_levels: usize,
+    best: Option<(f64, f64)>,
+}
+
+impl Detector {
+    pub fn new(min_levels: usize) -> Self {
+        Detector { min_levels: min_levels.max(1), best: None }
+    }
+
+    pub fn from_env() -> Self {
+        Self::new(min_levels())
+    }
+
+    /// Stamps the largest sweep among `trades`, those since the previous book, and
+    /// remembers `book`'s best prices for the next one. Nothing is flagged for a
+    /// run's first book, having no previous best to measure from.
+    pub fn flag(&mut self, book: OrderBook, trades: &[AggTrade]) -> OrderBook {
+        let sweep = self.best.and_then(|best| largest(trades, best, self.min_levels));
+        self.best = Some((book.best_bid(), book.best_ask()));
+        match sweep {
+            Some(sweep) => book.with_sweep(sweep.side, sweep.levels as i32, sweep.notional),
+            None => book,
+        }
+    }
+}
+
+/// The largest order in `trades` that swept `min_levels` or more prices from
+/// `(best_bid, best_ask)`.
+pub fn largest(trades: &[AggTrade], (best_bid, best_ask): (f64, f64), min_levels: usize) -> Option<Sweep> {
+    // (trade time, aggressive sell) -> (prices through the best, notional)
+    let mut orders: BTreeMap<(i64, bool), (Vec<f64>, f64)> = BTreeMap::new();
+    for trade in trades {
+        let (prices, notional) = orders.entry((trade.trade_time_ms, trade.is_buyer_maker)).or_default();
+        *notional += trade.price * trade.qty;
+        // a buyer-maker trade was an aggressive sell, hitting the bids
+        let through = if trade.is_buyer_maker { trade.price <= best_bid } else { trade.price >= best_ask };
+        if through && !prices.contains(&trade.price) {
+            prices.push(trade.price);
+        }
+    }
+    orders.into_iter()
+        .filter(|(_, (prices, notional))| prices.len() >= min_levels && notional.is_finite())
+        .map(|((_, sell), (prices, notional))| Sweep { side: if sell { "bid" } else { "ask" }, levels: prices.len(), notional })
+        .max_by(|a, b| a.notional.total_cmp(&b.notional))
+}
+
+/// A symbol's trades not yet taken, and the newest aggTrade id recorded.
+#[derive(Debug, Default)]
+struct Pending {
+    last_agg_id: Option<i64>,
+    trades: VecDeque<AggTrade>,
+}
+
+/// Per upper-cased symbol.
+static PENDING: LazyLock<Mutex<HashMap<String, Pending>>> = LazyLock::new(Default::default);
+
+/// Adds a trade for `symbol`'s collector to take, unless one with its aggTrade id or
+/// a later one was already recorded, e.g. by a second follower of the same stream.
+pub fn record(trade: AggTrade) {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    let pending = pending.entry(trade.symbol.to_uppercase()).or_default();
+    if pending.last_agg_id.is_some_and(|last| trade.agg_id <= last) {
+        return;
+    }
+    pending.last_agg_id = Some(trade.agg_id);
+    if pending.trades.len() == MAX_PENDING {
+        pending.trades.pop_front();
+    }
+    pending.trades.push_back(trade);
+}
+
+/// Takes `symbol`'s trades up to `until_ms`, leaving any later ones for the next book.
+pub fn take(symbol: &str, until_ms: i64) -> Vec<AggTrade> {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    let Some(pending) = pending.get_mut(&symbol.to_uppercase()) else { return Vec::new() };
+    let n = pending.trades.iter().take_while(|t| t.trade_time_ms <= until_ms).count();
+    pending.trades.drain(..n).collect()
+}
+
+/// Follows `symbol`'s trade stream for its collector until the task is dropped,
+/// reconnecting after a lost connection.
+pub async fn capture(venue: &Venue, symbol: String) -> Result<(), Error> {
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        match trades::follow(venue, &symbol, record).await {
+            Ok(()) => backoff = Duration::from_secs(1),
+            Err(e) => {
+                warn!(error = %e, symbol, "trade stream failed, reconnecting");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
@@ -0,0 +1,151 @@
+//! Top-of-book mode: only the best bid and ask, from Binance's `bookTicker` stream
+//! at its full update rate, with spread and micro-price. Quotes are much smaller and
+//! much more frequent than depth snapshots, so they're batched per minute into one
+//! object under `top/` instead of one object each. A minute two runs share is split
+//! across an object from each, which `merge` puts back together.
+
+use serde::{Deserialize, Serialize};
+
+use crate::binance::{self, parse_decimal};
+use crate::config;
+
+pub const TOP_PREFIX: &str = "top";
+
+const MINUTE_MS: i64 = 60_000;
+
+/// Whether `BOOK_MODE=top` asks for quotes instead of depth snapshots.
+pub fn enabled() -> bool {
+    config::var("BOOK_MODE").is_some_and(|m| m == "top")
+}
+
+/// Stream the collector subscribes to per symbol in this mode.
+pub fn stream(symbol: &str) -> String {
+    format!("{}@bookTicker", symbol.to_lowercase())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quote {
+    /// When the collector received the quote.
+    pub timestamp_ms: i64,
+    /// The exchange's event time, 0 on spot, which doesn't send one.
+    pub event_time_ms: i64,
+    pub exchange: String,
+    pub symbol: String,
+    /// The book's update id as of this quote.
+    pub update_id: i64,
+    pub bid_price: f64,
+    pub bid_qty: f64,
+    pub ask_price: f64,
+    pub ask_qty: f64,
+    pub spread: f64,
+    pub mid_price: f64,
+    /// Mid weighted towards the thinner side, where the price is more likely to go.
+    pub micro_price: f64,
+}
+
+impl Quote {
+    /// What partitions are keyed by, as for books.
+    pub fn partition_time_ms(&self) -> i64 {
+        if self.event_time_ms > 0 { self.event_time_ms } else { self.timestamp_ms }
+    }
+}
+
+/// A `bookTicker` payload, or the combined-stream envelope around one. Every field
+/// is optional so other messages, such as request replies, still parse.
+#[derive(Deserialize)]
+struct BookTicker<'a> {
+    #[serde(rename = "u")]
+    update_id: Option<i64>,
+    #[serde(rename = "E", default)]
+    event_time_ms: i64,
+    #[serde(rename = "s", default)]
+    symbol: &'a str,
+    #[serde(rename = "b")]
+    bid_price: Option<&'a str>,
+    #[serde(rename = "B")]
+    bid_qty: Option<&'a str>,
+    #[serde(rename = "a")]
+    ask_price: Option<&'a str>,
+    #[serde(rename = "A")]
+    ask_qty: Option<&'a str>,
+    #[serde(default, borrow)]
+    data: Option<Box<BookTicker<'a>>>,
+}
+
+/// The quote in `text` received at `received_ms`. `Ok(None)` for valid JSON that
+/// isn't a two-sided quote, such as request replies.
+pub fn parse(text: &str, received_ms: i64) -> Result<Option<Quote>, serde_json::Error> {
+    let mut ticker: BookTicker = serde_json::from_str(text)?;
+    if let Some(data) = ticker.data.take() {
+        ticker = *data;
+    }
+    let number = |s: Option<&str>| s.and_then(parse_decimal);
+    let (Some(update_id), Some(bid_price), Some(bid_qty), Some(ask_price), Some(ask_qty)) = (ticker.update_id,
+        number(ticker.bid_price), number(ticker.bid_qty), number(ticker.ask_price), number(ticker.ask_qty)) else {
+        return Ok(None);
+    };
+    if bid_price <= 0.0 || ask_price <= 0.0 {
+        return Ok(None);
+    }
+    let depth = bid_qty + ask_qty;
+    let mid_price = (bid_price + ask_price) / 2.0;
+    Ok(Some(Quote {
+        timestamp_ms: received_ms,
+        event_time_ms: ticker.event_time_ms,
+        exchange: binance::EXCHANGE.to_string(),
+        symbol: ticker.symbol.to_uppercase(),
+        update_id,
+        bid_price,
+        bid_qty,
+        ask_price,
+        ask_qty,
+        spread: ask_price - bid_price,
+        mid_price,
+        micro_price: if depth > 0.0 { (bid_price * ask_qty + ask_price * bid_qty) / depth } else { mid_price },
+    }))
+}
+
+/// The quotes of `parts`, the objects overlapping or successive runs wrote for the
+/// same minutes, in update order with a quote both runs stored kept once.
+pub fn merge(parts: impl IntoIterator<Item = Vec<Quote>>) -> Vec<Quote> {
+    let mut quotes: Vec<Quote> = parts.into_iter().flatten().collect();
+    quotes.sort_by_key(|q| (q.symbol.clone(), q.update_id, q.timestamp_ms));
+    quotes.dedup_by(|q, kept| q.symbol == kept.symbol && q.update_id == kept.update_id);
+    quotes.sort_by_key(|q| (q.partition_time_ms(), q.update_id));
+    quotes
+}
+
+/// Quotes of the current minute (by `partition_time_ms`).
+#[derive(Debug, Default)]
+pub struct QuoteBatcher {
+    minute_ms: i64,
+    quotes: Vec<Quote>,
+}
+
+impl QuoteBatcher {
+    /// Adds a quote, returning the previous minute's (start, quotes) once the minute rolls over.
+    pub fn push(&mut self, quote: Quote) -> Option<(i64, Vec<Quote>)> {
+        let at = quote.partition_time_ms();
+        let minute_ms = at - at.rem_euclid(MINUTE_MS);
+        let done = if minute_ms != self.minute_ms { self.flush() } else { None };
+        self.minute_ms = minute_ms;
+        self.quotes.push(quote);
+        done
+    }
+
+    /// `flush` once the buffered minute is over.
+    pub fn flush_ended(&mut self, now_ms: i64) -> Option<(i64, Vec<Quote>)> {
+        if now_ms - self.minute_ms < MINUTE_MS {
+            return None;
+        }
+        self.flush()
+    }
+
+    /// Takes whatever is buffered.
+    pub fn flush(&mut self) -> Option<(i64, Vec<Quote>)> {
+        if self.quotes.is_empty() {
+            return None;
+        }
+        Some((self.minute_ms, std::mem::take(&mut self.quotes)))
+    }
+}
@@ -0,0 +1,130 @@
+//! Aggregated trades, from REST history or the `@aggTrade` stream (both share field names).
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::archive::Archive;
+use crate::binance::{self, Venue};
+use crate::{cli, proxy, rest, schema, sink, Error};
+
+/// `source` of trades fetched after the fact rather than received live.
+pub const SOURCE_BACKFILL: &str = "backfill";
+pub const SOURCE_STREAM: &str = "stream";
+
+fn backfill_source() -> String {
+    SOURCE_BACKFILL.to_string()
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct AggTrade {
+    pub symbol: String,
+    pub agg_id: i64,
+    pub price: f64,
+    pub qty: f64,
+    pub first_trade_id: i64,
+    pub last_trade_id: i64,
+    pub trade_time_ms: i64,
+    pub is_buyer_maker: bool,
+    /// `stream` or `backfill`. Records written before the field existed came from
+    /// the backfill tool, so they read as `backfill`.
+    #[serde(default = "backfill_source")]
+    pub source: String,
+}
+
+impl AggTrade {
+    pub fn from_json(symbol: &str, v: &Value) -> Option<Self> {
+        Some(AggTrade {
+            symbol: symbol.to_string(),
+            agg_id: v["a"].as_i64()?,
+            price: v["p"].as_str()?.parse().ok()?,
+            qty: v["q"].as_str()?.parse().ok()?,
+            first_trade_id: v["f"].as_i64()?,
+            last_trade_id: v["l"].as_i64()?,
+            trade_time_ms: v["T"].as_i64()?,
+            is_buyer_maker: v["m"].as_bool()?,
+            source: SOURCE_STREAM.to_string(),
+        })
+    }
+}
+
+/// The live aggregated trade stream of `symbol`.
+pub fn stream(symbol: &str) -> String {
+    format!("{}@aggTrade", symbol.to_lowercase())
+}
+
+/// Passes each trade from `symbol`'s live stream to `on_trade` until the connection
+/// drops; other messages are skipped.
+pub async fn follow(venue: &Venue, symbol: &str, mut on_trade: impl FnMut(AggTrade)) -> Result<(), Error> {
+    let ws = proxy::connect(&format!("{}/{}", venue.ws, stream(symbol))).await?;
+    let (_, mut rx) = ws.split();
+    let symbol = symbol.to_uppercase();
+
+    while let Some(msg) = rx.next().await {
+        let msg = msg?;
+        if !msg.is_text() {
+            continue;
+        }
+        let Ok(v) = serde_json::from_str::<Value>(msg.to_text()?) else { continue };
+        if let Some(trade) = AggTrade::from_json(&symbol, &v) {
+            on_trade(trade);
+        }
+    }
+    Ok(())
+}
+
+/// Every aggregated trade in [from, to). Binance caps a time-bounded request at one
+/// hour and 1000 rows, so this walks the window hour by hour and pages by id within it.
+pub async fn fetch_agg_trades(symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<AggTrade>, Error> {
+    let mut trades: Vec<AggTrade> = Vec::new();
+    let weight = binance::venue()?.weight("aggTrades", 1000);
+    let mut start = from;
+    while start < to {
+        let end = (start + Duration::hours(1)).min(to);
+        let mut url = format!("{}?symbol={}&startTime={}&endTime={}&limit=1000",
+                              binance::venue()?.rest_url("aggTrades"), symbol, start.timestamp_millis(), end.timestamp_millis() - 1);
+        loop {
+            let page: Vec<Value> = rest::get(&url, weight).await?.error_for_status()?.json().await?;
+            let parsed: Vec<AggTrade> = page.iter()
+                .filter_map(|v| AggTrade::from_json(symbol, v))
+                .map(|t| AggTrade { source: SOURCE_BACKFILL.to_string(), ..t })
+                .collect();
+            let last = parsed.last().map(|t| (t.agg_id, t.trade_time_ms));
+            trades.extend(parsed);
+
+            match last {
+                Some((id, time)) if page.len() == 1000 && time < end.timestamp_millis() => {
+                    url = format!("{}?symbol={}&fromId={}&limit=1000", binance::venue()?.rest_url("aggTrades"), symbol, id + 1);
+                }
+                _ => break,
+            }
+        }
+        start = end;
+    }
+    Ok(window(trades, from.timestamp_millis(), to.timestamp_millis()))
+}
+
+/// `trades` traded in [from_ms, to_ms), each aggTrade once, in id order. Paging by
+/// `fromId` runs past the end of an hour, so the next hour fetches some trades again.
+pub fn window(mut trades: Vec<AggTrade>, from_ms: i64, to_ms: i64) -> Vec<AggTrade> {
+    trades.retain(|t| (from_ms..to_ms).contains(&t.trade_time_ms));
+    trades.sort_by_key(|t| t.agg_id);
+    trades.dedup_by_key(|t| t.agg_id);
+    trades
+}
+
+/// Fetches the trades of [from, to) into `trades/`, one object per hour keyed by its
+/// first trade, and returns each object's key and how many trades it holds.
+pub async fn backfill(archive: &Archive, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<(String, usize)>, Error> {
+    let mut written = Vec::new();
+    for hour in cli::hours(from, to) {
+        let trades = fetch_agg_trades(symbol, hour.max(from), (hour + Duration::hours(1)).min(to)).await?;
+        let Some(first) = trades.first() else { continue };
+
+        let key = sink::partition_key("trades", symbol, hour, first.trade_time_ms)?;
+        archive.put(&key, sink::encode(schema::AGG_TRADE, &trades)?).await?;
+        written.push((key, trades.len()));
+    }
+    Ok(written)
+}
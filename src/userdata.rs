@@ -0,0 +1,187 @@
+//! The account's own order and execution events from Binance's listenKey user data
+//! stream, archived under an encrypted `private/` prefix so strategy fills can be
+//! reconciled against the recorded book.
+
+use aws_sdk_s3::Client;
+use futures_util::StreamExt;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::time::Duration;
+use tracing::{info_span, warn, Instrument};
+
+use crate::auth::Credentials;
+use crate::binance::Venue;
+use crate::{proxy, rest, schema, sink, Error};
+
+pub const EXECUTIONS_PREFIX: &str = "private/executions";
+
+/// Binance expires a listenKey an hour after its last keepalive.
+const KEEPALIVE: Duration = Duration::from_secs(30 * 60);
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Execution {
+    pub event_time_ms: i64,
+    pub trade_time_ms: i64,
+    pub symbol: String,
+    pub client_order_id: String,
+    pub order_id: i64,
+    /// -1 for reports without a fill.
+    pub trade_id: i64,
+    pub side: String,
+    pub order_type: String,
+    /// `NEW`, `TRADE`, `CANCELED`, ...
+    pub execution_type: String,
+    pub order_status: String,
+    pub price: f64,
+    pub qty: f64,
+    pub last_price: f64,
+    pub last_qty: f64,
+    pub cumulative_qty: f64,
+    pub commission: f64,
+    pub commission_asset: String,
+    pub is_maker: bool,
+}
+
+impl Execution {
+    /// Maps a spot `executionReport` or futures `ORDER_TRADE_UPDATE` event; other
+    /// account events (balances, positions) are `None`.
+    pub fn from_event(v: &Value) -> Option<Self> {
+        let o = match v["e"].as_str()? {
+            "executionReport" => v,
+            "ORDER_TRADE_UPDATE" => &v["o"],
+            _ => return None,
+        };
+        let text = |k: &str| -> String { o[k].as_str().unwrap_or_default().to_string() };
+        let num = |k: &str| -> f64 { o[k].as_str().and_then(|x| x.parse().ok()).unwrap_or(0.0) };
+
+        Some(Execution {
+            event_time_ms: v["E"].as_i64().unwrap_or_default(),
+            trade_time_ms: o["T"].as_i64().unwrap_or_default(),
+            symbol: text("s"),
+            client_order_id: text("c"),
+            order_id: o["i"].as_i64().unwrap_or_default(),
+            trade_id: o["t"].as_i64().unwrap_or(-1),
+            side: text("S"),
+            order_type: text("o"),
+            execution_type: text("x"),
+            order_status: text("X"),
+            price: num("p"),
+            qty: num("q"),
+            last_price: num("L"),
+            last_qty: num("l"),
+            cumulative_qty: num("z"),
+            commission: num("n"),
+            commission_asset: text("N"),
+            is_maker: o["m"].as_bool().unwrap_or_default(),
+        })
+    }
+}
+
+/// Spot venues call it a user data stream, futures a listen key.
+fn listen_key_url(venue: &Venue) -> String {
+    venue.rest_url(if venue.futures { "listenKey" } else { "userDataStream" })
+}
+
+/// Opens a user data stream, returning its listenKey. Opening again while one is
+/// active returns the same key.
+pub async fn open_listen_key(creds: &Credentials, venue: &Venue) -> Result<String, Error> {
+    let request = rest::http().post(listen_key_url(venue)).header("X-MBX-APIKEY", &creds.api_key);
+    let body: Value = rest::send(request, venue.weight("userDataStream", 0)).await?
+        .error_for_status()?
+        .json().await?;
+    body["listenKey"].as_str()
+        .map(str::to_string)
+        .ok_or_else(|| Error::UserData(format!("no listenKey in {}", body)))
+}
+
+async fn keepalive(creds: &Credentials, venue: &Venue, listen_key: &str) -> Result<(), Error> {
+    let request = rest::http().put(listen_key_url(venue))
+        .header("X-MBX-APIKEY", &creds.api_key)
+        .query(&[("listenKey", listen_key)]);
+    rest::send(request, venue.weight("userDataStream", 0)).await?.error_for_status()?;
+    Ok(())
+}
+
+/// Key ids for events in the same millisecond, so each gets its own object.
+#[derive(Debug, Default)]
+pub struct EventIds {
+    last_ms: i64,
+    seq: i64,
+}
+
+impl EventIds {
+    /// `event_time_ms * 1000` plus the event's position within that millisecond.
+    pub fn next(&mut self, event_time_ms: i64) -> i64 {
+        if event_time_ms == self.last_ms {
+            self.seq += 1;
+        } else {
+            (self.last_ms, self.seq) = (event_time_ms, 0);
+        }
+        event_time_ms * 1000 + self.seq.min(999)
+    }
+}
+
+// Fills can't be fetched again from the stream, so each event is written as soon as it
+// arrives. A closed stream, expired listenKey or failed keepalive opens a new listenKey
+// and reconnects with backoff; a failed write is logged and the stream kept.
+pub async fn capture_user_data(s3: Client, creds: &Credentials, venue: &Venue) -> Result<(), Error> {
+    let mut ids = EventIds::default();
+    let mut backoff = Duration::from_secs(1);
+    loop {
+        match follow_user_data(&s3, creds, venue, &mut ids).await {
+            Ok(()) => {
+                warn!("user data stream closed, reconnecting");
+                backoff = Duration::from_secs(1);
+            }
+            Err(e) => {
+                warn!(error = %e, "user data stream failed, reconnecting");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
+
+/// One listenKey's stream, until it closes or fails.
+async fn follow_user_data(s3: &Client, creds: &Credentials, venue: &Venue, ids: &mut EventIds) -> Result<(), Error> {
+    let listen_key = open_listen_key(creds, venue).await?;
+    let ws = proxy::connect(&format!("{}/{}", venue.ws, listen_key)).await?;
+    let (_, mut rx) = ws.split();
+    let mut tick = tokio::time::interval_at(tokio::time::Instant::now() + KEEPALIVE, KEEPALIVE);
+
+    loop {
+        let msg = tokio::select! {
+            msg = rx.next() => match msg {
+                Some(msg) => msg?,
+                None => return Ok(()),
+            },
+            _ = tick.tick() => {
+                keepalive(creds, venue, &listen_key).await?;
+                continue;
+            }
+        };
+        if !msg.is_text() {
+            continue;
+        }
+        let v: Value = match serde_json::from_str(msg.to_text()?) {
+            Ok(v) => v,
+            Err(e) => {
+                warn!(error = %e, "skipping malformed user data event");
+                continue;
+            }
+        };
+        if v["e"] == "listenKeyExpired" {
+            return Err(Error::UserData("listenKey expired".into()));
+        }
+        let Some(execution) = Execution::from_event(&v) else { continue };
+
+        let id = ids.next(execution.event_time_ms);
+        let key = sink::partition_key(EXECUTIONS_PREFIX, &execution.symbol, sink::at_ms(execution.event_time_ms), id)?;
+        let span = info_span!("execution", symbol = %execution.symbol);
+        let body = sink::encode(schema::EXECUTION, &[execution])?;
+        if let Err(e) = sink::put_private(s3, &key, body).instrument(span).await {
+            warn!(error = %e, key, "failed to store execution");
+        }
+    }
+}
@@ -0,0 +1,115 @@
+//! Checks on books before they're stored, with `VALIDATE_BOOKS=1`. A book with a
+//! non-finite price or quantity, a quantity that isn't positive or an empty side, or
+//! whose mid jumped further than `VALIDATE_MAX_JUMP_PCT` from the last accepted book,
+//! isn't stored. Its payload goes under `quarantine/` instead, with the reason.
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::OnceLock;
+
+use crate::book::Level;
+use crate::{config, schema, sink, Error};
+
+pub const QUARANTINE_PREFIX: &str = "quarantine";
+
+pub const DEFAULT_MAX_JUMP_PCT: f64 = 10.0;
+
+/// Jumps in a row from the last accepted mid after which the new level is taken to
+/// be the market's, and accepted.
+const CONFIRM_JUMPS: u32 = 3;
+
+/// Whether `VALIDATE_BOOKS=1` asks for books to be checked; read once per process.
+pub fn enabled() -> bool {
+    static ON: OnceLock<bool> = OnceLock::new();
+
+    *ON.get_or_init(|| config::var("VALIDATE_BOOKS").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true")))
+}
+
+/// Why a book failed validation.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Reason {
+    NotFinite,
+    NonPositiveQty,
+    EmptySide,
+    PriceJump { from: f64, to: f64 },
+}
+
+impl fmt::Display for Reason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Reason::NotFinite => write!(f, "non-finite price or quantity"),
+            Reason::NonPositiveQty => write!(f, "non-positive quantity"),
+            Reason::EmptySide => write!(f, "empty side"),
+            Reason::PriceJump { from, to } => write!(f, "mid jumped from {} to {}", from, to),
+        }
+    }
+}
+
+/// What fails in a book's levels alone, before it's built.
+pub fn levels(bids: &[Level], asks: &[Level]) -> Option<Reason> {
+    let all = || bids.iter().chain(asks);
+    if bids.is_empty() || asks.is_empty() {
+        return Some(Reason::EmptySide);
+    }
+    if all().any(|l| !l.price.is_finite() || !l.qty.is_finite()) {
+        return Some(Reason::NotFinite);
+    }
+    if all().any(|l| l.qty <= 0.0) {
+        return Some(Reason::NonPositiveQty);
+    }
+    None
+}
+
+/// Follows the mid across a symbol's books to catch jumps.
+#[derive(Debug, Clone)]
+pub struct Validator {
+    max_jump: f64,
+    accepted_mid: Option<f64>,
+    jumps: u32,
+}
+
+impl Validator {
+    pub fn new(max_jump_pct: f64) -> Self {
+        Validator { max_jump: max_jump_pct / 100.0, accepted_mid: None, jumps: 0 }
+    }
+
+    /// `VALIDATE_MAX_JUMP_PCT`, or 10%.
+    pub fn from_env() -> Self {
+        let pct = config::var("VALIDATE_MAX_JUMP_PCT").and_then(|s| s.parse().ok()).filter(|&p: &f64| p > 0.0);
+        Self::new(pct.unwrap_or(DEFAULT_MAX_JUMP_PCT))
+    }
+
+    /// A `PriceJump` when `mid` is further from the last accepted mid than the limit,
+    /// unless it's the `CONFIRM_JUMPS`th such book in a row.
+    pub fn check(&mut self, mid: f64) -> Option<Reason> {
+        if let Some(from) = self.accepted_mid.filter(|&from| from > 0.0) {
+            if ((mid - from) / from).abs() > self.max_jump {
+                self.jumps += 1;
+                if self.jumps < CONFIRM_JUMPS {
+                    return Some(Reason::PriceJump { from, to: mid });
+                }
+            }
+        }
+        self.accepted_mid = Some(mid);
+        self.jumps = 0;
+        None
+    }
+}
+
+/// A payload that failed validation, as written under `quarantine/`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Quarantined {
+    /// Upper-cased.
+    pub symbol: String,
+    pub received_ms: i64,
+    pub reason: String,
+    pub payload: String,
+}
+
+impl Quarantined {
+    /// The key and encoded object, keyed by receive time like a book.
+    pub fn object(&self) -> Result<(String, Vec<u8>), Error> {
+        let key = sink::partition_key(QUARANTINE_PREFIX, &self.symbol, sink::at_ms(self.received_ms), self.received_ms)?;
+        Ok((key, sink::encode(schema::QUARANTINE, std::slice::from_ref(self))?))
+    }
+}
@@ -0,0 +1,187 @@
+//! Archive integrity checks for the `verify` tool: every object of a range of
+//! `orderbook/` partitions is decoded, its books' schema versions checked and their
+//! timestamps checked to only move forward, and every minute without a book is
+//! collected into holes. The report is JSON, so `repair` can work from it.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::archive::Archive;
+use crate::{cli, manifest, migrate, schema, sink, Error, OrderBook};
+
+const PREFIX: &str = "orderbook";
+const MINUTE_MS: i64 = 60_000;
+
+/// A run of minutes without a book, [from_ms, to_ms).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hole {
+    pub from_ms: i64,
+    pub to_ms: i64,
+}
+
+impl Hole {
+    pub fn minutes(&self) -> i64 {
+        (self.to_ms - self.from_ms) / MINUTE_MS
+    }
+}
+
+/// Something wrong with one object, as `kind: detail`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Problem {
+    pub key: String,
+    pub problem: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Report {
+    /// Upper-cased.
+    pub symbol: String,
+    pub from_ms: i64,
+    pub to_ms: i64,
+    pub files: usize,
+    pub books: usize,
+    pub minutes: i64,
+    pub covered_minutes: i64,
+    /// Start of every hour without a single book.
+    pub missing_hours: Vec<i64>,
+    pub holes: Vec<Hole>,
+    pub problems: Vec<Problem>,
+}
+
+impl Report {
+    /// Share of the range's minutes holding at least one book.
+    pub fn completeness(&self) -> f64 {
+        if self.minutes == 0 {
+            return 1.0;
+        }
+        self.covered_minutes as f64 / self.minutes as f64
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.holes.is_empty() && self.problems.is_empty()
+    }
+}
+
+/// The range's books, fed one object at a time in key order.
+#[derive(Debug)]
+pub struct Scan {
+    symbol: String,
+    from_ms: i64,
+    to_ms: i64,
+    files: usize,
+    books: usize,
+    /// Whether each minute of the range has a book.
+    covered: Vec<bool>,
+    /// The newest timestamp so far, and the key it came from.
+    last: Option<(i64, String)>,
+    problems: Vec<Problem>,
+}
+
+impl Scan {
+    /// A scan over [from, to), rounded out to whole minutes.
+    pub fn new(symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        let from_ms = from.timestamp_millis().div_euclid(MINUTE_MS) * MINUTE_MS;
+        let to_ms = (to.timestamp_millis() + MINUTE_MS - 1).div_euclid(MINUTE_MS) * MINUTE_MS;
+        Scan {
+            symbol: symbol.to_uppercase(),
+            from_ms,
+            to_ms,
+            files: 0,
+            books: 0,
+            covered: vec![false; ((to_ms - from_ms) / MINUTE_MS).max(0) as usize],
+            last: None,
+            problems: Vec::new(),
+        }
+    }
+
+    /// Decodes one object and checks its books, noting what's wrong with it.
+    pub fn file(&mut self, key: &str, bytes: &[u8]) {
+        self.files += 1;
+        match migrate::read_orderbooks(bytes) {
+            Ok(books) => self.books_of(key, &books),
+            Err(e) => self.problem(key, format!("undecodable: {}", e)),
+        }
+    }
+
+    fn books_of(&mut self, key: &str, books: &[OrderBook]) {
+        for book in books {
+            if schema::orderbook(book.schema_version).is_none() {
+                self.problem(key, format!("schema version: {} is unknown", book.schema_version));
+            }
+            if let Some((last_ms, last_key)) = &self.last {
+                if book.timestamp_ms < *last_ms {
+                    let problem = format!("timestamps: {} goes back from {} in {}", book.timestamp_ms, last_ms, last_key);
+                    self.problem(key, problem);
+                }
+            }
+            if self.last.as_ref().is_none_or(|(last_ms, _)| book.timestamp_ms >= *last_ms) {
+                self.last = Some((book.timestamp_ms, key.to_string()));
+            }
+            if (self.from_ms..self.to_ms).contains(&book.timestamp_ms) {
+                self.covered[((book.timestamp_ms - self.from_ms) / MINUTE_MS) as usize] = true;
+            }
+            self.books += 1;
+        }
+    }
+
+    /// One problem per object and kind, so a file of backwards books isn't listed
+    /// once per book.
+    fn problem(&mut self, key: &str, problem: String) {
+        let kind = problem.split(':').next().unwrap_or_default();
+        if !self.problems.iter().any(|p| p.key == key && p.problem.starts_with(kind)) {
+            self.problems.push(Problem { key: key.to_string(), problem });
+        }
+    }
+
+    pub fn finish(self) -> Report {
+        let mut holes: Vec<Hole> = Vec::new();
+        for (i, _) in self.covered.iter().enumerate().filter(|(_, c)| !**c) {
+            let from_ms = self.from_ms + i as i64 * MINUTE_MS;
+            match holes.last_mut() {
+                Some(hole) if hole.to_ms == from_ms => hole.to_ms += MINUTE_MS,
+                _ => holes.push(Hole { from_ms, to_ms: from_ms + MINUTE_MS }),
+            }
+        }
+        let hour_ms = Duration::hours(1).num_milliseconds();
+        let missing_hours = cli::hours(sink::at_ms(self.from_ms), sink::at_ms(self.to_ms)).into_iter()
+            .map(|h| h.timestamp_millis())
+            .filter(|&h| holes.iter().any(|hole| hole.from_ms <= h.max(self.from_ms) && (h + hour_ms).min(self.to_ms) <= hole.to_ms))
+            .collect();
+        Report {
+            symbol: self.symbol,
+            from_ms: self.from_ms,
+            to_ms: self.to_ms,
+            files: self.files,
+            books: self.books,
+            minutes: self.covered.len() as i64,
+            covered_minutes: self.covered.iter().filter(|c| **c).count() as i64,
+            missing_hours,
+            holes,
+            problems: self.problems,
+        }
+    }
+}
+
+/// Checks `symbol`'s books from `from` to `to`.
+pub async fn verify(archive: &Archive, symbol: &str, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Report, Error> {
+    let mut scan = Scan::new(symbol, from, to);
+    for hour in cli::hours(from, to) {
+        for key in hour_keys(archive, symbol, hour).await? {
+            scan.file(&key, &archive.get(&key).await?);
+        }
+    }
+    Ok(scan.finish())
+}
+
+/// The objects holding `symbol`'s books for the hour containing `hour`: the files its
+/// manifest names, once compacted, then the hour's own objects not among them.
+pub async fn hour_keys(archive: &Archive, symbol: &str, hour: DateTime<Utc>) -> Result<Vec<String>, Error> {
+    let mut keys: Vec<String> = manifest::read(archive, PREFIX, symbol, hour).await?
+        .map_or_else(Vec::new, |m| m.files.into_iter().map(|f| f.key).collect());
+    for key in archive.list(&format!("{}/", sink::hour_dir(PREFIX, symbol, hour)?)).await? {
+        if key.ends_with(".avro") && !keys.contains(&key) {
+            keys.push(key);
+        }
+    }
+    Ok(keys)
+}
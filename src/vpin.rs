@@ -0,0 +1,120 @@
+//! VPIN (volume-synchronized probability of informed trading), a streaming estimate
+//! of trade-flow toxicity. Live trades fill equal-notional buckets; each full bucket
+//! contributes its buy/sell imbalance, and the estimate is the mean imbalance over the
+//! last `VPIN_WINDOW` buckets as a fraction of bucket size. Aggregated trades say
+//! which side was the taker, so volume isn't classified statistically as in the paper.
+//! The estimate is stamped on every book the collector stores.
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::{LazyLock, Mutex};
+use std::time::Duration;
+use tracing::warn;
+
+use crate::binance::Venue;
+use crate::trades::{self, AggTrade};
+use crate::{config, Error};
+
+const DEFAULT_BUCKET_NOTIONAL: f64 = 1_000_000.0;
+const DEFAULT_WINDOW: usize = 50;
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Whether `VPIN=1` asks for the estimate.
+pub fn enabled() -> bool {
+    config::var("VPIN").is_some_and(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Equal-notional trade buckets and the imbalance of the last `window` full ones.
+#[derive(Debug, Clone)]
+pub struct Vpin {
+    /// Quote-currency notional per bucket, so one setting suits every symbol.
+    bucket_notional: f64,
+    window: usize,
+    /// Taker buy and sell notional in the bucket being filled.
+    buy: f64,
+    sell: f64,
+    imbalances: VecDeque<f64>,
+}
+
+impl Vpin {
+    pub fn new(bucket_notional: f64, window: usize) -> Self {
+        Vpin { bucket_notional, window: window.max(1), buy: 0.0, sell: 0.0, imbalances: VecDeque::new() }
+    }
+
+    /// `VPIN_BUCKET_NOTIONAL` (default 1,000,000) per bucket over `VPIN_WINDOW`
+    /// (default 50) buckets.
+    pub fn from_env() -> Self {
+        let bucket = config::var("VPIN_BUCKET_NOTIONAL").and_then(|n| n.parse().ok()).filter(|n: &f64| *n > 0.0 && n.is_finite());
+        let window = config::var("VPIN_WINDOW").and_then(|n| n.parse().ok()).unwrap_or(DEFAULT_WINDOW);
+        Vpin::new(bucket.unwrap_or(DEFAULT_BUCKET_NOTIONAL), window)
+    }
+
+    /// Adds a trade, splitting it across buckets when it overfills the current one.
+    pub fn add(&mut self, trade: &AggTrade) {
+        let mut notional = trade.price * trade.qty;
+        if !notional.is_finite() {
+            return;
+        }
+        while notional > 0.0 {
+            let room = self.bucket_notional - self.buy - self.sell;
+            let taken = notional.min(room);
+            // a buyer-maker trade was an aggressive sell
+            if trade.is_buyer_maker {
+                self.sell += taken;
+            } else {
+                self.buy += taken;
+            }
+            notional -= taken;
+            if taken >= room {
+                self.imbalances.push_back((self.buy - self.sell).abs());
+                if self.imbalances.len() > self.window {
+                    self.imbalances.pop_front();
+                }
+                (self.buy, self.sell) = (0.0, 0.0);
+            }
+        }
+    }
+
+    /// The estimate, between 0 and 1, and the number of full buckets behind it;
+    /// `None` before the first bucket fills.
+    pub fn estimate(&self) -> Option<(f64, usize)> {
+        let n = self.imbalances.len();
+        (n > 0).then(|| (self.imbalances.iter().sum::<f64>() / (n as f64 * self.bucket_notional), n))
+    }
+}
+
+/// The latest estimate per upper-cased symbol, read by the collectors.
+static ESTIMATES: LazyLock<Mutex<HashMap<String, (f64, usize)>>> = LazyLock::new(Default::default);
+
+/// `symbol`'s latest estimate and its bucket count, `None` until one is made.
+pub fn estimate(symbol: &str) -> Option<(f64, usize)> {
+    ESTIMATES.lock().unwrap_or_else(|e| e.into_inner()).get(&symbol.to_uppercase()).copied()
+}
+
+fn publish(symbol: &str, estimate: (f64, usize)) {
+    ESTIMATES.lock().unwrap_or_else(|e| e.into_inner()).insert(symbol.to_uppercase(), estimate);
+}
+
+/// Follows `symbol`'s trade stream and keeps its estimate current until the task is
+/// dropped, reconnecting after a lost connection. Buckets carry over reconnects;
+/// trades missed in between are simply absent from them.
+pub async fn capture(venue: &Venue, symbol: String) -> Result<(), Error> {
+    let mut vpin = Vpin::from_env();
+    let mut backoff = Duration::from_secs(1);
+
+    loop {
+        let followed = trades::follow(venue, &symbol, |trade| {
+            vpin.add(&trade);
+            if let Some(estimate) = vpin.estimate() {
+                publish(&symbol, estimate);
+            }
+        }).await;
+        match followed {
+            Ok(()) => backoff = Duration::from_secs(1),
+            Err(e) => {
+                warn!(error = %e, symbol, "trade stream failed, reconnecting");
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(MAX_BACKOFF);
+            }
+        }
+    }
+}
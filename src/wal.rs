@@ -0,0 +1,86 @@
+//! Write-ahead spill directory: every encoded batch is persisted locally before upload
+//! and removed once delivered, so a crash mid-upload leaves it behind for the next start.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::config;
+
+static SEQ: AtomicU64 = AtomicU64::new(0);
+
+pub struct Wal {
+    dir: PathBuf,
+}
+
+/// A batch that was written ahead but never confirmed delivered.
+pub struct Entry {
+    pub path: PathBuf,
+    pub key: String,
+    /// The book id of a conditional write (`sink::book_id`), so recovering it is one too.
+    pub id: Option<String>,
+    pub body: Vec<u8>,
+}
+
+impl Wal {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Wal { dir: dir.into() }
+    }
+
+    /// Uses `WAL_DIR`, defaulting to a directory under Lambda's writable `/tmp`.
+    pub fn from_env() -> Self {
+        Wal::new(config::var("WAL_DIR").unwrap_or_else(|| "/tmp/orderbook-wal".into()))
+    }
+
+    /// Persists `body` destined for `key`, written conditionally as book `id` if given.
+    /// Entries are written to a temp name and renamed so a crash never leaves a
+    /// half-written entry behind.
+    pub fn append(&self, key: &str, id: Option<&str>, body: &[u8]) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.dir)?;
+        let name = format!("{:020}-{:06}", chrono::Utc::now().timestamp_micros(), SEQ.fetch_add(1, Ordering::Relaxed));
+        let (tmp, path) = (self.dir.join(format!("{}.tmp", name)), self.dir.join(format!("{}.wal", name)));
+
+        let id = id.unwrap_or_default();
+        let mut buf = Vec::with_capacity(8 + key.len() + id.len() + body.len());
+        for field in [key, id] {
+            buf.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            buf.extend_from_slice(field.as_bytes());
+        }
+        buf.extend_from_slice(body);
+        fs::write(&tmp, buf)?;
+        fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+
+    pub fn remove(&self, path: &Path) -> io::Result<()> {
+        fs::remove_file(path)
+    }
+
+    /// Undelivered entries, oldest first. Unreadable entries are skipped.
+    pub fn pending(&self) -> io::Result<Vec<Entry>> {
+        let mut paths: Vec<PathBuf> = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries.flatten()
+                .map(|e| e.path())
+                .filter(|p| p.extension().is_some_and(|ext| ext == "wal"))
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e),
+        };
+        paths.sort();
+
+        Ok(paths.into_iter().filter_map(|path| {
+            let buf = fs::read(&path).ok()?;
+            let (key, rest) = field(&buf)?;
+            let (id, body) = field(rest)?;
+            Some(Entry { path, key, id: (!id.is_empty()).then_some(id), body: body.to_vec() })
+        }).collect())
+    }
+}
+
+/// A length-prefixed string at the start of `buf`, and what follows it.
+fn field(buf: &[u8]) -> Option<(String, &[u8])> {
+    let len = u32::from_le_bytes(buf.get(..4)?.try_into().ok()?) as usize;
+    let value = String::from_utf8(buf.get(4..4 + len)?.to_vec()).ok()?;
+    Some((value, &buf[4 + len..]))
+}
@@ -0,0 +1,34 @@
+use orderbook::alert::{self, Alert, Streak};
+
+#[test]
+fn a_streak_alerts_once_at_the_threshold_and_resets_on_success() {
+    let mut streak = Streak::new(alert::RECONNECT, 3);
+    assert_eq!(streak.fail("btcusdt", "refused", 1_000), None);
+    assert_eq!(streak.fail("btcusdt", "refused", 2_000), None);
+    let raised = streak.fail("btcusdt", "websocket closed", 9_000).expect("third failure alerts");
+    assert_eq!(raised, Alert {
+        symbol: "BTCUSDT".into(),
+        error_class: "reconnect".into(),
+        failures: 3,
+        duration_ms: 8_000,
+        first_failure_ms: 1_000,
+        last_error: "websocket closed".into(),
+    });
+    assert_eq!(streak.fail("btcusdt", "refused", 10_000), None, "one alert per streak");
+
+    streak.succeed();
+    assert_eq!(streak.fail("btcusdt", "refused", 20_000), None);
+    assert_eq!(streak.fail("btcusdt", "refused", 21_000), None);
+    assert_eq!(streak.fail("btcusdt", "refused", 22_000).map(|a| a.first_failure_ms), Some(20_000));
+}
+
+#[test]
+fn alerts_serialize_with_their_context() {
+    let mut streak = Streak::new(alert::S3_WRITE, 1);
+    let raised = streak.fail("ethusdt", "dead-lettered after retries", 5_000).unwrap();
+    assert_eq!(raised.subject(), "orderbook ETHUSDT: 1 consecutive s3_write failures");
+    let json: serde_json::Value = serde_json::to_value(&raised).unwrap();
+    assert_eq!(json["symbol"], "ETHUSDT");
+    assert_eq!(json["error_class"], "s3_write");
+    assert_eq!(json["duration_ms"], 0);
+}
@@ -0,0 +1,41 @@
+use orderbook::anomaly::{self, Detector, Settings};
+use orderbook::book::Level;
+use orderbook::OrderBook;
+
+fn book(spread: f64, qty: f64) -> OrderBook {
+    OrderBook::from_levels(0, &[Level::new(65_000.0, qty)], &[Level::new(65_000.0 + spread, qty)]).expect("two-sided")
+}
+
+#[test]
+fn spikes_are_scored_in_mads_against_the_books_before_them() {
+    let mut detector = Detector::new(Settings { window: 100, mads: 5.0 });
+    for i in 0..60 {
+        let book = detector.flag(book(0.01 + 0.001 * (i % 3) as f64, 2.0 + 0.1 * (i % 5) as f64));
+        if i < 30 {
+            assert_eq!((book.spread_mads, book.depth_mads), (0.0, 0.0), "too few books for a baseline");
+        }
+        assert!(!book.spread_anomaly && !book.depth_anomaly);
+    }
+
+    let wide = detector.flag(book(0.05, 2.2));
+    assert!(wide.spread_anomaly && wide.spread_mads > 5.0, "{}", wide.spread_mads);
+    assert!(!wide.depth_anomaly);
+
+    let thin = detector.flag(book(0.011, 0.1));
+    assert!(thin.depth_anomaly && thin.depth_mads < -5.0, "{}", thin.depth_mads);
+    assert!(!thin.spread_anomaly);
+}
+
+#[test]
+fn a_pinned_spread_measures_against_the_mean_deviation() {
+    let mut detector = Detector::new(Settings { window: 100, mads: 5.0 });
+    for _ in 0..40 {
+        let book = detector.flag(book(0.01, 1.0));
+        assert_eq!(book.spread_mads, 0.0, "no spread to measure against");
+    }
+    // one tick wider, once, then the same again: the first measures against a
+    // baseline with no deviation, the second against the mean deviation
+    assert_eq!(detector.flag(book(0.02, 1.0)).spread_mads, 0.0);
+    assert!(detector.flag(book(0.02, 1.0)).spread_anomaly);
+    assert!((anomaly::top_depth(&book(0.01, 1.5)) - 3.0).abs() < 1e-9);
+}
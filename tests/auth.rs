@@ -0,0 +1,33 @@
+#![cfg(feature = "userdata")]
+
+use orderbook::auth::Credentials;
+
+const BINANCE_SECRET: &str = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+
+#[test]
+fn binance_signature_matches_the_documented_example() {
+    let creds = Credentials { api_key: "key".into(), api_secret: BINANCE_SECRET.into(), passphrase: None };
+    let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1&recvWindow=5000&timestamp=1499827319559";
+    assert_eq!(creds.sign_binance(query), "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71");
+}
+
+#[test]
+fn coinbase_subscription_is_signed_with_the_decoded_secret() {
+    let creds = Credentials::from_json(r#"{"api_key":"key","api_secret":"Y29pbmJhc2UtdGVzdC1zZWNyZXQ=","passphrase":"pass"}"#).expect("valid");
+    let message: serde_json::Value = serde_json::from_str(&creds.coinbase_subscribe(&["BTC-USD"], &["level2"], 1700000000).expect("signed"))
+        .expect("json");
+    assert_eq!(message["signature"], "b7ZtQkD7N6J/G1oP7BRj15mJueqHODlCvZCBZYvplts=");
+    assert_eq!((&message["key"], &message["passphrase"], &message["timestamp"]), (&"key".into(), &"pass".into(), &"1700000000".into()));
+    assert_eq!(message["product_ids"][0], "BTC-USD");
+
+    let bad = Credentials { api_secret: "not base64!".into(), ..creds };
+    assert!(bad.coinbase_subscribe(&["BTC-USD"], &["level2"], 1700000000).is_err());
+}
+
+#[test]
+fn secrets_stay_out_of_debug_output() {
+    let creds = Credentials::from_json(r#"{"api_key":"key","api_secret":"hunter2"}"#).expect("valid");
+    assert!(!format!("{:?}", creds).contains("hunter2"));
+    assert!(Credentials::from_json(r#"{"api_key":"key","api_secret":""}"#).is_err());
+    assert!(Credentials::from_json(r#"{"api_key":"key"}"#).is_err());
+}
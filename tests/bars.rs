@@ -0,0 +1,31 @@
+#[allow(dead_code)]
+mod common;
+
+use common::around;
+use orderbook::bars::{self, BarBuilder};
+use orderbook::OrderBook;
+
+#[test]
+fn partial_bars_of_a_minute_merge_into_the_whole_minutes_bar() {
+    let minute = 1_756_873_800_000;
+    let books = [around(minute + 1_000, 100.0), around(minute + 20_000, 104.0), around(minute + 40_000, 98.0), around(minute + 59_000, 101.0)];
+    let build = |books: &[OrderBook]| {
+        let mut builder = BarBuilder::default();
+        books.iter().for_each(|b| assert!(builder.push(b).is_none()));
+        builder.flush().expect("bar")
+    };
+    let whole = build(&books);
+
+    // the run ending mid-minute and the one taking over each hold part of it
+    let (earlier, later) = (build(&books[..2]), build(&books[2..]));
+    assert_eq!((earlier.first_ms, later.first_ms), (minute + 1_000, minute + 40_000));
+    let merged = bars::merge(vec![later, earlier]);
+    let [bar] = &merged[..] else { panic!("expected one bar, got {}", merged.len()) };
+    assert_eq!((bar.snapshots, bar.open_mid, bar.close_mid), (4, 100.0, 101.0));
+    assert_eq!((bar.high_mid, bar.low_mid, bar.first_ms, bar.last_ms), (104.0, 98.0, minute + 1_000, minute + 59_000));
+    assert!((bar.mean_spread - whole.mean_spread).abs() < 1e-9);
+    assert!((bar.mean_imbalance - whole.mean_imbalance).abs() < 1e-9);
+
+    let next = build(&[around(minute + 61_000, 100.0)]);
+    assert_eq!(bars::merge(vec![next.clone(), whole.clone()]), [whole, next]);
+}
@@ -0,0 +1,124 @@
+use orderbook::binance::{self, DepthMessage, Venue};
+use orderbook::pipeline::{self, Outcome};
+
+/// Leaks the copy so parsed messages can borrow from it for the rest of the test.
+fn parse(text: &str) -> Option<DepthMessage<'static>> {
+    DepthMessage::parse(text.as_bytes().to_vec().leak()).expect("valid json")
+}
+
+#[test]
+fn spot_partial_depth() {
+    let msg = parse(r#"{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[["0.0026","100"]]}"#);
+    let Some(DepthMessage::Partial(depth)) = msg else { panic!("expected partial depth, got {:?}", msg) };
+    assert_eq!(depth.last_update_id, 160);
+    let (bids, asks) = depth.levels();
+    assert_eq!((bids[0].price, bids[0].qty), (0.0024, 10.0));
+    assert_eq!((asks[0].price, asks[0].qty), (0.0026, 100.0));
+}
+
+#[test]
+fn futures_depth_update() {
+    let msg = parse(r#"{"e":"depthUpdate","E":1571889248277,"T":1571889248276,"s":"BTCUSDT","U":390497796,"u":390497878,"pu":390497794,
+        "b":[["7403.89","0.002"],["7403.90","bad"]],"a":[["7405.96","3.340"]]}"#);
+    let Some(DepthMessage::Update(update)) = &msg else { panic!("expected depth update, got {:?}", msg) };
+    assert_eq!(update.symbol, "BTCUSDT");
+    assert_eq!(update.event_time_ms, 1571889248277);
+    assert_eq!((update.first_update_id, update.final_update_id), (390497796, 390497878));
+
+    // the unparseable quantity is dropped rather than failing the message
+    let (bids, asks) = msg.unwrap().levels();
+    assert_eq!(bids.len(), 1);
+    assert_eq!(asks.len(), 1);
+}
+
+#[test]
+fn books_carry_the_exchange_clock_when_sent() {
+    let update = r#"{"e":"depthUpdate","E":1571889248277,"s":"BTCUSDT","U":1,"u":2,"b":[["7403.89","0.002"]],"a":[["7405.96","3.340"]]}"#;
+    let Outcome::Book(book) = pipeline::process(update, 1571889248400, 5) else { panic!("expected a book") };
+    assert_eq!((book.event_time_ms, book.timestamp_ms), (1571889248277, 1571889248400));
+    assert_eq!((book.last_update_id, book.symbol.as_str()), (2, "BTCUSDT"));
+
+    // partial depth has no event time; it stays 0 rather than borrowing the local clock
+    let partial = r#"{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[["0.0026","100"]]}"#;
+    let Outcome::Book(book) = pipeline::process(partial, 1571889248400, 5) else { panic!("expected a book") };
+    assert_eq!((book.event_time_ms, book.last_update_id), (0, 160));
+    assert_eq!(book.partition_time_ms(), 1571889248400);
+}
+
+#[test]
+fn unknown_messages_are_skipped() {
+    assert!(parse(r#"{"result":null,"id":1}"#).is_none());
+    assert!(parse(r#"{"e":"aggTrade","E":123,"s":"BTCUSDT","p":"0.001"}"#).is_none());
+
+    assert!(matches!(pipeline::process(r#"{"result":null,"id":1}"#, 0, 3), Outcome::Skipped));
+    assert!(matches!(pipeline::process("1700000000000", 0, 3), Outcome::Skipped));
+    assert!(matches!(pipeline::process(r#"{"lastUpdateId":1,"bids":[["1","1"]"#, 0, 3), Outcome::Malformed(_)));
+}
+
+#[test]
+fn combined_stream_payloads_are_unwrapped() {
+    let text = r#"{"stream":"btcusdt@depth20@100ms","data":{"lastUpdateId":160,"bids":[["0.0024","10"]],"asks":[["0.0026","100"]]}}"#;
+    assert_eq!(binance::Control::parse(text).and_then(|c| c.stream), Some("btcusdt@depth20@100ms"));
+    let Some(DepthMessage::Partial(depth)) = parse(text) else { panic!("expected partial depth") };
+    assert_eq!(depth.last_update_id, 160);
+
+    assert_eq!(binance::stream_request("UNSUBSCRIBE", &[binance::depth_stream("ETHUSDT")], 7),
+               r#"{"id":7,"method":"UNSUBSCRIBE","params":["ethusdt@depth20@100ms"]}"#);
+    assert_eq!(binance::combined_url(binance::FUTURES_WS), "wss://fstream.binance.com/stream");
+}
+
+#[test]
+fn venues_pair_websocket_and_rest_hosts() {
+    let us = Venue::preset("us").expect("preset");
+    assert_eq!((us.ws.as_str(), us.rest_url("depth")), ("wss://stream.binance.us:9443/ws", "https://api.binance.us/api/v3/depth".into()));
+    let futures = Venue::preset("futures-testnet").expect("preset");
+    assert!(futures.futures);
+    assert_eq!(futures.rest_url("aggTrades"), "https://testnet.binancefuture.com/fapi/v1/aggTrades");
+    assert!(Venue::preset("kraken").is_none());
+}
+
+#[test]
+fn request_weights_follow_the_venue() {
+    let (spot, futures) = (Venue::preset("com").expect("preset"), Venue::preset("futures").expect("preset"));
+    assert_eq!((spot.weight("depth", 20), spot.weight("depth", 1000), spot.weight("depth", 5000)), (5, 50, 250));
+    assert_eq!((futures.weight("depth", 20), futures.weight("depth", 1000)), (2, 20));
+    assert_eq!((spot.weight("aggTrades", 1000), futures.weight("aggTrades", 1000)), (2, 20));
+    assert_eq!(futures.weight("premiumIndex", 0), 1);
+}
+
+#[test]
+fn symbols_are_checked_against_the_venue() {
+    let (spot, futures) = (Venue::preset("com").expect("preset"), Venue::preset("futures").expect("preset"));
+    for symbol in ["BTCUSDT", "BTCUSD", "1000SHIBUSDT"] {
+        assert!(spot.validate_symbol(symbol).is_ok(), "rejected {}", symbol);
+    }
+    assert!(futures.validate_symbol("BTCUSDT_250627").is_ok());
+    for symbol in ["BTCUSDT_250627", "BTC-USD", "B", "BTCUSDT@depth"] {
+        assert!(spot.validate_symbol(symbol).is_err(), "accepted {}", symbol);
+    }
+    assert!(futures.validate_symbol("BTCUSDT_PERP").is_err());
+}
+
+#[test]
+fn tick_sizes_come_from_the_price_filter() {
+    let info = r#"{"timezone":"UTC","symbols":[
+        {"symbol":"BTCUSDT","filters":[{"filterType":"LOT_SIZE","stepSize":"0.00001"},{"filterType":"PRICE_FILTER","minPrice":"0.01","tickSize":"0.01"}]},
+        {"symbol":"DOGEUSDT","filters":[{"filterType":"PRICE_FILTER","tickSize":"0.00001"}]},
+        {"symbol":"NOFILTER","filters":[]}
+    ]}"#;
+    let ticks = binance::parse_tick_sizes(info).expect("valid exchangeInfo");
+    assert_eq!(ticks.get("BTCUSDT"), Some(&0.01));
+    assert_eq!(ticks.get("DOGEUSDT"), Some(&0.00001));
+    assert!(!ticks.contains_key("NOFILTER"));
+}
+
+#[test]
+fn spreads_are_measured_in_basis_points_and_ticks() {
+    let partial = r#"{"lastUpdateId":160,"bids":[["64999.98","1"]],"asks":[["65000.02","1"]]}"#;
+    let Outcome::Book(book) = pipeline::process(partial, 0, 9) else { panic!("expected a book") };
+    assert!((book.spread_bps - 0.04 / 65000.0 * 10_000.0).abs() < 1e-9);
+    assert_eq!((book.tick_size, book.spread_in_ticks), (0.0, 0.0));
+
+    let book = book.with_tick_size(0.01);
+    assert_eq!((book.tick_size, book.spread_in_ticks), (0.01, 4.0));
+}
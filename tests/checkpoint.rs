@@ -0,0 +1,28 @@
+use aws_sdk_dynamodb::types::AttributeValue;
+use orderbook::checkpoint::Checkpoint;
+
+#[test]
+fn checkpoint_round_trips_through_a_dynamodb_item() {
+    let checkpoint = Checkpoint {
+        symbol: "BTCUSDT".into(),
+        last_update_id: 1027028,
+        last_flush_ms: 1_725_000_000_123,
+        last_key: "orderbook/exchange=binance/symbol=BTCUSDT/year=2024/month=08/day=30/hour=06/1725000000100.avro".into(),
+    };
+    let item = checkpoint.to_item();
+
+    assert_eq!(item["symbol"], AttributeValue::S("BTCUSDT".into()));
+    assert_eq!(item["last_update_id"], AttributeValue::N("1027028".into()));
+    assert_eq!(Checkpoint::from_item(&item), Some(checkpoint));
+}
+
+#[test]
+fn incomplete_items_are_not_checkpoints() {
+    let mut item = Checkpoint { symbol: "BTCUSDT".into(), ..Default::default() }.to_item();
+    item.remove("last_key");
+    assert_eq!(Checkpoint::from_item(&item), None);
+
+    let mut item = Checkpoint { symbol: "BTCUSDT".into(), ..Default::default() }.to_item();
+    item.insert("last_flush_ms".into(), AttributeValue::S("yesterday".into()));
+    assert_eq!(Checkpoint::from_item(&item), None);
+}
@@ -0,0 +1,35 @@
+use orderbook::binance::{DepthUpdate, PartialDepth, RawLevel};
+use orderbook::churn::{Churn, Counts};
+use orderbook::fulldepth::{Applied, LocalBook};
+use orderbook::{schema, sink};
+
+fn diff<'a>(id: u64, bids: Vec<RawLevel<'a>>, asks: Vec<RawLevel<'a>>) -> DepthUpdate<'a> {
+    DepthUpdate { event_time_ms: 1, symbol: "BTCUSDT", first_update_id: id, final_update_id: id, prev_final_update_id: 0, bids, asks }
+}
+
+#[test]
+fn diffs_are_counted_as_adds_cancels_and_modifications() {
+    let depth = PartialDepth { last_update_id: 10, bids: vec![["100.00", "1"], ["99.99", "2"]], asks: vec![["100.01", "1"]] };
+    let mut book = LocalBook::from_snapshot(&depth).expect("decimal levels");
+    assert_eq!(book.take_churn(), Counts::default(), "the snapshot isn't churn");
+
+    // resize, remove, add, an unchanged level and the removal of an absent one
+    let first = diff(11, vec![["100.00", "1.5"], ["99.99", "0"], ["99.98", "3"]], vec![["100.01", "1.0"], ["100.05", "0"]]);
+    assert_eq!(book.apply(&first), Applied::Applied);
+    assert_eq!(book.apply(&diff(12, vec![], vec![["100.02", "4"]])), Applied::Applied);
+
+    assert_eq!(book.take_churn(), Counts { updates: 2, adds: 2, cancels: 1, modifications: 1 });
+    assert_eq!(book.take_churn(), Counts::default());
+}
+
+#[test]
+fn rates_are_per_second_and_cancels_per_add() {
+    let churn = Churn::new("btcusdt", 0, 10_000, Counts { updates: 30, adds: 40, cancels: 30, modifications: 30 });
+    assert_eq!(churn.symbol, "BTCUSDT");
+    assert_eq!(churn.update_rate, 10.0);
+    assert_eq!(churn.cancel_ratio, 0.75);
+
+    let quiet = Churn::new("btcusdt", 0, 0, Counts::default());
+    assert_eq!((quiet.update_rate, quiet.cancel_ratio), (0.0, 0.0));
+    assert!(sink::encode(schema::CHURN, &[churn, quiet]).is_ok());
+}
@@ -0,0 +1,508 @@
+#[allow(dead_code)]
+mod common;
+
+use common::{depth_fixture, serve, serve_combined, serve_http, serve_open, serve_repeated, serve_scripted, MemoryOutput};
+use apache_avro::{from_value, Reader};
+use orderbook::bars::{self, Bar};
+use orderbook::collector::{self, Collector, MessageCounts};
+use orderbook::handoff::{Bounds, Window};
+use orderbook::mux::{self, Control, Multiplexer};
+use orderbook::poll::Fallback;
+use orderbook::sample::Sampler;
+use orderbook::sink::{Delivery, Output, Upload};
+use orderbook::top::{self, Quote};
+use orderbook::{migrate, schema, Error};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+
+async fn collect(messages: Vec<String>) -> MemoryOutput {
+    run(messages).await.into_output()
+}
+
+async fn run(messages: Vec<String>) -> Collector<MemoryOutput> {
+    let url = serve(messages).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default());
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+    collector
+}
+
+#[tokio::test]
+async fn writes_one_object_per_two_sided_snapshot() {
+    let output = collect(depth_fixture()).await;
+
+    // one-sided and truncated payloads are skipped, the other three are written
+    assert_eq!(output.objects.len(), 3);
+    for (key, _) in &output.objects {
+        assert!(key.starts_with("orderbook/exchange=binance/symbol=BTCUSDT/year="), "unexpected key {}", key);
+        assert!(key.ends_with(".avro"));
+    }
+}
+
+#[tokio::test]
+async fn every_message_is_accounted_for() {
+    let mut messages = depth_fixture();
+    messages.push("1700000000000".into());
+    let counts = run(messages).await.counts();
+
+    assert_eq!(counts, MessageCounts { received: 6, stored: 3, skipped: 2, malformed: 1, quarantined: 0, downsampled: 0, aggregated: 0, duplicates: 0, dead_lettered: 0, dropped: 0 });
+}
+
+#[tokio::test]
+async fn sampler_limits_what_is_stored() {
+    let url = serve(depth_fixture()).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_sampler(Sampler::new(Some(60_000), None));
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    let counts = collector.counts();
+    assert_eq!((counts.stored, counts.downsampled), (1, 2));
+    assert_eq!(collector.output().objects.len(), 1);
+}
+
+#[tokio::test]
+async fn each_symbol_runs_its_own_collector() {
+    let urls = [("btcusdt", serve(depth_fixture()).await), ("ethusdt", serve(depth_fixture()[..2].to_vec()).await)];
+    let symbols: Vec<String> = urls.iter().map(|(s, _)| s.to_string()).collect();
+    let counts = collector::run_each(&symbols, |symbol| {
+        let url = &urls.iter().find(|(s, _)| *s == symbol).expect("known symbol").1;
+        let mut collector = Collector::new(symbol, url, MemoryOutput::default());
+        collector.reconnect = false;
+        collector
+    }).await.expect("collectors run");
+
+    assert_eq!(counts.keys().collect::<Vec<_>>(), ["btcusdt", "ethusdt"]);
+    assert_eq!((counts["btcusdt"].received, counts["btcusdt"].stored), (5, 3));
+    assert_eq!(counts["ethusdt"].received, 2);
+}
+
+#[tokio::test]
+async fn subscribed_symbols_share_one_connection() {
+    let mut messages: Vec<(String, String)> = depth_fixture().into_iter().map(|p| ("btcusdt@depth20@100ms".into(), p)).collect();
+    messages.push(("ethusdt@depth20@100ms".into(), depth_fixture()[0].clone()));
+    messages.push(("xrpusdt@depth20@100ms".into(), depth_fixture()[0].clone()));
+    let (url, requests) = serve_combined(messages).await;
+
+    let (control, changes) = mpsc::channel(4);
+    control.send(Control::Subscribe(vec!["btcusdt".into(), "ethusdt".into()])).await.expect("send");
+    let mut mux = Multiplexer::new(&url, |symbol| Collector::new(symbol, &url, MemoryOutput::default()));
+    mux.reconnect = false;
+    let counts = mux.run(changes).await.expect("mux run");
+
+    let requests = requests.await.expect("server");
+    assert_eq!(requests, [r#"{"id":1,"method":"SUBSCRIBE","params":["btcusdt@depth20@100ms","ethusdt@depth20@100ms"]}"#]);
+    // the reply, the truncated payload (no readable stream name) and the unsubscribed stream aren't routed
+    assert_eq!(counts.keys().collect::<Vec<_>>(), ["btcusdt", "ethusdt"]);
+    assert_eq!((counts["btcusdt"].received, counts["btcusdt"].stored), (4, 3));
+    assert_eq!((counts["ethusdt"].received, counts["ethusdt"].stored), (1, 1));
+}
+
+#[tokio::test]
+async fn subscription_request_is_sent_on_connect() {
+    let (url, requests) = serve_combined(Vec::new()).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_subscription(r#"{"type":"subscribe"}"#.into());
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    assert_eq!(requests.await.expect("server"), [r#"{"type":"subscribe"}"#]);
+    assert_eq!((collector.counts().received, collector.counts().skipped), (1, 1));
+}
+
+#[test]
+fn diff_subscribes_new_and_unsubscribes_removed_symbols() {
+    let current = ["btcusdt".to_string(), "ethusdt".to_string()];
+    let desired = ["ethusdt".to_string(), "solusdt".to_string()];
+    assert_eq!(mux::diff(&current, &desired), [
+        Control::Subscribe(vec!["solusdt".into()]),
+        Control::Unsubscribe(vec!["btcusdt".into()]),
+    ]);
+    assert!(mux::diff(&current, &current).is_empty());
+}
+
+#[tokio::test]
+async fn bars_only_writes_minute_bars_instead_of_snapshots() {
+    let url = serve(depth_fixture()).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_bars(bars::Mode::Only);
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    assert_eq!((collector.counts().stored, collector.counts().aggregated), (0, 3));
+    let bars: Vec<Bar> = collector.output().objects.iter()
+        .inspect(|(key, _)| assert!(key.starts_with("aggregates/exchange=binance/symbol=BTCUSDT/"), "unexpected key {}", key))
+        .flat_map(|(_, body)| Reader::new(&body[..]).expect("valid avro").map(|v| from_value::<Bar>(&v.expect("record")).expect("bar")))
+        .collect();
+
+    // the three books normally share a minute, but the run may straddle a boundary
+    assert_eq!(bars.iter().map(|b| b.snapshots).sum::<i64>(), 3);
+    let bar = &bars[0];
+    assert_eq!(bar.start_ms % 60_000, 0);
+    assert!((bar.open_mid - 65000.15).abs() < 1e-9);
+    assert!(bar.low_mid <= bar.open_mid && bar.open_mid <= bar.high_mid);
+    assert!(bar.max_spread >= bar.mean_spread);
+    assert!(bar.min_bid_depth <= bar.mean_bid_depth && bar.min_ask_depth <= bar.mean_ask_depth);
+}
+
+#[tokio::test]
+async fn written_avro_decodes_to_normalized_books() {
+    let output = collect(depth_fixture()).await;
+    let books: Vec<_> = output.objects.iter()
+        .flat_map(|(_, body)| migrate::read_orderbooks(body).expect("valid avro"))
+        .collect();
+
+    let first = &books[0];
+    assert!((first.mid_price - 65000.15).abs() < 1e-9);
+    assert!((first.spread - 0.10).abs() < 1e-9);
+    assert_eq!(first.bids.len(), 5);
+    assert_eq!(first.asks.len(), 5);
+    assert_eq!(first.schema_version, schema::ORDERBOOK_VERSION);
+    assert_eq!((first.exchange.as_str(), first.symbol.as_str()), ("binance", "BTCUSDT"));
+
+    // imbalance over the top five levels: (7.5 - 9.4) / (7.5 + 9.4)
+    assert!((first.imbalance_ratio - (-1.9 / 16.9)).abs() < 1e-9);
+
+    // crossed book from the last fixture still produces a record with negative spread
+    assert!(books[2].spread < 0.0);
+}
+
+#[tokio::test]
+async fn batch_window_stores_its_books_in_one_object() {
+    let url = serve(depth_fixture()).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_batch_window(Duration::from_secs(86_400));
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    assert_eq!(collector.counts().stored, 3);
+    let output = collector.into_output();
+    let [(key, body)] = &output.objects[..] else { panic!("expected one object, got {}", output.objects.len()) };
+    let books = migrate::read_orderbooks(body).expect("valid avro");
+    assert_eq!(books.iter().map(|b| b.last_update_id).collect::<Vec<_>>(), [1027024, 1027025, 1027028]);
+    // keyed by its first book, not the window's start, so another run's part of the window keeps its own
+    assert!(key.ends_with(&format!("/{}.avro", books[0].timestamp_ms)), "unexpected key {}", key);
+    assert_eq!(output.checkpoints.last().map(|c| (c.last_update_id, &c.last_key)), Some((1027028, key)));
+}
+
+#[tokio::test]
+async fn avro_header_carries_the_orderbook_schema() {
+    let output = collect(depth_fixture()).await;
+    let (_, body) = &output.objects[0];
+
+    assert_eq!(&body[..4], b"Obj\x01");
+    let reader = apache_avro::Reader::new(&body[..]).expect("container");
+    let written = reader.writer_schema().canonical_form();
+    let expected = apache_avro::Schema::parse_str(schema::ORDERBOOK).expect("schema").canonical_form();
+    assert_eq!(written, expected);
+}
+
+#[tokio::test]
+async fn shutdown_stops_an_open_stream_and_writes_the_partial_bar() {
+    let (url, sent) = serve_open(depth_fixture()).await;
+    let (stop, shutdown) = watch::channel(false);
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default())
+        .with_bars(bars::Mode::Alongside)
+        .with_shutdown(shutdown);
+    let running = tokio::spawn(async move {
+        collector.run().await.expect("collector run");
+        collector
+    });
+
+    sent.await.expect("server");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    stop.send(true).expect("collector listening");
+    let collector = tokio::time::timeout(Duration::from_secs(5), running).await
+        .expect("collector stopped").expect("collector task");
+
+    // the server never closed, so the bar only went out on shutdown
+    assert_eq!(collector.counts().received, 5);
+    assert_eq!(collector.counts().stored, 3);
+    assert!(collector.output().objects.iter().any(|(key, _)| key.starts_with("aggregates/")));
+}
+
+/// Takes 100ms over each write, so frames queue up behind it.
+#[derive(Default)]
+struct SlowWrites(Vec<String>);
+
+impl Output for SlowWrites {
+    async fn write(&mut self, key: &str, _: &[u8]) -> Result<Delivery, Error> {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        self.0.push(key.to_string());
+        Ok(Delivery::Stored { latency: Duration::from_millis(100), retries: 0 })
+    }
+}
+
+#[tokio::test]
+async fn shutdown_handles_the_frames_already_queued() {
+    let (url, sent) = serve_open(depth_fixture()).await;
+    let (stop, shutdown) = watch::channel(false);
+    let mut collector = Collector::new("btcusdt", &url, SlowWrites::default()).with_shutdown(shutdown);
+    let running = tokio::spawn(async move {
+        collector.run().await.expect("collector run");
+        collector
+    });
+
+    // every frame is read at once, but only the first write is under way by the stop
+    sent.await.expect("server");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    stop.send(true).expect("collector listening");
+    let collector = tokio::time::timeout(Duration::from_secs(5), running).await
+        .expect("collector stopped").expect("collector task");
+
+    let counts = collector.counts();
+    assert_eq!((counts.received, counts.stored, counts.skipped, counts.malformed), (5, 3, 1, 1));
+    assert_eq!(collector.output().0.len(), 3);
+}
+
+#[tokio::test]
+async fn overlapping_invocations_store_each_book_once() {
+    let bounds = Bounds { duration_minutes: 1, overlap_secs: 30 };
+    let window = Window::new(&bounds, tokio::time::Instant::now());
+    let first = MemoryOutput::default();
+    let second = MemoryOutput { claims: first.claims.clone(), ..Default::default() };
+
+    let mut counts = Vec::new();
+    for output in [first, second] {
+        let url = serve(depth_fixture()).await;
+        let mut collector = Collector::new("btcusdt", &url, output).with_window(window);
+        collector.reconnect = false;
+        collector.run().await.expect("collector run");
+        counts.push(collector.counts());
+    }
+
+    assert_eq!((counts[0].stored, counts[0].duplicates), (3, 0));
+    assert_eq!((counts[1].stored, counts[1].duplicates), (0, 3));
+}
+
+#[tokio::test]
+async fn checkpoints_the_last_stored_book_when_the_stream_ends() {
+    let output = collect(depth_fixture()).await;
+
+    let [checkpoint] = &output.checkpoints[..] else { panic!("expected one checkpoint, got {:?}", output.checkpoints) };
+    assert_eq!((checkpoint.symbol.as_str(), checkpoint.last_update_id), ("BTCUSDT", 1027028));
+    assert_eq!(checkpoint.last_key, output.objects.last().expect("stored books").0);
+    assert!(checkpoint.last_flush_ms > 0);
+}
+
+#[tokio::test]
+async fn rotated_connections_store_each_book_once() {
+    let (url, sent) = serve_repeated(depth_fixture(), 2).await;
+    let (stop, shutdown) = watch::channel(false);
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_shutdown(shutdown);
+    collector.rotate_after = Duration::from_millis(300);
+    let running = tokio::spawn(async move {
+        collector.run().await.expect("collector run");
+        collector
+    });
+
+    sent.await.expect("server");
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    stop.send(true).expect("collector listening");
+    let collector = tokio::time::timeout(Duration::from_secs(5), running).await
+        .expect("collector stopped").expect("collector task");
+
+    // the second connection replays every book the first delivered
+    assert_eq!(collector.counts().received, 10);
+    assert_eq!(collector.counts().stored, 3);
+    assert_eq!(collector.output().objects.len(), 3);
+}
+
+/// Runs a collector rotating after 300ms against `scripts` until `stop_after`.
+async fn rotate(scripts: Vec<Vec<(Duration, String)>>, stop_after: Duration) -> Collector<MemoryOutput> {
+    let url = serve_scripted(scripts).await;
+    let (stop, shutdown) = watch::channel(false);
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_shutdown(shutdown);
+    collector.rotate_after = Duration::from_millis(300);
+    let running = tokio::spawn(async move {
+        collector.run().await.expect("collector run");
+        collector
+    });
+
+    tokio::time::sleep(stop_after).await;
+    stop.send(true).expect("collector listening");
+    tokio::time::timeout(Duration::from_secs(5), running).await.expect("collector stopped").expect("collector task")
+}
+
+#[tokio::test]
+async fn a_rotated_out_connection_keeps_streaming_until_the_new_one_catches_up() {
+    // the fixture's two-sided books are the first, second and last
+    let books = depth_fixture();
+    let now = Duration::ZERO;
+    let collector = rotate(vec![
+        vec![(now, books[0].clone()), (now, books[1].clone()), (Duration::from_millis(700), books[4].clone())],
+        // the replacement only ever delivers a book older than the last one handled
+        vec![(now, books[0].clone())],
+    ], Duration::from_millis(1200)).await;
+
+    assert_eq!(collector.counts().stored, 3);
+    assert_eq!(collector.counts().duplicates, 1);
+}
+
+#[tokio::test]
+async fn a_caught_up_replacement_drops_the_old_connection() {
+    let books = depth_fixture();
+    let now = Duration::ZERO;
+    let collector = rotate(vec![
+        vec![(now, books[0].clone()), (Duration::from_millis(700), books[4].clone())],
+        vec![(now, books[0].clone()), (now, books[1].clone())],
+    ], Duration::from_millis(1200)).await;
+
+    // the old connection's last book never arrives: it was closed once the new one
+    // delivered the book it had left off at
+    assert_eq!(collector.counts().received, 3);
+    assert_eq!(collector.counts().stored, 2);
+}
+
+#[tokio::test]
+async fn a_rotating_multiplexer_reads_both_connections_until_every_stream_has_moved() {
+    let books = depth_fixture();
+    let frame = |symbol: &str, book: &String| format!(r#"{{"stream":"{}@depth20@100ms","data":{}}}"#, symbol, book);
+    let ms = Duration::from_millis;
+    let url = serve_scripted(vec![
+        vec![(ms(100), frame("btcusdt", &books[0])), (ms(0), frame("ethusdt", &books[0])), (ms(600), frame("ethusdt", &books[1])), (ms(500), frame("ethusdt", &books[4]))],
+        // connected at the 300ms rotation: btcusdt moves over at once, ethusdt only at ~900ms
+        vec![(ms(100), frame("btcusdt", &books[1])), (ms(500), frame("ethusdt", &books[1]))],
+    ]).await;
+
+    let (control, changes) = mpsc::channel(4);
+    control.send(Control::Subscribe(vec!["btcusdt".into(), "ethusdt".into()])).await.expect("send");
+    let (stop, shutdown) = watch::channel(false);
+    let mut mux = Multiplexer::new(&url, |symbol| Collector::new(symbol, &url, MemoryOutput::default())).with_shutdown(shutdown);
+    mux.rotate_after = ms(300);
+    let stopping = async {
+        tokio::time::sleep(ms(1500)).await;
+        stop.send(true).expect("mux listening");
+    };
+    let (counts, ()) = tokio::join!(mux.run(changes), stopping);
+    let counts = counts.expect("mux run");
+
+    assert_eq!((counts["btcusdt"].stored, counts["btcusdt"].duplicates), (2, 0));
+    // ethusdt's second book only came on the old connection; its last book came after
+    // the new one had caught up and the old one was closed
+    assert_eq!((counts["ethusdt"].stored, counts["ethusdt"].duplicates), (2, 1));
+}
+
+#[tokio::test]
+async fn a_multiplexer_backing_off_stops_on_shutdown() {
+    // a port nothing listens on, so every connect fails and the mux backs off
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("bind");
+    let url = format!("ws://{}", listener.local_addr().expect("addr"));
+    drop(listener);
+
+    let (_control, changes) = mpsc::channel(4);
+    let (stop, shutdown) = watch::channel(false);
+    let mut mux = Multiplexer::new(&url, |symbol| Collector::new(symbol, &url, MemoryOutput::default())).with_shutdown(shutdown);
+    let stopping = async {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        stop.send(true).expect("mux listening");
+    };
+    // well inside the first second of backoff
+    let (counts, ()) = tokio::time::timeout(Duration::from_millis(600), async { tokio::join!(mux.run(changes), stopping) })
+        .await
+        .expect("shutdown during backoff");
+    assert!(counts.expect("mux run").is_empty());
+}
+
+#[tokio::test]
+async fn polls_rest_depth_while_the_websocket_is_down() {
+    let depth = serve_http(depth_fixture().remove(0)).await;
+    let (stop, shutdown) = watch::channel(false);
+    // nothing listens on the websocket port, so every connect fails
+    let mut collector = Collector::new("btcusdt", "ws://127.0.0.1:1", MemoryOutput::default())
+        .with_fallback(Fallback::new(&depth, 1, Duration::from_millis(50)))
+        .with_shutdown(shutdown);
+    let running = tokio::spawn(async move {
+        collector.run().await.expect("collector run");
+        collector
+    });
+
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    stop.send(true).expect("collector listening");
+    let collector = tokio::time::timeout(Duration::from_secs(5), running).await
+        .expect("collector stopped").expect("collector task");
+
+    // the idle book polled again and again is only stored once
+    let counts = collector.counts();
+    assert_eq!(counts.stored, 1);
+    assert!(counts.duplicates > 0, "{:?}", counts);
+    assert_eq!(counts.received, counts.stored + counts.duplicates);
+}
+
+#[tokio::test]
+async fn unchanged_books_are_stored_only_as_heartbeats() {
+    let idle = vec![depth_fixture().remove(0); 3];
+
+    let collector = run(idle.clone()).await;
+    assert_eq!((collector.counts().stored, collector.counts().duplicates), (1, 2));
+
+    let url = serve(idle).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default());
+    collector.reconnect = false;
+    collector.heartbeat = Some(Duration::ZERO);
+    collector.run().await.expect("collector run");
+    assert_eq!((collector.counts().stored, collector.counts().duplicates), (3, 0));
+}
+
+#[tokio::test]
+async fn top_of_book_mode_stores_a_minute_of_quotes_in_one_object() {
+    let quotes: Vec<String> = (1..=3)
+        .map(|u| format!(r#"{{"u":{},"s":"BTCUSDT","b":"65000.10","B":"1.5","a":"65000.20","A":"0.5"}}"#, u))
+        .chain([r#"{"result":null,"id":1}"#.to_string(), r#"{"u":2,"s":"BTCUSDT","b":"1","B":"1","a":"2","A":"1"}"#.to_string()])
+        .collect();
+    let url = serve(quotes).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default()).with_top_of_book();
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    let counts = collector.counts();
+    assert_eq!((counts.received, counts.stored, counts.skipped, counts.duplicates), (5, 3, 1, 1));
+    let [(key, body)] = &collector.output().objects[..] else { panic!("expected one object") };
+    assert!(key.starts_with("top/"), "{}", key);
+    let quotes: Vec<Quote> = Reader::new(&body[..]).expect("avro").map(|v| from_value(&v.expect("record")).expect("quote")).collect();
+    assert_eq!(quotes.len(), 3);
+    // keyed by its first quote, so another run's part of the minute keeps its own
+    assert!(key.ends_with(&format!("/{}.avro", quotes[0].timestamp_ms)), "{}", key);
+    assert_eq!(top::merge([quotes[1..].to_vec(), quotes[..2].to_vec()]), quotes);
+}
+
+/// Uploads that finish in reverse order, tracking how many run at once.
+#[derive(Default)]
+struct SlowOutput {
+    stored: Arc<Mutex<Vec<String>>>,
+    started: AtomicUsize,
+    running: Arc<AtomicUsize>,
+    most_running: Arc<AtomicUsize>,
+}
+
+impl Output for SlowOutput {
+    async fn write(&mut self, _: &str, _: &[u8]) -> Result<Delivery, Error> {
+        unreachable!("books are uploaded")
+    }
+
+    fn upload(&self, key: &str, _: Vec<u8>, _: Option<String>) -> Option<Upload> {
+        let (stored, running, most_running, key) = (self.stored.clone(), self.running.clone(), self.most_running.clone(), key.to_string());
+        let delay = Duration::from_millis(200 - 50 * self.started.fetch_add(1, Ordering::SeqCst).min(3) as u64);
+        Some(Box::pin(async move {
+            most_running.fetch_max(running.fetch_add(1, Ordering::SeqCst) + 1, Ordering::SeqCst);
+            tokio::time::sleep(delay).await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            stored.lock().unwrap().push(key);
+            Ok(Delivery::Stored { latency: delay, retries: 0 })
+        }))
+    }
+}
+
+#[tokio::test]
+async fn uploads_run_concurrently_up_to_the_limit() {
+    let url = serve(depth_fixture()).await;
+    let output = SlowOutput::default();
+    let (stored, most_running) = (output.stored.clone(), output.most_running.clone());
+    let mut collector = Collector::new("btcusdt", &url, output);
+    collector.reconnect = false;
+    collector.upload_concurrency = 2;
+    collector.run().await.expect("collector run");
+
+    // every upload finished before the run returned, never more than two at once
+    assert_eq!(stored.lock().unwrap().len(), 3);
+    assert_eq!(collector.counts().stored, 3);
+    assert_eq!(most_running.load(Ordering::SeqCst), 2);
+}
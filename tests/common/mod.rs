@@ -0,0 +1,222 @@
+//! Test-only websocket server, output and books for exercising the collector end to end.
+
+use futures_util::{SinkExt, StreamExt};
+use orderbook::book::Level;
+use orderbook::checkpoint::Checkpoint;
+use orderbook::sink::{Delivery, Output};
+use orderbook::{binance, Error, OrderBook};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+/// A one-level Binance BTCUSDT book at `ts`, bid 99 and ask 101.
+pub fn book(ts: i64) -> OrderBook {
+    priced(ts, 99.0, 101.0)
+}
+
+/// A one-level Binance BTCUSDT book at `ts` with a unit of quantity on each side.
+pub fn priced(ts: i64, bid: f64, ask: f64) -> OrderBook {
+    OrderBook::from_levels(ts, &[Level::new(bid, 1.0)], &[Level::new(ask, 1.0)]).expect("two-sided").with_source(binance::EXCHANGE, "btcusdt")
+}
+
+/// A one-level book a dollar wide around `mid`.
+pub fn around(ts: i64, mid: f64) -> OrderBook {
+    priced(ts, mid - 0.5, mid + 0.5)
+}
+
+static ENV: Mutex<()> = Mutex::new(());
+
+/// Environment variables set for one test, removed again when it drops. Tests of a
+/// binary that set any hold one for their whole run, so they take turns.
+pub struct Env {
+    names: Vec<&'static str>,
+    _turn: MutexGuard<'static, ()>,
+}
+
+pub fn set_env(vars: &[(&'static str, &str)]) -> Env {
+    let turn = ENV.lock().unwrap_or_else(PoisonError::into_inner);
+    for (name, value) in vars {
+        std::env::set_var(name, value);
+    }
+    Env { names: vars.iter().map(|(name, _)| *name).collect(), _turn: turn }
+}
+
+impl Drop for Env {
+    fn drop(&mut self) {
+        for name in &self.names {
+            std::env::remove_var(name);
+        }
+    }
+}
+
+/// Canned Binance partial depth payloads, one per line.
+pub fn depth_fixture() -> Vec<String> {
+    include_str!("../fixtures/depth20.jsonl").lines().map(str::to_string).collect()
+}
+
+/// Serves `messages` as text frames to the first client, then closes. Returns the ws:// URL.
+pub async fn serve(messages: Vec<String>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let addr = listener.local_addr().expect("local addr");
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("accept");
+        let mut ws = tokio_tungstenite::accept_async(stream).await.expect("handshake");
+        for msg in messages {
+            ws.send(Message::Text(msg)).await.expect("send");
+        }
+        ws.close(None).await.ok();
+    });
+
+    format!("ws://{}", addr)
+}
+
+/// Serves `messages` like `serve` but keeps the connection open until the client
+/// leaves. The handle finishes once everything has been sent.
+pub async fn serve_open(messages: Vec<String>) -> (String, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let addr = listener.local_addr().expect("local addr");
+    let (sent, done) = tokio::sync::oneshot::channel();
+
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("accept");
+        let mut ws = tokio_tungstenite::accept_async(stream).await.expect("handshake");
+        for msg in messages {
+            ws.send(Message::Text(msg)).await.expect("send");
+        }
+        sent.send(()).ok();
+        while let Some(Ok(_)) = ws.next().await {}
+    });
+
+    (format!("ws://{}", addr), tokio::spawn(async move { done.await.ok(); }))
+}
+
+/// Serves `messages` like `serve_open` to each of the first `clients` connections,
+/// as Binance does to overlapping ones. The handle finishes once the last has them.
+pub async fn serve_repeated(messages: Vec<String>, clients: usize) -> (String, tokio::task::JoinHandle<()>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let addr = listener.local_addr().expect("local addr");
+    let (sent, done) = tokio::sync::mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        for _ in 0..clients {
+            let (stream, _) = listener.accept().await.expect("accept");
+            let (messages, sent) = (messages.clone(), sent.clone());
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.expect("handshake");
+                for msg in messages {
+                    ws.send(Message::Text(msg)).await.expect("send");
+                }
+                sent.send(()).ok();
+                while let Some(Ok(_)) = ws.next().await {}
+            });
+        }
+    });
+
+    let mut done = done;
+    (format!("ws://{}", addr), tokio::spawn(async move {
+        for _ in 0..clients {
+            done.recv().await;
+        }
+    }))
+}
+
+/// Serves each script to one connection, in the order they connect: every message
+/// after waiting its delay, then keeps the connection open until the client leaves.
+pub async fn serve_scripted(scripts: Vec<Vec<(Duration, String)>>) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let addr = listener.local_addr().expect("local addr");
+
+    tokio::spawn(async move {
+        for script in scripts {
+            let (stream, _) = listener.accept().await.expect("accept");
+            tokio::spawn(async move {
+                let mut ws = tokio_tungstenite::accept_async(stream).await.expect("handshake");
+                for (delay, msg) in script {
+                    tokio::time::sleep(delay).await;
+                    if ws.send(Message::Text(msg)).await.is_err() {
+                        return;
+                    }
+                }
+                while let Some(Ok(_)) = ws.next().await {}
+            });
+        }
+    });
+
+    format!("ws://{}", addr)
+}
+
+/// Answers every HTTP request with `body`, like a REST depth endpoint. Returns the http:// URL.
+pub async fn serve_http(body: String) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let addr = listener.local_addr().expect("local addr");
+
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let body = body.clone();
+            tokio::spawn(async move {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 1024];
+                while !request.ends_with(b"\r\n\r\n") {
+                    match stream.read(&mut buf).await {
+                        Ok(0) | Err(_) => return,
+                        Ok(n) => request.extend_from_slice(&buf[..n]),
+                    }
+                }
+                let response = format!("HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}", body.len(), body);
+                stream.write_all(response.as_bytes()).await.ok();
+            });
+        }
+    });
+
+    format!("http://{}/api/v3/depth?symbol=BTCUSDT&limit=20", addr)
+}
+
+/// Waits for the client's first request, then wraps each `(stream, payload)` as a
+/// combined-stream message and closes. Returns the URL and the requests received.
+pub async fn serve_combined(messages: Vec<(String, String)>) -> (String, tokio::task::JoinHandle<Vec<String>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.expect("bind mock server");
+    let addr = listener.local_addr().expect("local addr");
+
+    let requests = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.expect("accept");
+        let mut ws = tokio_tungstenite::accept_async(stream).await.expect("handshake");
+        let request = ws.next().await.expect("request").expect("frame").into_text().expect("text");
+        ws.send(Message::Text(r#"{"result":null,"id":1}"#.into())).await.expect("send");
+        for (stream, payload) in messages {
+            ws.send(Message::Text(format!(r#"{{"stream":"{}","data":{}}}"#, stream, payload))).await.expect("send");
+        }
+        ws.close(None).await.ok();
+        vec![request]
+    });
+
+    (format!("ws://{}", addr), requests)
+}
+
+/// Collects every write in memory instead of uploading it.
+#[derive(Default)]
+pub struct MemoryOutput {
+    pub objects: Vec<(String, Vec<u8>)>,
+    /// Claimed keys; outputs sharing it stand in for instances sharing a bucket.
+    pub claims: Arc<Mutex<HashSet<String>>>,
+    pub checkpoints: Vec<Checkpoint>,
+}
+
+impl Output for MemoryOutput {
+    async fn write(&mut self, key: &str, body: &[u8]) -> Result<Delivery, Error> {
+        self.objects.push((key.to_string(), body.to_vec()));
+        Ok(Delivery::Stored { latency: Duration::ZERO, retries: 0 })
+    }
+
+    async fn claim(&mut self, key: &str) -> Result<bool, Error> {
+        Ok(self.claims.lock().expect("claims").insert(key.to_string()))
+    }
+
+    async fn checkpoint(&mut self, checkpoint: &Checkpoint) -> Result<(), Error> {
+        self.checkpoints.push(checkpoint.clone());
+        Ok(())
+    }
+}
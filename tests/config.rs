@@ -0,0 +1,111 @@
+#[allow(dead_code)]
+mod common;
+
+use common::set_env;
+use orderbook::book::{parse_depths, parse_notionals};
+use orderbook::config::{self, parse_symbols, Storage};
+use orderbook::params;
+use std::collections::BTreeMap;
+
+#[test]
+fn prefix_is_applied_and_stripped() {
+    let storage = Storage::new("orderbook-staging", "/env/staging/", Some("eu-west-1")).expect("valid");
+    assert_eq!(storage.prefix, "env/staging");
+    assert_eq!(storage.key("orderbook/year=2025/1.avro"), "env/staging/orderbook/year=2025/1.avro");
+    assert_eq!(storage.relative("env/staging/orderbook/year=2025/1.avro"), "orderbook/year=2025/1.avro");
+
+    let root = Storage::new("orderbook-data", "", None).expect("valid");
+    assert_eq!(root.key("orderbook/1.avro"), "orderbook/1.avro");
+    assert_eq!(root.relative("orderbook/1.avro"), "orderbook/1.avro");
+}
+
+#[test]
+fn invalid_settings_are_rejected() {
+    for bucket in ["ab", "Orderbook-Data", "orderbook_data", "-orderbook", "orderbook.", "order..book"] {
+        assert!(Storage::new(bucket, "", None).is_err(), "accepted bucket {:?}", bucket);
+    }
+    assert!(Storage::new("orderbook-data", "a//b", None).is_err());
+    for region in ["", "useast1", "us-east", "US-EAST-1", "us-east-x"] {
+        assert!(Storage::new("orderbook-data", "", Some(region)).is_err(), "accepted region {:?}", region);
+    }
+    assert!(Storage::new("orderbook-data", "", Some("us-gov-west-1")).is_ok());
+}
+
+#[test]
+fn kms_keys_must_be_key_or_alias_arns() {
+    let storage = Storage::new("orderbook-data", "", None).expect("valid");
+    assert_eq!(storage.kms_key, None);
+    for arn in [
+        "arn:aws:kms:us-east-1:123456789012:key/1234abcd-12ab-34cd-56ef-1234567890ab",
+        "arn:aws-us-gov:kms:us-gov-west-1:123456789012:alias/orderbook",
+    ] {
+        let encrypted = storage.clone().with_kms_key(Some(arn)).expect("valid");
+        assert_eq!(encrypted.kms_key.as_deref(), Some(arn));
+    }
+    for arn in ["1234abcd-12ab-34cd-56ef-1234567890ab", "alias/orderbook", "arn:aws:s3:::orderbook-data", "arn:aws:kms:us-east-1:1234:key/abc", "arn:aws:kms:us-east-1:123456789012:key/"] {
+        assert!(storage.clone().with_kms_key(Some(arn)).is_err(), "accepted {:?}", arn);
+    }
+}
+
+#[test]
+fn storage_classes_apply_per_dataset() {
+    let storage = Storage::new("orderbook-data", "staging", None).expect("valid")
+        .with_storage_classes(Some("intelligent_tiering"), Some("raw:ONEZONE_IA, fulldepth:STANDARD_IA"))
+        .expect("valid");
+    assert_eq!(storage.storage_class("raw/exchange=binance/1.avro"), Some("ONEZONE_IA"));
+    assert_eq!(storage.storage_class("fulldepth/exchange=binance/1.avro"), Some("STANDARD_IA"));
+    assert_eq!(storage.storage_class("orderbook/exchange=binance/1.avro"), Some("INTELLIGENT_TIERING"));
+
+    let plain = Storage::new("orderbook-data", "", None).expect("valid").with_storage_classes(None, Some("dlq:ONEZONE_IA")).expect("valid");
+    assert_eq!(plain.storage_class("dlq/orderbook/1.avro"), Some("ONEZONE_IA"));
+    assert_eq!(plain.storage_class("orderbook/1.avro"), None);
+
+    let storage = Storage::new("orderbook-data", "", None).expect("valid");
+    assert!(storage.clone().with_storage_classes(Some("DEEP_ARCHIVE"), None).is_err(), "can't be read back");
+    for list in ["raw", "raw:", ":ONEZONE_IA", "raw/x:STANDARD", "raw:COLD"] {
+        assert!(storage.clone().with_storage_classes(None, Some(list)).is_err(), "accepted {:?}", list);
+    }
+}
+
+#[test]
+fn symbols_are_lowercased_and_deduplicated() {
+    assert_eq!(parse_symbols("BTCUSDT, ethusdt,,btcusdt").expect("valid"), ["btcusdt", "ethusdt"]);
+    for list in ["", " , ", "btc/usdt", "btcusdt@depth"] {
+        assert!(parse_symbols(list).is_err(), "accepted {:?}", list);
+    }
+}
+
+#[test]
+fn overrides_take_precedence_over_the_environment() {
+    let _env = set_env(&[("ORDERBOOK_TEST_SETTING", "from-env")]);
+    assert_eq!(config::var("ORDERBOOK_TEST_SETTING").as_deref(), Some("from-env"));
+
+    config::set_overrides(BTreeMap::from([("ORDERBOOK_TEST_SETTING".to_string(), "from-ssm".to_string())]));
+    assert_eq!(config::var("ORDERBOOK_TEST_SETTING").as_deref(), Some("from-ssm"));
+    config::set_overrides(BTreeMap::new());
+    assert_eq!(config::var("ORDERBOOK_TEST_SETTING").as_deref(), Some("from-env"));
+}
+
+#[test]
+fn parameters_map_to_settings_directly_under_the_path() {
+    assert_eq!(params::setting_name("/orderbook/prod", "/orderbook/prod/SYMBOLS"), Some("SYMBOLS"));
+    assert_eq!(params::setting_name("/orderbook/prod/", "/orderbook/prod/SYMBOLS"), Some("SYMBOLS"));
+    assert_eq!(params::setting_name("/orderbook/prod", "/orderbook/prod/nested/SYMBOLS"), None);
+    assert_eq!(params::setting_name("/orderbook/prod", "/orderbook/production/SYMBOLS"), None);
+}
+
+#[test]
+fn depth_buckets_must_increase_within_the_book() {
+    assert_eq!(parse_depths("0.001, 0.005,0.01").expect("valid"), [0.001, 0.005, 0.01]);
+    for list in ["", "0.01,0.005", "0.001,0.001", "0,0.01", "0.5,1.5", "1%"] {
+        assert!(parse_depths(list).is_err(), "accepted {:?}", list);
+    }
+}
+
+#[test]
+fn notional_thresholds_must_be_increasing_amounts() {
+    assert_eq!(parse_notionals("10000, 250000").expect("valid"), [10_000.0, 250_000.0]);
+    for list in ["", "100000,10000", "0,10000", "-5", "inf", "$10k"] {
+        assert!(parse_notionals(list).is_err(), "accepted {:?}", list);
+    }
+}
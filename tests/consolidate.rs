@@ -0,0 +1,78 @@
+#[allow(dead_code)]
+mod common;
+
+use common::priced;
+use orderbook::consolidate::{align, consolidate};
+use orderbook::{schema, sink, OrderBook};
+use std::collections::BTreeMap;
+
+fn streams(venues: Vec<(&str, Vec<OrderBook>)>) -> BTreeMap<String, Vec<OrderBook>> {
+    venues.into_iter().map(|(venue, books)| (venue.to_string(), books)).collect()
+}
+
+#[test]
+fn align_takes_each_venues_latest_book_at_every_instant() {
+    let streams = streams(vec![
+        ("com", vec![priced(900, 100.0, 101.0), priced(1900, 100.5, 101.5)]),
+        ("us", vec![priced(1100, 99.0, 102.0)]),
+    ]);
+
+    let aligned: Vec<(i64, Vec<(&str, i64)>)> = align(&streams, 1000, 5000).into_iter()
+        .map(|(at, books)| (at, books.into_iter().map(|(venue, b)| (venue, b.timestamp_ms)).collect()))
+        .collect();
+    assert_eq!(aligned, vec![
+        (1000, vec![("com", 900)]),
+        (2000, vec![("com", 1900), ("us", 1100)]),
+    ]);
+}
+
+#[test]
+fn align_leaves_out_stale_books() {
+    let streams = streams(vec![
+        ("com", vec![priced(0, 100.0, 101.0), priced(3000, 100.0, 101.0)]),
+        ("us", vec![priced(0, 99.0, 102.0)]),
+    ]);
+
+    let venues: Vec<usize> = align(&streams, 1000, 1500).into_iter().map(|(_, books)| books.len()).collect();
+    // both at 0 and 1000, neither at 2000, then com's second book alone
+    assert_eq!(venues, vec![2, 2, 1]);
+}
+
+#[test]
+fn consolidated_quote_takes_the_best_price_on_each_side() {
+    let (com, us) = (priced(0, 100.0, 101.5), priced(0, 100.5, 102.0));
+    let record = consolidate("btcusdt", 0, &[("com", &com), ("us", &us)]).expect("books");
+
+    assert_eq!((record.best_bid, record.best_bid_venue.as_str()), (100.5, "us"));
+    assert_eq!((record.best_ask, record.best_ask_venue.as_str()), (101.5, "com"));
+    assert_eq!((record.spread, record.mid_price), (1.0, 101.0));
+    assert_eq!(record.symbol, "BTCUSDT");
+    assert_eq!(record.venues.iter().map(|v| v.venue.as_str()).collect::<Vec<_>>(), vec!["com", "us"]);
+}
+
+#[test]
+fn consolidated_depth_sums_the_venues_buckets() {
+    let (com, us) = (priced(0, 100.0, 101.0), priced(0, 100.0, 101.0));
+    let record = consolidate("BTCUSDT", 0, &[("com", &com), ("us", &us)]).expect("books");
+
+    for (i, level) in record.bids.iter().enumerate() {
+        assert_eq!(level.qty, com.bids[i].qty + us.bids[i].qty);
+        assert!(level.price < record.mid_price);
+    }
+    assert_eq!(record.venues[0].ask_depth, com.asks.iter().map(|l| l.qty).collect::<Vec<_>>());
+}
+
+#[test]
+fn crossed_venues_give_a_negative_spread() {
+    let (com, us) = (priced(0, 101.0, 101.5), priced(0, 100.0, 100.5));
+    let record = consolidate("BTCUSDT", 0, &[("com", &com), ("us", &us)]).expect("books");
+    assert_eq!(record.spread, -0.5);
+}
+
+#[test]
+fn consolidated_records_encode() {
+    let com = priced(0, 100.0, 101.0);
+    let record = consolidate("BTCUSDT", 0, &[("com", &com)]).expect("book");
+    assert!(sink::encode(schema::CONSOLIDATED, &[record]).is_ok());
+    assert!(consolidate("BTCUSDT", 0, &[]).is_none());
+}
@@ -0,0 +1,43 @@
+use orderbook::correlation::{correlate, lead_lag, parse_pairs, pearson, Settings};
+use orderbook::{schema, sink};
+
+#[test]
+fn pairs_are_lowercased_and_deduplicated() {
+    let pairs = parse_pairs("BTCUSDT:ethusdt, btcusdt:ETHUSDT,,btcusdt:solusdt").expect("valid");
+    assert_eq!(pairs, [("btcusdt".to_string(), "ethusdt".to_string()), ("btcusdt".to_string(), "solusdt".to_string())]);
+    for list in ["", "btcusdt", "btcusdt:btcusdt", "btcusdt:", "btc/usdt:ethusdt"] {
+        assert!(parse_pairs(list).is_err(), "accepted {:?}", list);
+    }
+}
+
+#[test]
+fn a_follower_shows_up_at_its_lag() {
+    // y repeats x's returns two steps later
+    let x: Vec<f64> = (0..60).map(|i| ((i * 7919) % 13) as f64 - 6.0).collect();
+    let y: Vec<f64> = (0..60).map(|i| if i >= 2 { x[i - 2] } else { 0.0 }).collect();
+    let (lag, corr) = lead_lag(&x, &y, 5).expect("varying series");
+    assert_eq!(lag, 2);
+    assert!((corr - 1.0).abs() < 1e-9);
+    assert_eq!(lead_lag(&y, &x, 5).map(|(lag, _)| lag), Some(-2));
+    assert_eq!(pearson(&x, &[1.0; 60]), None, "a flat series doesn't correlate");
+}
+
+#[test]
+fn mids_are_correlated_through_their_returns() {
+    let settings = Settings { step_ms: 1_000, max_lag_ms: 3_000, ..Settings::default() };
+    let mids: Vec<(f64, f64)> = (0..30).map(|i| {
+        let wiggle = if i % 3 == 0 { 1.0 } else { -0.5 };
+        (100.0 + i as f64 * 0.1 + wiggle, 50.0 + i as f64 * 0.05 + wiggle / 2.0)
+    }).collect();
+    let pair = ("btcusdt".to_string(), "ethusdt".to_string());
+
+    let record = correlate(&pair, &mids, 60_000, &settings).expect("enough samples");
+    assert_eq!((record.symbol.as_str(), record.other.as_str()), ("BTCUSDT", "ETHUSDT"));
+    assert_eq!((record.start_ms, record.end_ms, record.samples), (31_000, 60_000, 29));
+    assert!(record.correlation > 0.99, "co-moving mids, got {}", record.correlation);
+    assert_eq!(record.best_lag_ms % 1_000, 0);
+    assert!(record.lag_correlation.abs() >= record.correlation.abs() - 1e-12);
+    assert!(sink::encode(schema::CORRELATION, &[record]).is_ok());
+
+    assert!(correlate(&pair, &mids[..2], 60_000, &settings).is_none(), "one return can't correlate");
+}
@@ -0,0 +1,52 @@
+use orderbook::book::{exact_levels, ExactLevel, Level};
+use orderbook::{migrate, schema, sink, OrderBook};
+
+fn exact(price: i64, qty: i64) -> ExactLevel {
+    ExactLevel { price, qty }
+}
+
+#[test]
+fn levels_share_the_widest_scale_of_their_column() {
+    let bids = [["65000.1", "0.5"], ["64999", "1.250"]];
+    let asks = [["65000.20", "2"]];
+
+    let (bids, asks, price_scale, qty_scale) = exact_levels(&bids, &asks).expect("plain decimals");
+    assert_eq!((price_scale, qty_scale), (2, 3));
+    assert_eq!(bids, vec![exact(6500010, 500), exact(6499900, 1250)]);
+    assert_eq!(asks, vec![exact(6500020, 2000)]);
+}
+
+#[test]
+fn levels_that_are_not_plain_decimals_are_refused() {
+    assert_eq!(exact_levels(&[["6.5e4", "1"]], &[]), None);
+    assert_eq!(exact_levels(&[["-1.0", "1"]], &[]), None);
+    assert_eq!(exact_levels(&[[".5", "1"]], &[]), None);
+    // 20 digits don't fit in an i64
+    assert_eq!(exact_levels(&[["12345678901234567890", "1"]], &[]), None);
+}
+
+#[test]
+fn exact_levels_survive_avro_unchanged() {
+    let bids = [["65000.10000000", "0.00012345"]];
+    let asks = [["65000.20000000", "3.00000000"]];
+    let book = OrderBook::from_levels(1, &[Level::new(65000.1, 0.00012345)], &[Level::new(65000.2, 3.0)])
+        .expect("two-sided")
+        .with_exact_levels(&bids, &asks);
+
+    let bytes = sink::encode(schema::ORDERBOOK, &[book]).expect("encode");
+    let [decoded] = &migrate::read_orderbooks(&bytes).expect("decode")[..] else { panic!("one book") };
+    assert_eq!((decoded.price_scale, decoded.qty_scale), (8, 8));
+    assert_eq!(decoded.exact_bids, vec![exact(6_500_010_000_000, 12345)]);
+    assert_eq!(decoded.exact_asks, vec![exact(6_500_020_000_000, 300_000_000)]);
+}
+
+#[test]
+fn older_schemas_drop_exact_levels() {
+    let book = OrderBook::from_levels(1, &[Level::new(1.0, 1.0)], &[Level::new(2.0, 1.0)])
+        .expect("two-sided")
+        .with_exact_levels(&[["1.0", "1"]], &[["2.0", "1"]]);
+
+    let bytes = sink::encode(schema::ORDERBOOK_V6, &[book]).expect("encode");
+    let [decoded] = &migrate::read_orderbooks(&bytes).expect("decode")[..] else { panic!("one book") };
+    assert!(decoded.exact_bids.is_empty() && decoded.exact_asks.is_empty());
+}
@@ -0,0 +1,47 @@
+#![cfg(feature = "delta")]
+
+#[allow(dead_code)]
+mod common;
+
+use common::{book, set_env};
+use orderbook::delta::DeltaSink;
+
+#[test]
+fn books_are_batched_by_minute() {
+    let mut sink = DeltaSink::new("unused");
+    assert!(sink.push(book(60_000)).is_none());
+    assert!(sink.push(book(119_999)).is_none());
+    let done = sink.push(book(120_000)).expect("minute rolled over");
+    assert_eq!(done.iter().map(|b| b.timestamp_ms).collect::<Vec<_>>(), [60_000, 119_999]);
+    assert!(sink.flush_ended(179_999).is_none(), "minute still open");
+    assert_eq!(sink.flush_ended(180_000).map(|books| books.len()), Some(1));
+    assert!(sink.flush().is_none());
+}
+
+#[tokio::test]
+async fn appends_create_the_table_and_commit_one_file_each() {
+    let root = std::env::temp_dir().join(format!("orderbook-delta-{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let uri = root.to_str().unwrap();
+
+    let mut sink = DeltaSink::new(uri);
+    assert_eq!(sink.append(&[book(60_000), book(61_000)]).await.unwrap(), 1);
+    assert_eq!(sink.append(&[book(120_000)]).await.unwrap(), 2);
+
+    let table = orderbook::delta::open(uri).await.unwrap();
+    assert_eq!(table.version(), 2);
+    let files: Vec<_> = table.get_files_iter().unwrap().collect();
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().all(|f| f.as_ref().starts_with("BTCUSDT/") && f.as_ref().ends_with(".parquet")));
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn s3_tables_are_written_with_the_configured_kms_key() {
+    let key = "arn:aws:kms:us-east-1:123456789012:key/1234abcd-12ab-34cd-56ef-1234567890ab";
+    let _env = set_env(&[("S3_KMS_KEY_ARN", key)]);
+    let options = orderbook::delta::storage_options("s3://bucket/delta/orderbook").unwrap();
+    assert_eq!(options.get("aws_server_side_encryption").map(String::as_str), Some("aws:kms"));
+    assert_eq!(options.get("aws_sse_kms_key_id").map(String::as_str), Some(key));
+    assert!(orderbook::delta::storage_options("/tmp/delta").unwrap().is_empty());
+}
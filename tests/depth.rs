@@ -0,0 +1,131 @@
+use orderbook::book::{normalize_to_depths, notional_to_depths, slippage, sweeps, Level, DEPTHS};
+use orderbook::OrderBook;
+use proptest::prelude::*;
+
+fn level() -> impl Strategy<Value = Level> {
+    (1.0f64..200_000.0, 0.0f64..1_000.0).prop_map(|(price, qty)| Level::new(price, qty))
+}
+
+fn levels() -> impl Strategy<Value = Vec<Level>> {
+    prop::collection::vec(level(), 0..40)
+}
+
+proptest! {
+    #[test]
+    fn cumulative_volume_is_monotone(levels in levels(), mid in 1.0f64..200_000.0, is_ask: bool) {
+        let buckets = normalize_to_depths(&levels, mid, is_ask);
+        prop_assert_eq!(buckets.len(), DEPTHS.len());
+        for pair in buckets.windows(2) {
+            prop_assert!(pair[1].qty >= pair[0].qty, "{:?} then {:?}", pair[0], pair[1]);
+        }
+    }
+
+    #[test]
+    fn targets_sit_on_their_side_of_mid(levels in levels(), mid in 1.0f64..200_000.0) {
+        for bid in normalize_to_depths(&levels, mid, false) {
+            prop_assert!(bid.price < mid);
+        }
+        for ask in normalize_to_depths(&levels, mid, true) {
+            prop_assert!(ask.price > mid);
+        }
+    }
+
+    #[test]
+    fn volume_never_exceeds_the_side_total(levels in levels(), mid in 1.0f64..200_000.0, is_ask: bool) {
+        let total: f64 = levels.iter().map(|l| l.qty).sum();
+        for bucket in normalize_to_depths(&levels, mid, is_ask) {
+            prop_assert!(bucket.qty >= 0.0);
+            prop_assert!(bucket.qty <= total * (1.0 + 1e-9));
+        }
+    }
+
+    #[test]
+    fn notional_is_the_bucket_volume_at_its_levels_prices(levels in levels(), mid in 1.0f64..200_000.0, is_ask: bool) {
+        let volume = normalize_to_depths(&levels, mid, is_ask);
+        let notional = notional_to_depths(&levels, mid, is_ask);
+        prop_assert_eq!(notional.len(), volume.len());
+        for (amount, bucket) in notional.iter().zip(&volume) {
+            // every level counted is on the near side of the bucket's target price
+            let at_target = bucket.qty * bucket.price;
+            if is_ask {
+                prop_assert!(*amount <= at_target * (1.0 + 1e-9));
+            } else {
+                prop_assert!(*amount >= at_target * (1.0 - 1e-9));
+            }
+        }
+    }
+
+    #[test]
+    fn arbitrary_books_do_not_panic(
+        ts in any::<i64>(),
+        bids in prop::collection::vec((any::<f64>(), any::<f64>()), 0..10),
+        asks in prop::collection::vec((any::<f64>(), any::<f64>()), 0..10),
+    ) {
+        let bids: Vec<_> = bids.into_iter().map(|(p, q)| Level::new(p, q)).collect();
+        let asks: Vec<_> = asks.into_iter().map(|(p, q)| Level::new(p, q)).collect();
+        let book = OrderBook::from_levels(ts, &bids, &asks);
+        prop_assert_eq!(book.is_some(), !bids.is_empty() && !asks.is_empty());
+    }
+}
+
+#[test]
+fn empty_side_has_zero_volume_at_every_depth() {
+    for bucket in normalize_to_depths(&[], 65000.0, true) {
+        assert_eq!(bucket.qty, 0.0);
+    }
+}
+
+#[test]
+fn degenerate_mid_does_not_panic() {
+    let levels = [Level::new(1.0, 1.0), Level::new(0.0, 0.0)];
+    for mid in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+        assert_eq!(normalize_to_depths(&levels, mid, false).len(), DEPTHS.len());
+        assert_eq!(normalize_to_depths(&levels, mid, true).len(), DEPTHS.len());
+    }
+}
+
+#[test]
+fn zero_quantity_book_is_balanced() {
+    let book = OrderBook::from_levels(0, &[Level::new(99.0, 0.0)], &[Level::new(101.0, 0.0)]).unwrap();
+    assert_eq!(book.imbalance_ratio, 0.0);
+    assert!(book.bids.iter().chain(&book.asks).all(|l| l.qty == 0.0));
+}
+
+#[test]
+fn sweeps_stop_at_the_level_that_fills_each_notional() {
+    let asks = [Level::new(100.0, 50.0), Level::new(101.0, 100.0), Level::new(102.0, 1000.0)];
+    let filled = sweeps(&asks, &[1_000.0, 5_000.0, 12_000.0, 1_000_000.0]);
+
+    assert_eq!(filled[0], Level::new(100.0, 10.0));
+    assert_eq!(filled[1], Level::new(100.0, 50.0));
+    assert_eq!(filled[2].price, 101.0);
+    assert!((filled[2].qty - (50.0 + 7_000.0 / 101.0)).abs() < 1e-9);
+    // the visible book holds about 117k
+    assert_eq!(filled[3], Level::new(0.0, 0.0));
+}
+
+#[test]
+fn books_carry_notional_depth_and_sweeps() {
+    let book = OrderBook::from_levels(0, &[Level::new(99.99, 200.0)], &[Level::new(100.01, 50.0)]).unwrap();
+    assert_eq!(book.bid_notional.len(), DEPTHS.len());
+    assert!((book.bid_notional[0] - 99.99 * 200.0).abs() < 1e-9);
+    assert!((book.ask_notional[0] - 100.01 * 50.0).abs() < 1e-9);
+    assert_eq!(book.bid_sweeps[0].price, 99.99);
+    assert_eq!(book.ask_sweeps, vec![Level::new(0.0, 0.0); 3]);
+}
+
+#[test]
+fn slippage_is_measured_from_mid_against_the_order() {
+    let asks = [Level::new(100.5, 10.0), Level::new(101.5, 10.0)];
+    let bids = [Level::new(99.5, 10.0), Level::new(98.5, 10.0)];
+    let notionals = [1_005.0, 2_020.0, 1e9];
+
+    let (prices, bps) = slippage(&sweeps(&asks, &notionals), &notionals, 100.0, true);
+    assert_eq!(prices[0], 100.5);
+    assert!((bps[0] - 50.0).abs() < 1e-9);
+    assert!(prices[1] > 100.5 && prices[1] < 101.5 && bps[1] > bps[0]);
+    assert_eq!((prices[2], bps[2]), (0.0, 0.0));
+
+    let (prices, bps) = slippage(&sweeps(&bids, &notionals[..1]), &notionals[..1], 100.0, false);
+    assert!(prices[0] < 100.0 && bps[0] > 0.0, "selling fills below mid");
+}
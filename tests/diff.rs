@@ -0,0 +1,37 @@
+use orderbook::book::Level;
+use orderbook::diff;
+use orderbook::OrderBook;
+use serde_json::json;
+
+fn book(bids: &[Level], asks: &[Level]) -> OrderBook {
+    OrderBook::from_levels(1_000, bids, asks).expect("two-sided")
+}
+
+#[test]
+fn levels_and_features_that_differ_are_listed_with_their_change() {
+    let before = book(&[Level::new(100.0, 1.0)], &[Level::new(101.0, 1.0)]);
+    let after = book(&[Level::new(100.0, 1.0)], &[Level::new(101.0, 3.0)]);
+    assert!(diff::books(&before, &before).unwrap().is_empty());
+
+    let changes = diff::books(&before, &after).unwrap();
+    let fields: Vec<&str> = changes.iter().map(|c| c.field.as_str()).collect();
+    assert!(fields.contains(&"imbalance_ratio"), "{:?}", fields);
+    assert!(!fields.contains(&"mid_price") && !fields.iter().any(|f| f.starts_with("bids")), "{:?}", fields);
+
+    let qty = changes.iter().find(|c| c.field.starts_with("asks[") && c.field.ends_with(".qty")).expect("an ask level changed");
+    assert_eq!(qty.delta(), Some(qty.after.as_f64().unwrap() - qty.before.as_f64().unwrap()));
+    assert!(qty.to_string().starts_with(&format!("{}: ", qty.field)));
+}
+
+#[test]
+fn a_level_only_one_book_has_shows_as_missing_on_the_other() {
+    let mut before = book(&[Level::new(100.0, 1.0)], &[Level::new(101.0, 1.0)]);
+    let mut after = before.clone();
+    before.bid_notional = vec![10.0];
+    after.bid_notional = vec![10.0, 20.0];
+
+    let changes = diff::books(&before, &after).unwrap();
+    assert_eq!(changes.len(), 1, "{:?}", changes);
+    assert_eq!((changes[0].field.as_str(), &changes[0].before, &changes[0].after), ("bid_notional[1]", &json!(null), &json!(20.0)));
+    assert_eq!(changes[0].to_string(), "bid_notional[1]: - -> 20.0");
+}
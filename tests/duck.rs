@@ -0,0 +1,31 @@
+#![cfg(feature = "duckdb")]
+
+use orderbook::book::Level;
+use orderbook::duck::DuckSink;
+use orderbook::OrderBook;
+
+fn book(ts: i64, bid: f64) -> OrderBook {
+    OrderBook::from_levels(ts, &[Level::new(bid, 1.5), Level::new(bid - 0.5, 2.0)], &[Level::new(101.0, 1.0)])
+        .unwrap()
+        .with_source("binance", "btcusdt")
+}
+
+#[test]
+fn books_append_across_opens_and_query_back() {
+    let path = std::env::temp_dir().join(format!("orderbook-duck-{}.duckdb", std::process::id()));
+    let _ = std::fs::remove_file(&path);
+
+    assert_eq!(DuckSink::open(&path).unwrap().append(&[book(1_000, 99.0), book(2_000, 99.5)]).unwrap(), 2);
+    let mut sink = DuckSink::open(&path).unwrap();
+    sink.append(&[book(3_000, 100.0)]).unwrap();
+
+    let (rows, symbol): (i64, String) = sink.connection()
+        .query_row("SELECT count(*), any_value(symbol) FROM orderbook", [], |row| Ok((row.get(0)?, row.get(1)?)))
+        .unwrap();
+    assert_eq!((rows, symbol.as_str()), (3, "BTCUSDT"));
+    let best: f64 = sink.connection()
+        .query_row("SELECT bids[1].price FROM orderbook WHERE timestamp_ms = 3000", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(best, book(3_000, 100.0).bids[0].price);
+    let _ = std::fs::remove_file(&path);
+}
@@ -0,0 +1,19 @@
+use orderbook::book::Level;
+use orderbook::export::{self, Field};
+use orderbook::OrderBook;
+
+#[test]
+fn chosen_fields_flatten_into_columns_with_a_pair_per_depth_bucket() {
+    let fields = Field::parse_list(export::DEFAULT_FIELDS).unwrap();
+    assert_eq!(export::header(&fields),
+               "timestamp_ms,mid_price,spread,imbalance_ratio,\
+                bid_depth_1bps,bid_depth_5bps,bid_depth_10bps,bid_depth_50bps,bid_depth_100bps,\
+                ask_depth_1bps,ask_depth_5bps,ask_depth_10bps,ask_depth_50bps,ask_depth_100bps");
+
+    let book = OrderBook::from_levels(1_000, &[Level::new(99.0, 2.0)], &[Level::new(101.0, 1.0)]).unwrap();
+    let mut out = Vec::new();
+    export::write(&mut out, &[Field::Timestamp, Field::Mid, Field::Spread], &[book.clone(), book]).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "timestamp_ms,mid_price,spread\n1000,100,2\n1000,100,2\n");
+
+    assert!(Field::parse_list("timestamp,bogus").is_err());
+}
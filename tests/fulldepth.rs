@@ -0,0 +1,151 @@
+use chrono::{Duration, TimeZone, Utc};
+use orderbook::archive::Archive;
+use orderbook::binance::{DepthUpdate, PartialDepth, RawLevel};
+use orderbook::book::ExactLevel;
+use orderbook::fulldepth::{self, Applied, Encoder, LocalBook};
+use orderbook::{schema, sink};
+
+fn snapshot(last_update_id: u64) -> LocalBook {
+    let depth = PartialDepth {
+        last_update_id,
+        bids: vec![["100.00", "1.5"], ["99.99", "2"]],
+        asks: vec![["100.01", "0.25"], ["100.02", "3"]],
+    };
+    LocalBook::from_snapshot(&depth).expect("decimal levels")
+}
+
+fn diff<'a>(first: u64, last: u64, bids: Vec<RawLevel<'a>>, asks: Vec<RawLevel<'a>>) -> DepthUpdate<'a> {
+    DepthUpdate { event_time_ms: 1, symbol: "BTCUSDT", first_update_id: first, final_update_id: last, prev_final_update_id: 0, bids, asks }
+}
+
+fn level(price: i64, qty: i64) -> ExactLevel {
+    ExactLevel { price: price * 1_000_000, qty: qty * 1_000_000 }
+}
+
+#[test]
+fn snapshot_levels_are_stored_at_eight_decimals_best_first() {
+    let (bids, asks) = snapshot(10).levels(fulldepth::LIMIT);
+    assert_eq!(bids, vec![level(10_000, 150), level(9_999, 200)]);
+    assert_eq!(asks, vec![level(10_001, 25), level(10_002, 300)]);
+}
+
+#[test]
+fn diffs_follow_the_snapshot_and_then_each_other() {
+    let mut book = snapshot(10);
+    assert_eq!(book.apply(&diff(5, 10, vec![["100.00", "9"]], vec![])), Applied::Stale);
+    // the first diff straddles the snapshot
+    assert_eq!(book.apply(&diff(8, 12, vec![["100.00", "0"]], vec![["100.03", "1"]])), Applied::Applied);
+    assert_eq!(book.apply(&diff(13, 15, vec![], vec![])), Applied::Applied);
+    assert_eq!(book.last_update_id, 15);
+
+    let (bids, asks) = book.levels(fulldepth::LIMIT);
+    assert_eq!(bids, vec![level(9_999, 200)]);
+    assert_eq!(asks.last(), Some(&level(10_003, 100)));
+
+    assert_eq!(book.apply(&diff(17, 18, vec![], vec![])), Applied::Gap);
+}
+
+#[test]
+fn futures_diffs_chain_on_the_previous_final_id() {
+    let mut book = snapshot(10);
+    assert_eq!(book.apply(&diff(9, 12, vec![], vec![])), Applied::Applied);
+    let next = DepthUpdate { prev_final_update_id: 12, ..diff(20, 25, vec![], vec![]) };
+    assert_eq!(book.apply(&next), Applied::Applied);
+    let skipped = DepthUpdate { prev_final_update_id: 24, ..diff(26, 30, vec![], vec![]) };
+    assert_eq!(book.apply(&skipped), Applied::Gap);
+}
+
+#[test]
+fn a_diff_that_starts_past_the_snapshot_is_a_gap() {
+    assert_eq!(snapshot(10).apply(&diff(12, 14, vec![], vec![])), Applied::Gap);
+}
+
+#[test]
+fn changes_between_keyframes_rebuild_the_book() {
+    let mut book = snapshot(10);
+    let mut encoder = Encoder::new("btcusdt", 3);
+    let mut records = vec![encoder.encode(&book, 0, 1_000).expect("keyframe")];
+
+    book.apply(&diff(11, 11, vec![["99.99", "0"], ["99.98", "4"]], vec![["100.01", "1"]]));
+    records.push(encoder.encode(&book, 1, 2_000).expect("changes"));
+    assert!(encoder.encode(&book, 1, 3_000).is_none(), "unchanged books aren't written");
+    book.apply(&diff(12, 12, vec![], vec![["100.02", "0"]]));
+    records.push(encoder.encode(&book, 2, 4_000).expect("changes"));
+    records.push(encoder.encode(&book, 2, 5_000).expect("keyframes are written regardless"));
+
+    assert_eq!(records.iter().map(|r| r.keyframe).collect::<Vec<_>>(), vec![true, false, false, true]);
+    assert_eq!(records[1].bids, vec![level(9_999, 0), level(9_998, 400)]);
+    assert_eq!(records[1].base_update_id, 10);
+
+    let mut rebuilt = LocalBook::default();
+    for record in &records[..3] {
+        assert!(rebuilt.apply_record(record));
+    }
+    assert_eq!(rebuilt.levels(fulldepth::LIMIT), book.levels(fulldepth::LIMIT));
+    assert_eq!(rebuilt.last_update_id, 12);
+
+    // changes don't apply to any other book
+    assert!(!LocalBook::default().apply_record(&records[2]));
+    assert!(sink::encode(schema::FULL_DEPTH, &records).is_ok());
+}
+
+#[test]
+fn reconstruction_skips_changes_after_a_lost_record() {
+    let mut book = snapshot(10);
+    let mut encoder = Encoder::new("btcusdt", 3);
+    let mut records = vec![encoder.encode(&book, 0, 1_000).expect("keyframe")];
+    book.apply(&diff(11, 11, vec![["99.98", "4"]], vec![]));
+    records.push(encoder.encode(&book, 1, 2_000).expect("changes"));
+    book.apply(&diff(12, 12, vec![], vec![["100.02", "0"]]));
+    records.push(encoder.encode(&book, 2, 3_000).expect("changes"));
+    records.push(encoder.encode(&book, 2, 4_000).expect("keyframe"));
+
+    let books = fulldepth::reconstruct(&records);
+    assert_eq!(books.len(), 4);
+    assert!(books.iter().all(|b| b.keyframe && b.base_update_id == 0));
+    let (bids, asks) = book.levels(fulldepth::LIMIT);
+    assert_eq!((&books[2].bids, &books[2].asks), (&bids, &asks));
+    assert_eq!(books[2].timestamp_ms, 3_000);
+
+    // without the second record the third has nothing to apply to
+    let gapped = fulldepth::reconstruct(&[records[0].clone(), records[2].clone(), records[3].clone()]);
+    assert_eq!(gapped.iter().map(|b| b.timestamp_ms).collect::<Vec<_>>(), vec![1_000, 4_000]);
+    assert!(fulldepth::reconstruct(&records[1..3]).is_empty(), "changes need a keyframe first");
+}
+
+#[tokio::test]
+async fn books_are_read_back_from_the_keyframe_an_hour_earlier() {
+    let root = std::env::temp_dir().join(format!("orderbook-fulldepth-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 5, 0, 0).unwrap();
+    let at = |minutes: i64| (hour + Duration::minutes(minutes)).timestamp_millis();
+
+    let mut book = snapshot(10);
+    let mut encoder = Encoder::new("btcusdt", 60);
+    let mut records = vec![encoder.encode(&book, 0, at(-30)).expect("keyframe")];
+    book.apply(&diff(11, 11, vec![["99.98", "4"]], vec![]));
+    records.push(encoder.encode(&book, 1, at(1)).expect("changes"));
+    book.apply(&diff(12, 12, vec![["100.00", "0"]], vec![]));
+    records.push(encoder.encode(&book, 2, at(61)).expect("changes"));
+    for record in &records {
+        let key = sink::partition_key(fulldepth::FULL_DEPTH_PREFIX, "BTCUSDT", sink::at_ms(record.timestamp_ms), record.timestamp_ms).unwrap();
+        archive.put(&key, sink::encode(schema::FULL_DEPTH, std::slice::from_ref(record)).unwrap()).await.unwrap();
+    }
+
+    let books = fulldepth::read_books(&archive, "BTCUSDT", hour, hour + Duration::hours(1)).await.unwrap();
+    assert_eq!(books.len(), 1, "only the book inside the range");
+    assert_eq!(books[0].timestamp_ms, at(1));
+    assert_eq!(books[0].bids, vec![level(10_000, 150), level(9_999, 200), level(9_998, 400)]);
+
+    std::fs::remove_dir_all(root).ok();
+}
+
+#[test]
+fn resetting_the_encoder_forces_a_keyframe() {
+    let book = snapshot(10);
+    let mut encoder = Encoder::new("BTCUSDT", 60);
+    encoder.encode(&book, 0, 0);
+    encoder.reset();
+    assert!(encoder.encode(&book, 0, 1).expect("keyframe").keyframe);
+    assert_eq!(fulldepth::diff_stream("BTCUSDT"), "btcusdt@depth@100ms");
+}
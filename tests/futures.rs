@@ -0,0 +1,12 @@
+#![cfg(feature = "futures")]
+
+use orderbook::futures;
+
+#[test]
+fn funding_polls_fall_on_wall_clock_multiples_of_the_period() {
+    let period = 5 * 60_000;
+    let at = 1_756_873_800_000; // 04:30:00
+    assert_eq!(futures::next_poll_ms(at - 1, period), at);
+    assert_eq!(futures::next_poll_ms(at, period), at + period, "a poll is never due at once");
+    assert_eq!(futures::next_poll_ms(at + 61_000, period), at + period);
+}
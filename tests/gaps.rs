@@ -0,0 +1,113 @@
+use chrono::{TimeZone, Utc};
+use orderbook::archive::Archive;
+use orderbook::book::Level;
+use orderbook::checkpoint::Checkpoint;
+use orderbook::gaps::{self, Gap};
+use orderbook::manifest::{self, Manifest};
+use orderbook::sink::{Delivery, Output};
+use orderbook::{binance, schema, sink, Error, OrderBook};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Writes into a local archive, so markers can be read back through it.
+struct LocalOutput(Archive);
+
+impl Output for LocalOutput {
+    async fn write(&mut self, key: &str, body: &[u8]) -> Result<Delivery, Error> {
+        self.0.put(key, body.to_vec()).await?;
+        Ok(Delivery::Stored { latency: Duration::ZERO, retries: 0 })
+    }
+}
+
+fn scratch(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("orderbook-{}-{}", name, std::process::id()))
+}
+
+#[test]
+fn reports_a_gap_only_past_the_threshold() {
+    assert_eq!(gaps::detect("btcusdt", 1_000, 6_000, 5_000, "reconnect"), None);
+    assert_eq!(
+        gaps::detect("btcusdt", 1_000, 6_001, 5_000, "reconnect"),
+        Some(Gap { symbol: "BTCUSDT".into(), from_ms: 1_000, to_ms: 6_001, reason: "reconnect".into() }),
+    );
+}
+
+#[tokio::test]
+async fn latest_object_is_found_by_walking_partitions_newest_first() {
+    let root = scratch("latest");
+    let symbol_dir = root.join("orderbook/exchange=binance/symbol=BTCUSDT");
+    for (dir, file) in [
+        ("year=2024/month=12/day=31/hour=23", "1735689599000.avro"),
+        ("year=2025/month=09/day=03/hour=04", "999999999999.avro"),
+        ("year=2025/month=09/day=03/hour=04", "1756875599000.avro"),
+        ("year=2025/month=09/day=03/hour=04", "1756873800000.avro"),
+    ] {
+        fs::create_dir_all(symbol_dir.join(dir)).unwrap();
+        fs::write(symbol_dir.join(dir).join(file), b"").unwrap();
+    }
+    let archive = Archive::Local(root.clone());
+    // newer, but its manifest names no records
+    let empty = Manifest::new("orderbook", "BTCUSDT", Utc.with_ymd_and_hms(2025, 9, 3, 5, 0, 0).unwrap(), Vec::new());
+    manifest::write(&archive, &empty).await.unwrap();
+
+    // "999..." sorts last as a string but is the oldest
+    let (id, key) = archive.latest("orderbook", "btcusdt").await.unwrap().expect("latest");
+    assert_eq!(id, 1756875599000);
+    assert_eq!(key, "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04/1756875599000.avro");
+    assert_eq!(archive.latest("orderbook", "ethusdt").await.unwrap(), None);
+
+    fs::remove_dir_all(root).ok();
+}
+
+#[tokio::test]
+async fn recovery_measures_from_the_newest_book_of_idempotently_keyed_objects() {
+    let root = scratch("idempotent-keys");
+    let archive = Archive::Local(root.clone());
+    let at_ms = Utc.with_ymd_and_hms(2025, 9, 3, 4, 30, 0).unwrap().timestamp_millis();
+    assert_eq!(gaps::find(&archive, None, "BTCUSDT", at_ms, 5_000).await.unwrap(), None);
+
+    // partial depth has no event time, so its keys carry the lastUpdateId
+    for (ts, update_id) in [(at_ms - 2_000, 71_234_567_890), (at_ms, 71_234_567_990)] {
+        let book = OrderBook::from_levels(ts, &[Level::new(99.0, 1.0)], &[Level::new(101.0, 1.0)]).unwrap()
+            .with_exchange_clock(0, update_id)
+            .with_source(binance::EXCHANGE, "btcusdt");
+        let key = sink::partition_key("orderbook", "BTCUSDT", sink::at_ms(ts), update_id).unwrap();
+        archive.put(&key, sink::encode(schema::ORDERBOOK, &[book]).unwrap()).await.unwrap();
+    }
+
+    assert_eq!(gaps::last_stored_ms(&archive, "BTCUSDT").await.unwrap(), Some(at_ms));
+    assert_eq!(gaps::find(&archive, None, "BTCUSDT", at_ms + 3_000, 5_000).await.unwrap(), None);
+    let gap = gaps::find(&archive, None, "BTCUSDT", at_ms + 60_000, 5_000).await.unwrap().expect("gap");
+    assert_eq!((gap.from_ms, gap.to_ms, gap.hours().len()), (at_ms, at_ms + 60_000, 1));
+
+    fs::remove_dir_all(root).ok();
+}
+
+#[test]
+fn heartbeat_counts_seconds_since_the_checkpointed_write() {
+    let checkpoint = Checkpoint { symbol: "BTCUSDT".into(), last_flush_ms: 10_000, ..Default::default() };
+    assert_eq!(gaps::since_last_write_secs(&checkpoint, 190_500), 180.5);
+    assert_eq!(gaps::since_last_write_secs(&checkpoint, 9_000), 0.0, "clock skew doesn't go negative");
+}
+
+#[tokio::test]
+async fn gap_markers_cover_every_hour_and_read_back_as_a_manifest() {
+    let root = scratch("gaps");
+    let mut output = LocalOutput(Archive::Local(root.clone()));
+    let at = |h, m| Utc.with_ymd_and_hms(2025, 9, 3, h, m, 0).unwrap();
+    let long = Gap { symbol: "BTCUSDT".into(), from_ms: at(4, 50).timestamp_millis(), to_ms: at(6, 10).timestamp_millis(), reason: "recovery".into() };
+    let short = Gap { symbol: "BTCUSDT".into(), from_ms: at(7, 0).timestamp_millis(), to_ms: at(7, 1).timestamp_millis(), reason: "reconnect".into() };
+    assert_eq!(long.hours(), [at(4, 0), at(5, 0), at(6, 0)]);
+
+    gaps::record(&mut output, &long).await.unwrap();
+    gaps::record(&mut output, &short).await.unwrap();
+    let archive = &output.0;
+
+    // any hour inside the gap finds it, once
+    assert_eq!(gaps::manifest(archive, "BTCUSDT", at(5, 0), at(6, 0)).await.unwrap(), std::slice::from_ref(&long));
+    assert_eq!(gaps::manifest(archive, "BTCUSDT", at(4, 0), at(8, 0)).await.unwrap(), [long, short]);
+    assert_eq!(gaps::manifest(archive, "BTCUSDT", at(6, 10), at(7, 0)).await.unwrap(), []);
+
+    fs::remove_dir_all(root).ok();
+}
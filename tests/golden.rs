@@ -0,0 +1,130 @@
+//! Golden-file checks for the Avro we write. Fixture messages are run through the
+//! pipeline and encoded with a fixed sync marker; the bytes and decoded records must
+//! match what is checked in under tests/fixtures/golden. After an intentional format
+//! change, regenerate with `UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use orderbook::pipeline::{self, Outcome};
+use orderbook::{migrate, schema, sink, OrderBook};
+use std::path::PathBuf;
+
+const MARKER: [u8; 16] = *b"orderbook-golden";
+const RECEIVED_MS: i64 = 1_725_372_000_000;
+
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden").join(name)
+}
+
+fn books(version: i32) -> Vec<OrderBook> {
+    include_str!("fixtures/depth20.jsonl").lines().enumerate()
+        .filter_map(|(i, line)| match pipeline::process(line, RECEIVED_MS + i as i64 * 100, version) {
+            Outcome::Book(book) => Some(*book),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Compares `actual` to the checked-in file, or rewrites it when UPDATE_GOLDEN is set.
+fn check(name: &str, actual: &[u8]) {
+    let path = golden(name);
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = std::fs::read(&path).unwrap_or_else(|e| panic!("reading {}: {}", path.display(), e));
+    assert!(expected == actual, "{} differs from the golden file; rerun with UPDATE_GOLDEN=1 if intended", name);
+}
+
+fn check_version(version: i32) {
+    let schema = schema::orderbook(version).unwrap();
+    let books = books(version);
+    let avro = sink::encode_with_marker(schema, &books, MARKER).unwrap();
+    check(&format!("orderbook_v{}.avro", version), &avro);
+
+    let decoded = migrate::read_orderbooks(&avro).unwrap();
+    let json = serde_json::to_string_pretty(&decoded).unwrap() + "\n";
+    check(&format!("orderbook_v{}.json", version), json.as_bytes());
+}
+
+#[test]
+fn orderbook_v1_bytes_are_stable() {
+    check_version(1);
+}
+
+#[test]
+fn orderbook_v2_bytes_are_stable() {
+    check_version(2);
+}
+
+#[test]
+fn orderbook_v3_bytes_are_stable() {
+    check_version(3);
+}
+
+#[test]
+fn orderbook_v4_bytes_are_stable() {
+    check_version(4);
+}
+
+#[test]
+fn orderbook_v5_bytes_are_stable() {
+    check_version(5);
+}
+
+#[test]
+fn orderbook_v6_bytes_are_stable() {
+    check_version(6);
+}
+
+#[test]
+fn orderbook_v7_bytes_are_stable() {
+    check_version(7);
+}
+
+#[test]
+fn orderbook_v8_bytes_are_stable() {
+    check_version(8);
+}
+
+#[test]
+fn orderbook_v9_bytes_are_stable() {
+    check_version(9);
+}
+
+#[test]
+fn orderbook_v10_bytes_are_stable() {
+    check_version(10);
+}
+
+#[test]
+fn orderbook_v11_bytes_are_stable() {
+    check_version(11);
+}
+
+#[test]
+fn orderbook_v12_bytes_are_stable() {
+    check_version(12);
+}
+
+#[test]
+fn orderbook_v13_bytes_are_stable() {
+    check_version(13);
+}
+
+#[test]
+fn orderbook_v14_bytes_are_stable() {
+    check_version(14);
+}
+
+#[test]
+fn golden_files_still_decode() {
+    // readers of archived data only have the bytes; they must decode without the writer code
+    for version in 1..=schema::ORDERBOOK_VERSION {
+        let bytes = std::fs::read(golden(&format!("orderbook_v{}.avro", version))).unwrap();
+        let decoded = migrate::read_orderbooks(&bytes).unwrap();
+        assert!(decoded.iter().all(|book| book.schema_version == version));
+
+        let expected = std::fs::read_to_string(golden(&format!("orderbook_v{}.json", version))).unwrap();
+        assert_eq!(serde_json::to_string_pretty(&decoded).unwrap() + "\n", expected);
+    }
+}
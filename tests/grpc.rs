@@ -0,0 +1,31 @@
+#![cfg(feature = "grpc")]
+
+#[allow(dead_code)]
+mod common;
+
+use common::book;
+use futures_util::StreamExt;
+use orderbook::grpc::proto::order_book_service_server::OrderBookService;
+use orderbook::grpc::proto::SubscribeBookRequest;
+use orderbook::grpc::BookService;
+use orderbook::live;
+use tonic::{Code, Request};
+
+#[tokio::test]
+async fn subscribers_stream_only_their_symbol() {
+    let request = Request::new(SubscribeBookRequest { symbol: "ethusdt".into() });
+    let mut stream = BookService.subscribe_book(request).await.unwrap().into_inner();
+
+    for (ts, symbol) in [(1, "btcusdt"), (2, "ethusdt"), (3, "btcusdt"), (4, "ethusdt")] {
+        live::publish(&book(ts).with_source("binance", symbol));
+    }
+    for ts in [2, 4] {
+        let received = stream.next().await.unwrap().unwrap();
+        assert_eq!((received.timestamp_ms, received.symbol.as_str()), (ts, "ETHUSDT"));
+        let stored = book(ts).with_source("binance", "ethusdt");
+        assert_eq!((received.mid_price, received.bids.len(), received.bid_notional.len()), (stored.mid_price, stored.bids.len(), stored.bid_notional.len()));
+    }
+
+    let invalid = BookService.subscribe_book(Request::new(SubscribeBookRequest { symbol: "eth/usdt".into() })).await;
+    assert_eq!(invalid.err().map(|status| status.code()), Some(Code::InvalidArgument));
+}
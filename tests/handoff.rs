@@ -0,0 +1,44 @@
+use orderbook::handoff::{self, Bounds, Window};
+use serde_json::json;
+use std::time::Duration;
+use tokio::time::Instant;
+
+#[test]
+fn bounds_come_from_the_invocation_payload() {
+    assert_eq!(Bounds::from_event(&json!({})).unwrap(), None);
+    // EventBridge's own scheduled event carries no bounds
+    assert_eq!(Bounds::from_event(&json!({"source": "aws.events", "detail": {}})).unwrap(), None);
+    assert_eq!(
+        Bounds::from_event(&json!({"duration_minutes": 14})).unwrap(),
+        Some(Bounds { duration_minutes: 14, overlap_secs: 60 }),
+    );
+    assert_eq!(
+        Bounds::from_event(&json!({"duration_minutes": 5, "overlap_secs": 20})).unwrap(),
+        Some(Bounds { duration_minutes: 5, overlap_secs: 20 }),
+    );
+}
+
+#[test]
+fn rejects_bounds_the_overlap_does_not_fit_in() {
+    assert!(Bounds::from_event(&json!({"duration_minutes": 0})).is_err());
+    assert!(Bounds::from_event(&json!({"duration_minutes": 1, "overlap_secs": 60})).is_err());
+    assert!(Bounds::from_event(&json!({"duration_minutes": "ten"})).is_err());
+}
+
+#[test]
+fn overlaps_at_both_ends_of_the_invocation() {
+    let started = Instant::now();
+    let window = Window::new(&Bounds { duration_minutes: 10, overlap_secs: 60 }, started);
+
+    assert!(window.overlapping(started));
+    assert!(window.overlapping(started + Duration::from_secs(59)));
+    assert!(!window.overlapping(started + Duration::from_secs(60)));
+    assert!(!window.overlapping(started + Duration::from_secs(539)));
+    assert!(window.overlapping(started + Duration::from_secs(540)));
+    assert!(window.overlapping(started + Duration::from_secs(600)));
+}
+
+#[test]
+fn claims_are_per_symbol_and_update_id() {
+    assert_eq!(handoff::claim_key("btcusdt", 4_000_000_123), "claims/BTCUSDT/4000000123");
+}
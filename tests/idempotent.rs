@@ -0,0 +1,106 @@
+#[allow(dead_code)]
+mod common;
+
+use common::{depth_fixture, serve, set_env};
+use orderbook::collector::Collector;
+use orderbook::sink::{self, Delivery, Output};
+use orderbook::wal::Wal;
+use orderbook::Error;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// A bucket shared between collectors, honouring conditional writes like S3.
+#[derive(Clone, Default)]
+struct Bucket(Arc<Mutex<HashMap<String, String>>>);
+
+impl Output for Bucket {
+    async fn write(&mut self, key: &str, _: &[u8]) -> Result<Delivery, Error> {
+        self.0.lock().expect("bucket").insert(key.to_string(), String::new());
+        Ok(Delivery::Stored { latency: Duration::ZERO, retries: 0 })
+    }
+
+    async fn write_once(&mut self, key: &str, _: &[u8], id: &str) -> Result<Delivery, Error> {
+        let mut objects = self.0.lock().expect("bucket");
+        match objects.get(key) {
+            Some(taken) if taken == id => Ok(Delivery::Duplicate),
+            Some(taken) => Err(Error::Conflict(format!("{} holds {}", key, taken))),
+            None => {
+                objects.insert(key.to_string(), id.to_string());
+                Ok(Delivery::Stored { latency: Duration::ZERO, retries: 0 })
+            }
+        }
+    }
+}
+
+fn update(event_ms: i64, last_update_id: i64) -> String {
+    format!(r#"{{"e":"depthUpdate","E":{},"s":"BTCUSDT","U":{},"u":{},"b":[["7403.89","0.002"]],"a":[["7405.96","3.340"]]}}"#,
+            event_ms, last_update_id - 1, last_update_id)
+}
+
+#[test]
+fn books_are_identified_by_update_id_or_content() {
+    assert_eq!(sink::book_id(390497878, b"anything"), "update:390497878");
+    assert_eq!(sink::book_id(0, b"abc"), "sha256:ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+}
+
+#[tokio::test]
+async fn instances_receiving_the_same_books_store_each_once() {
+    let _env = set_env(&[("IDEMPOTENT_WRITES", "1")]);
+    let messages = vec![update(1_756_872_000_100, 11), update(1_756_872_000_200, 12)];
+    let bucket = Bucket::default();
+
+    let mut counts = Vec::new();
+    for _ in 0..2 {
+        let url = serve(messages.clone()).await;
+        let mut collector = Collector::new("btcusdt", &url, bucket.clone());
+        collector.reconnect = false;
+        collector.run().await.expect("collector run");
+        counts.push(collector.counts());
+    }
+
+    assert_eq!((counts[0].stored, counts[0].duplicates), (2, 0));
+    assert_eq!((counts[1].stored, counts[1].duplicates), (0, 2));
+    let mut keys: Vec<String> = bucket.0.lock().unwrap().keys().cloned().collect();
+    keys.sort();
+    assert_eq!(keys, [
+        "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04/1756872000100.avro",
+        "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04/1756872000200.avro",
+    ]);
+}
+
+#[tokio::test]
+async fn partial_depth_received_at_different_times_is_stored_once() {
+    let _env = set_env(&[("IDEMPOTENT_WRITES", "1")]);
+    let bucket = Bucket::default();
+
+    let mut counts = Vec::new();
+    for _ in 0..2 {
+        // spot depth20 has no event time, so the two runs only share lastUpdateIds
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let url = serve(depth_fixture()).await;
+        let mut collector = Collector::new("btcusdt", &url, bucket.clone());
+        collector.reconnect = false;
+        collector.run().await.expect("collector run");
+        counts.push(collector.counts());
+    }
+
+    assert_eq!((counts[0].stored, counts[0].duplicates), (3, 0));
+    assert_eq!((counts[1].stored, counts[1].duplicates), (0, 3));
+    let bucket = bucket.0.lock().unwrap();
+    assert_eq!(bucket.len(), 3);
+    assert!(bucket.iter().all(|(key, id)| key.ends_with(&format!("/{}.avro", id.trim_start_matches("update:")))));
+}
+
+#[test]
+fn spilled_batches_keep_their_book_id() {
+    let dir = std::env::temp_dir().join(format!("orderbook-wal-{}", std::process::id()));
+    let wal = Wal::new(&dir);
+    wal.append("orderbook/a.avro", Some("update:11"), b"first").unwrap();
+    wal.append("orderbook/b.avro", None, b"second").unwrap();
+
+    let pending = wal.pending().unwrap();
+    let entries: Vec<_> = pending.iter().map(|e| (e.key.as_str(), e.id.as_deref(), e.body.as_slice())).collect();
+    assert_eq!(entries, [("orderbook/a.avro", Some("update:11"), &b"first"[..]), ("orderbook/b.avro", None, &b"second"[..])]);
+    std::fs::remove_dir_all(dir).unwrap();
+}
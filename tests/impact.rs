@@ -0,0 +1,93 @@
+#[allow(dead_code)]
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::{around, serve_http, set_env};
+use orderbook::archive::Archive;
+use orderbook::impact::{self, regress};
+use orderbook::manifest::{self, FileEntry, Manifest};
+use orderbook::trades::{self, AggTrade};
+use orderbook::{binance, schema, sink};
+
+fn trade(at: i64, qty: f64, is_buyer_maker: bool) -> AggTrade {
+    AggTrade {
+        symbol: "BTCUSDT".into(), agg_id: at, price: 100.0, qty, first_trade_id: at, last_trade_id: at,
+        trade_time_ms: at, is_buyer_maker, source: trades::SOURCE_BACKFILL.into(),
+    }
+}
+
+#[test]
+fn the_fit_recovers_a_line() {
+    let points: Vec<(f64, f64)> = (0..10).map(|x| (x as f64, 0.5 * x as f64 + 2.0)).collect();
+    let fit = regress(&points).expect("x varies");
+    assert!((fit.slope - 0.5).abs() < 1e-12 && (fit.intercept - 2.0).abs() < 1e-12);
+    assert!((fit.r_squared - 1.0).abs() < 1e-12);
+
+    assert!(regress(&[(1.0, 1.0)]).is_none());
+    assert!(regress(&[(1.0, 1.0), (1.0, 2.0)]).is_none(), "flat flow has no slope");
+}
+
+#[test]
+fn intervals_pair_signed_flow_with_the_mid_change() {
+    let books = [around(0, 100.0), around(900, 101.0), around(1_500, 99.0)];
+    let trades = [trade(100, 2.0, false), trade(200, 0.5, true), trade(1_200, 3.0, true)];
+    let points = impact::intervals(&books, &trades, 0, 2_000, 1_000);
+    assert_eq!(points, vec![(1.5, 1.0), (-3.0, -2.0)]);
+
+    // no book yet at the start of the first interval
+    assert_eq!(impact::intervals(&books[1..], &trades, 0, 2_000, 1_000), vec![(-3.0, -2.0)]);
+}
+
+#[test]
+fn buying_pressure_gives_a_positive_lambda() {
+    let mut books = Vec::new();
+    let mut trades = Vec::new();
+    let mut mid = 100.0;
+    for i in 0..60 {
+        let flow = [3.0, -1.0, 2.0, -2.0, 1.0][i % 5];
+        books.push(around(i as i64 * 1_000, mid));
+        trades.push(trade(i as i64 * 1_000 + 10, f64::abs(flow), flow < 0.0));
+        mid += 0.25 * flow;
+    }
+    books.push(around(60_000, mid));
+
+    let estimate = impact::estimate("btcusdt", &books, &trades, 0, 60_000, 1_000).expect("a fit");
+    assert_eq!((estimate.symbol.as_str(), estimate.intervals), ("BTCUSDT", 60));
+    assert!((estimate.lambda - 0.25).abs() < 1e-9);
+    assert!(estimate.lambda_bps > 0.0);
+    assert!(sink::encode(schema::IMPACT, &[estimate]).is_ok());
+}
+
+#[tokio::test]
+async fn a_compacted_hour_is_read_through_its_manifest() {
+    let root = std::env::temp_dir().join(format!("orderbook-impact-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    let start = hour.timestamp_millis();
+
+    // a minute's buying or selling moves the mid by a quarter of the flow
+    let mut books = Vec::new();
+    let mut flows = Vec::new();
+    let mut mid = 100.0;
+    for i in 0..60 {
+        let flow: f64 = [3.0, -1.0, 2.0, -2.0, 1.0][i % 5];
+        let at = start + i as i64 * 60_000;
+        books.push(around(at, mid).with_source(binance::EXCHANGE, "btcusdt"));
+        flows.push(format!(r#"{{"a":{},"p":"100.0","q":"{}","f":{},"l":{},"T":{},"m":{}}}"#, i, flow.abs(), i, i, at + 10, flow < 0.0));
+        mid += 0.25 * flow;
+    }
+    books.push(around(start + 3_599_999, mid).with_source(binance::EXCHANGE, "btcusdt"));
+    let url = serve_http(format!("[{}]", flows.join(","))).await;
+    let _env = set_env(&[("REST_BASE_URL", url.split("/api/").next().expect("base url"))]);
+
+    // the hour's objects are gone, its books in a file the manifest names
+    archive.put("compacted/orderbook/x.avro", sink::encode(schema::ORDERBOOK, &books).unwrap()).await.unwrap();
+    let files = vec![FileEntry::new("compacted/orderbook/x.avro", &books)];
+    manifest::write(&archive, &Manifest::new("orderbook", "BTCUSDT", hour, files)).await.unwrap();
+
+    let estimate = impact::estimate_hour(&archive, "btcusdt", hour).await.unwrap().expect("a fit");
+    assert_eq!(estimate.intervals, 60);
+    assert!((estimate.lambda - 0.25).abs() < 1e-9);
+
+    std::fs::remove_dir_all(root).ok();
+}
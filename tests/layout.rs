@@ -0,0 +1,67 @@
+use chrono::{TimeZone, Utc};
+use orderbook::layout::{KeyParts, KeyTemplate};
+
+fn parts(prefix: &str) -> KeyParts<'_> {
+    KeyParts { prefix, exchange: "binance", symbol: "btcusdt", at: Utc.with_ymd_and_hms(2025, 9, 3, 4, 30, 0).unwrap() }
+}
+
+#[test]
+fn default_template_partitions_by_source_then_hour() {
+    let template = KeyTemplate::default();
+    assert_eq!(template.key(&parts("orderbook"), 1756873800000),
+               "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04/1756873800000.avro");
+    assert_eq!(template.dir(&parts("orderbook")), "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04");
+}
+
+#[test]
+fn custom_template_fills_every_placeholder() {
+    let template = KeyTemplate::new("{prefix}/exchange={exchange}/symbol={symbol}/dt={date}/hour={hour}/{ts}.avro").expect("valid");
+    assert_eq!(template.key(&parts("orderbook"), 42), "orderbook/exchange=binance/symbol=BTCUSDT/dt=2025-09-03/hour=04/42.avro");
+    assert_eq!(template.dir(&parts("liquidations")), "liquidations/exchange=binance/symbol=BTCUSDT/dt=2025-09-03/hour=04");
+}
+
+#[test]
+fn invalid_templates_are_rejected() {
+    for template in [
+        "{prefix}/{year}/{bucket}/{ts}.avro",
+        "{prefix}/{year/{ts}.avro",
+        "{prefix}/{year}/data.avro",
+        "{prefix}/{ts}/data.avro",
+        "year={year}/{ts}.avro",
+    ] {
+        assert!(KeyTemplate::new(template).is_err(), "accepted {:?}", template);
+    }
+}
+
+#[test]
+fn templates_without_the_symbol_are_rejected() {
+    let err = KeyTemplate::new("{prefix}/exchange={exchange}/dt={date}/hour={hour}/{ts}.avro").expect_err("symbols would collide");
+    assert!(err.to_string().contains("{symbol}"), "{}", err);
+}
+
+#[test]
+fn record_ids_are_read_back_from_keys() {
+    let template = KeyTemplate::default();
+    let key = template.key(&parts("orderbook"), 1756873800000);
+    assert_eq!(template.record_id(&key), Some(1756873800000));
+    assert_eq!(template.record_id("orderbook/.../_manifest.json"), None);
+    // compacted files share the partition but aren't a single record
+    assert_eq!(template.record_id("orderbook/.../1756873800000-1756877399900.compacted.avro"), None);
+
+    let template = KeyTemplate::new("{prefix}/dt={date}/{symbol}-{ts}.avro").expect("valid");
+    assert_eq!(template.record_id(&template.key(&parts("orderbook"), 42)), Some(42));
+
+    let keys: Vec<String> = ["a/999.avro", "a/1000.avro", "a/x.avro"].map(String::from).into();
+    assert_eq!(KeyTemplate::default().latest(&keys), Some((1000, &keys[1])));
+}
+
+#[test]
+fn time_segments_are_left_open() {
+    let template = KeyTemplate::new("{prefix}/dt={date}/symbol={symbol}/hour={hour}/{ts}.avro").expect("valid");
+    assert_eq!(template.dir_levels(&parts("orderbook")), [
+        Some("orderbook".to_string()),
+        None,
+        Some("symbol=BTCUSDT".to_string()),
+        None,
+    ]);
+}
@@ -0,0 +1,42 @@
+#[allow(dead_code)]
+mod common;
+
+use common::book;
+use orderbook::live;
+
+#[tokio::test]
+async fn subscribers_see_books_published_after_they_subscribe() {
+    live::publish(&book(1));
+    let (mut first, mut second) = (live::subscribe(), live::subscribe());
+    live::publish(&book(2));
+    assert_eq!(first.recv().await.unwrap().timestamp_ms, 2);
+    assert_eq!(second.recv().await.unwrap().timestamp_ms, 2);
+    assert!(first.try_recv().is_err(), "nothing else was published");
+}
+
+#[cfg(feature = "redis")]
+#[test]
+fn books_go_to_their_symbol_channel_and_latest_key() {
+    use orderbook::pubsub::Settings;
+
+    let settings = Settings { url: "redis://localhost".into(), prefix: "ob".into(), latest: true };
+    assert_eq!((settings.channel("btcusdt"), settings.latest_key("btcusdt")), ("ob:BTCUSDT".into(), "ob:latest:BTCUSDT".into()));
+    let packed = String::from_utf8_lossy(&settings.commands(&book(3)).unwrap().get_packed_pipeline()).into_owned();
+    assert!(packed.contains("PUBLISH") && packed.contains("ob:BTCUSDT") && packed.contains("SET") && packed.contains("ob:latest:BTCUSDT"));
+
+    let publish_only = Settings { latest: false, ..settings };
+    let packed = String::from_utf8_lossy(&publish_only.commands(&book(3)).unwrap().get_packed_pipeline()).into_owned();
+    assert!(!packed.contains("SET"));
+}
+
+#[cfg(feature = "nats")]
+#[test]
+fn jetstream_subjects_are_keyed_by_exchange_and_symbol() {
+    use orderbook::nats::{message_id, Settings};
+
+    let settings = Settings { url: "nats://localhost:4222".into(), prefix: "orderbook".into(), stream: "ORDERBOOK".into() };
+    let without_id = book(5);
+    assert_eq!(settings.subject(&without_id), "orderbook.binance.BTCUSDT");
+    assert_eq!(message_id(&without_id), "binance.BTCUSDT.5");
+    assert_eq!(message_id(&without_id.with_exchange_clock(4, 1234)), "binance.BTCUSDT.1234");
+}
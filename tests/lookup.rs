@@ -0,0 +1,96 @@
+#[allow(dead_code)]
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::book;
+use orderbook::archive::Archive;
+use orderbook::manifest::{self, FileEntry, Manifest};
+use orderbook::{lookup, schema, sink, OrderBook};
+
+async fn store(archive: &Archive, book: &OrderBook) -> String {
+    let key = sink::partition_key("orderbook", "BTCUSDT", sink::at_ms(book.timestamp_ms), book.timestamp_ms).unwrap();
+    archive.put(&key, sink::encode(schema::ORDERBOOK, std::slice::from_ref(book)).unwrap()).await.unwrap();
+    key
+}
+
+#[test]
+fn nearest_picks_the_closest_book_and_the_earlier_on_a_tie() {
+    let books = [book(1_000), book(2_000), book(4_000)];
+    assert_eq!(lookup::nearest(&books, 2_400).unwrap().timestamp_ms, 2_000);
+    assert_eq!(lookup::nearest(&books, 3_000).unwrap().timestamp_ms, 2_000);
+    assert_eq!(lookup::nearest(&books, 9_000).unwrap().timestamp_ms, 4_000);
+    assert!(lookup::nearest(&[], 3_000).is_none());
+}
+
+#[tokio::test]
+async fn nearest_book_looks_across_the_hour_boundary_and_through_manifests() {
+    let root = std::env::temp_dir().join(format!("orderbook-lookup-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap().timestamp_millis();
+    assert!(lookup::nearest_book(&archive, "btcusdt", hour).await.unwrap().is_none());
+
+    store(&archive, &book(hour - 1_000)).await;
+    store(&archive, &book(hour + 30 * 60_000)).await;
+    let found = lookup::nearest_book(&archive, "btcusdt", hour + 5_000).await.unwrap().unwrap();
+    assert_eq!(found.timestamp_ms, hour - 1_000, "the previous hour's last book is closer");
+    let found = lookup::nearest_book(&archive, "btcusdt", hour + 20 * 60_000).await.unwrap().unwrap();
+    assert_eq!(found.timestamp_ms, hour + 30 * 60_000);
+
+    // a compacted hour: its objects gone, its books in a file the manifest names
+    let later = hour + 2 * 3_600_000;
+    let books = [book(later + 60_000), book(later + 120_000)];
+    archive.put("orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=06/1-2.compacted.avro", sink::encode(schema::ORDERBOOK, &books).unwrap()).await.unwrap();
+    let files = vec![FileEntry::new("orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=06/1-2.compacted.avro", &books)];
+    manifest::write(&archive, &Manifest::new("orderbook", "BTCUSDT", sink::at_ms(later), files)).await.unwrap();
+    let found = lookup::nearest_book(&archive, "btcusdt", later + 100_000).await.unwrap().unwrap();
+    assert_eq!(found.timestamp_ms, later + 120_000);
+
+    std::fs::remove_dir_all(root).ok();
+}
+
+#[tokio::test]
+async fn hour_books_merges_a_compacted_hour_with_its_late_objects() {
+    let root = std::env::temp_dir().join(format!("orderbook-lookup-merged-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    let at = hour.timestamp_millis();
+
+    let compacted = [book(at + 60_000), book(at + 120_000)];
+    archive.put("compacted/orderbook/y.avro", sink::encode(schema::ORDERBOOK, &compacted).unwrap()).await.unwrap();
+    let files = vec![FileEntry::new("compacted/orderbook/y.avro", &compacted)];
+    manifest::write(&archive, &Manifest::new("orderbook", "BTCUSDT", hour, files)).await.unwrap();
+    // a recovery book landing after compaction, and an original the compactor left behind
+    store(&archive, &book(at + 90_000)).await;
+    store(&archive, &compacted[1]).await;
+
+    let books = lookup::hour_books(&archive, "btcusdt", hour).await.unwrap();
+    let times: Vec<i64> = books.iter().map(|b| b.timestamp_ms).collect();
+    assert_eq!(times, [at + 60_000, at + 90_000, at + 120_000]);
+
+    std::fs::remove_dir_all(root).ok();
+}
+
+#[tokio::test]
+async fn latest_book_without_checkpoints_is_the_newest_object() {
+    let root = std::env::temp_dir().join(format!("orderbook-lookup-latest-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    assert!(lookup::latest_book(&archive, None, "btcusdt").await.unwrap().is_none());
+
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap().timestamp_millis();
+    store(&archive, &book(hour + 1_000)).await;
+    store(&archive, &book(hour + 3_600_000)).await;
+    let latest = lookup::latest_book(&archive, None, "btcusdt").await.unwrap().unwrap();
+    assert_eq!(latest.timestamp_ms, hour + 3_600_000);
+
+    // the newest hour compacted: only its manifest and the files it names are left
+    let compacted = hour + 2 * 3_600_000;
+    let books = [book(compacted + 1_000), book(compacted + 2_000)];
+    let key = "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=06/1-2.compacted.avro";
+    archive.put(key, sink::encode(schema::ORDERBOOK, &books).unwrap()).await.unwrap();
+    manifest::write(&archive, &Manifest::new("orderbook", "BTCUSDT", sink::at_ms(compacted), vec![FileEntry::new(key, &books)])).await.unwrap();
+    assert_eq!(archive.latest("orderbook", "btcusdt").await.unwrap(), Some((compacted + 2_000, key.to_string())));
+    let latest = lookup::latest_book(&archive, None, "btcusdt").await.unwrap().unwrap();
+    assert_eq!(latest.timestamp_ms, compacted + 2_000);
+
+    std::fs::remove_dir_all(root).ok();
+}
@@ -0,0 +1,38 @@
+#[allow(dead_code)]
+mod common;
+
+use chrono::{TimeZone, Utc};
+use common::book;
+use orderbook::archive::Archive;
+use orderbook::layout;
+use orderbook::manifest::{self, FileEntry, Manifest};
+
+#[test]
+fn files_and_the_hour_carry_counts_and_update_id_ranges() {
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    let first = FileEntry::new("compacted/a.avro", &[book(1).with_exchange_clock(1, 120), book(2).with_exchange_clock(2, 100), book(3).with_exchange_clock(3, 140)]);
+    assert_eq!((first.records, first.first_ms, first.last_ms), (3, 1, 3));
+    assert_eq!((first.min_last_update_id, first.max_last_update_id), (100, 140));
+
+    let empty = FileEntry::new("compacted/empty.avro", &[]);
+    let manifest = Manifest::new("orderbook", "btcusdt", hour + chrono::Duration::minutes(17), vec![first, empty, FileEntry::new("b.avro", &[book(4).with_exchange_clock(4, 150)])]);
+    assert_eq!((manifest.symbol.as_str(), manifest.hour_ms), ("BTCUSDT", hour.timestamp_millis()));
+    assert_eq!(manifest.records, 4);
+    assert_eq!((manifest.min_last_update_id, manifest.max_last_update_id), (100, 150), "empty files don't count");
+}
+
+#[tokio::test]
+async fn manifests_live_in_their_partition_and_read_back() {
+    let root = std::env::temp_dir().join(format!("orderbook-manifest-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    assert_eq!(manifest::read(&archive, "orderbook", "BTCUSDT", hour).await.unwrap(), None);
+
+    let written = Manifest::new("orderbook", "BTCUSDT", hour, vec![FileEntry::new("compacted/a.avro", &[book(1).with_exchange_clock(1, 7)])]);
+    let key = manifest::write(&archive, &written).await.unwrap();
+    assert_eq!(key, "orderbook/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04/_manifest.json");
+    assert_eq!(layout::template().unwrap().record_id(&key), None, "not mistaken for a data file");
+    assert_eq!(manifest::read(&archive, "orderbook", "btcusdt", hour).await.unwrap(), Some(written));
+
+    std::fs::remove_dir_all(root).ok();
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "python")]
+
+use orderbook::python;
+use pyo3::prelude::*;
+use pyo3::types::{PyDict, PyList};
+use std::path::PathBuf;
+
+fn golden(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden").join(name)
+}
+
+#[test]
+fn files_of_any_version_read_into_dicts_of_the_current_schema() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = pyo3::wrap_pymodule!(python::orderbook)(py);
+        let books = module.getattr(py, "read_orderbook_files").unwrap()
+            .call1(py, (vec![golden("orderbook_v1.avro"), golden("orderbook_v14.avro")],)).unwrap();
+        let books = books.downcast_bound::<PyList>(py).unwrap();
+        assert!(books.len() >= 2);
+
+        let first = books.get_item(0).unwrap();
+        let first = first.downcast::<PyDict>().unwrap();
+        let bid = first.get_item("bids").unwrap().unwrap().get_item(0).unwrap();
+        assert!(bid.get_item("price").unwrap().extract::<f64>().unwrap() > 0.0);
+        // a v1 book still has every field of the current schema
+        assert!(!first.get_item("sweep").unwrap().unwrap().extract::<bool>().unwrap());
+        assert_eq!(first.get_item("sweep_side").unwrap().unwrap().extract::<String>().unwrap(), "");
+
+        let last = books.get_item(books.len() - 1).unwrap();
+        assert_eq!(last.get_item("schema_version").unwrap().extract::<i32>().unwrap(), 14);
+    });
+}
+
+#[test]
+fn a_missing_file_raises_oserror() {
+    pyo3::prepare_freethreaded_python();
+    Python::with_gil(|py| {
+        let module = pyo3::wrap_pymodule!(python::orderbook)(py);
+        let err = module.getattr(py, "read_orderbook_files").unwrap()
+            .call1(py, (vec![golden("missing.avro")],)).unwrap_err();
+        assert!(err.is_instance_of::<pyo3::exceptions::PyOSError>(py), "{}", err);
+    });
+}
@@ -0,0 +1,19 @@
+use chrono::{TimeZone, Utc};
+use orderbook::raw::{self, RawBatcher};
+
+#[test]
+fn batches_are_keyed_by_their_first_payload_under_the_layout() {
+    let minute = Utc.with_ymd_and_hms(2025, 9, 3, 4, 30, 0).unwrap().timestamp_millis();
+    let mut batcher = RawBatcher::new("btcusdt");
+    assert!(batcher.push(minute + 12_345, "{}").unwrap().is_none());
+    assert!(batcher.push(minute + 40_000, "{}").unwrap().is_none());
+
+    // a run starting mid-minute keys its part apart from the previous run's
+    let (key, body) = batcher.push(minute + 61_000, "{}").unwrap().expect("previous minute");
+    assert_eq!(key, format!("raw/exchange=binance/symbol=BTCUSDT/year=2025/month=09/day=03/hour=04/{}.jsonl.gz", minute + 12_345));
+    assert_eq!(raw::decode_recording(&body).unwrap().lines().count(), 2);
+
+    let (key, _) = batcher.flush().unwrap().expect("partial minute");
+    assert!(key.ends_with(&format!("/{}.jsonl.gz", minute + 61_000)), "{}", key);
+    assert!(batcher.flush().unwrap().is_none());
+}
@@ -0,0 +1,30 @@
+use orderbook::book::Level;
+use orderbook::registry::{self, frame};
+use orderbook::{schema, Error, OrderBook};
+use serde_json::json;
+
+#[test]
+fn frames_carry_the_magic_byte_and_a_big_endian_schema_id() {
+    let framed = frame(0x0102_0304, b"datum");
+    assert_eq!(framed, [&[0, 1, 2, 3, 4][..], b"datum"].concat());
+}
+
+#[test]
+fn a_framed_book_decodes_as_a_bare_datum_of_the_current_schema() {
+    let book = OrderBook::from_levels(1_000, &[Level::new(99.0, 1.0)], &[Level::new(101.0, 2.0)]).expect("two-sided");
+    let framed = registry::framed(7, schema::ORDERBOOK, &book).unwrap();
+    assert_eq!(framed[..5], [0, 0, 0, 0, 7]);
+
+    let value = apache_avro::from_avro_datum(schema::parsed(schema::ORDERBOOK).unwrap(), &mut &framed[5..], None).unwrap();
+    let decoded: OrderBook = apache_avro::from_value(&value).unwrap();
+    assert_eq!(decoded.timestamp_ms, 1_000);
+    assert_eq!(decoded.asks, book.asks);
+}
+
+#[test]
+fn a_reply_without_an_id_is_an_error_not_id_zero() {
+    assert_eq!(registry::schema_id("orderbook-value", &json!({"id": 12})).unwrap(), 12);
+    for reply in [json!({}), json!({"id": "12"}), json!({"id": u64::MAX}), json!({"error_code": 40403})] {
+        assert!(matches!(registry::schema_id("orderbook-value", &reply), Err(Error::Registry(_))), "{}", reply);
+    }
+}
@@ -0,0 +1,44 @@
+#[allow(dead_code)]
+mod common;
+
+use common::book;
+use futures_util::{SinkExt, StreamExt};
+use orderbook::relay::{self, Filter};
+use orderbook::{live, OrderBook};
+use tokio::net::TcpListener;
+use tokio_tungstenite::tungstenite::Message;
+
+#[test]
+fn filters_follow_the_query_and_control_messages() {
+    let mut filter = Filter::from_query(Some("depth=20&symbols=btcusdt,ETHUSDT"));
+    assert!(filter.wants("BTCUSDT") && filter.wants("ethusdt") && !filter.wants("SOLUSDT"));
+    filter.apply(r#"{"subscribe": ["solusdt"], "unsubscribe": ["btcusdt"]}"#).unwrap();
+    assert_eq!(filter.symbols(), ["ETHUSDT", "SOLUSDT"]);
+    filter.apply(r#"{"subscribe": ["*"]}"#).unwrap();
+    assert!(filter.wants("XRPUSDT"));
+    filter.apply(r#"{"unsubscribe": ["*"]}"#).unwrap();
+    assert!(filter.symbols().is_empty());
+    assert!(filter.apply("subscribe btcusdt").is_err());
+    assert_eq!(Filter::from_query(None), Filter::default());
+}
+
+#[tokio::test]
+async fn clients_receive_only_their_symbols() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(relay::serve(listener));
+
+    let (mut ws, _) = tokio_tungstenite::connect_async(format!("ws://{}/?symbols=btcusdt", addr)).await.unwrap();
+    ws.send(Message::Text(r#"{"subscribe": ["ethusdt"]}"#.into())).await.unwrap();
+    let ack = ws.next().await.unwrap().unwrap().into_text().unwrap();
+    assert_eq!(ack, r#"{"symbols":["BTCUSDT","ETHUSDT"]}"#);
+
+    for (ts, symbol) in [(1, "solusdt"), (2, "ethusdt"), (3, "btcusdt")] {
+        live::publish(&book(ts).with_source("binance", symbol));
+    }
+    for expected in [(2, "ETHUSDT"), (3, "BTCUSDT")] {
+        let text = ws.next().await.unwrap().unwrap().into_text().unwrap();
+        let received: OrderBook = serde_json::from_str(&text).unwrap();
+        assert_eq!((received.timestamp_ms, received.symbol.as_str()), expected);
+    }
+}
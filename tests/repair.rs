@@ -0,0 +1,42 @@
+#[allow(dead_code)]
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::book;
+use orderbook::archive::Archive;
+use orderbook::{gaps, manifest, repair, schema, sink, verify};
+
+#[tokio::test]
+async fn holes_get_gap_markers_and_hours_get_fresh_manifests() {
+    let root = std::env::temp_dir().join(format!("orderbook-repair-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let from = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    let start = from.timestamp_millis();
+    let minute = 60_000;
+
+    // the first half hour, then nothing until the next hour's last minute
+    for m in (0..30).chain([119]) {
+        let ts = start + m * minute;
+        let key = sink::partition_key("orderbook", "BTCUSDT", sink::at_ms(ts), ts).unwrap();
+        archive.put(&key, sink::encode(schema::ORDERBOOK, &[book(ts)]).unwrap()).await.unwrap();
+    }
+    let report = verify::verify(&archive, "btcusdt", from, from + Duration::hours(2)).await.unwrap();
+    assert_eq!(report.holes.len(), 1);
+
+    let planned = repair::repair(&archive, &report, false, true).await.unwrap();
+    assert_eq!(planned.manifests.len(), 2);
+    assert!(gaps::manifest(&archive, "btcusdt", from, from + Duration::hours(2)).await.unwrap().is_empty(), "a dry run writes nothing");
+
+    let repaired = repair::repair(&archive, &report, false, false).await.unwrap();
+    assert_eq!(repaired, planned);
+    let gaps = gaps::manifest(&archive, "btcusdt", from, from + Duration::hours(2)).await.unwrap();
+    assert_eq!(gaps, repaired.gaps);
+    assert_eq!((gaps[0].from_ms, gaps[0].to_ms, gaps[0].reason.as_str()), (start + 30 * minute, start + 119 * minute, "repair"));
+
+    let first = manifest::read(&archive, "orderbook", "btcusdt", from).await.unwrap().unwrap();
+    assert_eq!((first.files.len(), first.records), (30, 30));
+    let second = manifest::read(&archive, "orderbook", "btcusdt", from + Duration::hours(1)).await.unwrap().unwrap();
+    assert_eq!(second.records, 1);
+
+    std::fs::remove_dir_all(root).ok();
+}
@@ -0,0 +1,52 @@
+use orderbook::book::Level;
+use orderbook::resiliency::{LargeTrades, Settings, Tracker, CAUSE_REMOVAL, CAUSE_TRADE};
+use orderbook::{schema, sink, OrderBook};
+
+fn book(ts: i64, bid_qty: f64, ask_qty: f64) -> OrderBook {
+    OrderBook::from_levels(ts, &[Level::new(99.95, bid_qty)], &[Level::new(100.05, ask_qty)])
+        .unwrap()
+        .with_source("binance", "btcusdt")
+}
+
+#[test]
+fn a_depletion_closes_once_depth_is_back() {
+    let mut tracker = Tracker::new(Settings::default());
+    assert!(tracker.push(&book(0, 10.0, 10.0), LargeTrades::default()).is_empty());
+    // a large taker sell between the books empties most of the bids
+    let trades = LargeTrades { buy_ms: None, sell_ms: Some(500) };
+    assert!(tracker.push(&book(1_000, 2.0, 10.0), trades).is_empty());
+    assert!(tracker.push(&book(2_000, 1.0, 9.0), trades).is_empty(), "still below the baseline");
+
+    let closed = tracker.push(&book(5_000, 10.0, 10.0), trades);
+    assert_eq!(closed.len(), 1);
+    let event = &closed[0];
+    assert_eq!((event.side.as_str(), event.cause.as_str(), event.symbol.as_str()), ("bid", CAUSE_TRADE, "BTCUSDT"));
+    assert_eq!((event.start_ms, event.end_ms, event.recovery_ms), (1_000, 5_000, 4_000));
+    assert_eq!((event.baseline_depth, event.trough_depth, event.end_depth), (10.0, 1.0, 10.0));
+    assert!(event.recovered);
+    assert_eq!(event.score, 0.25, "all of the lost depth back in four seconds");
+    assert!(sink::encode(schema::RESILIENCY, &closed).is_ok());
+}
+
+#[test]
+fn removals_without_a_large_trade_time_out_unrecovered() {
+    let settings = Settings { timeout_ms: 10_000, ..Settings::default() };
+    let mut tracker = Tracker::new(settings);
+    tracker.push(&book(0, 10.0, 10.0), LargeTrades::default());
+    // the last large buy predates the previous book, so it can't explain the drop
+    let trades = LargeTrades { buy_ms: Some(-5), sell_ms: None };
+    assert!(tracker.push(&book(1_000, 10.0, 4.0), trades).is_empty());
+    assert!(tracker.push(&book(6_000, 10.0, 2.0), trades).is_empty());
+
+    let closed = tracker.push(&book(11_000, 10.0, 6.0), trades);
+    assert_eq!(closed.len(), 1);
+    let event = &closed[0];
+    assert_eq!((event.side.as_str(), event.cause.as_str()), ("ask", CAUSE_REMOVAL));
+    assert!(!event.recovered);
+    assert_eq!(event.recovery_ms, 10_000);
+    assert!((event.score - 0.05).abs() < 1e-12, "half the lost depth back in ten seconds, got {}", event.score);
+
+    // a small dip doesn't open an event
+    assert!(tracker.push(&book(12_000, 10.0, 5.0), trades).is_empty());
+    assert!(tracker.push(&book(13_000, 10.0, 6.0), trades).is_empty());
+}
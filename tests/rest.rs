@@ -0,0 +1,32 @@
+use orderbook::rest::Budget;
+use std::time::Duration;
+
+const MINUTE: i64 = 1_756_872_000_000;
+
+#[test]
+fn spends_weight_until_the_minute_is_full_then_waits_for_the_next() {
+    let mut budget = Budget::new(100);
+    assert_eq!(budget.acquire(50, MINUTE + 1_000), Duration::ZERO);
+    assert_eq!(budget.acquire(50, MINUTE + 2_000), Duration::ZERO);
+    assert_eq!(budget.acquire(1, MINUTE + 45_000), Duration::from_secs(15));
+    assert_eq!(budget.used(), 100);
+
+    assert_eq!(budget.acquire(1, MINUTE + 60_000), Duration::ZERO, "a new minute starts empty");
+    assert_eq!(budget.used(), 1);
+    assert_eq!(budget.acquire(250, MINUTE + 120_000), Duration::ZERO, "an oversized request goes first in a minute");
+}
+
+#[test]
+fn reported_weight_and_rate_limits_hold_requests_back() {
+    let mut budget = Budget::new(100);
+    assert_eq!(budget.acquire(5, MINUTE), Duration::ZERO);
+    // another process on the IP has spent most of the minute
+    budget.observe(98, MINUTE + 10_000);
+    assert_eq!(budget.acquire(5, MINUTE + 10_000), Duration::from_secs(50));
+    budget.observe(3, MINUTE + 11_000);
+    assert_eq!(budget.used(), 98, "never below what's been spent");
+
+    budget.block(MINUTE + 150_000);
+    assert_eq!(budget.acquire(1, MINUTE + 120_000), Duration::from_secs(30));
+    assert_eq!(budget.acquire(1, MINUTE + 150_000), Duration::ZERO);
+}
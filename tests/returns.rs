@@ -0,0 +1,30 @@
+#[allow(dead_code)]
+mod common;
+
+use common::around;
+use orderbook::returns::Returns;
+
+#[test]
+fn returns_measure_from_the_last_stored_book_and_the_window_start() {
+    let mut returns = Returns::new(60);
+    let first = around(0, 100.0);
+    returns.observe(&first);
+    let first = returns.stamp(first);
+    assert_eq!((first.mid_return, first.mid_return_window, first.return_window_secs), (0.0, 0.0, 60));
+
+    // seen but not stored: in the window's buffer, not the previous stored mid
+    returns.observe(&around(30_000, 150.0));
+
+    let second = around(45_000, 110.0);
+    returns.observe(&second);
+    let second = returns.stamp(second);
+    assert!((second.mid_return - (110.0f64 / 100.0).ln()).abs() < 1e-12);
+    assert_eq!(second.mid_return_window, 0.0, "less than a window of history");
+
+    let third = around(90_000, 121.0);
+    returns.observe(&third);
+    let third = returns.stamp(third);
+    assert!((third.mid_return - (121.0f64 / 110.0).ln()).abs() < 1e-12);
+    // the newest mid at least 60s old is the one seen at 30s
+    assert!((third.mid_return_window - (121.0f64 / 150.0).ln()).abs() < 1e-12, "{}", third.mid_return_window);
+}
@@ -0,0 +1,36 @@
+#[allow(dead_code)]
+mod common;
+
+use common::around;
+use orderbook::sample::Sampler;
+
+fn kept(sampler: &mut Sampler, books: &[(i64, f64)]) -> Vec<i64> {
+    books.iter().map(|&(at, mid)| around(at, mid)).filter(|b| sampler.keep(b)).map(|b| b.timestamp_ms).collect()
+}
+
+#[test]
+fn unconfigured_sampler_keeps_everything() {
+    let mut sampler = Sampler::default();
+    assert!(!sampler.is_enabled());
+    assert_eq!(kept(&mut sampler, &[(0, 100.0), (1, 100.0), (2, 100.0)]), vec![0, 1, 2]);
+}
+
+#[test]
+fn interval_keeps_at_most_one_per_window() {
+    let mut sampler = Sampler::new(Some(1000), None);
+    let books: Vec<(i64, f64)> = (0..25).map(|i| (i * 100, 100.0)).collect();
+    assert_eq!(kept(&mut sampler, &books), vec![0, 1000, 2000]);
+}
+
+#[test]
+fn mid_move_is_measured_from_the_last_kept_book() {
+    let mut sampler = Sampler::new(None, Some(10.0));
+    // 5 bps, then 10 bps from the first, then 5 bps back
+    assert_eq!(kept(&mut sampler, &[(0, 10_000.0), (1, 10_005.0), (2, 10_010.0), (3, 10_005.0)]), vec![0, 2]);
+}
+
+#[test]
+fn both_conditions_must_hold() {
+    let mut sampler = Sampler::new(Some(1000), Some(10.0));
+    assert_eq!(kept(&mut sampler, &[(0, 10_000.0), (500, 10_020.0), (1500, 10_000.5), (2000, 10_020.0)]), vec![0, 2000]);
+}
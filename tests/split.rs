@@ -0,0 +1,30 @@
+use orderbook::book::{ExactLevel, Level};
+use orderbook::split::{self, FEATURES_VERSION};
+use orderbook::{schema, sink, OrderBook};
+
+#[test]
+fn raw_levels_and_features_are_split_apart() {
+    let mut book = OrderBook::from_levels(1_000, &[Level::new(99.5, 2.0)], &[Level::new(100.5, 1.0)])
+        .unwrap()
+        .with_exchange_clock(990, 42)
+        .with_source("binance", "btcusdt")
+        .with_exact_levels(&[["99.50", "2.000"]], &[["100.50", "1.000"]])
+        .with_vpin((0.4, 12));
+
+    let (raw, features) = split::records(&mut book);
+    assert_eq!((raw.symbol.as_str(), raw.last_update_id, raw.event_time_ms), ("BTCUSDT", 42, 990));
+    assert_eq!((raw.price_scale, raw.qty_scale), (2, 3));
+    assert_eq!(raw.bids, vec![ExactLevel { price: 9_950, qty: 2_000 }]);
+    assert_eq!(raw.asks, vec![ExactLevel { price: 10_050, qty: 1_000 }]);
+    // without DECIMAL_PRICES the fixed-point levels live only in the raw record
+    assert!(book.exact_bids.is_empty() && book.exact_asks.is_empty());
+
+    assert_eq!((features.timestamp_ms, features.last_update_id), (raw.timestamp_ms, raw.last_update_id));
+    assert_eq!(features.features_version, FEATURES_VERSION);
+    assert_eq!((features.mid_price, features.spread, features.vpin_buckets), (100.0, 1.0, 12));
+    assert_eq!(features.bid_depth, book.bids);
+    assert_eq!(features.ask_sweeps, book.ask_sweeps);
+
+    assert!(sink::encode(schema::RAW_BOOK, &[raw]).is_ok());
+    assert!(sink::encode(schema::FEATURES, &[features]).is_ok());
+}
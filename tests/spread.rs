@@ -0,0 +1,68 @@
+use orderbook::book::Level;
+use orderbook::spread::{fill_price, spreads, Fees};
+use orderbook::{schema, sink, OrderBook};
+
+fn book(bid: f64, ask: f64) -> OrderBook {
+    OrderBook::from_levels(0, &[Level::new(bid, 5.0)], &[Level::new(ask, 5.0)]).expect("two-sided")
+}
+
+fn fees(default_bps: f64) -> Fees {
+    Fees { default_bps, ..Fees::default() }
+}
+
+#[test]
+fn fill_price_walks_the_buckets_at_their_far_edge() {
+    let buckets = [Level::new(100.0, 1.0), Level::new(101.0, 3.0), Level::new(102.0, 3.0)];
+    assert_eq!(fill_price(&buckets, 1.0), Some(100.0));
+    // 1 at 100, then 1 of the next 2 at 101
+    assert_eq!(fill_price(&buckets, 2.0), Some(100.5));
+    assert_eq!(fill_price(&buckets, 3.5), None);
+    assert_eq!(fill_price(&buckets, 0.0), None);
+}
+
+#[test]
+fn mid_difference_is_signed_by_venue_name() {
+    let (us, com) = (book(100.0, 101.0), book(102.0, 103.0));
+    let [pair] = &spreads("btcusdt", 7, &[("us", &us), ("com", &com)], &[], &Fees::default())[..] else { panic!("one pair") };
+
+    assert_eq!((pair.venue_a.as_str(), pair.venue_b.as_str()), ("com", "us"));
+    assert_eq!(pair.mid_diff, 2.0);
+    assert!((pair.mid_diff_bps - 2.0 / 101.5 * 10_000.0).abs() < 1e-9);
+    assert_eq!((pair.timestamp_ms, pair.symbol.as_str()), (7, "BTCUSDT"));
+}
+
+#[test]
+fn arbs_buy_on_the_cheap_venue_and_sell_on_the_dear_one() {
+    let (cheap, dear) = (book(99.0, 100.0), book(110.0, 111.0));
+    let [pair] = &spreads("BTCUSDT", 0, &[("cheap", &cheap), ("dear", &dear)], &[0.1], &fees(0.0))[..] else { panic!("one pair") };
+    let [arb] = &pair.arbs[..] else { panic!("one size") };
+
+    assert_eq!((arb.buy_venue.as_str(), arb.sell_venue.as_str()), ("cheap", "dear"));
+    assert!(arb.buy_price < arb.sell_price);
+    assert!(arb.edge_bps > 0.0);
+}
+
+#[test]
+fn fees_eat_the_edge() {
+    let (a, b) = (book(99.0, 100.0), book(100.02, 101.0));
+    let free = &spreads("BTCUSDT", 0, &[("a", &a), ("b", &b)], &[0.1], &fees(0.0))[0].arbs[0];
+    let taxed = &spreads("BTCUSDT", 0, &[("a", &a), ("b", &b)], &[0.1], &fees(10.0))[0].arbs[0];
+
+    assert!((free.edge_bps - taxed.edge_bps - 20.0).abs() < 0.1, "{} vs {}", free.edge_bps, taxed.edge_bps);
+}
+
+#[test]
+fn sizes_beyond_the_books_are_left_out() {
+    let (a, b) = (book(99.0, 100.0), book(99.5, 100.5));
+    let pair = &spreads("BTCUSDT", 0, &[("a", &a), ("b", &b)], &[1.0, 1000.0], &Fees::default())[0];
+
+    assert_eq!(pair.arbs.iter().map(|arb| arb.size).collect::<Vec<_>>(), vec![1.0]);
+    assert!(sink::encode(schema::VENUE_SPREAD, std::slice::from_ref(pair)).is_ok());
+}
+
+#[test]
+fn three_venues_give_three_pairs() {
+    let (a, b, c) = (book(99.0, 100.0), book(99.0, 100.0), book(99.0, 100.0));
+    assert_eq!(spreads("BTCUSDT", 0, &[("a", &a), ("b", &b), ("c", &c)], &[], &Fees::default()).len(), 3);
+    assert!(spreads("BTCUSDT", 0, &[("a", &a)], &[1.0], &Fees::default()).is_empty());
+}
@@ -0,0 +1,81 @@
+use futures_util::stream;
+use orderbook::pipeline::Outcome;
+use orderbook::stages::{self, Frame, Received};
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::{self, Message};
+
+const BOOK: &str = r#"{"lastUpdateId":1,"bids":[["100.0","1.0"]],"asks":[["101.0","1.0"]]}"#;
+
+async fn drain(mut rx: mpsc::Receiver<Frame>) -> Vec<Frame> {
+    let mut frames = Vec::new();
+    while let Some(frame) = rx.recv().await {
+        // a slow consumer, so the reader finds the buffer full
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+        frames.push(frame);
+    }
+    frames
+}
+
+fn texts(frames: &[Frame]) -> Vec<&str> {
+    frames.iter().filter_map(|f| match f {
+        Frame::Text(Received { text, .. }) => Some(text.as_str()),
+        Frame::Closed { .. } => None,
+    }).collect()
+}
+
+#[tokio::test]
+async fn the_reader_waits_on_a_full_buffer_and_loses_nothing() {
+    let messages: Vec<Result<Message, tungstenite::Error>> = vec![
+        Ok(Message::Text("a".into())),
+        Ok(Message::Ping(vec![1])),
+        Ok(Message::Text("b".into())),
+        Ok(Message::Text("c".into())),
+        Ok(Message::Text("d".into())),
+    ];
+    let (tx, rx) = mpsc::channel(1);
+    let reader = tokio::spawn(stages::read(7, stream::iter(messages), tx));
+    let frames = drain(rx).await;
+    reader.await.unwrap();
+
+    assert_eq!(texts(&frames), ["a", "b", "c", "d"]);
+    assert!(matches!(frames.last(), Some(Frame::Closed { connection: 7 })));
+    let stamps: Vec<i64> = frames.iter().filter_map(|f| match f { Frame::Text(r) => Some(r.received_ms), _ => None }).collect();
+    assert!(stamps.windows(2).all(|w| w[0] <= w[1]) && stamps[0] > 0);
+}
+
+#[tokio::test]
+async fn a_transport_error_closes_the_connection() {
+    let messages = vec![Ok(Message::Text("a".into())), Err(tungstenite::Error::ConnectionClosed), Ok(Message::Text("b".into()))];
+    let (tx, rx) = mpsc::channel(4);
+    stages::read(2, stream::iter(messages), tx).await;
+
+    let frames = drain(rx).await;
+    assert_eq!(texts(&frames), ["a"]);
+    assert!(matches!(frames.last(), Some(Frame::Closed { connection: 2 })));
+}
+
+#[tokio::test]
+async fn compute_parses_depth_frames_in_order_and_passes_the_rest_on() {
+    let (frames, to_compute) = mpsc::channel(4);
+    let (computed, staged) = mpsc::channel(4);
+    let compute = tokio::spawn(stages::compute(to_compute, computed, 11, false));
+    frames.send(Frame::Text(Received::new(5, BOOK.into()))).await.unwrap();
+    frames.send(Frame::Text(Received::new(6, "{".into()))).await.unwrap();
+    frames.send(Frame::Closed { connection: 1 }).await.unwrap();
+    drop(frames);
+    compute.await.unwrap();
+
+    let staged = drain(staged).await;
+    let Frame::Text(Received { outcome: Some(Outcome::Book(book)), .. }) = &staged[0] else { panic!("{:?}", staged[0]) };
+    assert_eq!((book.timestamp_ms, book.last_update_id), (5, 1));
+    assert!(matches!(&staged[1], Frame::Text(Received { outcome: Some(Outcome::Malformed(_)), .. })));
+    assert!(matches!(staged[2], Frame::Closed { connection: 1 }));
+
+    // quotes are parsed by the collector
+    let (frames, to_compute) = mpsc::channel(1);
+    let (computed, staged) = mpsc::channel(1);
+    frames.send(Frame::Text(Received::new(5, BOOK.into()))).await.unwrap();
+    drop(frames);
+    stages::compute(to_compute, computed, 11, true).await;
+    assert!(matches!(&drain(staged).await[..], [Frame::Text(Received { outcome: None, .. })]));
+}
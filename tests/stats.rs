@@ -0,0 +1,46 @@
+#[allow(dead_code)]
+mod common;
+
+use common::{depth_fixture, priced, serve, set_env, MemoryOutput};
+use orderbook::collector::Collector;
+use orderbook::stats::{HourlyStats, StatsBuilder};
+
+const HOUR_MS: i64 = 3_600_000;
+
+#[test]
+fn an_hour_rolls_up_spread_mid_and_gap_time() {
+    let hour = 1_756_872_000_000 - 1_756_872_000_000 % HOUR_MS;
+    let mut builder = StatsBuilder::default();
+    assert_eq!(builder.push(&priced(hour + 1_000, 100.0, 100.2)), None);
+    assert_eq!(builder.push(&priced(hour + 2_000, 101.0, 101.4)), None);
+    // 30s without a book is past the default 5s gap threshold
+    assert_eq!(builder.push(&priced(hour + 32_000, 99.0, 99.1)), None);
+
+    let stats = builder.push(&priced(hour + HOUR_MS + 500, 100.0, 100.2)).expect("the hour rolled over");
+    assert_eq!((stats.hour_ms, stats.first_ms, stats.last_ms, stats.snapshots, stats.gap_ms), (hour, hour + 1_000, hour + 32_000, 3, 30_000));
+    assert!((stats.mean_spread - (0.2 + 0.4 + 0.1) / 3.0).abs() < 1e-9);
+    assert!((stats.min_spread - 0.1).abs() < 1e-9 && (stats.max_spread - 0.4).abs() < 1e-9);
+    assert_eq!((stats.open_mid, stats.high_mid, stats.low_mid, stats.close_mid), (100.1, 101.2, 99.05, 99.05));
+
+    assert_eq!(builder.flush_ended(hour + 2 * HOUR_MS - 1), None);
+    assert_eq!(builder.flush_ended(hour + 2 * HOUR_MS).map(|s| s.hour_ms), Some(hour + HOUR_MS));
+    assert_eq!(builder.flush(), None);
+}
+
+#[tokio::test]
+async fn the_collector_writes_the_partial_hour_when_the_stream_ends() {
+    let _env = set_env(&[("HOURLY_STATS", "1")]);
+    let url = serve(depth_fixture()).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default());
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    let output = collector.into_output();
+    let [(key, body)] = &output.objects.iter().filter(|(key, _)| key.starts_with("stats/")).collect::<Vec<_>>()[..] else {
+        panic!("expected one stats object")
+    };
+    assert!(key.starts_with("stats/exchange=binance/symbol=BTCUSDT/year="), "{}", key);
+    let mut reader = apache_avro::Reader::new(&body[..]).expect("avro");
+    let stats: HourlyStats = apache_avro::from_value(&reader.next().expect("record").expect("value")).expect("stats");
+    assert_eq!((stats.symbol.as_str(), stats.snapshots), ("BTCUSDT", 3));
+}
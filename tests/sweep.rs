@@ -0,0 +1,70 @@
+#[allow(dead_code)]
+mod common;
+
+use common::priced;
+use orderbook::sweep::{self, Detector};
+use orderbook::trades::AggTrade;
+
+fn trade(agg_id: i64, trade_time_ms: i64, price: f64, qty: f64, is_buyer_maker: bool) -> AggTrade {
+    AggTrade {
+        symbol: "BTCUSDT".to_string(),
+        agg_id,
+        price,
+        qty,
+        first_trade_id: agg_id,
+        last_trade_id: agg_id,
+        trade_time_ms,
+        is_buyer_maker,
+        source: "stream".to_string(),
+    }
+}
+
+#[test]
+fn an_order_filling_through_several_levels_flags_the_next_book() {
+    let mut detector = Detector::new(2);
+    let first = detector.flag(priced(1_000, 99.0, 101.0), &[trade(1, 900, 101.0, 1.0, false), trade(2, 900, 102.0, 1.0, false)]);
+    assert!(!first.sweep, "nothing to measure from");
+
+    let trades = [
+        // one buy through three asks
+        trade(3, 1_500, 101.0, 1.0, false),
+        trade(4, 1_500, 102.0, 1.0, false),
+        trade(5, 1_500, 103.0, 2.0, false),
+        // a bigger sell, but at a single level
+        trade(6, 1_600, 99.0, 10.0, true),
+        // a smaller sell across two bids
+        trade(7, 1_700, 99.0, 0.5, true),
+        trade(8, 1_700, 98.0, 0.5, true),
+    ];
+    let swept = detector.flag(priced(2_000, 99.0, 104.0), &trades);
+    assert!(swept.sweep);
+    assert_eq!((swept.sweep_side.as_str(), swept.sweep_levels), ("ask", 3));
+    assert!((swept.sweep_notional - 409.0).abs() < 1e-9, "{}", swept.sweep_notional);
+
+    let quiet = detector.flag(priced(3_000, 99.0, 104.0), &[trade(9, 2_500, 104.0, 1.0, false)]);
+    assert!(!quiet.sweep && quiet.sweep_side.is_empty());
+}
+
+#[test]
+fn trades_after_a_book_wait_for_the_next_one() {
+    for (id, time) in [(1, 1_000), (2, 2_000), (3, 3_000)] {
+        sweep::record(trade(id, time, 100.0, 1.0, false));
+    }
+    assert_eq!(sweep::take("btcusdt", 2_000).iter().map(|t| t.agg_id).collect::<Vec<_>>(), [1, 2]);
+    assert_eq!(sweep::take("btcusdt", 5_000).len(), 1);
+    assert!(sweep::take("ethusdt", 5_000).is_empty());
+}
+
+#[test]
+fn a_trade_recorded_twice_is_taken_once() {
+    let trade = |agg_id, time| AggTrade { symbol: "SOLUSDT".to_string(), ..self::trade(agg_id, time, 100.0, 1.0, false) };
+    for (id, time) in [(1, 1_000), (1, 1_000), (2, 1_500), (1, 1_000)] {
+        sweep::record(trade(id, time));
+    }
+    assert_eq!(sweep::take("solusdt", 2_000).iter().map(|t| t.agg_id).collect::<Vec<_>>(), [1, 2]);
+
+    // nor again once taken
+    sweep::record(trade(2, 1_500));
+    sweep::record(trade(3, 2_500));
+    assert_eq!(sweep::take("solusdt", 5_000).iter().map(|t| t.agg_id).collect::<Vec<_>>(), [3]);
+}
@@ -0,0 +1,17 @@
+use orderbook::schema;
+use serde_json::Value;
+
+const TEMPLATE: &str = include_str!("../template.yaml");
+
+#[test]
+fn the_glue_table_covers_every_field_of_the_current_schema() {
+    let current: Value = serde_json::from_str(schema::ORDERBOOK).unwrap();
+    // exchange and symbol are partition keys, not columns
+    let fields = current["fields"].as_array().unwrap().iter()
+        .map(|f| f["name"].as_str().unwrap())
+        .filter(|name| !matches!(*name, "exchange" | "symbol"));
+    for name in fields {
+        assert!(TEMPLATE.contains(&format!("{{\"name\":\"{}\",", name)), "avro.schema.literal lacks {}", name);
+        assert!(TEMPLATE.contains(&format!("- {{ Name: {}, Type: ", name)), "Columns lack {}", name);
+    }
+}
@@ -0,0 +1,47 @@
+use orderbook::top::{self, QuoteBatcher};
+
+const SPOT: &str = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35","B":"30.00","a":"25.37","A":"10.00"}"#;
+
+#[test]
+fn spot_quotes_carry_spread_and_micro_price() {
+    let quote = top::parse(SPOT, 1_000).expect("json").expect("quote");
+    assert_eq!((quote.symbol.as_str(), quote.update_id), ("BNBUSDT", 400900217));
+    assert_eq!((quote.timestamp_ms, quote.event_time_ms, quote.partition_time_ms()), (1_000, 0, 1_000));
+    assert!((quote.spread - 0.02).abs() < 1e-9);
+    assert!((quote.mid_price - 25.36).abs() < 1e-9);
+    // three times as much bid as ask pulls the micro-price towards the ask
+    assert!((quote.micro_price - 25.365).abs() < 1e-9);
+}
+
+#[test]
+fn futures_and_combined_stream_quotes_parse() {
+    let futures = r#"{"e":"bookTicker","u":400900217,"E":1568014460893,"T":1568014460891,"s":"BTCUSDT","b":"25.35","B":"31.21","a":"25.36","A":"40.66"}"#;
+    let quote = top::parse(futures, 1_568_014_461_000).expect("json").expect("quote");
+    assert_eq!(quote.partition_time_ms(), 1568014460893);
+
+    let combined = format!(r#"{{"stream":"bnbusdt@bookTicker","data":{}}}"#, SPOT);
+    assert_eq!(top::parse(&combined, 1_000).expect("json").expect("quote").update_id, 400900217);
+}
+
+#[test]
+fn other_messages_are_not_quotes() {
+    assert_eq!(top::parse(r#"{"result":null,"id":1}"#, 0).expect("json"), None);
+    assert_eq!(top::parse(r#"{"u":1,"s":"BTCUSDT","b":"0","B":"0","a":"1","A":"1"}"#, 0).expect("json"), None);
+    assert!(top::parse(r#"{"u":1,"s""#, 0).is_err());
+    assert_eq!(top::stream("BTCUSDT"), "btcusdt@bookTicker");
+}
+
+#[test]
+fn quotes_are_batched_per_minute() {
+    let quote = |at| top::parse(SPOT, at).expect("json").expect("quote");
+    let mut batch = QuoteBatcher::default();
+
+    assert!(batch.push(quote(60_000)).is_none());
+    assert!(batch.push(quote(119_999)).is_none());
+    let (minute, quotes) = batch.push(quote(120_000)).expect("first minute done");
+    assert_eq!((minute, quotes.len()), (60_000, 2));
+
+    assert!(batch.flush_ended(179_999).is_none());
+    assert_eq!(batch.flush_ended(180_000).map(|(minute, quotes)| (minute, quotes.len())), Some((120_000, 1)));
+    assert!(batch.flush().is_none());
+}
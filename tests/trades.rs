@@ -0,0 +1,46 @@
+use apache_avro::{from_value, Reader, Schema, Writer};
+use orderbook::trades::{self, AggTrade};
+use serde::Serialize;
+use serde_json::json;
+
+#[test]
+fn streamed_trades_are_tagged_as_stream() {
+    let v = json!({"a": 26129, "p": "65000.10", "q": "0.5", "f": 27781, "l": 27781, "T": 1725000000123i64, "m": true});
+    let trade = AggTrade::from_json("BTCUSDT", &v).expect("trade");
+    assert_eq!((trade.agg_id, trade.source.as_str()), (26129, trades::SOURCE_STREAM));
+}
+
+#[test]
+fn a_window_keeps_its_own_trades_once_each() {
+    let trade = |a: i64, t: i64| AggTrade::from_json("BTCUSDT", &json!({"a": a, "p": "1", "q": "1", "f": a, "l": a, "T": t, "m": false})).expect("trade");
+    // a page that ran past the hour, then the next hour's first page overlapping it
+    let fetched = vec![trade(1, 999), trade(2, 1_000), trade(3, 2_500), trade(4, 3_100), trade(3, 2_500), trade(4, 3_100), trade(5, 3_200)];
+    let kept: Vec<i64> = trades::window(fetched, 1_000, 3_150).iter().map(|t| t.agg_id).collect();
+    assert_eq!(kept, [2, 3, 4]);
+}
+
+#[test]
+fn trades_written_before_the_source_field_read_as_backfill() {
+    #[derive(Serialize)]
+    struct Untagged {
+        symbol: String,
+        agg_id: i64,
+        price: f64,
+        qty: f64,
+        first_trade_id: i64,
+        last_trade_id: i64,
+        trade_time_ms: i64,
+        is_buyer_maker: bool,
+    }
+    let old = Schema::parse_str(&orderbook::schema::AGG_TRADE.replace(r#",
+    {"name": "source", "type": "string", "default": "backfill"}"#, "")).expect("old schema");
+    let mut writer = Writer::new(&old, Vec::new());
+    writer.append_ser(Untagged {
+        symbol: "BTCUSDT".into(), agg_id: 1, price: 1.0, qty: 1.0, first_trade_id: 1, last_trade_id: 1,
+        trade_time_ms: 1725000000123, is_buyer_maker: false,
+    }).expect("append");
+    let bytes = writer.into_inner().expect("encode");
+
+    let trade: AggTrade = from_value(&Reader::new(&bytes[..]).expect("reader").next().expect("record").expect("value")).expect("trade");
+    assert_eq!(trade.source, trades::SOURCE_BACKFILL);
+}
@@ -0,0 +1,39 @@
+#![cfg(feature = "userdata")]
+
+use orderbook::userdata::{EventIds, Execution};
+use orderbook::{schema, sink};
+use serde_json::json;
+
+#[test]
+fn spot_execution_report() {
+    let event = json!({"e":"executionReport","E":1499405658658_i64,"s":"ETHBTC","c":"mUvoqJxFIILMdfAW5iGSOW","S":"BUY","o":"LIMIT",
+        "q":"1.00000000","p":"0.10264410","x":"TRADE","X":"PARTIALLY_FILLED","i":4293153,"l":"0.40000000","z":"0.40000000",
+        "L":"0.10264410","n":"0.00004000","N":"ETH","T":1499405658657_i64,"t":718,"m":true});
+    let execution = Execution::from_event(&event).expect("execution");
+    assert_eq!((execution.symbol.as_str(), execution.order_id, execution.trade_id), ("ETHBTC", 4293153, 718));
+    assert_eq!((execution.execution_type.as_str(), execution.order_status.as_str()), ("TRADE", "PARTIALLY_FILLED"));
+    assert_eq!((execution.last_qty, execution.cumulative_qty, execution.commission), (0.4, 0.4, 0.00004));
+    assert!(execution.is_maker);
+
+    // the record encodes against the archived schema
+    sink::encode(schema::EXECUTION, &[execution]).expect("valid avro");
+}
+
+#[test]
+fn futures_order_update_and_other_events() {
+    let event = json!({"e":"ORDER_TRADE_UPDATE","E":1568879465651_i64,"T":1568879465650_i64,"o":{"s":"BTCUSDT","c":"TEST","S":"SELL",
+        "o":"TRAILING_STOP_MARKET","q":"0.001","p":"0","x":"NEW","X":"NEW","i":8886774,"l":"0","z":"0","L":"0","T":1568879465650_i64,"t":0,"m":false}});
+    let execution = Execution::from_event(&event).expect("execution");
+    assert_eq!((execution.event_time_ms, execution.trade_time_ms), (1568879465651, 1568879465650));
+    assert_eq!((execution.symbol.as_str(), execution.side.as_str(), execution.order_type.as_str()), ("BTCUSDT", "SELL", "TRAILING_STOP_MARKET"));
+    assert_eq!(execution.commission_asset, "");
+
+    assert!(Execution::from_event(&json!({"e":"outboundAccountPosition","E":1564034571105_i64})).is_none());
+    assert!(Execution::from_event(&json!({"result":null,"id":1})).is_none());
+}
+
+#[test]
+fn events_in_the_same_millisecond_get_distinct_ids() {
+    let mut ids = EventIds::default();
+    assert_eq!([ids.next(1000), ids.next(1000), ids.next(1001), ids.next(1000)], [1_000_000, 1_000_001, 1_001_000, 1_000_000]);
+}
@@ -0,0 +1,71 @@
+#[allow(dead_code)]
+mod common;
+
+use common::{serve, set_env, MemoryOutput};
+use orderbook::book::Level;
+use orderbook::collector::Collector;
+use orderbook::validate::{self, Quarantined, Reason, Validator};
+
+fn levels(levels: &[(f64, f64)]) -> Vec<Level> {
+    levels.iter().map(|&(price, qty)| Level::new(price, qty)).collect()
+}
+
+fn depth(last_update_id: i64, bid: &str, ask: &str, qty: &str) -> String {
+    format!(r#"{{"lastUpdateId":{},"bids":[["{}","{}"]],"asks":[["{}","1.0"]]}}"#, last_update_id, bid, qty, ask)
+}
+
+#[test]
+fn levels_fail_on_empty_sides_non_finite_values_and_non_positive_quantities() {
+    let ok = levels(&[(100.0, 1.0), (99.9, 2.0)]);
+    assert_eq!(validate::levels(&ok, &levels(&[(100.1, 0.5)])), None);
+    assert_eq!(validate::levels(&ok, &[]), Some(Reason::EmptySide));
+    assert_eq!(validate::levels(&ok, &levels(&[(f64::NAN, 1.0)])), Some(Reason::NotFinite));
+    assert_eq!(validate::levels(&ok, &levels(&[(100.1, f64::INFINITY)])), Some(Reason::NotFinite));
+    assert_eq!(validate::levels(&ok, &levels(&[(100.1, 0.0)])), Some(Reason::NonPositiveQty));
+    assert_eq!(validate::levels(&levels(&[(100.0, -1.0)]), &ok), Some(Reason::NonPositiveQty));
+}
+
+#[test]
+fn a_jump_is_caught_until_the_market_confirms_it() {
+    let mut validator = Validator::new(10.0);
+    assert_eq!(validator.check(100.0), None);
+    assert_eq!(validator.check(109.0), None);
+    // a one-book spike, and the book after it measured against 109 rather than the spike
+    assert_eq!(validator.check(200.0), Some(Reason::PriceJump { from: 109.0, to: 200.0 }));
+    assert_eq!(validator.check(108.0), None);
+    // a level that holds for three books is the market's
+    assert!(validator.check(150.0).is_some());
+    assert!(validator.check(151.0).is_some());
+    assert_eq!(validator.check(150.5), None);
+    assert_eq!(validator.check(152.0), None);
+}
+
+#[tokio::test]
+async fn failing_books_are_quarantined_with_their_reason() {
+    let _env = set_env(&[("VALIDATE_BOOKS", "1")]);
+    let messages = vec![
+        depth(1, "100.0", "100.2", "1.0"),
+        depth(2, "100.0", "100.2", "0"),
+        depth(3, "nan", "100.2", "1.0"),
+        depth(4, "200.0", "200.2", "1.0"),
+        r#"{"lastUpdateId":5,"bids":[],"asks":[["100.2","1.0"]]}"#.to_string(),
+        depth(6, "100.1", "100.3", "1.0"),
+    ];
+    let url = serve(messages.clone()).await;
+    let mut collector = Collector::new("btcusdt", &url, MemoryOutput::default());
+    collector.reconnect = false;
+    collector.run().await.expect("collector run");
+
+    let counts = collector.counts();
+    assert_eq!((counts.received, counts.stored, counts.quarantined), (6, 2, 4));
+    let quarantined: Vec<Quarantined> = collector.output().objects.iter()
+        .filter(|(key, _)| key.starts_with("quarantine/exchange=binance/symbol=BTCUSDT/"))
+        .map(|(_, body)| {
+            let mut reader = apache_avro::Reader::new(&body[..]).expect("avro");
+            apache_avro::from_value(&reader.next().expect("record").expect("value")).expect("quarantined")
+        })
+        .collect();
+    let reasons: Vec<&str> = quarantined.iter().map(|q| q.reason.as_str()).collect();
+    assert_eq!(reasons, ["non-positive quantity", "non-finite price or quantity", "mid jumped from 100.1 to 200.1", "empty side"]);
+    assert_eq!(quarantined[0].payload, messages[1]);
+}
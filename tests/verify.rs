@@ -0,0 +1,73 @@
+#[allow(dead_code)]
+mod common;
+
+use chrono::{Duration, TimeZone, Utc};
+use common::book;
+use orderbook::archive::Archive;
+use orderbook::manifest::{self, FileEntry, Manifest};
+use orderbook::verify::{self, Hole};
+use orderbook::{schema, sink, OrderBook};
+
+async fn store(archive: &Archive, books: &[OrderBook]) -> String {
+    let key = sink::partition_key("orderbook", "BTCUSDT", sink::at_ms(books[0].timestamp_ms), books[0].timestamp_ms).unwrap();
+    archive.put(&key, sink::encode(schema::ORDERBOOK, books).unwrap()).await.unwrap();
+    key
+}
+
+#[tokio::test]
+async fn holes_hours_and_bad_objects_are_reported() {
+    let root = std::env::temp_dir().join(format!("orderbook-verify-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let from = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    let start = from.timestamp_millis();
+    let minute = 60_000;
+
+    // every minute of the first hour but 10 and 11, nothing in the second
+    for m in (0..60).filter(|m| !(10..12).contains(m)) {
+        store(&archive, &[book(start + m * minute + 500)]).await;
+    }
+    let report = verify::verify(&archive, "btcusdt", from, from + Duration::hours(2)).await.unwrap();
+    assert_eq!((report.files, report.books, report.minutes, report.covered_minutes), (58, 58, 120, 58));
+    assert_eq!(report.holes, [
+        Hole { from_ms: start + 10 * minute, to_ms: start + 12 * minute },
+        Hole { from_ms: start + 60 * minute, to_ms: start + 120 * minute },
+    ]);
+    assert_eq!(report.missing_hours, [start + 60 * minute]);
+    assert!(report.problems.is_empty() && !report.is_complete());
+
+    // an object whose books go backwards, and one that isn't Avro at all
+    let backwards = store(&archive, &[book(start + 10 * minute + 1_000), book(start + 10 * minute)]).await;
+    let junk = sink::partition_key("orderbook", "BTCUSDT", sink::at_ms(start), start + 11 * minute).unwrap();
+    archive.put(&junk, b"not avro".to_vec()).await.unwrap();
+    let report = verify::verify(&archive, "btcusdt", from, from + Duration::hours(1)).await.unwrap();
+    assert_eq!(report.holes, [Hole { from_ms: start + 11 * minute, to_ms: start + 12 * minute }]);
+    let problems: Vec<(&str, &str)> = report.problems.iter().map(|p| (p.key.as_str(), p.problem.split(':').next().unwrap())).collect();
+    assert!(problems.contains(&(backwards.as_str(), "timestamps")), "{:?}", problems);
+    assert!(problems.contains(&(junk.as_str(), "undecodable")), "{:?}", problems);
+
+    std::fs::remove_dir_all(root).ok();
+}
+
+#[tokio::test]
+async fn an_hour_is_read_from_its_manifest_and_any_objects_written_since() {
+    let root = std::env::temp_dir().join(format!("orderbook-verify-keys-{}", std::process::id()));
+    let archive = Archive::Local(root.clone());
+    let hour = Utc.with_ymd_and_hms(2025, 9, 3, 4, 0, 0).unwrap();
+    let start = hour.timestamp_millis();
+
+    let compacted = "compacted/orderbook/x.avro";
+    archive.put(compacted, sink::encode(schema::ORDERBOOK, &[book(start)]).unwrap()).await.unwrap();
+    let listed = store(&archive, &[book(start + 60_000)]).await;
+    let manifest = Manifest::new("orderbook", "btcusdt", hour, vec![
+        FileEntry::new(compacted, &[book(start)]),
+        FileEntry::new(&listed, &[book(start + 60_000)]),
+    ]);
+    manifest::write(&archive, &manifest).await.unwrap();
+    // a late object landing after the hour was compacted
+    let late = store(&archive, &[book(start + 120_000)]).await;
+
+    assert_eq!(verify::hour_keys(&archive, "btcusdt", hour).await.unwrap(), [compacted.to_string(), listed, late]);
+    let report = verify::verify(&archive, "btcusdt", hour, hour + Duration::minutes(3)).await.unwrap();
+    assert_eq!((report.files, report.books, report.covered_minutes), (3, 3, 3));
+    std::fs::remove_dir_all(root).ok();
+}
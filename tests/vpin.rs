@@ -0,0 +1,46 @@
+use orderbook::trades::{self, AggTrade};
+use orderbook::vpin::Vpin;
+
+fn trade(price: f64, qty: f64, is_buyer_maker: bool) -> AggTrade {
+    AggTrade {
+        symbol: "BTCUSDT".into(), agg_id: 1, price, qty, first_trade_id: 1, last_trade_id: 1,
+        trade_time_ms: 0, is_buyer_maker, source: trades::SOURCE_STREAM.into(),
+    }
+}
+
+#[test]
+fn no_estimate_until_a_bucket_fills() {
+    let mut vpin = Vpin::new(1_000.0, 3);
+    vpin.add(&trade(100.0, 9.0, false));
+    assert_eq!(vpin.estimate(), None);
+    vpin.add(&trade(100.0, 1.0, true));
+    assert_eq!(vpin.estimate(), Some((0.8, 1)));
+}
+
+#[test]
+fn large_trades_spill_into_the_next_buckets() {
+    let mut vpin = Vpin::new(1_000.0, 10);
+    // 2.5 buckets of taker buys
+    vpin.add(&trade(100.0, 25.0, false));
+    assert_eq!(vpin.estimate(), Some((1.0, 2)));
+    // the half-full bucket finishes balanced
+    vpin.add(&trade(100.0, 5.0, true));
+    let (estimate, buckets) = vpin.estimate().expect("three buckets");
+    assert_eq!(buckets, 3);
+    assert!((estimate - 2.0 / 3.0).abs() < 1e-12);
+}
+
+#[test]
+fn only_the_last_window_of_buckets_counts() {
+    let mut vpin = Vpin::new(1_000.0, 2);
+    vpin.add(&trade(100.0, 10.0, true));
+    for _ in 0..2 {
+        vpin.add(&trade(100.0, 5.0, true));
+        vpin.add(&trade(100.0, 5.0, false));
+    }
+    assert_eq!(vpin.estimate(), Some((0.0, 2)));
+
+    vpin.add(&trade(f64::INFINITY, 1.0, false));
+    assert_eq!(vpin.estimate(), Some((0.0, 2)), "unusable trades are ignored");
+    assert_eq!(trades::stream("BTCUSDT"), "btcusdt@aggTrade");
+}